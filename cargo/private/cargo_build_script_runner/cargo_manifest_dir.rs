@@ -98,12 +98,12 @@ impl RunfilesMaker {
             arg
         );
 
-        let content = std::fs::read_to_string(
-            arg.strip_prefix('@')
-                .expect("Param files should start with @"),
-        )
-        .unwrap();
-        let mut args = content.lines();
+        let param_file = arg
+            .strip_prefix('@')
+            .expect("Param files should start with @");
+        let mut args = action_args::try_parse_args(Path::new(param_file))
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", param_file, e))
+            .into_iter();
 
         let output_dir = PathBuf::from(
             args.next()
@@ -117,11 +117,6 @@ impl RunfilesMaker {
             .collect::<BTreeSet<String>>();
         let runfiles = args
             .map(|s| {
-                let s = if s.starts_with('\'') && s.ends_with('\'') {
-                    s.trim_matches('\'')
-                } else {
-                    s
-                };
                 let (src, dest) = s
                     .split_once('=')
                     .unwrap_or_else(|| panic!("Unexpected runfiles argument: {}", s));
@@ -439,4 +434,37 @@ mod tests {
         let contents = fs::read_to_string(file_path).unwrap();
         assert_eq!(contents, "inside world");
     }
+
+    #[test]
+    fn runfiles_maker_from_param_file() {
+        let test_tmp = PathBuf::from(std::env::var("TEST_TMPDIR").unwrap());
+        let param_file = test_tmp.join("runfiles_maker.params");
+        action_args::try_write_args(
+            &param_file,
+            &[
+                "out dir",
+                ".rs,.txt",
+                "pkg/it's here.txt=_main/pkg/it's here.txt",
+                "pkg/lib.rs=_main/pkg/lib.rs",
+            ],
+        )
+        .unwrap();
+
+        let maker = RunfilesMaker::from_param_file(&format!("@{}", param_file.display()));
+        assert_eq!(maker.output_dir, PathBuf::from("out dir"));
+        assert_eq!(
+            maker.filename_suffixes_to_retain,
+            BTreeSet::from([".rs".to_owned(), ".txt".to_owned()])
+        );
+        assert_eq!(
+            maker.runfiles,
+            BTreeMap::from([
+                (
+                    PathBuf::from("pkg/it's here.txt"),
+                    "_main/pkg/it's here.txt".to_owned()
+                ),
+                (PathBuf::from("pkg/lib.rs"), "_main/pkg/lib.rs".to_owned()),
+            ])
+        );
+    }
 }
@@ -378,6 +378,7 @@ load("@bazel_tools//tools/build_defs/repo:http.bzl", "http_archive")
 load(
     "//crate_universe/private:common_utils.bzl",
     "new_cargo_bazel_fn",
+    "parse_override_rules",
 )
 load("//crate_universe/private:crates_repository.bzl", "SUPPORTED_PLATFORM_TRIPLES")
 load(
@@ -505,6 +506,13 @@ def _collect_render_config(module, repository):
             if config_kwargs[null_defaults] == "":
                 config_kwargs[null_defaults] = None
 
+        # Templates are keyed by label in the tag but by name in the config.
+        for templates in ["template_overrides", "template_partials"]:
+            config_kwargs[templates] = {
+                name: str(label)
+                for label, name in config_kwargs[templates].items()
+            }
+
         config = json.decode(generate_render_config(**config_kwargs))
 
     if not config:
@@ -1042,6 +1050,15 @@ def _crate_impl(module_ctx):
             if replacement:
                 annotation_dict["override_targets"]["bin"] = str(replacement)
 
+            # There is no dict type whose values are dicts, so extra rule attributes are json encoded.
+            annotation_dict["override_rules"] = parse_override_rules(
+                annotation_dict.pop("override_rules"),
+                {
+                    kind: json.decode(attrs)
+                    for kind, attrs in annotation_dict.pop("override_rule_attrs").items()
+                },
+            )
+
             if not repositories:
                 _insert_annotation(module_annotations, crate, version, annotation_dict)
             else:
@@ -1317,6 +1334,12 @@ _ANNOTATION_NORMAL_ATTRS = {
     "override_target_proc_macro": attr.label(
         doc = "An optional alternate target to use when something depends on this crate to allow the parent repo to provide its own version of this dependency.",
     ),
+    "override_rule_attrs": attr.string_dict(
+        doc = "A dictionary of json encoded extra attributes to pass to the rules in `override_rules`, keyed the same as `override_rules`. Eg. `{\"lib\": json.encode({\"sanitizer\": \"address\"})}`.",
+    ),
+    "override_rules": attr.string_dict(
+        doc = "A dictionary of rules or macros to render instead of the default `rules_rust` rule for a kind of target. Keys can be `proc-macro`, `custom-build`, `lib`, `bin` and values are in the form '<label to .bzl>:<rule>'.",
+    ),
     "patch_args": attr.string_list(
         doc = "The `patch_args` attribute of a Bazel repository rule. See [http_archive.patch_args](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patch_args)",
     ),
@@ -1534,6 +1557,14 @@ can be found below where the supported keys for each template can be found in th
             doc = "An optional command to demonstrate how generated files should be regenerated.",
             default = "",
         ),
+        "template_overrides": attr.label_keyed_string_dict(
+            doc = "A mapping of template files to the names of the built-in templates they replace. See the `render_config` macro for the templates which can be overridden and the variables available to them.",
+            allow_files = True,
+        ),
+        "template_partials": attr.label_keyed_string_dict(
+            doc = "A mapping of template files to names they can be included or imported by from other templates.",
+            allow_files = True,
+        ),
         "vendor_mode": attr.string(
             doc = "An optional configuration for rendering content to be rendered into repositories.",
             default = "",
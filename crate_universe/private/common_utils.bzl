@@ -211,3 +211,39 @@ def parse_alias_rule(value):
         bzl = str(bzl),
         rule = rule,
     )
+
+def parse_override_rules(rules, attrs = None):
+    """Parses the `override_rules` of a crate annotation into Rust compatible `RuleOverride`s.
+
+    Args:
+        rules (dict): A mapping of target kinds to rules in the form '<label to .bzl>:<rule>'.
+        attrs (dict, optional): A mapping of target kinds to extra attributes to pass to the rule.
+
+    Returns:
+        dict: A mapping of target kinds to Rust compatible `RuleOverride`s.
+    """
+    rules = rules or {}
+    attrs = attrs or {}
+    for kind in attrs:
+        if kind not in rules:
+            fail("`override_rule_attrs` were provided for `{}` which has no entry in `override_rules`.".format(kind))
+
+    if not rules:
+        return None
+
+    overrides = {}
+    for kind, value in rules.items():
+        if kind not in ["bin", "custom-build", "lib", "proc-macro"]:
+            fail("Invalid key for `override_rules`: `{}`. Keys must be one of `bin`, `custom-build`, `lib` or `proc-macro`.".format(kind))
+
+        if value.count(":") != 2:
+            fail("Invalid value for `override_rules`.\n{}\nValues must be in the format '<label to .bzl>:<rule>'.".format(value))
+
+        split = value.rsplit(":", 1)
+        overrides[kind] = struct(
+            bzl = str(Label(split[0])),
+            rule = split[1],
+            attrs = attrs.get(kind, {}),
+        )
+
+    return overrides
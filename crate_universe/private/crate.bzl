@@ -1,6 +1,6 @@
 """Macros used for representing crates or annotations for existing crates"""
 
-load(":common_utils.bzl", "parse_alias_rule")
+load(":common_utils.bzl", "parse_alias_rule", "parse_override_rules")
 
 def _workspace_member(version, sha256 = None):
     """Define information for extra workspace members
@@ -121,7 +121,9 @@ def _annotation(
         rustc_env_files = None,
         rustc_flags = None,
         shallow_since = None,
-        override_targets = None):
+        override_targets = None,
+        override_rules = None,
+        override_rule_attrs = None):
     """A collection of extra attributes and settings for a particular crate
 
     Args:
@@ -180,6 +182,11 @@ def _annotation(
             instead of a crate registry. This flag optimizes fetching the source code.
         override_targets (dict, optional): A dictionary of alternate targets to use when something depends on this crate to allow
             the parent repo to provide its own version of this dependency. Keys can be `proc-macro`, `custom-build`, `lib`, `bin`.
+        override_rules (dict, optional): A dictionary of rules or macros to render instead of the default `rules_rust` rule
+            for a kind of target. Keys can be `proc-macro`, `custom-build`, `lib`, `bin` and values are in the form
+            '<label to .bzl>:<rule>'. The rule is passed all attributes the default rule would have been.
+        override_rule_attrs (dict, optional): A dictionary of extra attributes to pass to the rules in `override_rules`,
+            keyed the same as `override_rules`. Eg. `{"lib": {"sanitizer": "address"}}`.
 
     Returns:
         string: A json encoded string containing the specified version and separately all other inputs.
@@ -228,6 +235,7 @@ def _annotation(
             rustc_flags = rustc_flags,
             shallow_since = shallow_since,
             override_targets = override_targets,
+            override_rules = parse_override_rules(override_rules, override_rule_attrs),
         ),
     ))
 
@@ -102,7 +102,9 @@ def render_config(
         platforms_template = "@rules_rust//rust/platform:{triple}",
         regen_command = None,
         vendor_mode = None,
-        generate_rules_license_metadata = False):
+        generate_rules_license_metadata = False,
+        template_overrides = {},
+        template_partials = {}):
     """Various settings used to configure rendered outputs
 
     The template parameters each support a select number of format keys. A description of each key
@@ -117,6 +119,27 @@ def render_config(
     | `target` | The library or binary target of the crate |
     | `file` | The basename of a file |
 
+    Rendering of generated files can be customized by providing [Tera](https://keats.github.io/tera/docs/) templates
+    through `template_overrides` and `template_partials`. The templates which can be overridden and the variables
+    they're rendered with are described below. Templates are checked when they're loaded, referencing an unknown variable or
+    including an unknown template is an error.
+
+    | template | variables |
+    | --- | --- |
+    | `crate_build_file.j2` | `crate` (the crate's `CrateContext`), `default_build_file` (the BUILD file cargo-bazel would have rendered), `platforms` (a mapping of configurations to platform labels) |
+    | `module_bzl.j2` | `context` (the full rendering `Context`), `generator`, `platforms` |
+    | `vendor_module.j2` | `context` |
+    | `partials/header.j2` | |
+    | `partials/module/aliases_map.j2` | `context`, `deps_type`, `platforms` |
+    | `partials/module/deps_map.j2` | `context`, `deps_type`, `platforms` |
+    | `partials/module/repo_git.j2` | `attrs`, `context`, `crate` |
+    | `partials/module/repo_http.j2` | `attrs`, `context`, `crate` |
+
+    Every template additionally has access to `default_package_name`, `default_select_list`, `regen_command`,
+    `repository_name`, `vendor_mode` and `Null`, as well as the functions `crate_alias`, `crate_build_file`,
+    `crate_label`, `crate_repository` and `crates_module_label`. `crate_build_file.j2` has no built-in version, when
+    provided it is used to render the BUILD file of every crate.
+
     Args:
         build_file_template (str, optional): The base template to use for BUILD file names. The available format keys
             are [`{name}`, {version}`].
@@ -145,6 +168,11 @@ def render_config(
         regen_command (str, optional): An optional command to demonstrate how generated files should be regenerated.
         vendor_mode (str, optional): An optional configuration for rendirng content to be rendered into repositories.
         generate_rules_license_metadata (bool, optional): Whether to generate rules license metadata
+        template_overrides (dict, optional): A mapping of built-in template names to labels of template files which
+            replace them. Not supported by the `crates_vendor` rule.
+        template_partials (dict, optional): A mapping of names to labels of additional template files which can be
+            included or imported by other templates (Eg. `{"partials/macros.j2": "//:macros.j2"}`). Not supported by
+            the `crates_vendor` rule.
 
     Returns:
         string: A json encoded struct to match the Rust `config::RenderConfig` struct
@@ -162,6 +190,8 @@ def render_config(
         generate_target_compatible_with = generate_target_compatible_with,
         platforms_template = platforms_template,
         regen_command = regen_command,
+        template_overrides = template_overrides,
+        template_partials = template_partials,
         vendor_mode = vendor_mode,
     ))
 
@@ -289,6 +319,18 @@ def compile_config(
     if unexpected:
         fail("The following annotations use `additive_build_file` which is not supported for {}: {}".format(repository_name, unexpected))
 
+    # Load user provided templates if any have been provided.
+    for key in ["template_overrides", "template_partials"]:
+        templates = render_config.get(key)
+        if not templates:
+            continue
+        if not repository_ctx:
+            fail("The `render_config` parameter `{}` is not supported for {}".format(key, repository_name))
+        render_config.update({key: {
+            name: repository_ctx.read(Label(template))
+            for name, template in templates.items()
+        }})
+
     # Deprecated: Apply `generate_target_compatible_with` to `render_config`.
     if not generate_target_compatible_with:
         # buildifier: disable=print
@@ -1,23 +1,40 @@
 //! The lockfile::public module represents a reasonable stable API for inspecting the contents of a lockfile which others can code against.
 
-use std::collections::BTreeSet;
-use std::fs::File;
-use std::io::BufReader;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
 use std::path::Path;
+use std::str::FromStr;
 
-use anyhow::Result;
-use serde::Deserialize;
+use anyhow::{bail, Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
 
-pub use crate::config::CrateId;
-use crate::context::crate_context::{CrateDependency, Rule};
-use crate::context::{CommonAttributes, Context};
-use crate::select::Select;
+pub use crate::config::{Commitish, CrateId, DEFAULT_CRATE_LABEL_TEMPLATE};
+pub use crate::context::crate_context::CrateDependency;
+pub use crate::metadata::metadata_annotation::SourceAnnotation;
+pub use crate::select::{Select, Selectable};
+
+use crate::context::crate_context::{CrateContext, Rule};
+use crate::context::Context;
+use crate::rendering::render_crate_bazel_label;
+use crate::utils::sanitize_repository_name;
+
+/// The version of the lockfile schema written by this version of `cargo-bazel`.
+///
+/// Lockfiles which do not record a version predate versioning and are treated as version `0`.
+pub const SCHEMA_VERSION: u32 = 1;
 
 /// Parse a lockfile at a path on disk.
-pub fn parse(path: &Path) -> Result<impl CargoBazelLockfile> {
-    let reader = BufReader::new(File::open(path)?);
-    let lockfile: CargoBazelLockfileImpl = serde_json::from_reader(reader)?;
-    Ok(lockfile)
+pub fn parse(path: &Path) -> Result<Lockfile> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read lockfile {}", path.display()))?;
+    Lockfile::from_str(&content)
+        .with_context(|| format!("Failed to parse lockfile {}", path.display()))
+}
+
+/// Write a lockfile to a path on disk.
+pub fn write(lockfile: &Lockfile, path: &Path) -> Result<()> {
+    fs::write(path, lockfile.to_string_pretty()? + "\n")
+        .with_context(|| format!("Failed to write lockfile {}", path.display()))
 }
 
 /// `CargoBazelLockfile` provides a view over `cargo-bazel`'s lockfile format.
@@ -36,91 +53,298 @@ pub trait CargoBazelLockfile {
     fn crate_info(&self, crate_id: &CrateId) -> Option<CrateInfo>;
 }
 
-#[derive(Deserialize)]
-#[serde(transparent)]
-struct CargoBazelLockfileImpl(Context);
+/// The contents of a `cargo-bazel` lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// The version of the schema the lockfile was written with, if it predates versioning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schema_version: Option<u32>,
+
+    #[serde(flatten)]
+    context: Context,
+}
+
+impl FromStr for Lockfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lockfile: Lockfile = serde_json::from_str(s)?;
+        if lockfile.schema_version() > SCHEMA_VERSION {
+            bail!(
+                "Lockfile schema version {} is newer than the supported version {}",
+                lockfile.schema_version(),
+                SCHEMA_VERSION
+            );
+        }
+        Ok(lockfile)
+    }
+}
+
+impl Lockfile {
+    /// Wrap a [Context] in the current schema version for writing.
+    pub(crate) fn from_context(context: Context) -> Self {
+        Self {
+            schema_version: Some(SCHEMA_VERSION),
+            context,
+        }
+    }
+
+    /// Render the lockfile in the same format `cargo-bazel` writes it.
+    pub fn to_string_pretty(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// The version of the schema the lockfile was written with, or `0` if it predates versioning.
+    ///
+    /// Editing a lockfile keeps its version, so unversioned lockfiles are written back unversioned.
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version.unwrap_or(0)
+    }
+
+    /// The ids of all crates in the lockfile, including workspace members.
+    pub fn crate_ids(&self) -> BTreeSet<CrateId> {
+        self.context.crates.keys().cloned().collect()
+    }
+
+    /// Add a crate to the lockfile, returning the previous entry for the same id if there was one.
+    pub fn insert_crate(&mut self, info: CrateInfo) -> Option<CrateInfo> {
+        self.context.crates.insert(info.id(), info.0).map(CrateInfo)
+    }
+
+    /// Remove a crate from the lockfile, along with any references to it as a direct or binary crate.
+    pub fn remove_crate(&mut self, crate_id: &CrateId) -> Option<CrateInfo> {
+        self.context.binary_crates.remove(crate_id);
+        self.context.direct_deps.remove(crate_id);
+        self.context.direct_dev_deps.remove(crate_id);
+        self.context.crates.remove(crate_id).map(CrateInfo)
+    }
+
+    /// Crates for which binary targets are generated.
+    pub fn binary_crates(&self) -> &BTreeSet<CrateId> {
+        &self.context.binary_crates
+    }
+
+    /// Crates which workspace members depend on directly.
+    pub fn direct_deps(&self) -> &BTreeSet<CrateId> {
+        &self.context.direct_deps
+    }
+
+    /// Crates which workspace members depend on directly, for development only.
+    pub fn direct_dev_deps(&self) -> &BTreeSet<CrateId> {
+        &self.context.direct_dev_deps
+    }
+
+    /// A mapping of `cfg` expressions (the configurations of a [Select]) to the platform triples they match.
+    pub fn conditions(&self) -> BTreeMap<String, BTreeSet<String>> {
+        self.context
+            .conditions
+            .iter()
+            .map(|(cfg, triples)| {
+                (
+                    cfg.clone(),
+                    triples.iter().map(|triple| triple.to_bazel()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// All platform triples the lockfile was generated for.
+    pub fn platforms(&self) -> BTreeSet<String> {
+        self.context
+            .conditions
+            .values()
+            .flatten()
+            .map(|triple| triple.to_bazel())
+            .collect()
+    }
+
+    /// The values of `select` which apply to `platform`, a platform triple.
+    ///
+    /// Configurations which are not `cfg` expressions of the lockfile only apply if they
+    /// are equal to `platform`.
+    pub fn resolve<T: Selectable>(&self, select: &Select<T>, platform: &str) -> Vec<T::ItemType> {
+        select
+            .items()
+            .into_iter()
+            .filter(|(configuration, _)| match configuration {
+                None => true,
+                Some(configuration) => match self.context.conditions.get(configuration) {
+                    Some(triples) => triples.iter().any(|triple| triple.to_bazel() == platform),
+                    None => configuration == platform,
+                },
+            })
+            .map(|(_, item)| item)
+            .collect()
+    }
+}
 
-impl CargoBazelLockfile for CargoBazelLockfileImpl {
+impl CargoBazelLockfile for Lockfile {
     fn workspace_members(&self) -> BTreeSet<CrateId> {
-        self.0.workspace_members.keys().cloned().collect()
+        self.context.workspace_members.keys().cloned().collect()
     }
 
     fn crate_info(&self, crate_id: &CrateId) -> Option<CrateInfo> {
-        let crate_context = self.0.crates.get(crate_id)?;
-        Some(CrateInfo {
-            name: crate_context.name.clone(),
-            version: crate_context.version.clone(),
-            library_target_name: crate_context.library_target_name.clone(),
-            is_proc_macro: crate_context
-                .targets
-                .iter()
-                .any(|t| matches!(t, Rule::ProcMacro(_))),
-            common_attributes: crate_context.common_attrs.clone(),
-        })
+        self.context.crates.get(crate_id).cloned().map(CrateInfo)
     }
 }
 
 /// Information about a crate (which may be in-workspace or a dependency).
-#[derive(Deserialize, PartialEq, Eq, Debug)]
-pub struct CrateInfo {
-    name: String,
-    version: semver::Version,
-    library_target_name: Option<String>,
-    is_proc_macro: bool,
-
-    common_attributes: CommonAttributes,
-}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateInfo(CrateContext);
 
 impl CrateInfo {
+    /// The identifier of the crate.
+    pub fn id(&self) -> CrateId {
+        CrateId::new(self.0.name.clone(), self.0.version.clone())
+    }
+
     /// The name of the crate.
     pub fn name(&self) -> &str {
-        &self.name
+        &self.0.name
     }
 
     /// The version of the crate.
     pub fn version(&self) -> &semver::Version {
-        &self.version
+        &self.0.version
+    }
+
+    /// The URL of the crate's homepage or repository, if known.
+    pub fn package_url(&self) -> Option<&str> {
+        self.0.package_url.as_deref()
+    }
+
+    /// Where the crate's source is downloaded from. Workspace members have no source.
+    pub fn source(&self) -> Option<&SourceAnnotation> {
+        self.0.repository.as_ref()
+    }
+
+    /// Replace where the crate's source is downloaded from.
+    pub fn set_source(&mut self, source: Option<SourceAnnotation>) {
+        self.0.repository = source;
     }
 
     /// The name of the crate's root library target. This is the target that a dependent
     /// would get if they were to depend on this crate.
     pub fn library_target_name(&self) -> Option<&str> {
-        self.library_target_name.as_deref()
+        self.0.library_target_name.as_deref()
     }
 
     /// Whether the crate is a procedural macro.
     pub fn is_proc_macro(&self) -> bool {
-        self.is_proc_macro
+        self.0
+            .targets
+            .iter()
+            .any(|t| matches!(t, Rule::ProcMacro(_)))
+    }
+
+    /// The features enabled for the crate.
+    pub fn features(&self) -> &Select<BTreeSet<String>> {
+        &self.0.common_attrs.crate_features
+    }
+
+    /// Enable a feature of the crate, optionally only for a configuration.
+    pub fn add_feature(&mut self, feature: String, configuration: Option<String>) {
+        self.0
+            .common_attrs
+            .crate_features
+            .insert(feature, configuration);
+    }
+
+    /// The SPDX license expression of the crate.
+    pub fn license(&self) -> Option<&str> {
+        self.0.license.as_deref()
+    }
+
+    /// The SPDX license ids found in the crate's license expression.
+    pub fn license_ids(&self) -> &BTreeSet<String> {
+        &self.0.license_ids
+    }
+
+    /// The path to the crate's license file, relative to the crate's root.
+    pub fn license_file(&self) -> Option<&str> {
+        self.0.license_file.as_deref()
     }
 
     /// Dependencies required to compile the crate, without procedural macro dependencies.
     pub fn normal_deps(&self) -> Select<BTreeSet<CrateDependency>> {
-        self.common_attributes.deps.clone()
+        self.0.common_attrs.deps.clone()
     }
 
     /// Dependencies required to compile the tests for the crate, but not needed to compile the crate itself, without procedural macro dependencies.
     pub fn dev_deps(&self) -> Select<BTreeSet<CrateDependency>> {
-        self.common_attributes.deps_dev.clone()
+        self.0.common_attrs.deps_dev.clone()
     }
 
     /// Procedural macro dependencies required to compile the crate.
     pub fn proc_macro_deps(&self) -> Select<BTreeSet<CrateDependency>> {
-        self.common_attributes.proc_macro_deps.clone()
+        self.0.common_attrs.proc_macro_deps.clone()
     }
 
     /// Procedural macro dependencies required to compile the tests for the crate, but not needed to compile the crate itself.
     pub fn proc_macro_dev_deps(&self) -> Select<BTreeSet<CrateDependency>> {
-        self.common_attributes.proc_macro_deps_dev.clone()
+        self.0.common_attrs.proc_macro_deps_dev.clone()
+    }
+
+    /// Whether a `cargo_build_script` target is generated for the crate.
+    pub fn has_build_script(&self) -> bool {
+        self.0.build_script_attrs.is_some()
+    }
+
+    /// Dependencies of the crate's build script, without procedural macro dependencies.
+    pub fn build_script_deps(&self) -> Option<Select<BTreeSet<CrateDependency>>> {
+        Some(self.0.build_script_attrs.as_ref()?.deps.clone())
+    }
+
+    /// Procedural macro dependencies of the crate's build script.
+    pub fn build_script_proc_macro_deps(&self) -> Option<Select<BTreeSet<CrateDependency>>> {
+        Some(self.0.build_script_attrs.as_ref()?.proc_macro_deps.clone())
     }
+
+    /// Dependencies whose `DEP_*` link metadata is passed to the crate's build script.
+    pub fn build_script_link_deps(&self) -> Option<Select<BTreeSet<CrateDependency>>> {
+        Some(self.0.build_script_attrs.as_ref()?.link_deps.clone())
+    }
+
+    /// Environment variables set when running the crate's build script.
+    pub fn build_script_env(&self) -> Option<Select<BTreeMap<String, String>>> {
+        Some(self.0.build_script_attrs.as_ref()?.build_script_env.clone())
+    }
+
+    /// The value of the crate's `links` key.
+    pub fn links(&self) -> Option<&str> {
+        self.0.build_script_attrs.as_ref()?.links.as_deref()
+    }
+
+    /// The label of one of the crate's targets, rendered with `template` (see [DEFAULT_CRATE_LABEL_TEMPLATE]).
+    pub fn label(&self, template: &str, repository_name: &str, target: &str) -> String {
+        render_label(template, repository_name, &self.id(), target)
+    }
+}
+
+/// The label of a dependency's target, rendered with `template` (see [DEFAULT_CRATE_LABEL_TEMPLATE]).
+pub fn dependency_label(dep: &CrateDependency, template: &str, repository_name: &str) -> String {
+    render_label(template, repository_name, &dep.id, &dep.target)
+}
+
+fn render_label(template: &str, repository_name: &str, id: &CrateId, target: &str) -> String {
+    sanitize_repository_name(&render_crate_bazel_label(
+        template,
+        repository_name,
+        &id.name,
+        &id.version.to_string(),
+        target,
+    ))
 }
 
 #[cfg(test)]
 mod test {
-    use super::{parse, CargoBazelLockfile};
-    use crate::config::CrateId;
-    use crate::context::crate_context::CrateDependency;
+    use super::*;
     use semver::Version;
-    use std::collections::BTreeSet;
+
+    const MULTI_PACKAGE_LOCKFILE: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test_data/cargo_bazel_lockfile/multi_package-cargo-bazel-lock.json"
+    ));
 
     #[test]
     fn exercise_public_lockfile_api() {
@@ -164,7 +388,7 @@ mod test {
         assert_eq!(got_serde_derive.name(), "serde_derive");
         assert_eq!(got_serde_derive.version(), &Version::new(1, 0, 152));
         assert_eq!(got_serde_derive.library_target_name(), Some("serde_derive"));
-        assert!(got_serde_derive.is_proc_macro);
+        assert!(got_serde_derive.is_proc_macro());
 
         assert_eq!(
             got_pkg_a.normal_deps().values(),
@@ -220,4 +444,196 @@ mod test {
             .collect::<BTreeSet<_>>(),
         );
     }
+
+    #[test]
+    fn serde_round_trip() {
+        let mut json: serde_json::Value = serde_json::from_str(MULTI_PACKAGE_LOCKFILE).unwrap();
+        json["schema_version"] = SCHEMA_VERSION.into();
+        let parsed = Lockfile::from_str(&json.to_string()).unwrap();
+        assert_eq!(parsed.schema_version(), SCHEMA_VERSION);
+
+        let serialized = parsed.to_string_pretty().unwrap();
+        let reparsed = Lockfile::from_str(&serialized).unwrap();
+        assert_eq!(parsed, reparsed);
+        assert_eq!(serialized, reparsed.to_string_pretty().unwrap());
+
+        let json: serde_json::Value = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn unversioned_lockfile() {
+        let parsed = Lockfile::from_str(MULTI_PACKAGE_LOCKFILE).unwrap();
+        assert_eq!(parsed.schema_version(), 0);
+
+        // Editing an unversioned lockfile does not add a version to it.
+        let json: serde_json::Value =
+            serde_json::from_str(&parsed.to_string_pretty().unwrap()).unwrap();
+        assert!(json.get("schema_version").is_none());
+    }
+
+    #[test]
+    fn versioned_context() {
+        let lockfile = Lockfile::from_context(Context::default());
+        assert_eq!(lockfile.schema_version(), SCHEMA_VERSION);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&lockfile.to_string_pretty().unwrap()).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn parse_and_write() {
+        let lockfile = Lockfile::from_str(MULTI_PACKAGE_LOCKFILE).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cargo-bazel-lock.json");
+        write(&lockfile, &path).unwrap();
+        assert_eq!(parse(&path).unwrap(), lockfile);
+    }
+
+    #[test]
+    fn reject_newer_schema_version() {
+        let content = serde_json::json!({
+            "schema_version": SCHEMA_VERSION + 1,
+            "checksum": null,
+            "crates": {},
+            "binary_crates": [],
+            "workspace_members": {},
+            "conditions": {},
+            "direct_deps": [],
+            "direct_dev_deps": [],
+        })
+        .to_string();
+        assert!(Lockfile::from_str(&content).is_err());
+    }
+
+    #[test]
+    fn edit_lockfile() {
+        let mut lockfile = Lockfile::from_str(MULTI_PACKAGE_LOCKFILE).unwrap();
+        let async_process = CrateId::new("async-process".to_owned(), Version::new(1, 6, 0));
+
+        let mut info = lockfile.crate_info(&async_process).unwrap();
+        info.add_feature("extra".to_owned(), None);
+        info.set_source(Some(SourceAnnotation::Path {
+            path: "vendor/async-process".into(),
+        }));
+        lockfile.insert_crate(info);
+
+        let reparsed = Lockfile::from_str(&lockfile.to_string_pretty().unwrap()).unwrap();
+        let info = reparsed.crate_info(&async_process).unwrap();
+        assert!(info.features().values().contains(&"extra".to_owned()));
+        assert_eq!(
+            info.source(),
+            Some(&SourceAnnotation::Path {
+                path: "vendor/async-process".into()
+            })
+        );
+
+        let removed = lockfile.remove_crate(&async_process).unwrap();
+        assert_eq!(removed.id(), async_process);
+        assert!(lockfile.crate_info(&async_process).is_none());
+        lockfile.insert_crate(removed);
+        assert!(lockfile.crate_info(&async_process).is_some());
+    }
+
+    #[test]
+    fn resolve_for_platform() {
+        let lockfile = Lockfile::from_str(MULTI_PACKAGE_LOCKFILE).unwrap();
+        let info = lockfile
+            .crate_info(&CrateId::new(
+                "async-process".to_owned(),
+                Version::new(1, 6, 0),
+            ))
+            .unwrap();
+
+        let names = |platform: &str| -> BTreeSet<String> {
+            lockfile
+                .resolve(&info.normal_deps(), platform)
+                .into_iter()
+                .map(|dep| dep.id.name)
+                .collect()
+        };
+
+        let linux = names("x86_64-unknown-linux-gnu");
+        assert!(linux.contains("libc"));
+        assert!(!linux.contains("windows-sys"));
+
+        let windows = names("x86_64-pc-windows-msvc");
+        assert!(windows.contains("windows-sys"));
+        assert!(!windows.contains("libc"));
+    }
+
+    #[test]
+    fn render_labels() {
+        let dep = CrateDependency {
+            id: CrateId::new("serde".to_owned(), Version::parse("1.0.0+meta").unwrap()),
+            target: "serde".to_owned(),
+            alias: None,
+            local_path: None,
+        };
+        assert_eq!(
+            dependency_label(&dep, DEFAULT_CRATE_LABEL_TEMPLATE, "crates"),
+            "@crates__serde-1.0.0-meta//:serde"
+        );
+    }
+
+    #[test]
+    fn build_script_and_license_attributes() {
+        let content = serde_json::json!({
+            "checksum": null,
+            "crates": {
+                "foo 0.1.0": {
+                    "name": "foo",
+                    "version": "0.1.0",
+                    "repository": {
+                        "Git": {
+                            "remote": "https://github.com/example/foo.git",
+                            "commitish": {"Rev": "abcdef"},
+                        },
+                    },
+                    "targets": [
+                        {"BuildScript": {"crate_name": "build_script_build", "crate_root": "build.rs", "srcs": {"allow_empty": true, "include": ["**/*.rs"]}}},
+                    ],
+                    "common_attrs": {"edition": "2021", "version": "0.1.0"},
+                    "build_script_attrs": {
+                        "links": "foo",
+                        "build_script_env": {"common": {"FOO": "1"}, "selects": {}},
+                    },
+                    "license": "MIT",
+                    "license_ids": ["MIT"],
+                    "license_file": "LICENSE",
+                },
+            },
+            "binary_crates": [],
+            "workspace_members": {},
+            "conditions": {},
+            "direct_deps": [],
+            "direct_dev_deps": [],
+        })
+        .to_string();
+
+        let lockfile = Lockfile::from_str(&content).unwrap();
+        let info = lockfile
+            .crate_info(&CrateId::new("foo".to_owned(), Version::new(0, 1, 0)))
+            .unwrap();
+        assert!(info.has_build_script());
+        assert_eq!(info.links(), Some("foo"));
+        assert_eq!(
+            info.build_script_env().unwrap().items(),
+            vec![(None, ("FOO".to_owned(), "1".to_owned()))]
+        );
+        assert_eq!(info.license(), Some("MIT"));
+        assert_eq!(info.license_file(), Some("LICENSE"));
+        assert!(matches!(
+            info.source(),
+            Some(SourceAnnotation::Git {
+                commitish: Commitish::Rev(_),
+                ..
+            })
+        ));
+
+        let reparsed = Lockfile::from_str(&lockfile.to_string_pretty().unwrap()).unwrap();
+        assert_eq!(lockfile, reparsed);
+    }
 }
@@ -183,11 +183,12 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
             metadata_path.display()
         )
     }
-    let (cargo_metadata, cargo_lockfile) = load_metadata(metadata_path, &lockfile_path)?;
+    let (cargo_metadata, artifacts, cargo_lockfile) = load_metadata(metadata_path, &lockfile_path)?;
 
     // Annotate metadata
     let annotations = Annotations::new(
         cargo_metadata,
+        &artifacts,
         &Some(lockfile_path),
         cargo_lockfile.clone(),
         config.clone(),
@@ -18,7 +18,7 @@ use serde::de::{Deserializer, SeqAccess, Unexpected, Visitor};
 use serde::{Deserialize, Serialize, Serializer};
 
 use crate::select::{Select, Selectable};
-use crate::utils::starlark::Label;
+use crate::utils::starlark::{Label, GENERATED_RULE_ATTRS};
 use crate::utils::target_triple::TargetTriple;
 
 /// Representations of different kinds of crate vendoring into workspaces.
@@ -107,6 +107,17 @@ pub(crate) struct RenderConfig {
     /// Whether to generate cargo_toml_env_vars targets.
     /// This is expected to always be true except for bootstrapping.
     pub(crate) generate_cargo_toml_env_vars: bool,
+
+    /// User provided templates which replace the built-in template of the same name.
+    /// Keys are template names (Eg. `module_bzl.j2`) and values are template contents.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) template_overrides: BTreeMap<String, String>,
+
+    /// Additional user provided templates which can be included or imported by
+    /// other templates. Keys are the names to register templates under and values
+    /// are template contents.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) template_partials: BTreeMap<String, String>,
 }
 
 // Default is manually implemented so that the default values match the default
@@ -129,6 +140,8 @@ impl Default for RenderConfig {
             regen_command: String::default(),
             vendor_mode: Option::default(),
             generate_rules_license_metadata: default_generate_rules_license_metadata(),
+            template_overrides: BTreeMap::default(),
+            template_partials: BTreeMap::default(),
         }
     }
 }
@@ -147,8 +160,11 @@ fn default_crates_module_template() -> String {
     "//:{file}".to_owned()
 }
 
+/// The default template used to render the labels of crate targets.
+pub const DEFAULT_CRATE_LABEL_TEMPLATE: &str = "@{repository}__{name}-{version}//:{target}";
+
 fn default_crate_label_template() -> String {
-    "@{repository}__{name}-{version}//:{target}".to_owned()
+    DEFAULT_CRATE_LABEL_TEMPLATE.to_owned()
 }
 
 fn default_crate_alias_template() -> String {
@@ -175,9 +191,47 @@ fn default_generate_rules_license_metadata() -> bool {
     false
 }
 
+/// A rule or macro used to render one of a crate's targets in place of the default
+/// `rules_rust` rule. Eg. a `rust_library` wrapper applying extra aspects and tags.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct RuleOverride {
+    /// The label of the `.bzl` file defining `rule`.
+    /// Eg. `@//:rust.bzl`
+    pub(crate) bzl: String,
+
+    /// The name of the rule or macro to render.
+    pub(crate) rule: String,
+
+    /// Additional attributes to pass to the rule.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "deserialize_rule_override_attrs"
+    )]
+    pub(crate) attrs: BTreeMap<String, serde_json::Value>,
+}
+
+fn deserialize_rule_override_attrs<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, serde_json::Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let attrs = BTreeMap::<String, serde_json::Value>::deserialize(deserializer)?;
+    if let Some(name) = attrs
+        .keys()
+        .find(|name| GENERATED_RULE_ATTRS.contains(&name.as_str()))
+    {
+        return Err(serde::de::Error::custom(format!(
+            "`{name}` is generated for every target and cannot be set as an override rule attribute"
+        )));
+    }
+    Ok(attrs)
+}
+
 /// A representation of some Git identifier used to represent the "revision" or "pin" of a checkout.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub(crate) enum Commitish {
+pub enum Commitish {
     /// From a tag.
     Tag(String),
 
@@ -364,6 +418,9 @@ pub(crate) struct CrateAnnotations {
 
     /// The crates to use instead of the generated one.
     pub(crate) override_targets: Option<BTreeMap<String, Label>>,
+
+    /// Rules to render instead of the default ones, keyed the same as `override_targets`.
+    pub(crate) override_rules: Option<BTreeMap<String, RuleOverride>>,
 }
 
 macro_rules! joined_extra_member {
@@ -441,6 +498,7 @@ impl Add for CrateAnnotations {
             extra_aliased_targets: joined_extra_member!(self.extra_aliased_targets, rhs.extra_aliased_targets, BTreeMap::new, BTreeMap::extend),
             alias_rule: self.alias_rule.or(rhs.alias_rule),
             override_targets: self.override_targets.or(rhs.override_targets),
+            override_rules: joined_extra_member!(self.override_rules, rhs.override_rules, BTreeMap::new, BTreeMap::extend),
         };
 
         output
@@ -1002,4 +1060,26 @@ mod test {
             "//custom/platform:{triple}"
         );
     }
+
+    #[test]
+    fn deserialize_rule_override_rejects_generated_attrs() {
+        let rule_override: RuleOverride = serde_json::from_value(serde_json::json!({
+            "bzl": "@//:rust.bzl",
+            "rule": "my_rust_library",
+            "attrs": {"aspect_hints": ["@//:hint"]},
+        }))
+        .unwrap();
+        assert_eq!(
+            rule_override.attrs,
+            BTreeMap::from([("aspect_hints".to_owned(), serde_json::json!(["@//:hint"]))])
+        );
+
+        let error = serde_json::from_value::<RuleOverride>(serde_json::json!({
+            "bzl": "@//:rust.bzl",
+            "rule": "my_rust_library",
+            "attrs": {"deps": ["@//:dep"]},
+        }))
+        .unwrap_err();
+        assert!(error.to_string().contains("`deps`"), "{error}");
+    }
 }
@@ -9,6 +9,7 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::Result;
+use cargo_metadata::PackageId;
 use serde::{Deserialize, Serialize};
 
 use crate::config::{CrateId, RenderConfig};
@@ -62,6 +63,20 @@ impl Context {
     }
 
     pub(crate) fn new(annotations: Annotations, sources_are_present: bool) -> anyhow::Result<Self> {
+        // Binaries of artifact dependencies must be generated for dependents to use them.
+        let artifact_packages: BTreeSet<PackageId> = annotations
+            .metadata
+            .crates
+            .values()
+            .flat_map(|annotation| {
+                let deps = &annotation.deps;
+                let mut artifacts = deps.artifact_deps.values();
+                artifacts.extend(deps.build_artifact_deps.values());
+                artifacts
+            })
+            .map(|artifact| artifact.package_id)
+            .collect();
+
         // Build a map of crate contexts
         let crates: BTreeMap<CrateId, CrateContext> = annotations
             .metadata
@@ -72,9 +87,13 @@ impl Context {
                     annotation,
                     &annotations.metadata.packages,
                     &annotations.lockfile.crates,
+                    &annotations.metadata.workspace_members,
                     &annotations.pairred_extras,
                     &annotations.metadata.workspace_metadata.tree_metadata,
-                    annotations.config.generate_binaries,
+                    &annotations.metadata.workspace_metadata.profiles,
+                    &annotations.metadata.workspace_metadata.lints,
+                    annotations.config.generate_binaries
+                        || artifact_packages.contains(&annotation.node.id),
                     annotations.config.generate_build_scripts,
                     sources_are_present,
                 )?;
@@ -295,10 +314,12 @@ mod test {
     use semver::Version;
 
     use crate::config::Config;
+    use crate::metadata::ArtifactDependencies;
 
     fn mock_context_common() -> Context {
         let annotations = Annotations::new(
             crate::test::metadata::common(),
+            &ArtifactDependencies::default(),
             &None,
             crate::test::lockfile::common(),
             Config::default(),
@@ -312,6 +333,7 @@ mod test {
     fn mock_context_aliases() -> Context {
         let annotations = Annotations::new(
             crate::test::metadata::alias(),
+            &ArtifactDependencies::default(),
             &None,
             crate::test::lockfile::alias(),
             Config::default(),
@@ -325,6 +347,7 @@ mod test {
     fn mock_context_workspace_build_scripts_deps() -> Context {
         let annotations = Annotations::new(
             crate::test::metadata::workspace_build_scripts_deps(),
+            &ArtifactDependencies::default(),
             &None,
             crate::test::lockfile::workspace_build_scripts_deps(),
             Config {
@@ -4,11 +4,13 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use camino::Utf8PathBuf;
 use cargo_metadata::{Node, Package, PackageId};
+use cargo_toml::{DebugSetting, LintGroups, LintLevel, LtoSetting, Profile, Profiles};
 use serde::{Deserialize, Serialize};
 
-use crate::config::{AliasRule, CrateId, GenBinaries};
+use crate::config::{AliasRule, CrateId, GenBinaries, RuleOverride};
 use crate::metadata::{
-    CrateAnnotation, Dependency, PairedExtras, SourceAnnotation, TreeResolverMetadata,
+    ArtifactDependency, CrateAnnotation, Dependency, PairedExtras, SourceAnnotation,
+    TreeResolverMetadata,
 };
 use crate::select::Select;
 use crate::utils::sanitize_module_name;
@@ -33,6 +35,22 @@ pub struct CrateDependency {
     pub(crate) local_path: Option<Utf8PathBuf>,
 }
 
+/// A binary provided by an artifact dependency (`artifact = "bin"`).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct CrateArtifact {
+    /// The binary target of the dependency.
+    pub(crate) dependency: CrateDependency,
+
+    /// The `CARGO_BIN_FILE_*` environment variables Cargo would set to the path of the binary.
+    pub(crate) env_vars: BTreeSet<String>,
+
+    /// Whether or not the binary is built for the target platform (`target = ...`)
+    /// instead of the exec platform. Only relevant for build script dependencies.
+    /// Note that an explicit target triple is treated the same as `target = "target"`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) for_target: bool,
+}
+
 #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub(crate) struct TargetAttributes {
@@ -121,6 +139,9 @@ pub(crate) struct CommonAttributes {
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) deps_dev: Select<BTreeSet<CrateDependency>>,
 
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) artifacts: Select<BTreeSet<CrateArtifact>>,
+
     pub(crate) edition: String,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -144,6 +165,10 @@ pub(crate) struct CommonAttributes {
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) rustc_flags: Select<Vec<String>>,
 
+    /// Settings from the Cargo workspace's `[profile.*]` tables, keyed by profile name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) profiles: BTreeMap<String, ProfileSettings>,
+
     pub(crate) version: String,
 
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -163,6 +188,7 @@ impl Default for CommonAttributes {
             deps: Default::default(),
             extra_deps: Default::default(),
             deps_dev: Default::default(),
+            artifacts: Default::default(),
             edition: Default::default(),
             linker_script: Default::default(),
             proc_macro_deps: Default::default(),
@@ -171,6 +197,7 @@ impl Default for CommonAttributes {
             rustc_env: Default::default(),
             rustc_env_files: Default::default(),
             rustc_flags: Default::default(),
+            profiles: Default::default(),
             version: Default::default(),
             tags: Default::default(),
         }
@@ -203,6 +230,9 @@ pub(crate) struct BuildScriptAttributes {
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) extra_deps: Select<BTreeSet<Label>>,
 
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) artifacts: Select<BTreeSet<CrateArtifact>>,
+
     // TODO: refactor a crate with a build.rs file from two into three bazel
     // rules in order to deduplicate link_dep information. Currently as the
     // crate depends upon the build.rs file, the build.rs cannot find the
@@ -276,6 +306,7 @@ impl Default for BuildScriptAttributes {
             data_glob: BTreeSet::from(["**".to_owned()]),
             deps: Default::default(),
             extra_deps: Default::default(),
+            artifacts: Default::default(),
             link_deps: Default::default(),
             extra_link_deps: Default::default(),
             build_script_env: Default::default(),
@@ -294,6 +325,156 @@ impl Default for BuildScriptAttributes {
     }
 }
 
+/// The subset of a [Cargo profile](https://doc.rust-lang.org/cargo/reference/profiles.html)
+/// which can be expressed as `rustc` flags.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ProfileSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) opt_level: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) debug: Option<u8>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) codegen_units: Option<u16>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) lto: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) panic: Option<String>,
+}
+
+impl ProfileSettings {
+    /// The Cargo profiles which are translated and the Bazel `compilation_mode`s they apply to.
+    pub(crate) const PROFILE_COMPILATION_MODES: [(&'static str, &'static [&'static str]); 2] =
+        [("dev", &["dbg", "fastbuild"]), ("release", &["opt"])];
+
+    /// Resolve the settings of each translated profile for a given package. Per-package
+    /// overrides are applied on top of the profile using the same precedence as Cargo:
+    /// `"*"` (only for non-workspace members), then `name`, then `name@version`.
+    fn for_package(
+        profiles: &Profiles,
+        package: &Package,
+        is_workspace_member: bool,
+    ) -> BTreeMap<String, ProfileSettings> {
+        let package_keys = [
+            (!is_workspace_member).then(|| "*".to_owned()),
+            Some(package.name.clone()),
+            Some(format!("{}@{}", package.name, package.version)),
+        ];
+
+        Self::PROFILE_COMPILATION_MODES
+            .iter()
+            .filter_map(|(name, _)| {
+                let profile = match *name {
+                    "dev" => profiles.dev.as_ref(),
+                    "release" => profiles.release.as_ref(),
+                    _ => None,
+                }?;
+
+                let mut settings = ProfileSettings::default();
+                settings.apply(profile);
+                for key in package_keys.iter().flatten() {
+                    // Overrides which fail to parse are rejected by Cargo during splicing.
+                    if let Some(Ok(package_profile)) = profile
+                        .package
+                        .get(key)
+                        .map(|value| value.clone().try_into::<Profile>())
+                    {
+                        settings.apply(&package_profile);
+                    }
+                }
+
+                (settings != ProfileSettings::default()).then(|| (name.to_string(), settings))
+            })
+            .collect()
+    }
+
+    fn apply(&mut self, profile: &Profile) {
+        if let Some(opt_level) = &profile.opt_level {
+            self.opt_level = match opt_level {
+                toml::Value::String(level) => Some(level.clone()),
+                level => Some(level.to_string()),
+            };
+        }
+        if let Some(debug) = &profile.debug {
+            self.debug = Some(match debug {
+                DebugSetting::None => 0,
+                DebugSetting::Lines => 1,
+                DebugSetting::Full => 2,
+            });
+        }
+        if let Some(codegen_units) = profile.codegen_units {
+            self.codegen_units = Some(codegen_units);
+        }
+        if let Some(lto) = &profile.lto {
+            self.lto = match lto {
+                LtoSetting::None => Some("off".to_owned()),
+                LtoSetting::ThinLocal => None,
+                LtoSetting::Thin => Some("thin".to_owned()),
+                LtoSetting::Fat => Some("fat".to_owned()),
+            };
+        }
+        if let Some(panic) = &profile.panic {
+            self.panic = Some(panic.clone());
+        }
+    }
+
+    /// The `rustc` flags for these settings. `lto` is only meaningful when linking a final
+    /// artifact and `panic` does not apply to proc-macros, matching Cargo's behavior.
+    pub(crate) fn rustc_flags(&self, is_binary: bool, is_proc_macro: bool) -> Vec<String> {
+        let mut flags = Vec::new();
+        if let Some(opt_level) = &self.opt_level {
+            flags.push(format!("-Copt-level={opt_level}"));
+        }
+        if let Some(debug) = self.debug {
+            flags.push(format!("-Cdebuginfo={debug}"));
+        }
+        if let Some(codegen_units) = self.codegen_units {
+            flags.push(format!("-Ccodegen-units={codegen_units}"));
+        }
+        if let Some(lto) = self.lto.as_ref().filter(|_| is_binary) {
+            flags.push(format!("-Clto={lto}"));
+        }
+        if let Some(panic) = self.panic.as_ref().filter(|_| !is_proc_macro) {
+            flags.push(format!("-Cpanic={panic}"));
+        }
+        flags
+    }
+}
+
+/// Translates the `[lints]` tables of a package into `rustc` flags, using the same mapping
+/// as `cargo_toml_info` does for first-party crates. Lints are ordered by priority so lints
+/// with a higher priority override the groups they belong to.
+pub(crate) fn lint_rustc_flags(lints: &LintGroups) -> Vec<String> {
+    let mut flags: Vec<(i8, String)> = lints
+        .iter()
+        .filter_map(|(group, lints)| {
+            let prefix = match group.as_str() {
+                "rust" => "",
+                "clippy" => "clippy::",
+                "rustdoc" => "rustdoc::",
+                _ => return None,
+            };
+            Some(lints.iter().map(move |(name, lint)| {
+                let level = match lint.level {
+                    LintLevel::Allow => "allow",
+                    LintLevel::Warn => "warn",
+                    LintLevel::ForceWarn => "force-warn",
+                    LintLevel::Deny => "deny",
+                    LintLevel::Forbid => "forbid",
+                };
+                (lint.priority, format!("--{level}={prefix}{name}"))
+            }))
+        })
+        .flatten()
+        .collect();
+    flags.sort_by_key(|(priority, _)| *priority);
+    flags.into_iter().map(|(_, flag)| flag).collect()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct CrateContext {
     /// The package name of the current crate
@@ -367,6 +548,11 @@ pub(crate) struct CrateContext {
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     #[serde(default)]
     pub(crate) override_targets: BTreeMap<String, Label>,
+
+    /// Rules to render instead of the default rule for each kind of target.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde(default)]
+    pub(crate) override_rules: BTreeMap<String, RuleOverride>,
 }
 
 impl CrateContext {
@@ -375,8 +561,11 @@ impl CrateContext {
         annotation: &CrateAnnotation,
         packages: &BTreeMap<PackageId, Package>,
         source_annotations: &BTreeMap<PackageId, SourceAnnotation>,
+        workspace_members: &BTreeSet<PackageId>,
         extras: &BTreeMap<CrateId, PairedExtras>,
         resolver_data: &TreeResolverMetadata,
+        profiles: &Profiles,
+        lints: &BTreeMap<CrateId, LintGroups>,
         include_binaries: bool,
         include_build_scripts: bool,
         sources_are_present: bool,
@@ -403,6 +592,46 @@ impl CrateContext {
             }
         };
 
+        let new_crate_artifact = |artifact: ArtifactDependency| -> BTreeSet<CrateArtifact> {
+            let pkg = &packages[&artifact.package_id];
+            let dep_env_name = artifact.name.to_uppercase().replace('-', "_");
+            artifact
+                .bins
+                .iter()
+                .map(|bin| {
+                    let mut env_vars =
+                        BTreeSet::from([format!("CARGO_BIN_FILE_{dep_env_name}_{bin}")]);
+                    // Cargo provides a shorthand for the binary named after the package.
+                    if *bin == pkg.name {
+                        env_vars.insert(format!("CARGO_BIN_FILE_{dep_env_name}"));
+                    }
+                    CrateArtifact {
+                        dependency: CrateDependency {
+                            id: CrateId::new(pkg.name.clone(), pkg.version.clone()),
+                            target: format!("{bin}__bin"),
+                            alias: None,
+                            local_path: match source_annotations.get(&artifact.package_id) {
+                                Some(SourceAnnotation::Path { path }) => Some(path.clone()),
+                                _ => None,
+                            },
+                        },
+                        env_vars,
+                        for_target: artifact.target.is_some(),
+                    }
+                })
+                .collect()
+        };
+        let collect_artifacts =
+            |artifacts: &Select<BTreeSet<ArtifactDependency>>| -> Select<BTreeSet<CrateArtifact>> {
+                let mut select = Select::default();
+                for (configuration, artifact) in artifacts.items() {
+                    for crate_artifact in new_crate_artifact(artifact) {
+                        select.insert(crate_artifact, configuration.clone());
+                    }
+                }
+                select
+            };
+
         // Convert the dependencies into renderable strings
         let deps = annotation.deps.normal_deps.clone().map(new_crate_dep);
         let deps_dev = annotation.deps.normal_dev_deps.clone().map(new_crate_dep);
@@ -426,6 +655,8 @@ impl CrateContext {
             })
             .unwrap_or_default();
 
+        let is_workspace_member = workspace_members.contains(&package.id);
+
         // Gather all "common" attributes
         let mut common_attrs = CommonAttributes {
             crate_features,
@@ -434,10 +665,17 @@ impl CrateContext {
             edition: package.edition.as_str().to_string(),
             proc_macro_deps,
             proc_macro_deps_dev,
+            artifacts: collect_artifacts(&annotation.deps.artifact_deps),
+            profiles: ProfileSettings::for_package(profiles, package, is_workspace_member),
             version: package.version.to_string(),
             ..Default::default()
         };
 
+        // Only workspace members have lints, Cargo caps the lints of all other packages.
+        if let Some(lints) = lints.get(&current_crate_id) {
+            common_attrs.rustc_flags = Select::from_value(lint_rustc_flags(lints));
+        }
+
         // Locate extra settings for the current package.
         let package_extra = extras
             .iter()
@@ -515,6 +753,7 @@ impl CrateContext {
 
             Some(BuildScriptAttributes {
                 deps: build_deps,
+                artifacts: collect_artifacts(&annotation.deps.build_artifact_deps),
                 link_deps: build_link_deps,
                 proc_macro_deps: build_proc_macro_deps,
                 links: package.links.clone(),
@@ -564,6 +803,7 @@ impl CrateContext {
             extra_aliased_targets: BTreeMap::new(),
             alias_rule: None,
             override_targets: BTreeMap::new(),
+            override_rules: BTreeMap::new(),
         }
         .with_overrides(extras))
     }
@@ -765,6 +1005,10 @@ impl CrateContext {
             if let Some(override_targets) = &crate_extra.override_targets {
                 self.override_targets.extend(override_targets.clone());
             }
+
+            if let Some(override_rules) = &crate_extra.override_rules {
+                self.override_rules.extend(override_rules.clone());
+            }
         }
 
         self
@@ -902,11 +1146,12 @@ mod test {
     use semver::Version;
 
     use crate::config::CrateAnnotations;
-    use crate::metadata::{Annotations, CargoTreeEntry};
+    use crate::metadata::{Annotations, ArtifactDependencies, CargoTreeEntry};
 
     fn common_annotations() -> Annotations {
         Annotations::new(
             crate::test::metadata::common(),
+            &ArtifactDependencies::default(),
             &None,
             crate::test::lockfile::common(),
             crate::config::Config::default(),
@@ -930,8 +1175,11 @@ mod test {
             crate_annotation,
             &annotations.metadata.packages,
             &annotations.lockfile.crates,
+            &annotations.metadata.workspace_members,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.metadata.workspace_metadata.profiles,
+            &annotations.metadata.workspace_metadata.lints,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -949,6 +1197,202 @@ mod test {
         );
     }
 
+    #[test]
+    fn profile_settings_for_package() {
+        let annotations = common_annotations();
+        let package = &annotations.metadata.packages[&PackageId {
+            repr: "path+file://{TEMP_DIR}/common#0.1.0".to_owned(),
+        }];
+
+        let profiles: Profiles = toml::from_str(indoc::indoc! {r#"
+            [dev]
+            debug = "line-tables-only"
+
+            [dev.package."*"]
+            opt-level = 2
+
+            [dev.package.common]
+            codegen-units = 4
+
+            [release]
+            lto = true
+            panic = "abort"
+        "#})
+        .unwrap();
+
+        let member_settings = ProfileSettings::for_package(&profiles, package, true);
+        assert_eq!(
+            member_settings,
+            BTreeMap::from([
+                (
+                    "dev".to_owned(),
+                    ProfileSettings {
+                        debug: Some(1),
+                        codegen_units: Some(4),
+                        ..ProfileSettings::default()
+                    }
+                ),
+                (
+                    "release".to_owned(),
+                    ProfileSettings {
+                        lto: Some("fat".to_owned()),
+                        panic: Some("abort".to_owned()),
+                        ..ProfileSettings::default()
+                    }
+                ),
+            ])
+        );
+
+        // The `"*"` override only applies to packages outside of the workspace.
+        let dependency_settings = ProfileSettings::for_package(&profiles, package, false);
+        assert_eq!(dependency_settings["dev"].opt_level, Some("2".to_owned()));
+        assert_eq!(
+            dependency_settings["release"].rustc_flags(false, false),
+            vec!["-Cpanic=abort".to_owned()]
+        );
+        assert_eq!(
+            dependency_settings["release"].rustc_flags(true, false),
+            vec!["-Clto=fat".to_owned(), "-Cpanic=abort".to_owned()]
+        );
+    }
+
+    #[test]
+    fn profile_settings_follow_workspace_members() {
+        let annotations = common_annotations();
+        let package_id = PackageId {
+            repr: "path+file://{TEMP_DIR}/common#0.1.0".to_owned(),
+        };
+        let crate_annotation = &annotations.metadata.crates[&package_id];
+
+        let profiles: Profiles = toml::from_str(indoc::indoc! {r#"
+            [dev.package."*"]
+            opt-level = 2
+        "#})
+        .unwrap();
+
+        let new_context = |workspace_members: &BTreeSet<PackageId>| {
+            CrateContext::new(
+                crate_annotation,
+                &annotations.metadata.packages,
+                &annotations.lockfile.crates,
+                workspace_members,
+                &annotations.pairred_extras,
+                &annotations.metadata.workspace_metadata.tree_metadata,
+                &profiles,
+                &BTreeMap::new(),
+                false,
+                false,
+                false,
+            )
+            .unwrap()
+        };
+
+        // A path dependency without a source annotation is still not a member
+        // unless cargo reports it as one.
+        let member = new_context(&BTreeSet::from([package_id.clone()]));
+        assert!(!member.common_attrs.profiles.contains_key("dev"));
+
+        let non_member = new_context(&BTreeSet::new());
+        assert_eq!(
+            non_member.common_attrs.profiles["dev"].opt_level,
+            Some("2".to_owned())
+        );
+    }
+
+    #[test]
+    fn lint_rustc_flags_by_priority() {
+        let lints: LintGroups = toml::from_str(indoc::indoc! {r#"
+            [rust]
+            unsafe_code = "forbid"
+            unused = { level = "deny", priority = -1 }
+
+            [clippy]
+            pedantic = { level = "warn", priority = -1 }
+            module_name_repetitions = "allow"
+
+            [rustdoc]
+            broken_intra_doc_links = "force-warn"
+        "#})
+        .unwrap();
+
+        assert_eq!(
+            lint_rustc_flags(&lints),
+            vec![
+                "--warn=clippy::pedantic",
+                "--deny=unused",
+                "--allow=clippy::module_name_repetitions",
+                "--forbid=unsafe_code",
+                "--force-warn=rustdoc::broken_intra_doc_links",
+            ]
+        );
+    }
+
+    #[test]
+    fn context_with_lints() {
+        let annotations = common_annotations();
+        let crate_annotation = &annotations.metadata.crates[&PackageId {
+            repr: "path+file://{TEMP_DIR}/common#0.1.0".to_owned(),
+        }];
+
+        let new_context = |lints: &BTreeMap<CrateId, LintGroups>| {
+            CrateContext::new(
+                crate_annotation,
+                &annotations.metadata.packages,
+                &annotations.lockfile.crates,
+                &annotations.metadata.workspace_members,
+                &annotations.pairred_extras,
+                &annotations.metadata.workspace_metadata.tree_metadata,
+                &annotations.metadata.workspace_metadata.profiles,
+                lints,
+                false,
+                false,
+                false,
+            )
+            .unwrap()
+        };
+        let common = CrateId::new("common".to_owned(), semver::Version::new(0, 1, 0));
+
+        // Lints inherited from `[workspace.lints]` are resolved during splicing.
+        let workspace: cargo_toml::Manifest = toml::from_str(indoc::indoc! {r#"
+            [workspace]
+            members = ["common"]
+
+            [workspace.lints.rust]
+            missing_docs = "warn"
+        "#})
+        .unwrap();
+        let context = new_context(&BTreeMap::from([(
+            common.clone(),
+            workspace.workspace.unwrap().lints,
+        )]));
+        assert_eq!(
+            context.common_attrs.rustc_flags,
+            Select::from_value(vec!["--warn=missing_docs".to_owned()])
+        );
+
+        let package: cargo_toml::Manifest = toml::from_str(indoc::indoc! {r#"
+            [package]
+            name = "common"
+            version = "0.1.0"
+
+            [lints.clippy]
+            unwrap_used = "deny"
+        "#})
+        .unwrap();
+        let cargo_toml::Inheritable::Set(package_lints) = package.lints else {
+            panic!("the package does not inherit its lints");
+        };
+        let context = new_context(&BTreeMap::from([(common, package_lints)]));
+        assert_eq!(
+            context.common_attrs.rustc_flags,
+            Select::from_value(vec!["--deny=clippy::unwrap_used".to_owned()])
+        );
+
+        // Crates without lints have no lint flags.
+        let context = new_context(&BTreeMap::new());
+        assert!(context.common_attrs.rustc_flags.is_empty());
+    }
+
     #[test]
     fn context_with_overrides() {
         let annotations = common_annotations();
@@ -979,8 +1423,11 @@ mod test {
             crate_annotation,
             &annotations.metadata.packages,
             &annotations.lockfile.crates,
+            &annotations.metadata.workspace_members,
             &pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.metadata.workspace_metadata.profiles,
+            &annotations.metadata.workspace_metadata.lints,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1012,6 +1459,7 @@ mod test {
     fn build_script_annotations() -> Annotations {
         Annotations::new(
             crate::test::metadata::build_scripts(),
+            &ArtifactDependencies::default(),
             &None,
             crate::test::lockfile::build_scripts(),
             crate::config::Config::default(),
@@ -1023,6 +1471,7 @@ mod test {
     fn crate_type_annotations() -> Annotations {
         Annotations::new(
             crate::test::metadata::crate_types(),
+            &ArtifactDependencies::default(),
             &None,
             crate::test::lockfile::crate_types(),
             crate::config::Config::default(),
@@ -1049,8 +1498,11 @@ mod test {
             crate_annotation,
             &annotations.metadata.packages,
             &annotations.lockfile.crates,
+            &annotations.metadata.workspace_members,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.metadata.workspace_metadata.profiles,
+            &annotations.metadata.workspace_metadata.lints,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1097,8 +1549,11 @@ mod test {
             crate_annotation,
             &annotations.metadata.packages,
             &annotations.lockfile.crates,
+            &annotations.metadata.workspace_members,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.metadata.workspace_metadata.profiles,
+            &annotations.metadata.workspace_metadata.lints,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1134,8 +1589,11 @@ mod test {
             crate_annotation,
             &annotations.metadata.packages,
             &annotations.lockfile.crates,
+            &annotations.metadata.workspace_members,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.metadata.workspace_metadata.profiles,
+            &annotations.metadata.workspace_metadata.lints,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1177,8 +1635,11 @@ mod test {
             crate_annotation,
             &annotations.metadata.packages,
             &annotations.lockfile.crates,
+            &annotations.metadata.workspace_members,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.metadata.workspace_metadata.profiles,
+            &annotations.metadata.workspace_metadata.lints,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1310,8 +1771,11 @@ mod test {
             crate_annotation,
             &annotations.metadata.packages,
             &annotations.lockfile.crates,
+            &annotations.metadata.workspace_members,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.metadata.workspace_metadata.profiles,
+            &annotations.metadata.workspace_metadata.lints,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1328,6 +1792,7 @@ mod test {
     fn absolute_paths_for_srcs_are_errors() {
         let annotations = Annotations::new(
             crate::test::metadata::abspath(),
+            &ArtifactDependencies::default(),
             &None,
             crate::test::lockfile::abspath(),
             crate::config::Config::default(),
@@ -1346,8 +1811,11 @@ mod test {
             crate_annotation,
             &annotations.metadata.packages,
             &annotations.lockfile.crates,
+            &annotations.metadata.workspace_members,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.metadata.workspace_metadata.profiles,
+            &annotations.metadata.workspace_metadata.lints,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -11,6 +11,7 @@ use hex::ToHex;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest as Sha2Digest, Sha256};
 
+use crate::api::lockfile::Lockfile;
 use crate::config::Config;
 use crate::context::Context;
 use crate::metadata::Cargo;
@@ -35,9 +36,9 @@ pub(crate) fn lock_context(
     })
 }
 
-/// Write a [crate::context::Context] to disk
+/// Write a [crate::context::Context] to disk, along with the lockfile schema version.
 pub(crate) fn write_lockfile(lockfile: Context, path: &Path, dry_run: bool) -> Result<()> {
-    let content = serde_json::to_string_pretty(&lockfile)?;
+    let content = Lockfile::from_context(lockfile).to_string_pretty()?;
 
     if dry_run {
         println!("{content:#?}");
@@ -231,6 +232,33 @@ mod test {
 
     use std::collections::BTreeSet;
 
+    #[test]
+    fn write_lockfile_with_schema_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cargo-bazel-lock.json");
+
+        write_lockfile(Context::default(), &path, false).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(json["schema_version"], crate::api::lockfile::SCHEMA_VERSION);
+
+        // The version is not part of the context, so it does not change the digest.
+        let context = Context::try_from_path(&path).unwrap();
+        assert_eq!(context, Context::default());
+        let digest = |context: &Context| {
+            Digest::compute(
+                context,
+                &Config::default(),
+                &SplicingMetadata::default(),
+                "0.1.0",
+                "cargo 1.57.0 (b2e52d7ca 2021-10-21)",
+                "rustc 1.57.0 (f1edd0429 2021-11-29)",
+            )
+        };
+        assert_eq!(digest(&context), digest(&Context::default()));
+    }
+
     #[test]
     fn simple_digest() {
         let context = Context::default();
@@ -292,7 +320,7 @@ mod test {
         );
 
         assert_eq!(
-            Digest("17a426b0cb5c1eff5114997a35562bc8560afd365ec80ae31b3dcc449ee1fa60".to_owned()),
+            Digest("3546123a989f7aea79d734b9ee85df07540aeb4321c29f3af5fb1d0767164562".to_owned()),
             digest,
         );
     }
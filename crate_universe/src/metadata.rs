@@ -3,7 +3,7 @@
 mod cargo_bin;
 mod cargo_tree_resolver;
 mod dependency;
-mod metadata_annotation;
+pub(crate) mod metadata_annotation;
 
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -11,6 +11,7 @@ use std::str::FromStr;
 
 use anyhow::{bail, Context, Result};
 use camino::Utf8Path;
+use cargo_metadata::MetadataCommand;
 use tracing::debug;
 
 pub(crate) use self::cargo_bin::*;
@@ -280,17 +281,56 @@ impl VendorGenerator {
 pub(crate) fn load_metadata(
     metadata_path: &Path,
     lockfile_path: &Path,
-) -> Result<(cargo_metadata::Metadata, cargo_lock::Lockfile)> {
+) -> Result<(
+    cargo_metadata::Metadata,
+    ArtifactDependencies,
+    cargo_lock::Lockfile,
+)> {
     let content = fs::read_to_string(metadata_path)
         .with_context(|| format!("Failed to load Cargo Metadata: {}", metadata_path.display()))?;
 
-    let metadata =
-        serde_json::from_str(&content).context("Unable to deserialize Cargo metadata")?;
+    let (metadata, artifacts) = parse_metadata(&content)?;
 
     let lockfile = cargo_lock::Lockfile::load(lockfile_path)
         .with_context(|| format!("Failed to load lockfile: {}", lockfile_path.display()))?;
 
-    Ok((metadata, lockfile))
+    Ok((metadata, artifacts, lockfile))
+}
+
+/// Run a `cargo metadata` command, keeping the [ArtifactDependencies] which
+/// [MetadataCommand::exec] would discard.
+pub(crate) fn exec_metadata(
+    command: &MetadataCommand,
+) -> Result<(cargo_metadata::Metadata, ArtifactDependencies)> {
+    let output = command
+        .cargo_command()
+        .output()
+        .context("Failed to spawn `cargo metadata`")?;
+    if !output.status.success() {
+        bail!(
+            "`cargo metadata` failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let stdout =
+        String::from_utf8(output.stdout).context("`cargo metadata` output is not UTF-8")?;
+    let json = stdout
+        .lines()
+        .find(|line| line.starts_with('{'))
+        .context("`cargo metadata` did not produce any output")?;
+
+    parse_metadata(json)
+}
+
+fn parse_metadata(content: &str) -> Result<(cargo_metadata::Metadata, ArtifactDependencies)> {
+    let value: serde_json::Value =
+        serde_json::from_str(content).context("Unable to deserialize Cargo metadata")?;
+
+    let artifacts = ArtifactDependencies::from_metadata_json(&value)?;
+    let metadata = serde_json::from_value(value).context("Unable to deserialize Cargo metadata")?;
+
+    Ok((metadata, artifacts))
 }
 
 #[cfg(test)]
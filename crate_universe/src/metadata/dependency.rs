@@ -2,12 +2,13 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use cargo_metadata::{
     DependencyKind, Metadata as CargoMetadata, Node, NodeDep, Package, PackageId, Target,
 };
 use cargo_platform::Platform;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 use crate::config::CrateId;
 use crate::metadata::TreeResolverMetadata;
@@ -27,6 +28,125 @@ pub(crate) struct Dependency {
     pub(crate) alias: Option<String>,
 }
 
+/// A binary [artifact dependency](https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#artifact-dependencies)
+/// (`artifact = "bin"`).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct ArtifactDependency {
+    /// The PackageId of the dependency
+    pub(crate) package_id: PackageId,
+
+    /// The name of the dependency from the perspective of the current package.
+    pub(crate) name: String,
+
+    /// The names of the binary targets requested from the dependency.
+    pub(crate) bins: BTreeSet<String>,
+
+    /// The `target` the artifact is built for when it's not the default
+    /// (either `"target"` or a target triple).
+    pub(crate) target: Option<String>,
+}
+
+/// The `artifact` table Cargo reports for a dependency in `cargo metadata`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub(crate) struct ArtifactMetadata {
+    /// The requested artifact kinds, e.g. `bin`, `bin:name` or `cdylib`.
+    pub(crate) kinds: Vec<String>,
+
+    /// Whether or not the library of the dependency is also requested.
+    #[serde(default)]
+    pub(crate) lib: bool,
+
+    /// The `target` the artifact is built for when it's not the default.
+    #[serde(default)]
+    pub(crate) target: Option<String>,
+}
+
+/// A dependency entry of a package in `cargo metadata` which requests artifacts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub(crate) struct ArtifactDependencyMetadata {
+    /// The name of the package of the dependency.
+    pub(crate) name: String,
+
+    /// The name of the dependency if it's renamed in the manifest.
+    #[serde(default)]
+    pub(crate) rename: Option<String>,
+
+    /// The version requirement of the dependency.
+    pub(crate) req: semver::VersionReq,
+
+    /// The kind of the dependency. `null` denotes a normal dependency.
+    #[serde(default, deserialize_with = "deserialize_dependency_kind")]
+    pub(crate) kind: DependencyKind,
+
+    /// The platform the dependency is specific to.
+    #[serde(default)]
+    pub(crate) target: Option<Platform>,
+
+    pub(crate) artifact: ArtifactMetadata,
+}
+
+fn deserialize_dependency_kind<'de, D>(deserializer: D) -> Result<DependencyKind, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<DependencyKind>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// The [artifact dependencies](https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#artifact-dependencies)
+/// of each package. Cargo reports them as the `artifact` field of dependencies in
+/// `cargo metadata`, which is not modeled by `cargo_metadata`, so they are read from
+/// the raw output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ArtifactDependencies(BTreeMap<PackageId, Vec<ArtifactDependencyMetadata>>);
+
+impl ArtifactDependencies {
+    /// Collect the artifact dependencies from `cargo metadata` JSON output.
+    pub(crate) fn from_metadata_json(metadata: &serde_json::Value) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct RawPackage {
+            id: PackageId,
+            dependencies: Vec<serde_json::Value>,
+        }
+
+        #[derive(Deserialize)]
+        struct RawMetadata {
+            packages: Vec<RawPackage>,
+        }
+
+        let raw = RawMetadata::deserialize(metadata)
+            .context("Failed to read packages from Cargo metadata")?;
+
+        let mut artifacts = BTreeMap::new();
+        for package in raw.packages {
+            let deps = package
+                .dependencies
+                .into_iter()
+                .filter(|dep| {
+                    dep.get("artifact")
+                        .is_some_and(|artifact| !artifact.is_null())
+                })
+                .map(|dep| {
+                    serde_json::from_value::<ArtifactDependencyMetadata>(dep).with_context(|| {
+                        format!(
+                            "Failed to parse artifact dependency of `{}` from Cargo metadata",
+                            package.id
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if !deps.is_empty() {
+                artifacts.insert(package.id, deps);
+            }
+        }
+
+        Ok(Self(artifacts))
+    }
+
+    fn get(&self, id: &PackageId) -> &[ArtifactDependencyMetadata] {
+        self.0.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
 /// A collection of [Dependency]s sorted by dependency kind.
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub(crate) struct DependencySet {
@@ -37,6 +157,8 @@ pub(crate) struct DependencySet {
     pub(crate) build_deps: Select<BTreeSet<Dependency>>,
     pub(crate) build_link_deps: Select<BTreeSet<Dependency>>,
     pub(crate) build_proc_macro_deps: Select<BTreeSet<Dependency>>,
+    pub(crate) artifact_deps: Select<BTreeSet<ArtifactDependency>>,
+    pub(crate) build_artifact_deps: Select<BTreeSet<ArtifactDependency>>,
 }
 
 impl DependencySet {
@@ -45,6 +167,7 @@ impl DependencySet {
         node: &Node,
         metadata: &CargoMetadata,
         tree_data: Option<&Select<BTreeSet<CrateId>>>,
+        artifacts: &ArtifactDependencies,
     ) -> Self {
         let (normal_dev_deps, normal_deps) = {
             let (dev, normal) = node
@@ -100,6 +223,11 @@ impl DependencySet {
                 .filter(|dep| !is_workspace_member(dep, metadata))
                 .filter(|dep| is_build_dependency(dep))
                 .filter(|dep| !is_dev_dependency(dep))
+                // Packages providing only binaries can only be artifact dependencies.
+                .filter(|dep| {
+                    is_lib_package(&metadata[&dep.pkg])
+                        || is_proc_macro_package(&metadata[&dep.pkg])
+                })
                 .partition(|dep| is_proc_macro_package(&metadata[&dep.pkg]));
 
             (
@@ -132,6 +260,8 @@ impl DependencySet {
             build_link_deps.insert(dependency, configuration);
         }
 
+        let (artifact_deps, build_artifact_deps) = collect_artifact_deps(node, metadata, artifacts);
+
         Self {
             normal_deps,
             normal_dev_deps,
@@ -140,6 +270,8 @@ impl DependencySet {
             build_deps,
             build_link_deps,
             build_proc_macro_deps,
+            artifact_deps,
+            build_artifact_deps,
         }
     }
 }
@@ -244,6 +376,87 @@ fn collect_deps_selectable(
     select
 }
 
+/// Collect the binary artifact dependencies of a node, split into those of the
+/// package itself and those of its build script.
+fn collect_artifact_deps(
+    node: &Node,
+    metadata: &CargoMetadata,
+    artifacts: &ArtifactDependencies,
+) -> (
+    Select<BTreeSet<ArtifactDependency>>,
+    Select<BTreeSet<ArtifactDependency>>,
+) {
+    let mut normal: Select<BTreeSet<ArtifactDependency>> = Select::default();
+    let mut build: Select<BTreeSet<ArtifactDependency>> = Select::default();
+
+    for dep in artifacts.get(&node.id) {
+        let select = match dep.kind {
+            DependencyKind::Normal => &mut normal,
+            DependencyKind::Build => &mut build,
+            _ => continue,
+        };
+        if let Some(artifact) = get_artifact_dependency(node, metadata, dep) {
+            select.insert(
+                artifact,
+                dep.target.as_ref().map(|target| target.to_string()),
+            );
+        }
+    }
+
+    (normal, build)
+}
+
+fn get_artifact_dependency(
+    node: &Node,
+    metadata: &CargoMetadata,
+    dep: &ArtifactDependencyMetadata,
+) -> Option<ArtifactDependency> {
+    let name = dep.rename.as_deref().unwrap_or(&dep.name);
+    let Some(package) = node
+        .dependencies
+        .iter()
+        .map(|id| &metadata[id])
+        .find(|pkg| pkg.name == dep.name && dep.req.matches(&pkg.version))
+    else {
+        warn!(
+            "Artifact dependency `{}` of `{}` is not in the resolve graph",
+            name, metadata[&node.id].name
+        );
+        return None;
+    };
+
+    let mut bins = BTreeSet::new();
+    for kind in &dep.artifact.kinds {
+        match kind.split_once(':') {
+            Some(("bin", bin)) => {
+                bins.insert(bin.to_owned());
+            }
+            None if kind == "bin" => bins.extend(
+                package
+                    .targets
+                    .iter()
+                    .filter(|target| target.is_bin())
+                    .map(|target| target.name.clone()),
+            ),
+            _ => warn!(
+                "Unsupported artifact kind `{}` for dependency `{}` of `{}`",
+                kind, name, metadata[&node.id].name
+            ),
+        }
+    }
+
+    if bins.is_empty() {
+        return None;
+    }
+
+    Some(ArtifactDependency {
+        package_id: package.id.clone(),
+        name: name.to_owned(),
+        bins,
+        target: dep.artifact.target.clone(),
+    })
+}
+
 /// Packages may have targets that match aliases of dependents. This function
 /// checks a target to see if it's an unexpected type for a dependency.
 fn is_ignored_package_target(target: &Target) -> bool {
@@ -520,7 +733,8 @@ mod test {
         let metadata = metadata::example_proc_macro_dep();
 
         let node = find_metadata_node("example-proc-macro-dep", &metadata);
-        let dependencies = DependencySet::new_for_node(node, &metadata, None);
+        let dependencies =
+            DependencySet::new_for_node(node, &metadata, None, &ArtifactDependencies::default());
 
         let normal_deps: Vec<_> = dependencies
             .normal_deps
@@ -544,7 +758,8 @@ mod test {
         let metadata = metadata::alias();
 
         let node = find_metadata_node("surrealdb-core", &metadata);
-        let dependencies = DependencySet::new_for_node(node, &metadata, None);
+        let dependencies =
+            DependencySet::new_for_node(node, &metadata, None, &ArtifactDependencies::default());
 
         let bindings = dependencies.normal_deps.items();
 
@@ -567,7 +782,12 @@ mod test {
 
         let openssl_node = find_metadata_node("openssl", &metadata);
 
-        let dependencies = DependencySet::new_for_node(openssl_node, &metadata, None);
+        let dependencies = DependencySet::new_for_node(
+            openssl_node,
+            &metadata,
+            None,
+            &ArtifactDependencies::default(),
+        );
 
         let normal_sys_crate =
             dependencies
@@ -595,12 +815,102 @@ mod test {
         assert!(link_dep_sys_crate.is_some());
     }
 
+    #[test]
+    fn artifact_dependency() {
+        let metadata = metadata::build_scripts();
+
+        let openssl_node = find_metadata_node("openssl", &metadata);
+
+        let dep: ArtifactDependencyMetadata = serde_json::from_value(serde_json::json!({
+            "name": "openssl-sys",
+            "rename": "ssl-gen",
+            "req": "^0.9",
+            "kind": null,
+            "target": null,
+            "artifact": {
+                "kinds": ["bin:generator", "cdylib"],
+                "lib": false,
+                "target": "target"
+            }
+        }))
+        .unwrap();
+        assert_eq!(dep.kind, DependencyKind::Normal);
+
+        let artifact = get_artifact_dependency(openssl_node, &metadata, &dep).unwrap();
+
+        assert_eq!(metadata[&artifact.package_id].name, "openssl-sys");
+        assert_eq!(artifact.name, "ssl-gen");
+        // Only binary artifacts are supported.
+        assert_eq!(artifact.bins, BTreeSet::from(["generator".to_owned()]));
+        assert_eq!(artifact.target, Some("target".to_owned()));
+
+        // Dependencies which are not in the resolve graph are skipped.
+        let dep = ArtifactDependencyMetadata {
+            req: semver::VersionReq::parse("^2").unwrap(),
+            ..dep
+        };
+        assert!(get_artifact_dependency(openssl_node, &metadata, &dep).is_none());
+    }
+
+    #[test]
+    fn artifact_dependencies_from_metadata_json() {
+        let metadata = metadata::build_scripts();
+        let openssl_node = find_metadata_node("openssl", &metadata);
+
+        let mut json = serde_json::to_value(&metadata).unwrap();
+        let openssl = json["packages"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .find(|pkg| pkg["id"] == openssl_node.id.repr.as_str())
+            .unwrap();
+        let deps = openssl["dependencies"].as_array_mut().unwrap();
+        let regular_deps = deps.len();
+        deps.push(serde_json::json!({
+            "name": "openssl-sys",
+            "source": null,
+            "req": "^0.9",
+            "kind": "build",
+            "optional": false,
+            "uses_default_features": true,
+            "features": [],
+            "target": "cfg(unix)",
+            "rename": null,
+            "registry": null,
+            "artifact": {
+                "kinds": ["bin:generator"],
+                "lib": false
+            }
+        }));
+
+        let artifacts = ArtifactDependencies::from_metadata_json(&json).unwrap();
+        // Only dependencies with an `artifact` field are collected.
+        assert_eq!(artifacts.get(&openssl_node.id).len(), 1);
+        assert!(regular_deps > 0);
+
+        let (normal, build) = collect_artifact_deps(openssl_node, &metadata, &artifacts);
+        assert!(normal.is_empty());
+        let build = build.items();
+        assert_eq!(build.len(), 1);
+        assert_eq!(build[0].0, Some("cfg(unix)".to_owned()));
+        assert_eq!(build[0].1.name, "openssl-sys");
+        assert_eq!(build[0].1.target, None);
+
+        // Malformed artifact dependencies are reported instead of ignored.
+        json["packages"][0]["dependencies"] = serde_json::json!([{
+            "name": "broken",
+            "artifact": {"kinds": "bin"}
+        }]);
+        assert!(ArtifactDependencies::from_metadata_json(&json).is_err());
+    }
+
     #[test]
     fn sys_crate_with_build_script() {
         let metadata = metadata::build_scripts();
 
         let libssh2 = find_metadata_node("libssh2-sys", &metadata);
-        let libssh2_depset = DependencySet::new_for_node(libssh2, &metadata, None);
+        let libssh2_depset =
+            DependencySet::new_for_node(libssh2, &metadata, None, &ArtifactDependencies::default());
 
         // Collect build dependencies into a set
         let build_deps: BTreeSet<String> = libssh2_depset
@@ -649,7 +959,12 @@ mod test {
         let metadata = metadata::alias();
 
         let aliases_node = find_metadata_node("aliases", &metadata);
-        let dependencies = DependencySet::new_for_node(aliases_node, &metadata, None);
+        let dependencies = DependencySet::new_for_node(
+            aliases_node,
+            &metadata,
+            None,
+            &ArtifactDependencies::default(),
+        );
 
         let aliases: Vec<Dependency> = dependencies
             .normal_deps
@@ -675,7 +990,8 @@ mod test {
         let metadata = metadata::crate_types();
 
         let node = find_metadata_node("crate-types", &metadata);
-        let dependencies = DependencySet::new_for_node(node, &metadata, None);
+        let dependencies =
+            DependencySet::new_for_node(node, &metadata, None, &ArtifactDependencies::default());
 
         let rlib_deps: Vec<Dependency> = dependencies
             .normal_deps
@@ -705,7 +1021,8 @@ mod test {
         let metadata = metadata::multi_cfg_dep();
 
         let node = find_metadata_node("cpufeatures", &metadata);
-        let dependencies = DependencySet::new_for_node(node, &metadata, None);
+        let dependencies =
+            DependencySet::new_for_node(node, &metadata, None, &ArtifactDependencies::default());
 
         let libc_cfgs: BTreeSet<Option<String>> = dependencies
             .normal_deps
@@ -730,7 +1047,8 @@ mod test {
         let metadata = metadata::multi_kind_proc_macro_dep();
 
         let node = find_metadata_node("multi-kind-proc-macro-dep", &metadata);
-        let dependencies = DependencySet::new_for_node(node, &metadata, None);
+        let dependencies =
+            DependencySet::new_for_node(node, &metadata, None, &ArtifactDependencies::default());
 
         let lib_deps: Vec<_> = dependencies
             .proc_macro_deps
@@ -754,7 +1072,8 @@ mod test {
         let metadata = metadata::optional_deps_disabled();
 
         let node = find_metadata_node("clap", &metadata);
-        let dependencies = DependencySet::new_for_node(node, &metadata, None);
+        let dependencies =
+            DependencySet::new_for_node(node, &metadata, None, &ArtifactDependencies::default());
 
         assert!(!dependencies
             .normal_deps
@@ -769,7 +1088,12 @@ mod test {
         let metadata = metadata::renamed_optional_deps_disabled();
 
         let serde_with = find_metadata_node("serde_with", &metadata);
-        let serde_with_depset = DependencySet::new_for_node(serde_with, &metadata, None);
+        let serde_with_depset = DependencySet::new_for_node(
+            serde_with,
+            &metadata,
+            None,
+            &ArtifactDependencies::default(),
+        );
         assert!(!serde_with_depset
             .normal_deps
             .items()
@@ -797,7 +1121,12 @@ mod test {
 
         let clap = find_metadata_node("clap", &metadata);
         let dep_tree = build_dep_tree(&resolver_data);
-        let clap_depset = DependencySet::new_for_node(clap, &metadata, dep_tree.get(&crate_id));
+        let clap_depset = DependencySet::new_for_node(
+            clap,
+            &metadata,
+            dep_tree.get(&crate_id),
+            &ArtifactDependencies::default(),
+        );
         assert_eq!(
             clap_depset
                 .normal_deps
@@ -810,7 +1139,8 @@ mod test {
         );
 
         let notify = find_metadata_node("notify", &metadata);
-        let notify_depset = DependencySet::new_for_node(notify, &metadata, None);
+        let notify_depset =
+            DependencySet::new_for_node(notify, &metadata, None, &ArtifactDependencies::default());
 
         // mio is not present in the common list of dependencies
         assert!(!notify_depset
@@ -843,7 +1173,8 @@ mod test {
         let metadata = metadata::optional_deps_disabled_build_dep_enabled();
 
         let node = find_metadata_node("gherkin", &metadata);
-        let dependencies = DependencySet::new_for_node(node, &metadata, None);
+        let dependencies =
+            DependencySet::new_for_node(node, &metadata, None, &ArtifactDependencies::default());
 
         assert!(!dependencies
             .normal_deps
@@ -876,7 +1207,12 @@ mod test {
 
         let p256 = find_metadata_node("p256", &metadata);
         let dep_tree = build_dep_tree(&resolver_data);
-        let p256_depset = DependencySet::new_for_node(p256, &metadata, dep_tree.get(&crate_id));
+        let p256_depset = DependencySet::new_for_node(
+            p256,
+            &metadata,
+            dep_tree.get(&crate_id),
+            &ArtifactDependencies::default(),
+        );
         assert_eq!(
             p256_depset
                 .normal_deps
@@ -933,8 +1269,12 @@ mod test {
 
         let tokio_node = find_metadata_node("tokio", &metadata);
         let dep_tree = build_dep_tree(&resolver_data);
-        let tokio_depset =
-            DependencySet::new_for_node(tokio_node, &metadata, dep_tree.get(&crate_id));
+        let tokio_depset = DependencySet::new_for_node(
+            tokio_node,
+            &metadata,
+            dep_tree.get(&crate_id),
+            &ArtifactDependencies::default(),
+        );
         assert_eq!(
             tokio_depset
                 .normal_deps
@@ -10,7 +10,7 @@ use hex::ToHex;
 use serde::{Deserialize, Serialize};
 
 use crate::config::{Commitish, Config, CrateAnnotations, CrateId};
-use crate::metadata::dependency::{build_dep_tree, DependencySet};
+use crate::metadata::dependency::{build_dep_tree, ArtifactDependencies, DependencySet};
 use crate::select::Select;
 use crate::splicing::{SourceInfo, WorkspaceMetadata};
 
@@ -49,6 +49,7 @@ pub(crate) struct MetadataAnnotation {
 impl MetadataAnnotation {
     pub(crate) fn new(
         metadata: CargoMetadata,
+        artifacts: &ArtifactDependencies,
         workspace_metadata: WorkspaceMetadata,
     ) -> MetadataAnnotation {
         let resolve = metadata
@@ -76,7 +77,7 @@ impl MetadataAnnotation {
             .map(|node| {
                 (
                     node.id.clone(),
-                    Self::annotate_crate(node, &metadata, &dep_tree),
+                    Self::annotate_crate(node, &metadata, artifacts, &dep_tree),
                 )
             })
             .collect();
@@ -99,12 +100,13 @@ impl MetadataAnnotation {
     fn annotate_crate(
         node: Node,
         metadata: &CargoMetadata,
+        artifacts: &ArtifactDependencies,
         dep_tree: &BTreeMap<CrateId, Select<BTreeSet<CrateId>>>,
     ) -> CrateAnnotation {
         // Gather all dependencies
         let tree_data = dep_tree.get(&CrateId::from(&metadata[&node.id]));
 
-        let deps = DependencySet::new_for_node(&node, metadata, tree_data);
+        let deps = DependencySet::new_for_node(&node, metadata, tree_data, artifacts);
 
         CrateAnnotation { node, deps }
     }
@@ -112,7 +114,7 @@ impl MetadataAnnotation {
 
 /// Additional information about how and where to acquire a crate's source code from.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
-pub(crate) enum SourceAnnotation {
+pub enum SourceAnnotation {
     Git {
         /// The Git url where to clone the source from.
         remote: String,
@@ -463,6 +465,7 @@ pub(crate) struct Annotations {
 impl Annotations {
     pub(crate) fn new(
         cargo_metadata: CargoMetadata,
+        artifacts: &ArtifactDependencies,
         cargo_lockfile_path: &Option<PathBuf>,
         cargo_lockfile: CargoLockfile,
         config: Config,
@@ -480,7 +483,8 @@ impl Annotations {
         )?;
 
         // Annotate the cargo metadata
-        let metadata_annotation = MetadataAnnotation::new(cargo_metadata, workspace_metadata);
+        let metadata_annotation =
+            MetadataAnnotation::new(cargo_metadata, artifacts, workspace_metadata);
 
         let mut unused_extra_annotations = config.annotations.clone();
 
@@ -575,7 +579,11 @@ mod test {
     fn annotate_metadata_with_aliases() {
         let metadata = test::metadata::alias();
         let workspace_metadata = find_workspace_metadata(&metadata).unwrap_or_default();
-        let annotations = MetadataAnnotation::new(metadata, workspace_metadata);
+        let annotations = MetadataAnnotation::new(
+            metadata,
+            &ArtifactDependencies::default(),
+            workspace_metadata,
+        );
         let log_crates: BTreeMap<&PackageId, &CrateAnnotation> = annotations
             .crates
             .iter()
@@ -605,7 +613,11 @@ mod test {
     fn annotate_metadata_with_build_scripts() {
         let metadata = test::metadata::build_scripts();
         let workspace_metadata = find_workspace_metadata(&metadata).unwrap_or_default();
-        MetadataAnnotation::new(metadata, workspace_metadata);
+        MetadataAnnotation::new(
+            metadata,
+            &ArtifactDependencies::default(),
+            workspace_metadata,
+        );
     }
 
     #[test]
@@ -727,6 +739,7 @@ mod test {
 
         let result = Annotations::new(
             test::metadata::no_deps(),
+            &ArtifactDependencies::default(),
             &None,
             test::lockfile::no_deps(),
             config,
@@ -766,6 +779,7 @@ mod test {
         // crate author in package metadata.
         let combined_annotations = Annotations::new(
             test::metadata::has_package_metadata(),
+            &ArtifactDependencies::default(),
             &None,
             test::lockfile::has_package_metadata(),
             config,
@@ -12,7 +12,9 @@ use anyhow::{bail, Context as AnyhowContext, Result};
 use itertools::Itertools;
 
 use crate::config::{AliasRule, RenderConfig, VendorMode};
-use crate::context::crate_context::{CrateContext, CrateDependency, Rule};
+use crate::context::crate_context::{
+    CrateArtifact, CrateContext, CrateDependency, ProfileSettings, Rule,
+};
 use crate::context::{Context, TargetAttributes};
 use crate::metadata::SourceAnnotation;
 use crate::rendering::template_engine::TemplateEngine;
@@ -52,7 +54,7 @@ impl Renderer {
         generator: Option<Label>,
     ) -> Result<BTreeMap<PathBuf, String>> {
         let conditions = Arc::new(context.conditions.clone());
-        let engine = self.create_engine(Arc::clone(&conditions));
+        let engine = self.create_engine(Arc::clone(&conditions))?;
 
         let mut output = BTreeMap::new();
 
@@ -77,7 +79,7 @@ impl Renderer {
     pub(crate) fn create_engine(
         &self,
         conditions: Arc<BTreeMap<String, BTreeSet<TargetTriple>>>,
-    ) -> TemplateEngine {
+    ) -> Result<TemplateEngine> {
         TemplateEngine::new(
             Arc::clone(&self.config),
             Arc::clone(&self.supported_platform_triples),
@@ -482,11 +484,29 @@ impl Renderer {
                     tags: BTreeSet::from(["manual".to_owned()]),
                 }));
             } else {
+                // A rule provided by the user replaces the default for this kind of target.
+                let rule_override = krate.override_rules.get(rule.override_target_key());
+                let mut load_rule = |bzl: &str, item: &str| match rule_override {
+                    Some(rule_override) => {
+                        load(&rule_override.bzl, &rule_override.rule);
+                        (rule_override.rule.clone(), rule_override.attrs.clone())
+                    }
+                    None => {
+                        load(bzl, item);
+                        (item.to_owned(), BTreeMap::new())
+                    }
+                };
                 match rule {
                     Rule::BuildScript(target) => {
-                        load("@rules_rust//cargo:defs.bzl", "cargo_build_script");
-                        let cargo_build_script =
-                            self.make_cargo_build_script(platforms, &krate, target)?;
+                        let (rule, extra_attrs) =
+                            load_rule("@rules_rust//cargo:defs.bzl", "cargo_build_script");
+                        let cargo_build_script = self.make_cargo_build_script(
+                            platforms,
+                            &krate,
+                            target,
+                            rule,
+                            extra_attrs,
+                        )?;
                         starlark.push(Starlark::CargoBuildScript(cargo_build_script));
                         starlark.push(Starlark::Alias(Alias {
                             rule: AliasRule::default().rule(),
@@ -496,19 +516,29 @@ impl Renderer {
                         }));
                     }
                     Rule::ProcMacro(target) => {
-                        load("@rules_rust//rust:defs.bzl", "rust_proc_macro");
-                        let rust_proc_macro =
-                            self.make_rust_proc_macro(platforms, &krate, target)?;
+                        let (rule, extra_attrs) =
+                            load_rule("@rules_rust//rust:defs.bzl", "rust_proc_macro");
+                        let rust_proc_macro = self.make_rust_proc_macro(
+                            platforms,
+                            &krate,
+                            target,
+                            rule,
+                            extra_attrs,
+                        )?;
                         starlark.push(Starlark::RustProcMacro(rust_proc_macro));
                     }
                     Rule::Library(target) => {
-                        load("@rules_rust//rust:defs.bzl", "rust_library");
-                        let rust_library = self.make_rust_library(platforms, &krate, target)?;
+                        let (rule, extra_attrs) =
+                            load_rule("@rules_rust//rust:defs.bzl", "rust_library");
+                        let rust_library =
+                            self.make_rust_library(platforms, &krate, target, rule, extra_attrs)?;
                         starlark.push(Starlark::RustLibrary(rust_library));
                     }
                     Rule::Binary(target) => {
-                        load("@rules_rust//rust:defs.bzl", "rust_binary");
-                        let rust_binary = self.make_rust_binary(platforms, &krate, target)?;
+                        let (rule, extra_attrs) =
+                            load_rule("@rules_rust//rust:defs.bzl", "rust_binary");
+                        let rust_binary =
+                            self.make_rust_binary(platforms, &krate, target, rule, extra_attrs)?;
                         starlark.push(Starlark::RustBinary(rust_binary));
                     }
                 }
@@ -528,6 +558,9 @@ impl Renderer {
         starlark.splice(1..1, loads);
 
         let starlark = starlark::serialize(&starlark)?;
+        if let Some(rendered) = engine.render_crate_build_file(&krate, platforms, &starlark)? {
+            return Ok(rendered);
+        }
         Ok(starlark)
     }
 
@@ -536,10 +569,22 @@ impl Renderer {
         platforms: &Platforms,
         krate: &CrateContext,
         target: &TargetAttributes,
+        rule: String,
+        extra_attrs: BTreeMap<String, serde_json::Value>,
     ) -> Result<CargoBuildScript> {
         let attrs = krate.build_script_attrs.as_ref();
 
+        // Artifact dependencies are built for the exec platform unless a `target` was requested.
+        let artifacts = attrs
+            .map(|attrs| attrs.artifacts.clone())
+            .unwrap_or_default();
+        let (artifact_tools, exec_artifact_env) =
+            self.make_artifacts(&artifacts, |artifact| !artifact.for_target);
+        let (artifact_data, target_artifact_env) =
+            self.make_artifacts(&artifacts, |artifact| artifact.for_target);
+
         Ok(CargoBuildScript {
+            rule,
             // Because `cargo_build_script` does some invisible target name
             // mutating to determine the package and crate name for a build
             // script, the Bazel target name of any build script cannot be the
@@ -552,9 +597,12 @@ impl Renderer {
             name: "_bs".to_string(),
             aliases: SelectDict::new(self.make_aliases(krate, true, false), platforms),
             build_script_env: SelectDict::new(
-                attrs
-                    .map(|attrs| attrs.build_script_env.clone())
-                    .unwrap_or_default(),
+                Select::merge(
+                    attrs
+                        .map(|attrs| attrs.build_script_env.clone())
+                        .unwrap_or_default(),
+                    Select::merge(exec_artifact_env, target_artifact_env),
+                ),
                 platforms,
             ),
             use_default_shell_env: krate
@@ -582,7 +630,10 @@ impl Renderer {
                     .map(|attrs| attrs.data_glob.clone())
                     .unwrap_or_default(),
                 Default::default(),
-                attrs.map(|attrs| attrs.data.clone()).unwrap_or_default(),
+                Select::merge(
+                    attrs.map(|attrs| attrs.data.clone()).unwrap_or_default(),
+                    artifact_data,
+                ),
             ),
             deps: SelectSet::new(
                 self.make_deps(
@@ -659,12 +710,16 @@ impl Renderer {
                 tags
             },
             tools: SelectSet::new(
-                attrs.map(|attrs| attrs.tools.clone()).unwrap_or_default(),
+                Select::merge(
+                    attrs.map(|attrs| attrs.tools.clone()).unwrap_or_default(),
+                    artifact_tools,
+                ),
                 platforms,
             ),
             toolchains: attrs.map_or_else(BTreeSet::new, |attrs| attrs.toolchains.clone()),
             version: krate.common_attrs.version.clone(),
             visibility: BTreeSet::from(["//visibility:private".to_owned()]),
+            extra_attrs,
         })
     }
 
@@ -673,8 +728,11 @@ impl Renderer {
         platforms: &Platforms,
         krate: &CrateContext,
         target: &TargetAttributes,
+        rule: String,
+        extra_attrs: BTreeMap<String, serde_json::Value>,
     ) -> Result<RustProcMacro> {
         Ok(RustProcMacro {
+            rule,
             name: target.crate_name.clone(),
             deps: SelectSet::new(
                 self.make_deps(
@@ -691,7 +749,13 @@ impl Renderer {
                 platforms,
             ),
             aliases: SelectDict::new(self.make_aliases(krate, false, false), platforms),
-            common: self.make_common_attrs(platforms, krate, target)?,
+            common: self.make_common_attrs(
+                platforms,
+                krate,
+                target,
+                make_profile_rustc_flags(krate, false, true),
+            )?,
+            extra_attrs,
         })
     }
 
@@ -700,8 +764,11 @@ impl Renderer {
         platforms: &Platforms,
         krate: &CrateContext,
         target: &TargetAttributes,
+        rule: String,
+        extra_attrs: BTreeMap<String, serde_json::Value>,
     ) -> Result<RustLibrary> {
         Ok(RustLibrary {
+            rule,
             name: target.crate_name.clone(),
             deps: SelectSet::new(
                 self.make_deps(
@@ -718,8 +785,14 @@ impl Renderer {
                 platforms,
             ),
             aliases: SelectDict::new(self.make_aliases(krate, false, false), platforms),
-            common: self.make_common_attrs(platforms, krate, target)?,
+            common: self.make_common_attrs(
+                platforms,
+                krate,
+                target,
+                make_profile_rustc_flags(krate, false, false),
+            )?,
             disable_pipelining: krate.disable_pipelining,
+            extra_attrs,
         })
     }
 
@@ -728,8 +801,11 @@ impl Renderer {
         platforms: &Platforms,
         krate: &CrateContext,
         target: &TargetAttributes,
+        rule: String,
+        extra_attrs: BTreeMap<String, serde_json::Value>,
     ) -> Result<RustBinary> {
         Ok(RustBinary {
+            rule,
             name: format!("{}__bin", target.crate_name),
             deps: {
                 let mut deps = self.make_deps(
@@ -752,7 +828,13 @@ impl Renderer {
                 platforms,
             ),
             aliases: SelectDict::new(self.make_aliases(krate, false, false), platforms),
-            common: self.make_common_attrs(platforms, krate, target)?,
+            common: self.make_common_attrs(
+                platforms,
+                krate,
+                target,
+                make_profile_rustc_flags(krate, true, false),
+            )?,
+            extra_attrs,
         })
     }
 
@@ -761,7 +843,11 @@ impl Renderer {
         platforms: &Platforms,
         krate: &CrateContext,
         target: &TargetAttributes,
+        profile_rustc_flags: BTreeMap<String, Vec<String>>,
     ) -> Result<CommonAttrs> {
+        let (artifact_data, artifact_env) =
+            self.make_artifacts(&krate.common_attrs.artifacts, |_| true);
+
         Ok(CommonAttrs {
             compile_data: make_data(
                 platforms,
@@ -775,11 +861,14 @@ impl Renderer {
                 platforms,
                 krate.common_attrs.data_glob.clone(),
                 Default::default(),
-                krate.common_attrs.data.clone(),
+                Select::merge(krate.common_attrs.data.clone(), artifact_data),
             ),
             edition: krate.common_attrs.edition.clone(),
             linker_script: krate.common_attrs.linker_script.clone(),
-            rustc_env: SelectDict::new(krate.common_attrs.rustc_env.clone(), platforms),
+            rustc_env: SelectDict::new(
+                Select::merge(krate.common_attrs.rustc_env.clone(), artifact_env),
+                platforms,
+            ),
             rustc_env_files: SelectSet::new(krate.common_attrs.rustc_env_files.clone(), platforms),
             rustc_flags: SelectList::new(
                 // In most cases, warnings in 3rd party crates are not
@@ -791,7 +880,8 @@ impl Renderer {
                     krate.common_attrs.rustc_flags.clone(),
                 ),
                 platforms,
-            ),
+            )
+            .with_configurations(profile_rustc_flags),
             srcs: target.srcs.clone(),
             tags: {
                 let mut tags = BTreeSet::from_iter(krate.common_attrs.tags.iter().cloned());
@@ -862,19 +952,43 @@ impl Renderer {
         deps: Select<BTreeSet<CrateDependency>>,
         extra_deps: Select<BTreeSet<Label>>,
     ) -> Select<BTreeSet<Label>> {
-        Select::merge(
-            deps.map(|dep| {
-                match (dep.local_path, self.config.vendor_mode) {
-                    // In local vendor mode, we use paths within the the repo.
-                    (Some(path), Some(VendorMode::Local)) => {
-                        Label::from_str(&format!("//{}:{}", path, &dep.target)).unwrap()
-                    }
-                    // If we're not vendoring source, or don't have a path for the dep, construct the label we expect.
-                    _ => self.crate_label(&dep.id.name, &dep.id.version.to_string(), &dep.target),
-                }
-            }),
-            extra_deps,
-        )
+        Select::merge(deps.map(|dep| self.make_dep_label(dep)), extra_deps)
+    }
+
+    fn make_dep_label(&self, dep: CrateDependency) -> Label {
+        match (dep.local_path, self.config.vendor_mode) {
+            // In local vendor mode, we use paths within the the repo.
+            (Some(path), Some(VendorMode::Local)) => {
+                Label::from_str(&format!("//{}:{}", path, &dep.target)).unwrap()
+            }
+            // If we're not vendoring source, or don't have a path for the dep, construct the label we expect.
+            _ => self.crate_label(&dep.id.name, &dep.id.version.to_string(), &dep.target),
+        }
+    }
+
+    /// Renders the binaries of artifact dependencies as labels and the
+    /// `CARGO_BIN_FILE_*` environment variables pointing to them.
+    fn make_artifacts(
+        &self,
+        artifacts: &Select<BTreeSet<CrateArtifact>>,
+        filter: impl Fn(&CrateArtifact) -> bool,
+    ) -> (Select<BTreeSet<Label>>, Select<BTreeMap<String, String>>) {
+        let mut labels: Select<BTreeSet<Label>> = Select::default();
+        let mut env: Select<BTreeMap<String, String>> = Select::default();
+        for (configuration, artifact) in artifacts.items() {
+            if !filter(&artifact) {
+                continue;
+            }
+            let label = self.make_dep_label(artifact.dependency);
+            for env_var in artifact.env_vars {
+                env.insert(
+                    (env_var, format!("$(execpath {label})")),
+                    configuration.clone(),
+                );
+            }
+            labels.insert(label, configuration);
+        }
+        (labels, env)
     }
 
     fn render_vendor_support_files(
@@ -996,6 +1110,31 @@ fn render_build_file_template(template: &str, name: &str, version: &str) -> Resu
     )
 }
 
+/// Translates the Cargo profile settings of a crate into `rustc` flags keyed by the
+/// `compilation_mode` config settings of each profile.
+fn make_profile_rustc_flags(
+    krate: &CrateContext,
+    is_binary: bool,
+    is_proc_macro: bool,
+) -> BTreeMap<String, Vec<String>> {
+    ProfileSettings::PROFILE_COMPILATION_MODES
+        .iter()
+        .filter_map(|(profile, compilation_modes)| {
+            let settings = krate.common_attrs.profiles.get(*profile)?;
+            let flags = settings.rustc_flags(is_binary, is_proc_macro);
+            Some(compilation_modes.iter().map(move |compilation_mode| {
+                (
+                    format!(
+                        "@rules_rust//crate_universe/private:compilation_mode_{compilation_mode}"
+                    ),
+                    flags.clone(),
+                )
+            }))
+        })
+        .flatten()
+        .collect()
+}
+
 fn make_data_with_exclude(
     platforms: &Platforms,
     include: BTreeSet<String>,
@@ -1041,9 +1180,9 @@ mod test {
     use camino::Utf8Path;
     use indoc::indoc;
 
-    use crate::config::{Config, CrateId};
+    use crate::config::{Config, CrateId, RuleOverride};
     use crate::context::{BuildScriptAttributes, CommonAttributes};
-    use crate::metadata::Annotations;
+    use crate::metadata::{Annotations, ArtifactDependencies};
     use crate::test;
     use crate::utils::normalize_cargo_file_paths;
 
@@ -1116,6 +1255,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1154,6 +1294,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1196,6 +1337,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1270,6 +1412,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1333,6 +1476,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1371,6 +1515,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1386,6 +1531,239 @@ mod test {
         assert!(build_file_content.contains("\"crate-name=mock_crate\""));
     }
 
+    #[test]
+    fn render_override_rules() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                name: crate_id.name,
+                version: crate_id.version,
+                package_url: None,
+                repository: None,
+                targets: BTreeSet::from([
+                    Rule::Library(mock_target_attributes()),
+                    Rule::BuildScript(TargetAttributes {
+                        crate_name: "build_script_build".to_owned(),
+                        crate_root: Some("build.rs".to_owned()),
+                        ..TargetAttributes::default()
+                    }),
+                ]),
+                library_target_name: None,
+                common_attrs: CommonAttributes::default(),
+                build_script_attrs: Some(BuildScriptAttributes::default()),
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: None,
+                disable_pipelining: false,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::from([(
+                    "lib".to_owned(),
+                    RuleOverride {
+                        bzl: "@//:rust.bzl".to_owned(),
+                        rule: "hardened_rust_library".to_owned(),
+                        attrs: BTreeMap::from([
+                            ("sanitizer".to_owned(), serde_json::json!("address")),
+                            ("strip".to_owned(), serde_json::json!(true)),
+                        ]),
+                    },
+                )]),
+            },
+        );
+
+        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        assert!(build_file_content.contains("load(\"@//:rust.bzl\", \"hardened_rust_library\")"));
+        assert!(build_file_content.contains("hardened_rust_library(\n    name = \"mock_crate\","));
+        assert!(build_file_content.contains("    sanitizer = \"address\",\n    strip = True,\n)"));
+        assert!(!build_file_content.contains("\"rust_library\""));
+        // Targets without an override still use the default rule.
+        assert!(build_file_content.contains("cargo_build_script(\n    name = \"_bs\","));
+    }
+
+    #[test]
+    fn render_artifact_dependencies() {
+        let artifact = |name: &str, env_var: &str, for_target: bool| CrateArtifact {
+            dependency: CrateDependency {
+                id: CrateId::new(name.to_owned(), VERSION_ZERO_ONE_ZERO),
+                target: format!("{name}__bin"),
+                alias: None,
+                local_path: None,
+            },
+            env_vars: BTreeSet::from([env_var.to_owned()]),
+            for_target,
+        };
+
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                name: crate_id.name,
+                version: crate_id.version,
+                package_url: None,
+                repository: None,
+                targets: BTreeSet::from([
+                    Rule::Library(mock_target_attributes()),
+                    Rule::BuildScript(TargetAttributes {
+                        crate_name: "build_script_build".to_owned(),
+                        crate_root: Some("build.rs".to_owned()),
+                        ..TargetAttributes::default()
+                    }),
+                ]),
+                library_target_name: None,
+                common_attrs: CommonAttributes {
+                    artifacts: Select::from_value(BTreeSet::from([artifact(
+                        "embedded",
+                        "CARGO_BIN_FILE_EMBEDDED",
+                        false,
+                    )])),
+                    ..CommonAttributes::default()
+                },
+                build_script_attrs: Some(BuildScriptAttributes {
+                    artifacts: Select::from_value(BTreeSet::from([
+                        artifact("codegen", "CARGO_BIN_FILE_CODEGEN_codegen", false),
+                        artifact("firmware", "CARGO_BIN_FILE_FIRMWARE", true),
+                    ])),
+                    ..BuildScriptAttributes::default()
+                }),
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: None,
+                disable_pipelining: false,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
+            },
+        );
+
+        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        // Binaries for the exec platform are tools of the build script.
+        assert!(build_file_content.contains(
+            "    tools = [\n        \"@test_rendering__codegen-0.1.0//:codegen__bin\",\n    ],"
+        ));
+        // Binaries requested with `target` are built for the target platform.
+        assert!(build_file_content.contains(
+            "\"CARGO_BIN_FILE_FIRMWARE\": \"$(execpath @test_rendering__firmware-0.1.0//:firmware__bin)\","
+        ));
+        assert!(build_file_content.contains(
+            "\"CARGO_BIN_FILE_CODEGEN_codegen\": \"$(execpath @test_rendering__codegen-0.1.0//:codegen__bin)\","
+        ));
+        // Normal artifact dependencies are available when compiling the crate.
+        assert!(build_file_content.contains(
+            "\"CARGO_BIN_FILE_EMBEDDED\": \"$(execpath @test_rendering__embedded-0.1.0//:embedded__bin)\","
+        ));
+        assert_eq!(
+            build_file_content
+                .matches("\"@test_rendering__firmware-0.1.0//:firmware__bin\",")
+                .count(),
+            1
+        );
+        assert_eq!(
+            build_file_content
+                .matches("\"@test_rendering__embedded-0.1.0//:embedded__bin\",")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn render_profile_rustc_flags() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                name: crate_id.name,
+                version: crate_id.version,
+                package_url: None,
+                repository: None,
+                targets: BTreeSet::from([
+                    Rule::Library(mock_target_attributes()),
+                    Rule::Binary(mock_target_attributes()),
+                ]),
+                library_target_name: None,
+                common_attrs: CommonAttributes {
+                    profiles: BTreeMap::from([
+                        (
+                            "dev".to_owned(),
+                            ProfileSettings {
+                                opt_level: Some("1".to_owned()),
+                                ..ProfileSettings::default()
+                            },
+                        ),
+                        (
+                            "release".to_owned(),
+                            ProfileSettings {
+                                codegen_units: Some(1),
+                                lto: Some("fat".to_owned()),
+                                panic: Some("abort".to_owned()),
+                                ..ProfileSettings::default()
+                            },
+                        ),
+                    ]),
+                    ..CommonAttributes::default()
+                },
+                build_script_attrs: None,
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: None,
+                disable_pipelining: false,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
+            },
+        );
+
+        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        let library_flags = indoc! {r#"
+            rustc_flags = [
+                "--cap-lints=allow",
+            ] + select({
+                "@rules_rust//crate_universe/private:compilation_mode_dbg": [
+                    "-Copt-level=1",
+                ],
+                "@rules_rust//crate_universe/private:compilation_mode_fastbuild": [
+                    "-Copt-level=1",
+                ],
+                "@rules_rust//crate_universe/private:compilation_mode_opt": [
+                    "-Ccodegen-units=1",
+                    "-Cpanic=abort",
+                ],
+                "//conditions:default": [],
+            }),
+        "#};
+        assert!(build_file_content.contains(&textwrap::indent(library_flags, "    ")));
+
+        // LTO is only applied to binaries.
+        assert_eq!(build_file_content.matches("\"-Clto=fat\",").count(), 1);
+    }
+
     #[test]
     fn render_additive_build_contents() {
         let mut context = Context::default();
@@ -1411,6 +1789,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1424,6 +1803,227 @@ mod test {
         assert!(build_file_content.contains("# Hello World from additive section!"));
     }
 
+    #[test]
+    fn render_user_crate_build_file_template() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                name: crate_id.name,
+                version: crate_id.version,
+                package_url: None,
+                repository: None,
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                library_target_name: None,
+                common_attrs: CommonAttributes::default(),
+                build_script_attrs: None,
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: None,
+                disable_pipelining: false,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
+            },
+        );
+
+        let config = RenderConfig {
+            repository_name: "test_rendering".to_owned(),
+            regen_command: "cargo_bazel_regen_command".to_owned(),
+            template_overrides: BTreeMap::from([(
+                "crate_build_file.j2".to_owned(),
+                indoc! {r#"
+                    {%- import "partials/macros.j2" as macros -%}
+                    load("//:rust.bzl", "my_rust_library")
+                    {% for target in crate.targets %}
+                    {%- for kind, attrs in target %}
+                    {{ macros::rule(kind=kind, name=attrs.crate_name) }}
+                    {%- endfor %}
+                    {%- endfor %}
+                "#}
+                .to_owned(),
+            )]),
+            template_partials: BTreeMap::from([(
+                "partials/macros.j2".to_owned(),
+                indoc! {r#"
+                    {% macro rule(kind, name) -%}
+                    my_rust_{{ kind | lower }}(name = "{{ name }}")
+                    {%- endmacro rule %}
+                "#}
+                .to_owned(),
+            )]),
+            ..RenderConfig::default()
+        };
+
+        let renderer = Renderer::new(Arc::new(config), mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        assert!(build_file_content.contains("load(\"//:rust.bzl\", \"my_rust_library\")"));
+        assert!(build_file_content.contains("my_rust_library(name = \"mock_crate\")"));
+        assert!(!build_file_content.contains("@rules_rust//rust:defs.bzl"));
+    }
+
+    #[test]
+    fn render_user_header_template() {
+        let config = RenderConfig {
+            repository_name: "test_rendering".to_owned(),
+            template_overrides: BTreeMap::from([(
+                "partials/header.j2".to_owned(),
+                "# Generated for {{ repository_name }}".to_owned(),
+            )]),
+            ..RenderConfig::default()
+        };
+
+        let renderer = Renderer::new(Arc::new(config), mock_supported_platform_triples());
+        let output = renderer.render(&Context::default(), None).unwrap();
+
+        let build_file_content = output.get(&PathBuf::from("BUILD.bazel")).unwrap();
+
+        assert!(build_file_content.starts_with("# Generated for test_rendering\n"));
+    }
+
+    #[test]
+    fn render_unknown_template_override() {
+        let config = RenderConfig {
+            template_overrides: BTreeMap::from([("does_not_exist.j2".to_owned(), String::new())]),
+            ..RenderConfig::default()
+        };
+
+        let renderer = Renderer::new(Arc::new(config), mock_supported_platform_triples());
+        let error = renderer.render(&Context::default(), None).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("`does_not_exist.j2` is not a template which can be overridden."));
+    }
+
+    #[test]
+    fn render_user_template_missing_attribute() {
+        let config = RenderConfig {
+            template_overrides: BTreeMap::from([(
+                "partials/header.j2".to_owned(),
+                "# {{ repository_name.not_an_attribute }}".to_owned(),
+            )]),
+            ..RenderConfig::default()
+        };
+
+        let renderer = Renderer::new(Arc::new(config), mock_supported_platform_triples());
+        let engine = renderer.create_engine(Arc::new(BTreeMap::new())).unwrap();
+        let error = engine.render_header().unwrap_err();
+
+        assert!(error.to_string().contains(
+            "the variables available to `partials/header.j2` are: Null, default_package_name"
+        ));
+    }
+
+    #[test]
+    fn reject_user_template_unknown_variable() {
+        let config = RenderConfig {
+            template_overrides: BTreeMap::from([(
+                "partials/header.j2".to_owned(),
+                "# {{ not_a_variable }}".to_owned(),
+            )]),
+            ..RenderConfig::default()
+        };
+
+        let renderer = Renderer::new(Arc::new(config), mock_supported_platform_triples());
+        let error = renderer
+            .create_engine(Arc::new(BTreeMap::new()))
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "User provided template `partials/header.j2` uses the unknown variable `not_a_variable`. \
+            The variables available to it are: Null, default_package_name, default_select_list, \
+            regen_command, repository_name, vendor_mode"
+        );
+    }
+
+    #[test]
+    fn reject_user_template_unknown_include() {
+        let config = RenderConfig {
+            template_overrides: BTreeMap::from([(
+                "crate_build_file.j2".to_owned(),
+                r#"{% include "partials/missing.j2" %}"#.to_owned(),
+            )]),
+            ..RenderConfig::default()
+        };
+
+        let renderer = Renderer::new(Arc::new(config), mock_supported_platform_triples());
+        let error = renderer
+            .create_engine(Arc::new(BTreeMap::new()))
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "User provided template `crate_build_file.j2` includes the unknown template `partials/missing.j2`"
+        );
+    }
+
+    #[test]
+    fn reject_user_partial_unknown_variable() {
+        let config = RenderConfig {
+            template_overrides: BTreeMap::from([(
+                "crate_build_file.j2".to_owned(),
+                indoc! {r#"
+                    {% for target in crate.targets %}
+                    {%- set index = loop.index %}
+                    {% include "partials/target.j2" %}
+                    {%- endfor %}
+                "#}
+                .to_owned(),
+            )]),
+            template_partials: BTreeMap::from([(
+                "partials/target.j2".to_owned(),
+                "{{ index }}: {{ target }} {{ name }}".to_owned(),
+            )]),
+            ..RenderConfig::default()
+        };
+
+        let renderer = Renderer::new(Arc::new(config), mock_supported_platform_triples());
+        let error = renderer
+            .create_engine(Arc::new(BTreeMap::new()))
+            .err()
+            .unwrap();
+
+        assert!(error.to_string().starts_with(
+            "User provided template `partials/target.j2` uses the unknown variable `name` \
+            (included from `crate_build_file.j2`)."
+        ));
+    }
+
+    #[test]
+    fn accept_user_partial_with_includer_variables() {
+        let config = RenderConfig {
+            template_overrides: BTreeMap::from([(
+                "crate_build_file.j2".to_owned(),
+                indoc! {r#"
+                    {% set name = crate.name %}
+                    {%- if platforms is defined %}{% include "partials/name.j2" %}{% endif %}
+                "#}
+                .to_owned(),
+            )]),
+            template_partials: BTreeMap::from([(
+                "partials/name.j2".to_owned(),
+                "{{ name | upper }} {{ crate.targets[0] }} {{ default_build_file }}".to_owned(),
+            )]),
+            ..RenderConfig::default()
+        };
+
+        let renderer = Renderer::new(Arc::new(config), mock_supported_platform_triples());
+
+        assert!(renderer.create_engine(Arc::new(BTreeMap::new())).is_ok());
+    }
+
     #[test]
     fn render_aliases() {
         let config = Config {
@@ -1432,6 +2032,7 @@ mod test {
         };
         let annotations = Annotations::new(
             test::metadata::alias(),
+            &ArtifactDependencies::default(),
             &None,
             test::lockfile::alias(),
             config,
@@ -1472,6 +2073,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1506,6 +2108,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1546,6 +2149,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1598,6 +2202,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1646,6 +2251,7 @@ mod test {
 
         let annotations = Annotations::new(
             metadata,
+            &ArtifactDependencies::default(),
             &None,
             lockfile,
             config.clone(),
@@ -1720,6 +2326,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1767,6 +2374,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1822,6 +2430,7 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1888,6 +2497,7 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -1961,6 +2571,7 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -2044,6 +2655,7 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -2138,6 +2750,7 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -2177,6 +2790,7 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                override_rules: BTreeMap::default(),
             },
         );
 
@@ -3,8 +3,10 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 
-use anyhow::{Context as AnyhowContext, Result};
+use anyhow::{bail, Context as AnyhowContext, Result};
+use itertools::Itertools;
 use serde_json::{from_value, to_value, Value};
+use tera::ast::{Expr, ExprVal, FunctionCall, In, LogicExpr, MathExpr, Node};
 
 use crate::config::RenderConfig;
 use crate::context::{Context, SingleBuildFileRenderContext};
@@ -17,9 +19,53 @@ use crate::utils::sanitize_repository_name;
 use crate::utils::starlark::Label;
 use crate::utils::target_triple::TargetTriple;
 
+/// The name of the template used to render each crate's BUILD file. There is no
+/// built-in version of this template, it's only used when provided by a user.
+const CRATE_BUILD_FILE_TEMPLATE: &str = "crate_build_file.j2";
+
+/// Variables available to every template.
+const COMMON_TEMPLATE_VARIABLES: &[&str] = &[
+    "Null",
+    "default_package_name",
+    "default_select_list",
+    "regen_command",
+    "repository_name",
+    "vendor_mode",
+];
+
+/// The templates which can be replaced by [RenderConfig::template_overrides] and the
+/// variables each is rendered with in addition to [COMMON_TEMPLATE_VARIABLES]. Partials
+/// additionally see any variables set by the template including them.
+const TEMPLATE_SCHEMA: &[(&str, &[&str])] = &[
+    (
+        CRATE_BUILD_FILE_TEMPLATE,
+        &["crate", "default_build_file", "platforms"],
+    ),
+    ("module_bzl.j2", &["context", "generator", "platforms"]),
+    (
+        "partials/module/aliases_map.j2",
+        &["context", "deps_type", "platforms"],
+    ),
+    (
+        "partials/module/deps_map.j2",
+        &["context", "deps_type", "platforms"],
+    ),
+    (
+        "partials/module/repo_git.j2",
+        &["attrs", "context", "crate"],
+    ),
+    (
+        "partials/module/repo_http.j2",
+        &["attrs", "context", "crate"],
+    ),
+    ("partials/header.j2", &[]),
+    ("vendor_module.j2", &["context"]),
+];
+
 pub(crate) struct TemplateEngine {
     engine: tera::Tera,
     context: tera::Context,
+    user_templates: BTreeSet<String>,
 }
 
 impl TemplateEngine {
@@ -27,7 +73,7 @@ impl TemplateEngine {
         render_config: Arc<RenderConfig>,
         supported_platform_triples: Arc<BTreeSet<TargetTriple>>,
         platform_conditions: Arc<BTreeMap<String, BTreeSet<TargetTriple>>>,
-    ) -> Self {
+    ) -> Result<Self> {
         let mut tera = tera::Tera::default();
         tera.add_raw_templates(vec![
             (
@@ -82,6 +128,8 @@ impl TemplateEngine {
         ])
         .unwrap();
 
+        let user_templates = add_user_templates(&mut tera, &render_config)?;
+
         tera.register_function(
             "crate_build_file",
             crate_build_file_fn_generator(render_config.build_file_template.clone()),
@@ -134,26 +182,79 @@ impl TemplateEngine {
             },
         );
 
-        Self {
+        Ok(Self {
             engine: tera,
             context,
-        }
+            user_templates,
+        })
     }
 
     fn new_tera_ctx(&self) -> tera::Context {
         self.context.clone()
     }
 
+    /// Render a template, describing the variables available to it when a user
+    /// provided template fails to render.
+    fn render(&self, template: &str, context: &tera::Context, error: &str) -> Result<String> {
+        self.engine.render(template, context).with_context(|| {
+            if self.user_templates.is_empty() {
+                return error.to_owned();
+            }
+            let variables = TEMPLATE_SCHEMA
+                .iter()
+                .find(|(name, _)| *name == template)
+                .map(|(_, variables)| *variables)
+                .unwrap_or_default();
+            format!(
+                "{}. User provided templates were in use, the variables available to `{}` are: {}",
+                error,
+                template,
+                COMMON_TEMPLATE_VARIABLES
+                    .iter()
+                    .chain(variables)
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .join(", "),
+            )
+        })
+    }
+
     pub(crate) fn render_header(&self) -> Result<String> {
         let context = self.new_tera_ctx();
-        let mut header = self
-            .engine
-            .render("partials/header.j2", &context)
-            .context("Failed to render header comment")?;
+        let mut header = self.render(
+            "partials/header.j2",
+            &context,
+            "Failed to render header comment",
+        )?;
         header.push('\n');
         Ok(header)
     }
 
+    /// Render a crate's BUILD file with the user provided `crate_build_file.j2`
+    /// template. Returns `None` if no such template was provided.
+    pub(crate) fn render_crate_build_file(
+        &self,
+        krate: &CrateContext,
+        platforms: &Platforms,
+        default_build_file: &str,
+    ) -> Result<Option<String>> {
+        if !self.user_templates.contains(CRATE_BUILD_FILE_TEMPLATE) {
+            return Ok(None);
+        }
+
+        let mut context = self.new_tera_ctx();
+        context.insert("crate", krate);
+        context.insert("platforms", platforms);
+        context.insert("default_build_file", default_build_file);
+
+        self.render(
+            CRATE_BUILD_FILE_TEMPLATE,
+            &context,
+            &format!("Failed to render BUILD file for {}", krate.name),
+        )
+        .map(Some)
+    }
+
     pub(crate) fn render_module_bzl(
         &self,
         data: &Context,
@@ -165,18 +266,265 @@ impl TemplateEngine {
         context.insert("platforms", platforms);
         context.insert("generator", &generator);
 
-        self.engine
-            .render("module_bzl.j2", &context)
-            .context("Failed to render crates module")
+        self.render("module_bzl.j2", &context, "Failed to render crates module")
     }
 
     pub(crate) fn render_vendor_module_file(&self, data: &Context) -> Result<String> {
         let mut context = self.new_tera_ctx();
         context.insert("context", data);
 
-        self.engine
-            .render("vendor_module.j2", &context)
-            .context("Failed to render vendor module")
+        self.render(
+            "vendor_module.j2",
+            &context,
+            "Failed to render vendor module",
+        )
+    }
+}
+
+/// Register any user provided templates with the engine, returning the names of
+/// all templates which came from the user.
+fn add_user_templates(tera: &mut tera::Tera, config: &RenderConfig) -> Result<BTreeSet<String>> {
+    for name in config.template_overrides.keys() {
+        if !TEMPLATE_SCHEMA.iter().any(|(known, _)| known == name) {
+            bail!(
+                "`{}` is not a template which can be overridden. Supported templates are: {}",
+                name,
+                TEMPLATE_SCHEMA.iter().map(|(known, _)| known).join(", ")
+            );
+        }
+    }
+    for name in config.template_partials.keys() {
+        if TEMPLATE_SCHEMA.iter().any(|(known, _)| known == name) {
+            bail!(
+                "The template partial `{}` conflicts with a built-in template. Use `template_overrides` to replace it instead.",
+                name
+            );
+        }
+    }
+
+    let templates: Vec<(&str, &str)> = config
+        .template_partials
+        .iter()
+        .chain(config.template_overrides.iter())
+        .map(|(name, content)| (name.as_str(), content.as_str()))
+        .collect();
+    tera.add_raw_templates(templates)
+        .context("Failed to parse user provided templates")?;
+
+    let user_templates = config
+        .template_partials
+        .keys()
+        .chain(config.template_overrides.keys())
+        .cloned()
+        .collect();
+
+    for (name, variables) in TEMPLATE_SCHEMA {
+        if !config.template_overrides.contains_key(*name) {
+            continue;
+        }
+        let scope = COMMON_TEMPLATE_VARIABLES
+            .iter()
+            .chain(*variables)
+            .map(|variable| variable.to_string())
+            .collect();
+        TemplateValidator {
+            tera,
+            user_templates: &user_templates,
+            stack: Vec::new(),
+        }
+        .template(name, scope)?;
+    }
+
+    Ok(user_templates)
+}
+
+/// Checks that user provided templates only reference variables they will be
+/// rendered with and only include templates which exist. User provided partials
+/// are checked with the variables available where they're included.
+struct TemplateValidator<'a> {
+    tera: &'a tera::Tera,
+    user_templates: &'a BTreeSet<String>,
+    /// The templates currently being checked, outermost first.
+    stack: Vec<&'a str>,
+}
+
+impl<'a> TemplateValidator<'a> {
+    fn template(&mut self, name: &'a str, mut scope: BTreeSet<String>) -> Result<()> {
+        // Recursive includes are left for tera to report when rendering.
+        if self.stack.contains(&name) {
+            return Ok(());
+        }
+        let template = self.tera.get_template(name)?;
+        self.stack.push(name);
+        self.nodes(&template.ast, &mut scope)?;
+        self.stack.pop();
+        Ok(())
+    }
+
+    fn nodes(&mut self, nodes: &'a [Node], scope: &mut BTreeSet<String>) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::VariableBlock(_, expr) => self.expr(expr, scope)?,
+                Node::MacroDefinition(_, definition, _) => {
+                    // Macros only see their own arguments.
+                    let mut macro_scope = definition.args.keys().cloned().collect();
+                    for default in definition.args.values().flatten() {
+                        self.expr(default, &macro_scope)?;
+                    }
+                    self.nodes(&definition.body, &mut macro_scope)?;
+                }
+                Node::Include(_, names, ignore_missing) => {
+                    for name in names {
+                        if self.user_templates.contains(name) {
+                            self.template(name, scope.clone())?;
+                        } else if !ignore_missing && self.tera.get_template(name).is_err() {
+                            bail!(
+                                "User provided template `{}` includes the unknown template `{}`{}",
+                                self.current(),
+                                name,
+                                self.included_from(),
+                            );
+                        }
+                    }
+                }
+                Node::ImportMacro(_, name, _) => {
+                    if self.user_templates.contains(name) {
+                        self.template(name, BTreeSet::new())?;
+                    }
+                }
+                Node::Set(_, set) => {
+                    self.expr(&set.value, scope)?;
+                    scope.insert(set.key.clone());
+                }
+                Node::FilterSection(_, section, _) => {
+                    self.function_call(&section.filter, scope)?;
+                    self.nodes(&section.body, scope)?;
+                }
+                Node::Block(_, block, _) => self.nodes(&block.body, scope)?,
+                Node::Forloop(_, forloop, _) => {
+                    self.expr(&forloop.container, scope)?;
+                    let mut loop_scope = scope.clone();
+                    loop_scope.insert("loop".to_owned());
+                    loop_scope.insert(forloop.value.clone());
+                    loop_scope.extend(forloop.key.clone());
+                    self.nodes(&forloop.body, &mut loop_scope)?;
+                    if let Some(empty_body) = &forloop.empty_body {
+                        self.nodes(empty_body, scope)?;
+                    }
+                }
+                Node::If(condition, _) => {
+                    for (_, expr, body) in &condition.conditions {
+                        self.expr(expr, scope)?;
+                        self.nodes(body, scope)?;
+                    }
+                    if let Some((_, body)) = &condition.otherwise {
+                        self.nodes(body, scope)?;
+                    }
+                }
+                Node::Super
+                | Node::Text(_)
+                | Node::Extends(_, _)
+                | Node::Raw(_, _, _)
+                | Node::Break(_)
+                | Node::Continue(_)
+                | Node::Comment(_, _) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn expr(&self, expr: &Expr, scope: &BTreeSet<String>) -> Result<()> {
+        self.expr_val(&expr.val, scope)?;
+        for filter in &expr.filters {
+            self.function_call(filter, scope)?;
+        }
+        Ok(())
+    }
+
+    fn expr_val(&self, val: &ExprVal, scope: &BTreeSet<String>) -> Result<()> {
+        match val {
+            ExprVal::Ident(ident) => self.ident(ident, scope)?,
+            ExprVal::Math(MathExpr { lhs, rhs, .. })
+            | ExprVal::Logic(LogicExpr { lhs, rhs, .. })
+            | ExprVal::In(In { lhs, rhs, .. }) => {
+                self.expr(lhs, scope)?;
+                self.expr(rhs, scope)?;
+            }
+            ExprVal::Test(test) => {
+                // Testing whether a variable exists is always allowed.
+                if !matches!(test.name.as_str(), "defined" | "undefined") {
+                    self.ident(&test.ident, scope)?;
+                }
+                for arg in &test.args {
+                    self.expr(arg, scope)?;
+                }
+            }
+            ExprVal::MacroCall(call) => {
+                for arg in call.args.values() {
+                    self.expr(arg, scope)?;
+                }
+            }
+            ExprVal::FunctionCall(call) => self.function_call(call, scope)?,
+            ExprVal::Array(items) => {
+                for item in items {
+                    self.expr(item, scope)?;
+                }
+            }
+            ExprVal::StringConcat(concat) => {
+                for value in &concat.values {
+                    self.expr_val(value, scope)?;
+                }
+            }
+            ExprVal::String(_) | ExprVal::Int(_) | ExprVal::Float(_) | ExprVal::Bool(_) => {}
+        }
+        Ok(())
+    }
+
+    fn function_call(&self, call: &FunctionCall, scope: &BTreeSet<String>) -> Result<()> {
+        for arg in call.args.values() {
+            self.expr(arg, scope)?;
+        }
+        Ok(())
+    }
+
+    /// Check the variable an identifier like `crate.targets[index]` starts with,
+    /// along with any variables used to index into it.
+    fn ident(&self, ident: &str, scope: &BTreeSet<String>) -> Result<()> {
+        let variable = ident.split(['.', '[']).next().unwrap_or_default();
+        if variable != "__tera_context" && !scope.contains(variable) {
+            bail!(
+                "User provided template `{}` uses the unknown variable `{}`{}. The variables available to it are: {}",
+                self.current(),
+                variable,
+                self.included_from(),
+                scope.iter().join(", "),
+            );
+        }
+        for index in ident.split('[').skip(1) {
+            let index = index.split(']').next().unwrap_or_default();
+            if !index.starts_with(['"', '\'']) && index.parse::<usize>().is_err() {
+                self.ident(index, scope)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn current(&self) -> &str {
+        self.stack.last().copied().unwrap_or_default()
+    }
+
+    fn included_from(&self) -> String {
+        match self.stack.split_last() {
+            Some((_, includers)) if !includers.is_empty() => format!(
+                " (included from {})",
+                includers
+                    .iter()
+                    .rev()
+                    .map(|name| format!("`{name}`"))
+                    .join(" <- ")
+            ),
+            _ => String::new(),
+        }
     }
 }
 
@@ -12,7 +12,7 @@ use std::str::FromStr;
 use anyhow::{anyhow, bail, Context, Result};
 use camino::{Utf8Path, Utf8PathBuf};
 use cargo_lock::package::SourceKind;
-use cargo_toml::Manifest;
+use cargo_toml::{Inheritable, LintGroups, Manifest, Profiles};
 use serde::{Deserialize, Serialize};
 
 use crate::config::CrateId;
@@ -175,6 +175,20 @@ pub(crate) struct WorkspaceMetadata {
     /// We store this here because it's computed during the splicing phase via
     /// calls to "cargo tree" which need the full spliced workspace.
     pub(crate) tree_metadata: TreeResolverMetadata,
+
+    /// The `[profile]` tables of the root manifest. Cargo ignores profiles
+    /// defined anywhere else in a workspace.
+    #[serde(default, skip_serializing_if = "profiles_are_empty")]
+    pub(crate) profiles: Profiles,
+
+    /// The `[lints]` tables of workspace members, with `lints.workspace = true`
+    /// resolved to the `[workspace.lints]` table of the root manifest.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) lints: BTreeMap<CrateId, LintGroups>,
+}
+
+fn profiles_are_empty(profiles: &Profiles) -> bool {
+    profiles == &Profiles::default()
 }
 
 impl TryFrom<toml::Value> for WorkspaceMetadata {
@@ -208,6 +222,7 @@ impl WorkspaceMetadata {
     fn new(
         splicing_manifest: &SplicingManifest,
         member_manifests: BTreeMap<&Utf8PathBuf, String>,
+        profiles: Profiles,
     ) -> Result<Self> {
         let mut package_prefixes: BTreeMap<String, String> = member_manifests
             .iter()
@@ -248,11 +263,13 @@ impl WorkspaceMetadata {
             workspace_prefix,
             package_prefixes,
             tree_metadata: TreeResolverMetadata::new(),
+            profiles,
+            lints: BTreeMap::new(),
         })
     }
 
-    /// Update an existing Cargo manifest with metadata about registry urls and target
-    /// features that are needed in generator steps beyond splicing.
+    /// Update an existing Cargo manifest with metadata about registry urls, target
+    /// features and lints that are needed in generator steps beyond splicing.
     #[tracing::instrument(skip_all)]
     pub(crate) fn write_registry_urls_and_feature_map(
         cargo: &Cargo,
@@ -420,6 +437,8 @@ impl WorkspaceMetadata {
             .sources
             .extend(additional_sources.into_iter());
         workspace_metadata.tree_metadata = resolver_data;
+        workspace_metadata.lints =
+            Self::workspace_member_lints(cargo, input_manifest_path, &manifest)?;
         workspace_metadata.inject_into(&mut manifest)?;
 
         write_root_manifest(output_manifest_path.as_std_path(), manifest)?;
@@ -427,6 +446,38 @@ impl WorkspaceMetadata {
         Ok(())
     }
 
+    /// Collect the `[lints]` of each member of the workspace at `manifest_path`.
+    fn workspace_member_lints(
+        cargo: &Cargo,
+        manifest_path: &Utf8Path,
+        root_manifest: &Manifest,
+    ) -> Result<BTreeMap<CrateId, LintGroups>> {
+        let metadata = cargo
+            .metadata_command_with_options(
+                manifest_path.as_std_path(),
+                vec!["--no-deps".to_owned()],
+            )?
+            .exec()
+            .context("Failed to locate workspace members")?;
+
+        let workspace_lints = root_manifest
+            .workspace
+            .as_ref()
+            .map(|workspace| workspace.lints.clone())
+            .unwrap_or_default();
+
+        let mut lints = BTreeMap::new();
+        for package in metadata.packages {
+            let manifest = read_manifest(&package.manifest_path)
+                .with_context(|| format!("Failed to read {}", package.manifest_path))?;
+            let package_lints = resolve_lints(&manifest, &workspace_lints);
+            if !package_lints.is_empty() {
+                lints.insert(CrateId::new(package.name, package.version), package_lints);
+            }
+        }
+        Ok(lints)
+    }
+
     fn inject_into(&self, manifest: &mut Manifest) -> Result<()> {
         let metadata_value = toml::Value::try_from(self)?;
         let workspace = manifest.workspace.as_mut().unwrap();
@@ -466,6 +517,15 @@ impl SplicedManifest {
     }
 }
 
+/// The lints of a package manifest, taking them from `workspace_lints` if the
+/// package inherits the lints of its workspace.
+fn resolve_lints(manifest: &Manifest, workspace_lints: &LintGroups) -> LintGroups {
+    match &manifest.lints {
+        Inheritable::Set(lints) => lints.clone(),
+        Inheritable::Inherited => workspace_lints.clone(),
+    }
+}
+
 pub(crate) fn read_manifest(manifest: &Utf8Path) -> Result<Manifest> {
     let content = fs::read_to_string(manifest.as_std_path())?;
     cargo_toml::Manifest::from_str(content.as_str()).context("Failed to deserialize manifest")
@@ -686,4 +746,49 @@ mod test {
             "serialized metadata should not contain absolute path"
         );
     }
+
+    #[test]
+    fn resolve_package_and_workspace_lints() {
+        let workspace = Manifest::from_str(indoc::indoc! {r#"
+            [workspace]
+            members = ["inherits", "overrides", "without_lints"]
+
+            [workspace.lints.rust]
+            unsafe_code = "forbid"
+        "#})
+        .unwrap();
+        let workspace_lints = workspace.workspace.unwrap().lints;
+
+        let inherits = Manifest::from_str(indoc::indoc! {r#"
+            [package]
+            name = "inherits"
+            version = "0.1.0"
+
+            [lints]
+            workspace = true
+        "#})
+        .unwrap();
+        assert_eq!(resolve_lints(&inherits, &workspace_lints), workspace_lints);
+
+        let overrides = Manifest::from_str(indoc::indoc! {r#"
+            [package]
+            name = "overrides"
+            version = "0.1.0"
+
+            [lints.clippy]
+            pedantic = { level = "warn", priority = -1 }
+        "#})
+        .unwrap();
+        let lints = resolve_lints(&overrides, &workspace_lints);
+        assert_eq!(lints.keys().collect::<Vec<_>>(), ["clippy"]);
+        assert_eq!(lints["clippy"]["pedantic"].priority, -1);
+
+        let without_lints = Manifest::from_str(indoc::indoc! {r#"
+            [package]
+            name = "without_lints"
+            version = "0.1.0"
+        "#})
+        .unwrap();
+        assert!(resolve_lints(&without_lints, &workspace_lints).is_empty());
+    }
 }
@@ -6,7 +6,7 @@ use std::path::Path;
 
 use anyhow::{bail, Context, Result};
 use camino::{Utf8Path, Utf8PathBuf};
-use cargo_toml::Manifest;
+use cargo_toml::{Manifest, Profiles};
 use tracing::debug;
 
 use crate::config::CrateId;
@@ -181,7 +181,11 @@ impl<'a> SplicerKind<'a> {
         let member_manifests = BTreeMap::from([(*path, String::new())]);
 
         // Write the generated metadata to the manifest
-        let workspace_metadata = WorkspaceMetadata::new(splicing_manifest, member_manifests)?;
+        let workspace_metadata = WorkspaceMetadata::new(
+            splicing_manifest,
+            member_manifests,
+            manifest.profile.clone(),
+        )?;
         workspace_metadata.inject_into(&mut manifest)?;
 
         // Write the root manifest
@@ -233,7 +237,11 @@ impl<'a> SplicerKind<'a> {
         let member_manifests = BTreeMap::from([(*path, String::new())]);
 
         // Write the generated metadata to the manifest
-        let workspace_metadata = WorkspaceMetadata::new(splicing_manifest, member_manifests)?;
+        let workspace_metadata = WorkspaceMetadata::new(
+            splicing_manifest,
+            member_manifests,
+            manifest.profile.clone(),
+        )?;
         workspace_metadata.inject_into(&mut manifest)?;
 
         // Write the root manifest
@@ -272,7 +280,10 @@ impl<'a> SplicerKind<'a> {
         }
 
         // Write the generated metadata to the manifest
-        let workspace_metadata = WorkspaceMetadata::new(splicing_manifest, installations)?;
+        // Profiles of the individual manifests are not carried over as Cargo
+        // would ignore them in non-root manifests.
+        let workspace_metadata =
+            WorkspaceMetadata::new(splicing_manifest, installations, Profiles::default())?;
         workspace_metadata.inject_into(&mut manifest)?;
 
         // Add any additional dependencies to the root package
@@ -10,6 +10,7 @@ mod select_set;
 mod serialize;
 mod target_compatible_with;
 
+use std::collections::BTreeMap;
 use std::collections::BTreeSet as Set;
 
 use serde::{Serialize, Serializer};
@@ -34,6 +35,7 @@ pub(crate) enum Starlark {
     ExportsFiles(ExportsFiles),
     Filegroup(Filegroup),
     Alias(Alias),
+    #[serde(serialize_with = "serialize::cargo_build_script")]
     CargoBuildScript(CargoBuildScript),
     CargoTomlEnvVars(CargoTomlEnvVars),
     #[serde(serialize_with = "serialize::rust_proc_macro")]
@@ -90,9 +92,44 @@ pub(crate) struct Alias {
     pub(crate) tags: Set<String>,
 }
 
+/// The attributes rendered for build scripts and Rust targets. Extra attributes
+/// of overridden rules may not use these names, as they would be rendered twice.
+pub(crate) const GENERATED_RULE_ATTRS: &[&str] = &[
+    "aliases",
+    "build_script_env",
+    "compile_data",
+    "crate_features",
+    "crate_name",
+    "crate_root",
+    "data",
+    "deps",
+    "disable_pipelining",
+    "edition",
+    "link_deps",
+    "linker_script",
+    "links",
+    "name",
+    "pkg_name",
+    "proc_macro_deps",
+    "rundir",
+    "rustc_env",
+    "rustc_env_files",
+    "rustc_flags",
+    "srcs",
+    "tags",
+    "target_compatible_with",
+    "toolchains",
+    "tools",
+    "use_default_shell_env",
+    "version",
+    "visibility",
+];
+
 #[derive(Debug, Serialize)]
-#[serde(rename = "cargo_build_script")]
 pub(crate) struct CargoBuildScript {
+    /// The rule to render, `cargo_build_script` unless overridden.
+    #[serde(skip)]
+    pub(crate) rule: String,
     pub(crate) name: String,
     #[serde(skip_serializing_if = "SelectDict::is_empty")]
     pub(crate) aliases: SelectDict<Label, String>,
@@ -139,6 +176,8 @@ pub(crate) struct CargoBuildScript {
     pub(crate) use_default_shell_env: Option<i32>,
     pub(crate) version: String,
     pub(crate) visibility: Set<String>,
+    #[serde(flatten)]
+    pub(crate) extra_attrs: BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Serialize)]
@@ -150,6 +189,9 @@ pub(crate) struct CargoTomlEnvVars {
 
 #[derive(Serialize)]
 pub(crate) struct RustProcMacro {
+    /// The rule to render, `rust_proc_macro` unless overridden.
+    #[serde(skip)]
+    pub(crate) rule: String,
     pub(crate) name: String,
     #[serde(skip_serializing_if = "SelectSet::is_empty")]
     pub(crate) deps: SelectSet<Label>,
@@ -159,10 +201,15 @@ pub(crate) struct RustProcMacro {
     pub(crate) aliases: SelectDict<Label, String>,
     #[serde(flatten)]
     pub(crate) common: CommonAttrs,
+    #[serde(flatten)]
+    pub(crate) extra_attrs: BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Serialize)]
 pub(crate) struct RustLibrary {
+    /// The rule to render, `rust_library` unless overridden.
+    #[serde(skip)]
+    pub(crate) rule: String,
     pub(crate) name: String,
     #[serde(skip_serializing_if = "SelectSet::is_empty")]
     pub(crate) deps: SelectSet<Label>,
@@ -174,10 +221,15 @@ pub(crate) struct RustLibrary {
     pub(crate) common: CommonAttrs,
     #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub(crate) disable_pipelining: bool,
+    #[serde(flatten)]
+    pub(crate) extra_attrs: BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Serialize)]
 pub(crate) struct RustBinary {
+    /// The rule to render, `rust_binary` unless overridden.
+    #[serde(skip)]
+    pub(crate) rule: String,
     pub(crate) name: String,
     #[serde(skip_serializing_if = "SelectSet::is_empty")]
     pub(crate) deps: SelectSet<Label>,
@@ -187,6 +239,8 @@ pub(crate) struct RustBinary {
     pub(crate) aliases: SelectDict<Label, String>,
     #[serde(flatten)]
     pub(crate) common: CommonAttrs,
+    #[serde(flatten)]
+    pub(crate) extra_attrs: BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Serialize)]
@@ -22,6 +22,10 @@ where
     // new configuration. They could be ignored, but are preserved here to
     // generate comments that help the user understand what happened.
     unmapped: BTreeMap<String, Vec<T>>,
+    // Elements appended in a separate `select` whose configurations are not
+    // platforms (e.g. `compilation_mode` settings) and thus may match at the
+    // same time as one of the platform configurations above.
+    configurations: BTreeMap<String, Vec<T>>,
 }
 
 impl<T> SelectList<T>
@@ -83,12 +87,26 @@ where
             common,
             selects: remapped,
             unmapped,
+            configurations: BTreeMap::new(),
         }
     }
 
+    /// Appends a `select` keyed by the given Bazel configuration labels which is
+    /// resolved independently of the platform specific values.
+    pub(crate) fn with_configurations(mut self, configurations: BTreeMap<String, Vec<T>>) -> Self {
+        self.configurations = configurations
+            .into_iter()
+            .filter(|(_, values)| !values.is_empty())
+            .collect();
+        self
+    }
+
     /// Determine whether or not the select should be serialized
     pub(crate) fn is_empty(&self) -> bool {
-        self.common.is_empty() && self.selects.is_empty() && self.unmapped.is_empty()
+        self.common.is_empty()
+            && self.selects.is_empty()
+            && self.unmapped.is_empty()
+            && self.configurations.is_empty()
     }
 }
 
@@ -130,10 +148,24 @@ where
         //             ],
         //         },
         //     })
+        //
+        // Configurations which are not platforms are rendered in an additional
+        // select so they can match alongside the platform configurations:
+        //
+        //     [
+        //         "common...",
+        //     ] + select({
+        //         "//:compilation_mode_opt": [
+        //             "value...",
+        //         ],
+        //         "//conditions:default": [],
+        //     })
 
         let mut plus = serializer.serialize_tuple_struct("+", MULTILINE)?;
 
-        if !self.common.is_empty() || self.selects.is_empty() && self.unmapped.is_empty() {
+        if !self.common.is_empty()
+            || self.selects.is_empty() && self.unmapped.is_empty() && self.configurations.is_empty()
+        {
             plus.serialize_field(&MultilineArray(&self.common))?;
         }
 
@@ -194,6 +226,34 @@ where
             plus.serialize_field(&FunctionCall::new(function, [SelectInner(self)]))?;
         }
 
+        if !self.configurations.is_empty() {
+            struct ConfigurationsInner<'a, T>(&'a BTreeMap<String, Vec<T>>)
+            where
+                T: SelectableValue;
+
+            impl<T> Serialize for ConfigurationsInner<'_, T>
+            where
+                T: SelectableValue,
+            {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    let mut map = serializer.serialize_map(Some(MULTILINE))?;
+                    for (cfg, values) in self.0.iter() {
+                        map.serialize_entry(cfg, &MultilineArray(values))?;
+                    }
+                    map.serialize_entry("//conditions:default", &[] as &[T])?;
+                    map.end()
+                }
+            }
+
+            plus.serialize_field(&FunctionCall::new(
+                "select",
+                [ConfigurationsInner(&self.configurations)],
+            ))?;
+        }
+
         plus.end()
     }
 }
@@ -397,6 +457,7 @@ mod test {
                 ),
             ]),
             unmapped: BTreeMap::from([("cfg(pdp11)".to_owned(), Vec::from(["dep-e".to_owned()]))]),
+            configurations: BTreeMap::new(),
         };
 
         assert_eq!(select_list, expected);
@@ -441,4 +502,43 @@ mod test {
             expected_starlark,
         );
     }
+
+    #[test]
+    fn configurations_select_list() {
+        let mut select: Select<Vec<String>> = Select::default();
+        select.insert("Hello".to_owned(), Some("platform".to_owned()));
+        select.insert("Goodbye".to_owned(), None);
+
+        let platforms = BTreeMap::from([(
+            "platform".to_owned(),
+            BTreeSet::from(["platform".to_owned()]),
+        )]);
+
+        let select_list =
+            SelectList::new(select, &platforms).with_configurations(BTreeMap::from([
+                ("//:opt".to_owned(), Vec::from(["Fast".to_owned()])),
+                ("//:dbg".to_owned(), Vec::new()),
+            ]));
+
+        let expected_starlark = indoc! {r#"
+            [
+                "Goodbye",
+            ] + select({
+                "platform": [
+                    "Hello",  # platform
+                ],
+                "//conditions:default": [],
+            }) + select({
+                "//:opt": [
+                    "Fast",
+                ],
+                "//conditions:default": [],
+            })
+        "#};
+
+        assert_eq!(
+            select_list.serialize(serde_starlark::Serializer).unwrap(),
+            expected_starlark,
+        );
+    }
 }
@@ -3,32 +3,43 @@ use serde::Serialize;
 use serde_starlark::{FunctionCall, MULTILINE, ONELINE};
 
 use super::{
-    Data, ExportsFiles, License, Load, Package, PackageInfo, RustBinary, RustLibrary, RustProcMacro,
+    CargoBuildScript, Data, ExportsFiles, License, Load, Package, PackageInfo, RustBinary,
+    RustLibrary, RustProcMacro,
 };
 
 // For structs that contain #[serde(flatten)], a quirk of how Serde processes
 // that attribute is that they get serialized as a map, not struct. In Starlark
 // unlike in JSON, maps and structs are differently serialized, so we need to
 // help fill in the function name or else we'd get a Starlark map instead.
+pub(crate) fn cargo_build_script<S>(
+    rule: &CargoBuildScript,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    FunctionCall::new(&rule.rule, rule).serialize(serializer)
+}
+
 pub(crate) fn rust_proc_macro<S>(rule: &RustProcMacro, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    FunctionCall::new("rust_proc_macro", rule).serialize(serializer)
+    FunctionCall::new(&rule.rule, rule).serialize(serializer)
 }
 
 pub(crate) fn rust_library<S>(rule: &RustLibrary, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    FunctionCall::new("rust_library", rule).serialize(serializer)
+    FunctionCall::new(&rule.rule, rule).serialize(serializer)
 }
 
 pub(crate) fn rust_binary<S>(rule: &RustBinary, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    FunctionCall::new("rust_binary", rule).serialize(serializer)
+    FunctionCall::new(&rule.rule, rule).serialize(serializer)
 }
 
 // Serialize an array with each element on its own line, even if there is just a
@@ -6,6 +6,7 @@ Bazel rules for [mdBook](https://github.com/rust-lang/mdBook).
 
 - [mdbook](#mdbook)
 - [mdbook_server](#mdbook_server)
+- [mdbook_test](#mdbook_test)
 - [mdbook_toolchain](#mdbook_toolchain)
 
 ## Setup
@@ -38,6 +39,7 @@ load(
     "//private:mdbook.bzl",
     _mdbook = "mdbook",
     _mdbook_server = "mdbook_server",
+    _mdbook_test = "mdbook_test",
 )
 load(
     "//private:toolchain.bzl",
@@ -46,4 +48,5 @@ load(
 
 mdbook = _mdbook
 mdbook_server = _mdbook_server
+mdbook_test = _mdbook_test
 mdbook_toolchain = _mdbook_toolchain
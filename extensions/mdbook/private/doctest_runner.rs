@@ -0,0 +1,396 @@
+//! A test runner for compiling and running the Rust code blocks of an mdBook.
+//!
+//! Each chapter is reduced to its fenced `rust` code blocks (and headings, which
+//! rustdoc uses to name tests) while preserving line numbers. The result is then
+//! passed to `rustdoc --test`, which honors annotations such as `ignore`, `no_run`
+//! and `should_panic` and reports failures with the chapter and line of the block.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+use runfiles::rlocation;
+
+/// A chapter of a book.
+struct Chapter {
+    /// The runfiles path of the chapter.
+    path: PathBuf,
+
+    /// The path of the chapter relative to the book's `book.toml`.
+    relative: PathBuf,
+}
+
+/// A crate passed to rustdoc via `--extern`.
+struct Extern {
+    /// The name of the crate.
+    name: String,
+
+    /// The path of the crate's library.
+    path: PathBuf,
+}
+
+struct Args {
+    pub rustdoc: PathBuf,
+
+    pub sysroot: PathBuf,
+
+    pub edition: String,
+
+    pub chapters: Vec<Chapter>,
+
+    pub externs: Vec<Extern>,
+
+    pub dependencies: Vec<PathBuf>,
+
+    pub rustdoc_flags: Vec<String>,
+
+    pub test_args: Vec<String>,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let runfiles = runfiles::Runfiles::create().unwrap();
+
+        let args_env = env::var("RULES_MDBOOK_DOCTEST_ARGS_FILE").unwrap();
+        let args_file = rlocation!(runfiles, args_env).unwrap();
+        let raw_args = action_args::try_parse_args(&args_file).unwrap();
+
+        let mut rustdoc: Option<PathBuf> = None;
+        let mut sysroot: Option<PathBuf> = None;
+        let mut edition: Option<String> = None;
+        let mut chapters: Vec<Chapter> = Vec::new();
+        let mut externs: Vec<Extern> = Vec::new();
+        let mut dependencies: Vec<PathBuf> = Vec::new();
+        let mut rustdoc_flags: Vec<String> = Vec::new();
+
+        for arg in raw_args {
+            let (flag, val) = arg
+                .split_once('=')
+                .unwrap_or_else(|| panic!("Unexpected argument: {}", arg));
+            match flag {
+                "--rustdoc" => rustdoc = Some(rlocation!(runfiles, val).unwrap()),
+                "--sysroot" => sysroot = Some(rlocation!(runfiles, val).unwrap()),
+                "--edition" => edition = Some(val.to_string()),
+                "--chapter" => {
+                    let (path, relative) = val
+                        .split_once('=')
+                        .unwrap_or_else(|| panic!("Unexpected chapter: {}", val));
+                    chapters.push(Chapter {
+                        path: rlocation!(runfiles, path).unwrap(),
+                        relative: PathBuf::from(relative),
+                    });
+                }
+                "--extern" => {
+                    let (name, path) = val
+                        .split_once('=')
+                        .unwrap_or_else(|| panic!("Unexpected extern: {}", val));
+                    externs.push(Extern {
+                        name: name.to_string(),
+                        path: rlocation!(runfiles, path).unwrap(),
+                    });
+                }
+                "--dependency" => dependencies.push(rlocation!(runfiles, val).unwrap()),
+                "--rustdoc_flag" => rustdoc_flags.push(val.to_string()),
+                _ => panic!("Unexpected argument: {}", arg),
+            }
+        }
+
+        Self {
+            rustdoc: rustdoc.unwrap(),
+            sysroot: sysroot.unwrap(),
+            edition: edition.unwrap(),
+            chapters,
+            externs,
+            dependencies,
+            rustdoc_flags,
+            test_args: env::args().skip(1).collect(),
+        }
+    }
+}
+
+/// An open code fence.
+struct Fence {
+    /// The character used for the fence (`` ` `` or `~`).
+    marker: char,
+
+    /// The number of marker characters which opened the fence.
+    len: usize,
+
+    /// Whether or not the fenced block is Rust code.
+    is_rust: bool,
+}
+
+/// Parse a line as a code fence, returning the fence and its info string.
+fn parse_fence(line: &str) -> Option<(char, usize, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = &line[indent..];
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.len() - line.trim_start_matches(marker).len();
+    if len < 3 {
+        return None;
+    }
+    Some((marker, len, line[len..].trim()))
+}
+
+/// Determine whether or not the info string of a code fence marks a Rust code block.
+fn is_rust_info(info: &str) -> bool {
+    info.split(|c: char| c == ',' || c.is_whitespace())
+        .any(|token| token == "rust")
+}
+
+/// Determine whether or not a line is an ATX heading.
+fn is_heading(line: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return false;
+    }
+    let level = trimmed.len() - trimmed.trim_start_matches('#').len();
+    (1..=6).contains(&level)
+        && trimmed[level..]
+            .chars()
+            .next()
+            .is_none_or(char::is_whitespace)
+}
+
+/// Reduce a markdown document to its fenced `rust` code blocks and headings.
+///
+/// All other lines are replaced with empty lines so the line numbers reported
+/// by rustdoc match the original document.
+///
+/// Returns the reduced document and the number of Rust code blocks it contains.
+fn extract_rust_blocks(content: &str) -> (String, usize) {
+    let mut output = String::with_capacity(content.len());
+    let mut blocks = 0;
+    let mut fence: Option<Fence> = None;
+
+    for line in content.lines() {
+        let keep = match &fence {
+            Some(open) => {
+                let keep = open.is_rust;
+                if let Some((marker, len, info)) = parse_fence(line) {
+                    if marker == open.marker && len >= open.len && info.is_empty() {
+                        fence = None;
+                    }
+                }
+                keep
+            }
+            None => {
+                if let Some((marker, len, info)) = parse_fence(line) {
+                    let is_rust = is_rust_info(info);
+                    if is_rust {
+                        blocks += 1;
+                    }
+                    fence = Some(Fence {
+                        marker,
+                        len,
+                        is_rust,
+                    });
+                    is_rust
+                } else {
+                    is_heading(line)
+                }
+            }
+        };
+
+        if keep {
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+
+    (output, blocks)
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let work_dir = PathBuf::from(env::var("TEST_TMPDIR").expect("TEST_TMPDIR is not set"))
+        .join("rules_mdbook_doctest");
+
+    let mut tested = 0;
+    let mut failures: Vec<&Path> = Vec::new();
+
+    for chapter in &args.chapters {
+        let content = fs::read_to_string(&chapter.path)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", chapter.path.display(), e));
+        let (doctest, blocks) = extract_rust_blocks(&content);
+        if blocks == 0 {
+            continue;
+        }
+
+        // Write the reduced chapter to the same relative path so rustdoc
+        // reports failures in terms of the chapter.
+        let doctest_path = work_dir.join(&chapter.relative);
+        fs::create_dir_all(doctest_path.parent().unwrap()).unwrap();
+        fs::write(&doctest_path, doctest).unwrap();
+
+        let mut command = Command::new(&args.rustdoc);
+        command
+            .current_dir(&work_dir)
+            .arg("--test")
+            .arg(&chapter.relative)
+            .arg("--edition")
+            .arg(&args.edition)
+            .arg("--sysroot")
+            .arg(&args.sysroot);
+
+        for dependency in &args.dependencies {
+            let mut flag = std::ffi::OsString::from("dependency=");
+            flag.push(dependency.parent().unwrap());
+            command.arg("-L").arg(flag);
+        }
+
+        for ext in &args.externs {
+            command
+                .arg("--extern")
+                .arg(format!("{}={}", ext.name, ext.path.display()));
+        }
+
+        command.args(&args.rustdoc_flags);
+
+        for test_arg in &args.test_args {
+            command.arg("--test-args").arg(test_arg);
+        }
+
+        println!(
+            "Testing {} ({} code blocks)",
+            chapter.relative.display(),
+            blocks
+        );
+        let status = command
+            .status()
+            .unwrap_or_else(|e| panic!("Failed to spawn rustdoc command\n{:?}\n{:#?}", e, command));
+
+        tested += 1;
+        if !status.success() {
+            failures.push(&chapter.relative);
+        }
+    }
+
+    if !failures.is_empty() {
+        eprintln!("Doc tests failed in {} chapter(s):", failures.len());
+        for chapter in &failures {
+            eprintln!("    {}", chapter.display());
+        }
+        std::process::exit(1);
+    }
+
+    println!("Doc tests passed in {} chapter(s).", tested);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_extract_rust_blocks() {
+        let content = [
+            "# Chapter 1",
+            "",
+            "Some prose.",
+            "",
+            "```rust",
+            "assert_eq!(1 + 1, 2);",
+            "```",
+            "",
+            "```toml",
+            "[package]",
+            "```",
+            "",
+            "## Section",
+            "",
+            "~~~rust,should_panic",
+            "panic!();",
+            "~~~",
+            "",
+            "```",
+            "not rust",
+            "```",
+        ]
+        .join("\n");
+
+        let (output, blocks) = extract_rust_blocks(&content);
+        assert_eq!(blocks, 2);
+        assert_eq!(
+            output.lines().collect::<Vec<_>>(),
+            vec![
+                "# Chapter 1",
+                "",
+                "",
+                "",
+                "```rust",
+                "assert_eq!(1 + 1, 2);",
+                "```",
+                "",
+                "",
+                "",
+                "",
+                "",
+                "## Section",
+                "",
+                "~~~rust,should_panic",
+                "panic!();",
+                "~~~",
+                "",
+                "",
+                "",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_rust_blocks_nested_fences() {
+        let content = [
+            "````markdown",
+            "```rust",
+            "fn main() {}",
+            "```",
+            "````",
+            "```rust,ignore",
+            "# hidden line",
+            "```",
+        ]
+        .join("\n");
+
+        let (output, blocks) = extract_rust_blocks(&content);
+        assert_eq!(blocks, 1);
+        assert_eq!(
+            output.lines().collect::<Vec<_>>(),
+            vec![
+                "",
+                "",
+                "",
+                "",
+                "",
+                "```rust,ignore",
+                "# hidden line",
+                "```"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_rust_info() {
+        assert!(is_rust_info("rust"));
+        assert!(is_rust_info("rust,no_run"));
+        assert!(is_rust_info("rust ignore"));
+        assert!(is_rust_info("ignore,rust"));
+        assert!(!is_rust_info(""));
+        assert!(!is_rust_info("toml"));
+        assert!(!is_rust_info("rustc"));
+    }
+
+    #[test]
+    fn test_is_heading() {
+        assert!(is_heading("# Title"));
+        assert!(is_heading("### Title"));
+        assert!(is_heading("#"));
+        assert!(!is_heading("#Title"));
+        assert!(!is_heading("####### Title"));
+        assert!(!is_heading("    # Title"));
+    }
+}
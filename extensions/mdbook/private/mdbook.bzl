@@ -1,5 +1,7 @@
 """mdBook rules"""
 
+load("@rules_rust//rust:defs.bzl", "rust_common")
+
 MdBookInfo = provider(
     doc = "Information about a `mdbook` target.",
     fields = {
@@ -184,3 +186,147 @@ mdbook_server = rule(
     toolchains = ["@rules_rust_mdbook//:toolchain_type"],
     executable = True,
 )
+
+def _chapter_relative_path(file, book_dir):
+    if file.short_path.startswith(book_dir + "/"):
+        return file.short_path[len(book_dir) + 1:]
+
+    return file.basename
+
+def _mdbook_test_impl(ctx):
+    toolchain = ctx.toolchains["@rules_rust//rust:toolchain_type"]
+    book_info = ctx.attr.book[MdBookInfo]
+    workspace_name = ctx.workspace_name
+
+    args = ctx.actions.args()
+
+    args.add("--rustdoc={}".format(_rlocationpath(toolchain.rust_doc, workspace_name)))
+    sysroot = toolchain.sysroot_short_path
+    if sysroot.startswith("../"):
+        sysroot = sysroot[len("../"):]
+    else:
+        sysroot = "{}/{}".format(workspace_name, sysroot)
+    args.add("--sysroot={}".format(sysroot))
+    args.add("--edition={}".format(ctx.attr.edition or toolchain.default_edition))
+
+    book_dir = book_info.config.short_path.rpartition("/")[0]
+    chapters = [src for src in book_info.srcs.to_list() if src.extension == "md"]
+    for chapter in chapters:
+        args.add("--chapter={}={}".format(
+            _rlocationpath(chapter, workspace_name),
+            _chapter_relative_path(chapter, book_dir),
+        ))
+
+    crate_files = []
+    transitive_crates = []
+    for dep in ctx.attr.deps:
+        crate_info = dep[rust_common.crate_info]
+        crate_files.append(crate_info.output)
+        args.add("--extern={}={}".format(
+            crate_info.name,
+            _rlocationpath(crate_info.output, workspace_name),
+        ))
+        transitive_crates.append(dep[rust_common.dep_info].transitive_crates)
+
+    for crate_info in depset(transitive = transitive_crates).to_list():
+        crate_files.append(crate_info.output)
+        args.add("--dependency={}".format(_rlocationpath(crate_info.output, workspace_name)))
+
+    for flag in ctx.attr.rustdoc_flags:
+        args.add("--rustdoc_flag={}".format(flag))
+
+    args_file = ctx.actions.declare_file("{}.mdbook_doctest_args.txt".format(ctx.label.name))
+    ctx.actions.write(
+        output = args_file,
+        content = args,
+    )
+
+    is_windows = toolchain.target_os == "windows"
+    executable = ctx.actions.declare_file("{}{}".format(
+        ctx.label.name,
+        ".exe" if is_windows else "",
+    ))
+
+    ctx.actions.symlink(
+        output = executable,
+        target_file = ctx.executable._doctest_runner,
+        is_executable = True,
+    )
+
+    return [
+        DefaultInfo(
+            executable = executable,
+            files = depset([executable]),
+            runfiles = ctx.runfiles(
+                files = [
+                    args_file,
+                    ctx.executable._doctest_runner,
+                ] + chapters + crate_files,
+                transitive_files = toolchain.all_files,
+            ).merge(ctx.attr._doctest_runner[DefaultInfo].default_runfiles),
+        ),
+        RunEnvironmentInfo(
+            environment = {
+                "RULES_MDBOOK_DOCTEST_ARGS_FILE": _rlocationpath(args_file, workspace_name),
+            },
+        ),
+    ]
+
+mdbook_test = rule(
+    implementation = _mdbook_test_impl,
+    doc = """\
+Compile and run the fenced `rust` code blocks of an `mdbook` as doc tests.
+
+Code blocks are tested with `rustdoc --test`, so annotations such as `ignore`, `no_run`,
+`should_panic` and `compile_fail` are honored and failures are reported with the chapter
+and line number of the code block. Additional arguments passed to the test (e.g.
+`--test_arg=<filter>`) are forwarded to the doc test harness.
+
+```python
+load("@rules_rust//rust:defs.bzl", "rust_library")
+load("@rules_rust_mdbook//:defs.bzl", "mdbook", "mdbook_test")
+
+rust_library(
+    name = "greeter",
+    srcs = ["greeter.rs"],
+)
+
+mdbook(
+    name = "book",
+    srcs = glob(["src/**/*.md"]),
+    book = "book.toml",
+)
+
+mdbook_test(
+    name = "book_test",
+    book = ":book",
+    deps = [":greeter"],
+)
+```
+""",
+    attrs = {
+        "book": attr.label(
+            doc = "The `mdbook` target whose code blocks should be tested.",
+            providers = [MdBookInfo],
+            mandatory = True,
+        ),
+        "deps": attr.label_list(
+            doc = "Rust libraries made available to code blocks via `--extern`.",
+            providers = [rust_common.crate_info],
+        ),
+        "edition": attr.string(
+            doc = "The Rust edition of the code blocks. Defaults to the edition of the Rust toolchain.",
+        ),
+        "rustdoc_flags": attr.string_list(
+            doc = "Additional flags passed to `rustdoc`.",
+        ),
+        "_doctest_runner": attr.label(
+            doc = "The test runner which invokes `rustdoc --test` for each chapter.",
+            cfg = "target",
+            executable = True,
+            default = Label("//private:doctest_runner"),
+        ),
+    },
+    toolchains = ["@rules_rust//rust:toolchain_type"],
+    test = True,
+)
@@ -0,0 +1,7 @@
+//! A library used by the code blocks of `@rules_rust_mdbook//test/doctest`.
+
+/// Greet someone by name.
+pub fn greet(name: &str) -> String {
+    assert!(!name.is_empty(), "A name is required");
+    format!("Hello, {}!", name)
+}
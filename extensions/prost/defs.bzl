@@ -131,6 +131,31 @@ toolchain(
 )
 ```
 
+To also generate JSON support with [pbjson](https://crates.io/crates/pbjson), add the
+[`protoc-gen-prost-serde`](https://crates.io/crates/protoc-gen-prost-serde) plugin and the
+`pbjson` and `serde` runtime crates to the toolchain. Serde implementations are generated into
+the same modules as the messages they are for. When `compile_well_known_types` is disabled,
+`serde_types` maps well known types to a crate with serde support such as `pbjson-types`:
+
+```python
+rust_library_group(
+    name = "serde_runtime",
+    deps = [
+        "@crates_io//:pbjson",
+        "@crates_io//:serde",
+    ],
+)
+
+rust_prost_toolchain(
+    name = "prost_toolchain_impl",
+    # ...
+    compile_well_known_types = False,
+    serde_plugin = "@crates_io//:protoc-gen-prost-serde__protoc-gen-prost-serde",
+    serde_runtime = ":serde_runtime",
+    serde_types = "@crates_io//:pbjson-types",
+)
+```
+
 Lastly, you must register the toolchain in your `WORKSPACE` file. For example:
 
 ```python
@@ -296,10 +296,16 @@ _NORMAL_DEPENDENCIES = {
     "": {
         _COMMON_CONDITION: {
             "h2": Label("@rrprd//:h2-0.4.6"),
+            "pbjson": Label("@rrprd//:pbjson-0.7.0"),
+            "pbjson-types": Label("@rrprd//:pbjson-types-0.7.0"),
             "prost": Label("@rrprd//:prost-0.13.1"),
+            "prost-reflect": Label("@rrprd//:prost-reflect-0.14.7"),
             "prost-types": Label("@rrprd//:prost-types-0.13.1"),
             "protoc-gen-prost": Label("@rrprd//:protoc-gen-prost-0.4.0"),
+            "protoc-gen-prost-serde": Label("@rrprd//:protoc-gen-prost-serde-0.3.1"),
             "protoc-gen-tonic": Label("@rrprd//:protoc-gen-tonic-0.4.1"),
+            "serde": Label("@rrprd//:serde-1.0.209"),
+            "serde_json": Label("@rrprd//:serde_json-1.0.143"),
             "tokio": Label("@rrprd//:tokio-1.39.3"),
             "tokio-stream": Label("@rrprd//:tokio-stream-0.1.15"),
             "tonic": Label("@rrprd//:tonic-0.12.1"),
@@ -387,7 +393,6 @@ _CONDITIONS = {
     "cfg(all(target_arch = \"x86\", target_env = \"gnu\", not(target_abi = \"llvm\"), not(windows_raw_dylib)))": ["@rules_rust//rust/platform:i686-unknown-linux-gnu"],
     "cfg(all(target_arch = \"x86\", target_env = \"msvc\", not(windows_raw_dylib)))": ["@rules_rust//rust/platform:i686-pc-windows-msvc"],
     "cfg(all(target_arch = \"x86_64\", target_env = \"gnu\", not(target_abi = \"llvm\"), not(windows_raw_dylib)))": ["@rules_rust//rust/platform:x86_64-unknown-linux-gnu", "@rules_rust//rust/platform:x86_64-unknown-nixos-gnu"],
-    "cfg(any())": [],
     "cfg(any(unix, target_os = \"wasi\"))": ["@rules_rust//rust/platform:aarch64-apple-darwin", "@rules_rust//rust/platform:aarch64-apple-ios", "@rules_rust//rust/platform:aarch64-apple-ios-sim", "@rules_rust//rust/platform:aarch64-linux-android", "@rules_rust//rust/platform:aarch64-unknown-fuchsia", "@rules_rust//rust/platform:aarch64-unknown-linux-gnu", "@rules_rust//rust/platform:aarch64-unknown-nixos-gnu", "@rules_rust//rust/platform:aarch64-unknown-nto-qnx710", "@rules_rust//rust/platform:arm-unknown-linux-gnueabi", "@rules_rust//rust/platform:armv7-linux-androideabi", "@rules_rust//rust/platform:armv7-unknown-linux-gnueabi", "@rules_rust//rust/platform:i686-apple-darwin", "@rules_rust//rust/platform:i686-linux-android", "@rules_rust//rust/platform:i686-unknown-freebsd", "@rules_rust//rust/platform:i686-unknown-linux-gnu", "@rules_rust//rust/platform:powerpc-unknown-linux-gnu", "@rules_rust//rust/platform:s390x-unknown-linux-gnu", "@rules_rust//rust/platform:wasm32-unknown-emscripten", "@rules_rust//rust/platform:wasm32-wasip1", "@rules_rust//rust/platform:wasm32-wasip1-threads", "@rules_rust//rust/platform:wasm32-wasip2", "@rules_rust//rust/platform:x86_64-apple-darwin", "@rules_rust//rust/platform:x86_64-apple-ios", "@rules_rust//rust/platform:x86_64-linux-android", "@rules_rust//rust/platform:x86_64-unknown-freebsd", "@rules_rust//rust/platform:x86_64-unknown-fuchsia", "@rules_rust//rust/platform:x86_64-unknown-linux-gnu", "@rules_rust//rust/platform:x86_64-unknown-nixos-gnu"],
     "cfg(not(all(windows, target_env = \"msvc\", not(target_vendor = \"uwp\"))))": ["@rules_rust//rust/platform:aarch64-apple-darwin", "@rules_rust//rust/platform:aarch64-apple-ios", "@rules_rust//rust/platform:aarch64-apple-ios-sim", "@rules_rust//rust/platform:aarch64-linux-android", "@rules_rust//rust/platform:aarch64-unknown-fuchsia", "@rules_rust//rust/platform:aarch64-unknown-linux-gnu", "@rules_rust//rust/platform:aarch64-unknown-nixos-gnu", "@rules_rust//rust/platform:aarch64-unknown-nto-qnx710", "@rules_rust//rust/platform:aarch64-unknown-uefi", "@rules_rust//rust/platform:arm-unknown-linux-gnueabi", "@rules_rust//rust/platform:armv7-linux-androideabi", "@rules_rust//rust/platform:armv7-unknown-linux-gnueabi", "@rules_rust//rust/platform:i686-apple-darwin", "@rules_rust//rust/platform:i686-linux-android", "@rules_rust//rust/platform:i686-unknown-freebsd", "@rules_rust//rust/platform:i686-unknown-linux-gnu", "@rules_rust//rust/platform:powerpc-unknown-linux-gnu", "@rules_rust//rust/platform:riscv32imc-unknown-none-elf", "@rules_rust//rust/platform:riscv64gc-unknown-none-elf", "@rules_rust//rust/platform:s390x-unknown-linux-gnu", "@rules_rust//rust/platform:thumbv7em-none-eabi", "@rules_rust//rust/platform:thumbv8m.main-none-eabi", "@rules_rust//rust/platform:wasm32-unknown-emscripten", "@rules_rust//rust/platform:wasm32-unknown-unknown", "@rules_rust//rust/platform:wasm32-wasip1", "@rules_rust//rust/platform:wasm32-wasip1-threads", "@rules_rust//rust/platform:wasm32-wasip2", "@rules_rust//rust/platform:x86_64-apple-darwin", "@rules_rust//rust/platform:x86_64-apple-ios", "@rules_rust//rust/platform:x86_64-linux-android", "@rules_rust//rust/platform:x86_64-unknown-freebsd", "@rules_rust//rust/platform:x86_64-unknown-fuchsia", "@rules_rust//rust/platform:x86_64-unknown-linux-gnu", "@rules_rust//rust/platform:x86_64-unknown-nixos-gnu", "@rules_rust//rust/platform:x86_64-unknown-none", "@rules_rust//rust/platform:x86_64-unknown-uefi"],
     "cfg(target_os = \"hermit\")": [],
@@ -554,6 +559,16 @@ def crate_repositories():
         build_file = Label("//private/3rdparty/crates:BUILD.backtrace-0.3.73.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "rrprd__base64-0.21.7",
+        sha256 = "9d297deb1925b89f2ccc13d7635fa0714f12c87adce1c75356b39ca9b7178567",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/base64/0.21.7/download"],
+        strip_prefix = "base64-0.21.7",
+        build_file = Label("//private/3rdparty/crates:BUILD.base64-0.21.7.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "rrprd__base64-0.22.1",
@@ -614,6 +629,16 @@ def crate_repositories():
         build_file = Label("//private/3rdparty/crates:BUILD.cfg-if-1.0.0.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "rrprd__chrono-0.4.45",
+        sha256 = "1aa79e62e7697b8e29b513a68abacf485adcd1fe8284a4316c5ae868e6633327",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/chrono/0.4.45/download"],
+        strip_prefix = "chrono-0.4.45",
+        build_file = Label("//private/3rdparty/crates:BUILD.chrono-0.4.45.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "rrprd__either-1.13.0",
@@ -1014,6 +1039,16 @@ def crate_repositories():
         build_file = Label("//private/3rdparty/crates:BUILD.multimap-0.10.0.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "rrprd__num-traits-0.2.19",
+        sha256 = "071dfc062690e90b734c0b2273ce72ad0ffa95f0c74596bc250dcfd960262841",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/num-traits/0.2.19/download"],
+        strip_prefix = "num-traits-0.2.19",
+        build_file = Label("//private/3rdparty/crates:BUILD.num-traits-0.2.19.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "rrprd__object-0.36.3",
@@ -1054,6 +1089,36 @@ def crate_repositories():
         build_file = Label("//private/3rdparty/crates:BUILD.parking_lot_core-0.9.10.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "rrprd__pbjson-0.7.0",
+        sha256 = "c7e6349fa080353f4a597daffd05cb81572a9c031a6d4fff7e504947496fcc68",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/pbjson/0.7.0/download"],
+        strip_prefix = "pbjson-0.7.0",
+        build_file = Label("//private/3rdparty/crates:BUILD.pbjson-0.7.0.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "rrprd__pbjson-build-0.7.0",
+        sha256 = "6eea3058763d6e656105d1403cb04e0a41b7bbac6362d413e7c33be0c32279c9",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/pbjson-build/0.7.0/download"],
+        strip_prefix = "pbjson-build-0.7.0",
+        build_file = Label("//private/3rdparty/crates:BUILD.pbjson-build-0.7.0.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "rrprd__pbjson-types-0.7.0",
+        sha256 = "e54e5e7bfb1652f95bc361d76f3c780d8e526b134b85417e774166ee941f0887",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/pbjson-types/0.7.0/download"],
+        strip_prefix = "pbjson-types-0.7.0",
+        build_file = Label("//private/3rdparty/crates:BUILD.pbjson-types-0.7.0.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "rrprd__percent-encoding-2.3.1",
@@ -1174,6 +1239,16 @@ def crate_repositories():
         build_file = Label("//private/3rdparty/crates:BUILD.prost-derive-0.13.1.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "rrprd__prost-reflect-0.14.7",
+        sha256 = "7b5edd582b62f5cde844716e66d92565d7faf7ab1445c8cebce6e00fba83ddb2",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/prost-reflect/0.14.7/download"],
+        strip_prefix = "prost-reflect-0.14.7",
+        build_file = Label("//private/3rdparty/crates:BUILD.prost-reflect-0.14.7.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "rrprd__prost-types-0.13.1",
@@ -1194,6 +1269,16 @@ def crate_repositories():
         build_file = Label("//private/3rdparty/crates:BUILD.protoc-gen-prost-0.4.0.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "rrprd__protoc-gen-prost-serde-0.3.1",
+        sha256 = "82173b1dec0d3d8e75788d94781c6a18a37f1e0622c90acd566f62088b2538f5",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/protoc-gen-prost-serde/0.3.1/download"],
+        strip_prefix = "protoc-gen-prost-serde-0.3.1",
+        build_file = Label("//private/3rdparty/crates:BUILD.protoc-gen-prost-serde-0.3.1.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "rrprd__protoc-gen-tonic-0.4.1",
@@ -1314,6 +1399,16 @@ def crate_repositories():
         build_file = Label("//private/3rdparty/crates:BUILD.rustversion-1.0.17.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "rrprd__ryu-1.0.23",
+        sha256 = "9774ba4a74de5f7b1c1451ed6cd5285a32eddb5cccb8cc655a4e50009e06477f",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/ryu/1.0.23/download"],
+        strip_prefix = "ryu-1.0.23",
+        build_file = Label("//private/3rdparty/crates:BUILD.ryu-1.0.23.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "rrprd__scopeguard-1.2.0",
@@ -1344,6 +1439,16 @@ def crate_repositories():
         build_file = Label("//private/3rdparty/crates:BUILD.serde_derive-1.0.209.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "rrprd__serde_json-1.0.143",
+        sha256 = "d401abef1d108fbd9cbaebc3e46611f4b1021f714a0597a71f41ee463f5f4a5a",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/serde_json/1.0.143/download"],
+        strip_prefix = "serde_json-1.0.143",
+        build_file = Label("//private/3rdparty/crates:BUILD.serde_json-1.0.143.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "rrprd__shlex-1.3.0",
@@ -1726,10 +1831,16 @@ def crate_repositories():
 
     return [
         struct(repo = "rrprd__h2-0.4.6", is_dev_dep = False),
+        struct(repo = "rrprd__pbjson-0.7.0", is_dev_dep = False),
+        struct(repo = "rrprd__pbjson-types-0.7.0", is_dev_dep = False),
         struct(repo = "rrprd__prost-0.13.1", is_dev_dep = False),
+        struct(repo = "rrprd__prost-reflect-0.14.7", is_dev_dep = False),
         struct(repo = "rrprd__prost-types-0.13.1", is_dev_dep = False),
         struct(repo = "rrprd__protoc-gen-prost-0.4.0", is_dev_dep = False),
+        struct(repo = "rrprd__protoc-gen-prost-serde-0.3.1", is_dev_dep = False),
         struct(repo = "rrprd__protoc-gen-tonic-0.4.1", is_dev_dep = False),
+        struct(repo = "rrprd__serde-1.0.209", is_dev_dep = False),
+        struct(repo = "rrprd__serde_json-1.0.143", is_dev_dep = False),
         struct(repo = "rrprd__tokio-1.39.3", is_dev_dep = False),
         struct(repo = "rrprd__tokio-stream-0.1.15", is_dev_dep = False),
         struct(repo = "rrprd__tonic-0.12.1", is_dev_dep = False),
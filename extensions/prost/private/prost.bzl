@@ -46,6 +46,21 @@ def _create_proto_lang_toolchain(ctx, prost_toolchain):
 
     return proto_lang_toolchain
 
+def _path_attributes(attributes):
+    """Flattens a mapping of protobuf paths to attributes into `PATH=ATTRIBUTE` values.
+
+    Args:
+      attributes (dict): A mapping of protobuf paths to lists of Rust attributes.
+
+    Returns:
+      list: A `PATH=ATTRIBUTE` value for each attribute.
+    """
+    return [
+        "{}={}".format(path, attribute)
+        for path, path_attributes in attributes.items()
+        for attribute in path_attributes
+    ]
+
 def _compile_proto(
         *,
         ctx,
@@ -71,10 +86,24 @@ def _compile_proto(
     tonic_opts = []
     prost_opts = []
     additional_srcs = []
+    type_attributes = _path_attributes(prost_toolchain.type_attributes)
+    field_attributes = _path_attributes(prost_toolchain.field_attributes)
+    bytes_paths = list(prost_toolchain.bytes)
+    btree_map_paths = list(prost_toolchain.btree_map)
+    boxed_paths = list(prost_toolchain.boxed)
+    tonic_generate_server = prost_toolchain.tonic_generate_server
+    tonic_generate_client = prost_toolchain.tonic_generate_client
     for transform_info in transform_infos:
         tonic_opts.extend(transform_info.tonic_opts)
         prost_opts.extend(transform_info.prost_opts)
         additional_srcs.append(transform_info.srcs)
+        type_attributes.extend(_path_attributes(transform_info.type_attributes))
+        field_attributes.extend(_path_attributes(transform_info.field_attributes))
+        bytes_paths.extend(transform_info.bytes)
+        btree_map_paths.extend(transform_info.btree_map)
+        boxed_paths.extend(transform_info.boxed)
+        tonic_generate_server = tonic_generate_server and transform_info.tonic_generate_server
+        tonic_generate_client = tonic_generate_client and transform_info.tonic_generate_client
 
     all_additional_srcs = depset(transitive = additional_srcs)
     direct_crate_names = [dep[ProstProtoInfo].dep_variant_info.crate_info.name for dep in deps]
@@ -92,23 +121,65 @@ def _compile_proto(
     additional_args.add(proto_info.direct_descriptor_set, format = "--descriptor_set=%s")
     additional_args.add_joined(all_additional_srcs, join_with = ",", format_joined = "--additional_srcs=%s")
     additional_args.add_all(prost_toolchain.prost_opts + prost_opts, format_each = "--prost_opt=%s")
+    additional_args.add_all(type_attributes, format_each = "--type_attribute=%s")
+    additional_args.add_all(field_attributes, format_each = "--field_attribute=%s")
+    additional_args.add_all(bytes_paths, format_each = "--bytes=%s")
+    additional_args.add_all(btree_map_paths, format_each = "--btree_map=%s")
+    additional_args.add_all(boxed_paths, format_each = "--boxed=%s")
 
     if prost_toolchain.tonic_plugin:
         tonic_plugin = prost_toolchain.tonic_plugin[DefaultInfo].files_to_run
         additional_args.add(tonic_plugin.executable, format = prost_toolchain.tonic_plugin_flag)
         additional_args.add("--tonic_opt=no_include")
         additional_args.add("--is_tonic")
+        if not tonic_generate_server:
+            additional_args.add("--no_tonic_server")
+        if not tonic_generate_client:
+            additional_args.add("--no_tonic_client")
 
         additional_args.add_all(prost_toolchain.tonic_opts + tonic_opts, format_each = "--tonic_opt=%s")
         tools.append(tonic_plugin.executable)
 
+    if prost_toolchain.serde_plugin:
+        serde_plugin = prost_toolchain.serde_plugin[DefaultInfo].files_to_run
+        additional_args.add(serde_plugin.executable, format = prost_toolchain.serde_plugin_flag)
+        additional_args.add("--is_serde")
+        additional_args.add_all(prost_toolchain.serde_opts, format_each = "--prost-serde_opt=%s")
+        tools.append(serde_plugin.executable)
+
+        # Prost types do not implement serde traits, so well known types are
+        # mapped to a crate which does.
+        if prost_toolchain.serde_types and not prost_toolchain.compile_well_known_types:
+            additional_args.add(
+                prost_toolchain.serde_types[rust_common.crate_info].name,
+                format = "--well_known_types_crate=%s",
+            )
+
+    reflect_descriptor_sets = depset()
+    compile_data = []
+    if prost_toolchain.reflect_runtime:
+        reflect_descriptor_sets = proto_info.transitive_descriptor_sets
+
+        # The merged descriptor set is embedded in the crate with `include_bytes!`
+        # so it must be a sibling of `lib_rs`.
+        descriptor_set_file = ctx.actions.declare_file("{}.descriptor_set.bin".format(ctx.label.name))
+        compile_data.append(descriptor_set_file)
+        additional_args.add(
+            prost_toolchain.reflect_runtime[rust_common.crate_info].name,
+            format = "--reflect_crate=%s",
+        )
+        additional_args.add_all(reflect_descriptor_sets, format_each = "--reflect_descriptor_set=%s")
+        additional_args.add(descriptor_set_file, format = "--reflect_descriptor_set_out=%s")
+        if prost_toolchain.reflect_messages:
+            additional_args.add("--reflect_messages")
+
     if rustfmt_toolchain:
         additional_args.add(rustfmt_toolchain.rustfmt, format = "--rustfmt=%s")
         tools = depset(tools, transitive = [rustfmt_toolchain.all_files]).to_list()
 
     additional_inputs = depset(
         [deps_info_file, proto_info.direct_descriptor_set] + [dep[ProstProtoInfo].package_info for dep in deps],
-        transitive = [all_additional_srcs],
+        transitive = [all_additional_srcs, reflect_descriptor_sets],
     )
 
     proto_common.compile(
@@ -117,12 +188,12 @@ def _compile_proto(
         additional_tools = tools,
         additional_inputs = additional_inputs,
         additional_args = additional_args,
-        generated_files = [lib_rs, package_info_file],
+        generated_files = [lib_rs, package_info_file] + compile_data,
         proto_lang_toolchain_info = _create_proto_lang_toolchain(ctx, prost_toolchain),
         plugin_output = ctx.bin_dir.path,
     )
 
-    return lib_rs, package_info_file
+    return lib_rs, package_info_file, compile_data
 
 def _get_crate_info(providers):
     """Finds the CrateInfo provider in the list of providers."""
@@ -152,7 +223,8 @@ def _compile_rust(
         crate_name,
         src,
         deps,
-        edition):
+        edition,
+        compile_data = []):
     """Compiles a Rust source file.
 
     Args:
@@ -162,6 +234,7 @@ def _compile_rust(
       src (File): The crate root source file to be compiled.
       deps (List of DepVariantInfo): A list of dependencies needed.
       edition (str): The Rust edition to use.
+      compile_data (List of File): Files needed at compile time, such as those included with `include_bytes!`.
 
     Returns:
       A DepVariantInfo provider.
@@ -218,7 +291,7 @@ def _compile_rust(
             edition = edition,
             is_test = False,
             rustc_env = {},
-            compile_data = depset([]),
+            compile_data = depset(compile_data),
             compile_data_targets = depset([]),
             owner = ctx.label,
         ),
@@ -245,9 +318,16 @@ def _rust_prost_aspect_impl(target, ctx):
     rustfmt_toolchain = ctx.toolchains["@rules_rust//rust/rustfmt:toolchain_type"]
     prost_toolchain = ctx.toolchains[TOOLCHAIN_TYPE]
     rust_analyzer_deps = []
-    runtimes = [prost_toolchain.prost_runtime, prost_toolchain.tonic_runtime]
+    runtimes = [
+        prost_toolchain.prost_runtime,
+        prost_toolchain.tonic_runtime,
+        prost_toolchain.serde_runtime,
+        prost_toolchain.reflect_runtime,
+    ]
     if not prost_toolchain.compile_well_known_types:
         runtimes.append(prost_toolchain.prost_types)
+        if prost_toolchain.serde_plugin:
+            runtimes.append(prost_toolchain.serde_types)
     for prost_runtime in runtimes:
         if not prost_runtime:
             continue
@@ -303,7 +383,7 @@ def _rust_prost_aspect_impl(target, ctx):
 
     proto_info = target[ProtoInfo]
 
-    lib_rs, package_info_file = _compile_proto(
+    lib_rs, package_info_file, compile_data = _compile_proto(
         ctx = ctx,
         crate_name = crate_name,
         proto_info = proto_info,
@@ -320,6 +400,7 @@ def _rust_prost_aspect_impl(target, ctx):
         src = lib_rs,
         deps = rust_deps,
         edition = RUST_EDITION,
+        compile_data = compile_data,
     )
 
     # Always add `test` & `debug_assertions`. See rust-analyzer source code:
@@ -455,6 +536,12 @@ def _rust_prost_toolchain_impl(ctx):
     if any(tonic_attrs) and not all(tonic_attrs):
         fail("When one tonic attribute is added, all must be added")
 
+    if bool(ctx.attr.serde_plugin) != bool(ctx.attr.serde_runtime):
+        fail("`serde_plugin` and `serde_runtime` must be set together")
+
+    if ctx.attr.reflect_messages and not ctx.attr.reflect_runtime:
+        fail("`reflect_messages` requires `reflect_runtime` to be set")
+
     proto_toolchain = proto_toolchains.find_toolchain(
         ctx,
         legacy_attr = "_legacy_proto_toolchain",
@@ -469,6 +556,10 @@ def _rust_prost_toolchain_impl(ctx):
         proto_compiler = proto_toolchain.proto_compiler
 
     return [platform_common.ToolchainInfo(
+        boxed = ctx.attr.boxed,
+        btree_map = ctx.attr.btree_map,
+        bytes = ctx.attr.bytes,
+        field_attributes = ctx.attr.field_attributes,
         prost_opts = ctx.attr.prost_opts,
         prost_plugin = ctx.attr.prost_plugin,
         prost_plugin_flag = ctx.attr.prost_plugin_flag,
@@ -476,12 +567,22 @@ def _rust_prost_toolchain_impl(ctx):
         prost_types = ctx.attr.prost_types,
         proto_compiler = proto_compiler,
         protoc_opts = ctx.fragments.proto.experimental_protoc_opts,
+        reflect_messages = ctx.attr.reflect_messages,
+        reflect_runtime = ctx.attr.reflect_runtime,
+        serde_opts = ctx.attr.serde_opts,
+        serde_plugin = ctx.attr.serde_plugin,
+        serde_plugin_flag = ctx.attr.serde_plugin_flag,
+        serde_runtime = ctx.attr.serde_runtime,
+        serde_types = ctx.attr.serde_types,
+        tonic_generate_client = ctx.attr.tonic_generate_client,
+        tonic_generate_server = ctx.attr.tonic_generate_server,
         tonic_opts = ctx.attr.tonic_opts,
         tonic_plugin = ctx.attr.tonic_plugin,
         tonic_plugin_flag = ctx.attr.tonic_plugin_flag,
         tonic_runtime = ctx.attr.tonic_runtime,
         include_transitive_deps = ctx.attr.include_transitive_deps,
         compile_well_known_types = ctx.attr.compile_well_known_types,
+        type_attributes = ctx.attr.type_attributes,
     )]
 
 rust_prost_toolchain = rule(
@@ -489,10 +590,22 @@ rust_prost_toolchain = rule(
     doc = "Rust Prost toolchain rule.",
     fragments = ["proto"],
     attrs = dict({
+        "boxed": attr.string_list(
+            doc = "Protobuf paths of message fields to wrap in a `Box`. Corresponds to prost_build's `boxed` option.",
+        ),
+        "btree_map": attr.string_list(
+            doc = "Protobuf paths of `map` fields to generate as `BTreeMap` instead of `HashMap`. Use `.` for all fields. Corresponds to prost_build's `btree_map` option.",
+        ),
+        "bytes": attr.string_list(
+            doc = "Protobuf paths of `bytes` fields to generate as `bytes::Bytes` instead of `Vec<u8>`. Use `.` for all fields. Corresponds to prost_build's `bytes` option.",
+        ),
         "compile_well_known_types": attr.bool(
             doc = "Corresponds to prost_build's `compile_well_known_types` option. If set to False, well-known-types will not be compiled by prost, and instead rely on the provided Prost types crate.",
             default = True,
         ),
+        "field_attributes": attr.string_list_dict(
+            doc = "Attributes to add to the generated fields matching a protobuf path (e.g. `{\".my.pkg.Message.field\": [\"#[serde(default)]\"]}`). Corresponds to prost_build's `field_attribute` option.",
+        ),
         "include_transitive_deps": attr.bool(
             doc = "Whether to include transitive dependencies. If set to True, all transitive dependencies will directly accessible by the dependent crate.",
             default = False,
@@ -526,6 +639,44 @@ rust_prost_toolchain = rule(
             cfg = "exec",
             executable = True,
         ),
+        "reflect_messages": attr.bool(
+            doc = "Whether to implement `prost_reflect::ReflectMessage` for every generated message. Requires `reflect_runtime`.",
+            default = False,
+        ),
+        "reflect_runtime": attr.label(
+            doc = "An optional [prost-reflect](https://crates.io/crates/prost-reflect) crate. When set, every generated crate embeds the `FileDescriptorSet` of its protos and their dependencies as `FILE_DESCRIPTOR_SET` and exposes it as a `DESCRIPTOR_POOL` static.",
+            providers = [rust_common.crate_info],
+            aspects = [rust_analyzer_aspect],
+        ),
+        "serde_opts": attr.string_list(
+            doc = "Additional options to add to the serde plugin.",
+        ),
+        "serde_plugin": attr.label(
+            doc = "An optional `protoc-gen-prost-serde` plugin used to generate [pbjson](https://crates.io/crates/pbjson) serde implementations for all messages and enums.",
+            cfg = "exec",
+            executable = True,
+        ),
+        "serde_plugin_flag": attr.string(
+            doc = "Serde plugin flag format. (e.g. `--plugin=protoc-gen-prost-serde=%s`)",
+            default = "--plugin=protoc-gen-prost-serde=%s",
+        ),
+        "serde_runtime": attr.label(
+            doc = "The serde runtime crates to use (e.g. `pbjson` and `serde`). Required when `serde_plugin` is set.",
+            providers = [[rust_common.crate_info], [rust_common.crate_group_info]],
+            aspects = [rust_analyzer_aspect],
+        ),
+        "serde_types": attr.label(
+            doc = "A well known types crate with serde implementations (e.g. `pbjson-types`). When `compile_well_known_types` is False and `serde_plugin` is set, well known types are mapped to this crate instead of `prost_types`.",
+            providers = [rust_common.crate_info],
+        ),
+        "tonic_generate_client": attr.bool(
+            doc = "Whether to generate tonic client code. Corresponds to tonic_build's `build_client` option.",
+            default = True,
+        ),
+        "tonic_generate_server": attr.bool(
+            doc = "Whether to generate tonic server code. Corresponds to tonic_build's `build_server` option.",
+            default = True,
+        ),
         "tonic_opts": attr.string_list(
             doc = "Additional options to add to Tonic.",
         ),
@@ -543,6 +694,9 @@ rust_prost_toolchain = rule(
             providers = [[rust_common.crate_info], [rust_common.crate_group_info]],
             aspects = [rust_analyzer_aspect],
         ),
+        "type_attributes": attr.string_list_dict(
+            doc = "Attributes to add to the generated types matching a protobuf path (e.g. `{\".\": [\"#[derive(serde::Serialize)]\"]}`). Corresponds to prost_build's `type_attribute` option.",
+        ),
     }, **proto_toolchains.if_legacy_toolchain({
         "_legacy_proto_toolchain": attr.label(
             default = Label("//private:legacy_proto_toolchain"),
@@ -6,11 +6,18 @@ load("@rules_rust//rust:defs.bzl", "rust_common")
 ProstTransformInfo = provider(
     doc = "Info about transformations to apply to Prost generated source code.",
     fields = {
+        "boxed": "List[str]: Protobuf paths of message fields to wrap in a `Box`.",
+        "btree_map": "List[str]: Protobuf paths of `map` fields to generate as `BTreeMap`.",
+        "bytes": "List[str]: Protobuf paths of `bytes` fields to generate as `bytes::Bytes`.",
         "crate_name": "str: The name of crate generated by Prost.",
         "deps": "List[DepVariantInfo]: Additional dependencies to compile into the Prost target.",
+        "field_attributes": "Dict[str, List[str]]: Attributes to add to generated fields, keyed by protobuf path.",
         "prost_opts": "List[str]: Additional prost flags.",
         "srcs": "Depset[File]: Additional source files to include in generated Prost source code.",
+        "tonic_generate_client": "bool: Whether to generate tonic client code.",
+        "tonic_generate_server": "bool: Whether to generate tonic server code.",
         "tonic_opts": "List[str]: Additional tonic flags.",
+        "type_attributes": "Dict[str, List[str]]: Attributes to add to generated types, keyed by protobuf path.",
     },
 )
 
@@ -28,11 +35,18 @@ def _rust_prost_transform_impl(ctx):
     # consumers of the `proto_library` target this rule is expected to be passed
     # to.
     return [ProstTransformInfo(
+        boxed = ctx.attr.boxed,
+        btree_map = ctx.attr.btree_map,
+        bytes = ctx.attr.bytes,
         crate_name = ctx.attr.crate_name if ctx.attr.crate_name else None,
         deps = deps,
+        field_attributes = ctx.attr.field_attributes,
         prost_opts = ctx.attr.prost_opts,
         srcs = depset(ctx.files.srcs),
+        tonic_generate_client = ctx.attr.tonic_generate_client,
+        tonic_generate_server = ctx.attr.tonic_generate_server,
         tonic_opts = ctx.attr.tonic_opts,
+        type_attributes = ctx.attr.type_attributes,
     )]
 
 rust_prost_transform = rule(
@@ -73,6 +87,15 @@ actions with minimal impact to other consumers.
 """,
     implementation = _rust_prost_transform_impl,
     attrs = {
+        "boxed": attr.string_list(
+            doc = "Protobuf paths of message fields to wrap in a `Box`. Corresponds to prost_build's `boxed` option.",
+        ),
+        "btree_map": attr.string_list(
+            doc = "Protobuf paths of `map` fields to generate as `BTreeMap` instead of `HashMap`. Use `.` for all fields. Corresponds to prost_build's `btree_map` option.",
+        ),
+        "bytes": attr.string_list(
+            doc = "Protobuf paths of `bytes` fields to generate as `bytes::Bytes` instead of `Vec<u8>`. Use `.` for all fields. Corresponds to prost_build's `bytes` option.",
+        ),
         "crate_name": attr.string(
             doc = "The name of the crate generated by Prost. This is used to override the default name which is the name of the proto_library target.",
             mandatory = False,
@@ -81,6 +104,9 @@ actions with minimal impact to other consumers.
             doc = "Additional dependencies to add to the compiled crate.",
             providers = [[rust_common.crate_info], [rust_common.crate_group_info]],
         ),
+        "field_attributes": attr.string_list_dict(
+            doc = "Attributes to add to the generated fields matching a protobuf path (e.g. `{\".my.pkg.Message.field\": [\"#[serde(default)]\"]}`). Corresponds to prost_build's `field_attribute` option.",
+        ),
         "prost_opts": attr.string_list(
             doc = "Additional options to add to Prost.",
         ),
@@ -88,8 +114,19 @@ actions with minimal impact to other consumers.
             doc = "Additional source files to include in generated Prost source code.",
             allow_files = True,
         ),
+        "tonic_generate_client": attr.bool(
+            doc = "Whether to generate tonic client code. Setting this to False here or on the toolchain disables client generation.",
+            default = True,
+        ),
+        "tonic_generate_server": attr.bool(
+            doc = "Whether to generate tonic server code. Setting this to False here or on the toolchain disables server generation.",
+            default = True,
+        ),
         "tonic_opts": attr.string_list(
             doc = "Additional options to add to Tonic.",
         ),
+        "type_attributes": attr.string_list_dict(
+            doc = "Attributes to add to the generated types matching a protobuf path (e.g. `{\".\": [\"#[derive(serde::Serialize)]\"]}`). Corresponds to prost_build's `type_attribute` option.",
+        ),
     },
 )
@@ -155,6 +155,7 @@ fn generate_lib_rs(
     prost_outputs: &BTreeSet<PathBuf>,
     is_tonic: bool,
     direct_dep_crate_names: Vec<String>,
+    root_content: String,
     additional_content: String,
 ) -> String {
     let mut contents = vec!["// @generated".to_string(), "".to_string()];
@@ -162,6 +163,9 @@ fn generate_lib_rs(
         contents.push(format!("pub use {crate_name};"));
     }
     contents.push("".to_string());
+    if !root_content.is_empty() {
+        contents.push(root_content);
+    }
 
     let mut module_info = Module {
         name: "".to_string(),
@@ -308,6 +312,38 @@ impl From<&str> for RustModulePath {
     }
 }
 
+/// The kind of Rust type prost generates for a proto type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TypeKind {
+    Message,
+    Enum,
+    Oneof,
+}
+
+/// A collection of proto paths and the Rust paths of the types prost generates for them.
+trait TypePaths {
+    /// Record the Rust path of a generated type.
+    fn insert(&mut self, kind: TypeKind, proto_path: ProtoPath, rust_path: RustModulePath);
+}
+
+impl TypePaths for BTreeMap<ProtoPath, RustModulePath> {
+    fn insert(&mut self, _kind: TypeKind, proto_path: ProtoPath, rust_path: RustModulePath) {
+        BTreeMap::insert(self, proto_path, rust_path);
+    }
+}
+
+/// The paths of generated message types, ignoring enums and oneofs.
+#[derive(Debug, Default)]
+struct MessagePaths(BTreeMap<ProtoPath, RustModulePath>);
+
+impl TypePaths for MessagePaths {
+    fn insert(&mut self, kind: TypeKind, proto_path: ProtoPath, rust_path: RustModulePath) {
+        if kind == TypeKind::Message {
+            self.0.insert(proto_path, rust_path);
+        }
+    }
+}
+
 /// Compute the `--extern_path` flags for a list of proto files. This is
 /// expected to convert proto files into a BTreeMap of
 /// `example.prost.helloworld`: `crate_name::example::prost::helloworld`.
@@ -320,13 +356,7 @@ fn get_extern_paths(
     let rust_path = RustModulePath(escape_keyword(crate_name.to_string()));
 
     for file in descriptor_set.file.iter() {
-        if !compile_well_known_types
-            && (file.package.as_deref() == Some("google.protobuf")
-                || file
-                    .package
-                    .as_deref()
-                    .is_some_and(|p| p.starts_with("google.protobuf.")))
-        {
+        if !compile_well_known_types && is_well_known_types_file(file) {
             continue;
         }
 
@@ -336,9 +366,18 @@ fn get_extern_paths(
     Ok(extern_paths)
 }
 
+/// Whether a file belongs to the `google.protobuf` well known types package.
+fn is_well_known_types_file(file: &FileDescriptorProto) -> bool {
+    file.package.as_deref() == Some("google.protobuf")
+        || file
+            .package
+            .as_deref()
+            .is_some_and(|p| p.starts_with("google.protobuf."))
+}
+
 /// Add the extern_path pairs for a file descriptor type.
 fn descriptor_set_file_to_extern_paths(
-    extern_paths: &mut BTreeMap<ProtoPath, RustModulePath>,
+    extern_paths: &mut impl TypePaths,
     rust_path: &RustModulePath,
     file: &FileDescriptorProto,
 ) {
@@ -358,25 +397,37 @@ fn descriptor_set_file_to_extern_paths(
 }
 
 /// Add the extern_path pairs for a message descriptor type.
+///
+/// Nested types keep the message name in their proto path and are generated
+/// in a module named after the snake cased message name.
 fn message_type_to_extern_paths(
-    extern_paths: &mut BTreeMap<ProtoPath, RustModulePath>,
+    extern_paths: &mut impl TypePaths,
     proto_path: &ProtoPath,
     rust_path: &RustModulePath,
     message_type: &DescriptorProto,
 ) {
+    // Prost generates `HashMap` or `BTreeMap` fields for map entries rather than types.
+    if message_type
+        .options
+        .as_ref()
+        .is_some_and(|options| options.map_entry())
+    {
+        return;
+    }
+
     let message_type_name = message_type
         .name
         .as_ref()
         .expect("Failed to get message type name");
 
     extern_paths.insert(
+        TypeKind::Message,
         proto_path.join(message_type_name),
         rust_path.join(&message_type_name.to_upper_camel_case()),
     );
 
-    let name_lower = message_type_name.to_lowercase();
-    let proto_path = proto_path.join(&name_lower);
-    let rust_path = rust_path.join(&name_lower);
+    let proto_path = proto_path.join(message_type_name);
+    let rust_path = rust_path.join(&message_type_name.to_snake_case());
 
     for nested_type in message_type.nested_type.iter() {
         message_type_to_extern_paths(extern_paths, &proto_path, &rust_path, nested_type)
@@ -393,7 +444,7 @@ fn message_type_to_extern_paths(
 
 /// Add the extern_path pairs for an enum type.
 fn enum_type_to_extern_paths(
-    extern_paths: &mut BTreeMap<ProtoPath, RustModulePath>,
+    extern_paths: &mut impl TypePaths,
     proto_path: &ProtoPath,
     rust_path: &RustModulePath,
     enum_type: &EnumDescriptorProto,
@@ -403,13 +454,14 @@ fn enum_type_to_extern_paths(
         .as_ref()
         .expect("Failed to get enum type name");
     extern_paths.insert(
+        TypeKind::Enum,
         proto_path.join(enum_type_name),
         rust_path.join(&enum_type_name.to_upper_camel_case()),
     );
 }
 
 fn oneof_type_to_extern_paths(
-    extern_paths: &mut BTreeMap<ProtoPath, RustModulePath>,
+    extern_paths: &mut impl TypePaths,
     proto_path: &ProtoPath,
     rust_path: &RustModulePath,
     oneof_type: &OneofDescriptorProto,
@@ -419,11 +471,262 @@ fn oneof_type_to_extern_paths(
         .as_ref()
         .expect("Failed to get oneof type name");
     extern_paths.insert(
+        TypeKind::Oneof,
         proto_path.join(oneof_type_name),
-        rust_path.join(oneof_type_name),
+        rust_path.join(&oneof_type_name.to_upper_camel_case()),
     );
 }
 
+/// Merge descriptor sets into a single set with every file ordered after its dependencies.
+///
+/// Files which appear in more than one descriptor set are only included once.
+fn merge_descriptor_sets(descriptor_sets: Vec<FileDescriptorSet>) -> FileDescriptorSet {
+    let mut files: BTreeMap<String, FileDescriptorProto> = BTreeMap::new();
+    for descriptor_set in descriptor_sets {
+        for file in descriptor_set.file {
+            files.entry(file.name().to_owned()).or_insert(file);
+        }
+    }
+
+    fn visit(
+        name: &str,
+        files: &BTreeMap<String, FileDescriptorProto>,
+        visited: &mut BTreeSet<String>,
+        ordered: &mut Vec<FileDescriptorProto>,
+    ) {
+        if !visited.insert(name.to_owned()) {
+            return;
+        }
+        if let Some(file) = files.get(name) {
+            for dependency in file.dependency.iter() {
+                visit(dependency, files, visited, ordered);
+            }
+            ordered.push(file.clone());
+        }
+    }
+
+    let mut visited = BTreeSet::new();
+    let mut ordered = Vec::new();
+    for name in files.keys() {
+        visit(name, &files, &mut visited, &mut ordered);
+    }
+
+    FileDescriptorSet { file: ordered }
+}
+
+/// Compute the proto names and Rust paths of all messages generated for a descriptor set.
+///
+/// The paths are relative to the root of the crate so they can be used from its `lib.rs`.
+fn get_reflect_message_paths(
+    descriptor_set: &FileDescriptorSet,
+    compile_well_known_types: bool,
+) -> BTreeMap<ProtoPath, RustModulePath> {
+    let mut message_paths = MessagePaths::default();
+    let rust_path = RustModulePath("crate".to_owned());
+
+    for file in descriptor_set.file.iter() {
+        if !compile_well_known_types && is_well_known_types_file(file) {
+            continue;
+        }
+
+        descriptor_set_file_to_extern_paths(&mut message_paths, &rust_path, file);
+    }
+
+    message_paths.0
+}
+
+/// Generate the embedded descriptor pool and, optionally, `ReflectMessage`
+/// implementations for all messages in the crate.
+///
+/// The descriptor set is expected to be written next to the generated `lib.rs`
+/// under the name `descriptor_set_file_name`.
+fn generate_reflect_rs(
+    reflect_crate: &str,
+    descriptor_set_file_name: &str,
+    message_paths: &BTreeMap<ProtoPath, RustModulePath>,
+) -> String {
+    let mut content = String::new();
+    writeln!(
+        content,
+        "/// The encoded `FileDescriptorSet` of all protos in this crate and their dependencies."
+    )
+    .unwrap();
+    writeln!(
+        content,
+        "pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!({:?});",
+        descriptor_set_file_name
+    )
+    .unwrap();
+    writeln!(
+        content,
+        "/// A descriptor pool of all protos in this crate and their dependencies."
+    )
+    .unwrap();
+    writeln!(
+        content,
+        "pub static DESCRIPTOR_POOL: ::std::sync::LazyLock<::{c}::DescriptorPool> = \
+         ::std::sync::LazyLock::new(|| ::{c}::DescriptorPool::decode(FILE_DESCRIPTOR_SET)\
+         .expect(\"Failed to decode the embedded descriptor set\"));",
+        c = reflect_crate
+    )
+    .unwrap();
+
+    for (proto_path, rust_path) in message_paths {
+        writeln!(
+            content,
+            "impl ::{c}::ReflectMessage for {rust_path} {{\n\
+             fn descriptor(&self) -> ::{c}::MessageDescriptor {{\n\
+             crate::DESCRIPTOR_POOL.get_message_by_name(\"{proto_path}\")\
+             .expect(\"Message `{proto_path}` is missing from the descriptor pool\")\n\
+             }}\n\
+             }}",
+            c = reflect_crate
+        )
+        .unwrap();
+    }
+
+    content
+}
+
+/// Options for embedding descriptors for runtime reflection with `prost-reflect`.
+#[derive(Debug)]
+struct ReflectOptions {
+    /// The name of the `prost-reflect` crate.
+    crate_name: String,
+
+    /// The descriptor sets of the crate's protos and all of their dependencies.
+    descriptor_sets: Vec<PathBuf>,
+
+    /// The output path of the merged descriptor set embedded in the crate.
+    descriptor_set_out: PathBuf,
+
+    /// Whether to implement `ReflectMessage` for every generated message.
+    messages: bool,
+}
+
+/// Structured code generation options for the prost and tonic plugins.
+///
+/// These are passed to the wrapper as dedicated flags and rendered into the
+/// plugin parameters understood by `protoc-gen-prost` and `protoc-gen-tonic`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CodegenOptions {
+    /// Pairs of protobuf paths and attributes to add to the matching generated types.
+    type_attributes: Vec<(String, String)>,
+
+    /// Pairs of protobuf paths and attributes to add to the matching generated fields.
+    field_attributes: Vec<(String, String)>,
+
+    /// Protobuf paths of `bytes` fields to generate as `bytes::Bytes`.
+    bytes: Vec<String>,
+
+    /// Protobuf paths of `map` fields to generate as `BTreeMap`.
+    btree_map: Vec<String>,
+
+    /// Protobuf paths of message fields to wrap in a `Box`.
+    boxed: Vec<String>,
+
+    /// Whether to generate tonic server code.
+    tonic_server: bool,
+
+    /// Whether to generate tonic client code.
+    tonic_client: bool,
+}
+
+impl Default for CodegenOptions {
+    fn default() -> Self {
+        Self {
+            type_attributes: Vec::new(),
+            field_attributes: Vec::new(),
+            bytes: Vec::new(),
+            btree_map: Vec::new(),
+            boxed: Vec::new(),
+            tonic_server: true,
+            tonic_client: true,
+        }
+    }
+}
+
+impl CodegenOptions {
+    /// Consume a command-line argument if it is a code generation option.
+    ///
+    /// Returns `false` if the argument is not a code generation option.
+    fn handle_arg(&mut self, arg: &str) -> bool {
+        match arg {
+            "--no_tonic_server" => self.tonic_server = false,
+            "--no_tonic_client" => self.tonic_client = false,
+            _ => {
+                let (flag, value) = match arg.split_once('=') {
+                    Some(parts) => parts,
+                    None => return false,
+                };
+                match flag {
+                    "--type_attribute" => self.type_attributes.push(split_path_attribute(value)),
+                    "--field_attribute" => self.field_attributes.push(split_path_attribute(value)),
+                    "--bytes" => self.bytes.push(value.to_owned()),
+                    "--btree_map" => self.btree_map.push(value.to_owned()),
+                    "--boxed" => self.boxed.push(value.to_owned()),
+                    _ => return false,
+                }
+            }
+        }
+        true
+    }
+
+    /// The parameters to pass to `protoc-gen-prost`.
+    fn prost_opts(&self) -> Vec<String> {
+        let attributes = self
+            .type_attributes
+            .iter()
+            .map(|(path, attribute)| ("type_attribute", path, attribute))
+            .chain(
+                self.field_attributes
+                    .iter()
+                    .map(|(path, attribute)| ("field_attribute", path, attribute)),
+            )
+            .map(|(option, path, attribute)| {
+                format!("{}={}={}", option, path, escape_plugin_parameter(attribute))
+            });
+
+        let paths = [
+            ("bytes", &self.bytes),
+            ("btree_map", &self.btree_map),
+            ("boxed", &self.boxed),
+        ]
+        .into_iter()
+        .flat_map(|(option, paths)| paths.iter().map(move |path| format!("{}={}", option, path)));
+
+        attributes.chain(paths).collect()
+    }
+
+    /// The parameters to pass to `protoc-gen-tonic`.
+    fn tonic_opts(&self) -> Vec<String> {
+        let mut opts = Vec::new();
+        if !self.tonic_server {
+            opts.push("no_server".to_owned());
+        }
+        if !self.tonic_client {
+            opts.push("no_client".to_owned());
+        }
+        opts
+    }
+}
+
+/// Split a `PATH=ATTRIBUTE` value into its protobuf path and attribute.
+fn split_path_attribute(value: &str) -> (String, String) {
+    let (path, attribute) = value
+        .split_once('=')
+        .unwrap_or_else(|| panic!("Expected a value of the form `PATH=ATTRIBUTE`: `{}`", value));
+    (path.to_owned(), attribute.to_owned())
+}
+
+/// Escape a value for use in a protoc plugin parameter.
+///
+/// Plugin parameters are separated by commas, so commas within a value (e.g.
+/// `#[derive(Eq, Hash)]`) are escaped as `\,` and backslashes as `\\`.
+fn escape_plugin_parameter(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(',', "\\,")
+}
+
 /// The parsed command-line arguments.
 struct Args {
     /// The path to the protoc binary.
@@ -471,6 +774,15 @@ struct Args {
     /// Whether to generate tonic code.
     is_tonic: bool,
 
+    /// Whether to generate serde implementations with `protoc-gen-prost-serde`.
+    is_serde: bool,
+
+    /// Structured code generation options for prost and tonic.
+    codegen: CodegenOptions,
+
+    /// Options for embedding a descriptor pool for reflection.
+    reflect: Option<ReflectOptions>,
+
     /// Extra arguments to pass to protoc.
     extra_args: Vec<String>,
 }
@@ -493,7 +805,14 @@ impl Args {
         let mut tonic_or_prost_opts = Vec::new();
         let mut direct_dep_crate_names = Vec::new();
         let mut is_tonic = false;
+        let mut is_serde = false;
         let mut compile_well_known_types = false;
+        let mut codegen = CodegenOptions::default();
+        let mut reflect_crate: Option<String> = None;
+        let mut reflect_descriptor_sets = Vec::new();
+        let mut reflect_descriptor_set_out: Option<PathBuf> = None;
+        let mut reflect_messages = false;
+        let mut well_known_types_crate: Option<String> = None;
 
         let mut extra_args = Vec::new();
 
@@ -517,11 +836,25 @@ impl Args {
                 return;
             }
 
+            if arg == "--is_serde" {
+                is_serde = true;
+                return;
+            }
+
+            if arg == "--reflect_messages" {
+                reflect_messages = true;
+                return;
+            }
+
             if arg == "--compile_well_known_types" {
                 compile_well_known_types = true;
                 return;
             }
 
+            if codegen.handle_arg(&arg) {
+                return;
+            }
+
             if !arg.contains('=') {
                 extra_args.push(arg);
                 return;
@@ -585,6 +918,18 @@ impl Args {
                 ("--label", value) => {
                     label = Some(value.to_string());
                 }
+                ("--reflect_crate", value) => {
+                    reflect_crate = Some(value.to_string());
+                }
+                ("--reflect_descriptor_set", value) => {
+                    reflect_descriptor_sets.push(PathBuf::from(value));
+                }
+                ("--reflect_descriptor_set_out", value) => {
+                    reflect_descriptor_set_out = Some(PathBuf::from(value));
+                }
+                ("--well_known_types_crate", value) => {
+                    well_known_types_crate = Some(value.to_string());
+                }
                 (arg, value) => {
                     extra_args.push(format!("{}={}", arg, value));
                 }
@@ -607,11 +952,27 @@ impl Args {
             }
         }
 
+        if let Some(well_known_types_crate) = well_known_types_crate {
+            // prost maps `.google.protobuf` to `prost_types` unless it compiles
+            // well known types itself, which would make this extern path a duplicate.
+            extra_args.push("--prost_opt=compile_well_known_types".to_owned());
+            if is_tonic {
+                extra_args.push("--tonic_opt=compile_well_known_types".to_owned());
+            }
+            tonic_or_prost_opts.push(format!(
+                "extern_path=.google.protobuf=::{}",
+                well_known_types_crate
+            ));
+        }
+
         for tonic_or_prost_opt in tonic_or_prost_opts {
             extra_args.push(format!("--prost_opt={}", tonic_or_prost_opt));
             if is_tonic {
                 extra_args.push(format!("--tonic_opt={}", tonic_or_prost_opt));
             }
+            if is_serde {
+                extra_args.push(format!("--prost-serde_opt={}", tonic_or_prost_opt));
+            }
         }
 
         if protoc.is_none() {
@@ -650,6 +1011,28 @@ impl Args {
             );
         }
 
+        let reflect = match (reflect_crate, reflect_descriptor_set_out) {
+            (Some(crate_name), Some(descriptor_set_out)) => Some(ReflectOptions {
+                crate_name,
+                descriptor_sets: reflect_descriptor_sets,
+                descriptor_set_out,
+                messages: reflect_messages,
+            }),
+            (Some(_), None) => {
+                return Err(
+                    "No `--reflect_descriptor_set_out` value was found. Unable to embed descriptors."
+                        .to_string(),
+                );
+            }
+            (None, _) if reflect_messages || !reflect_descriptor_sets.is_empty() => {
+                return Err(
+                    "No `--reflect_crate` value was found. Unable to generate reflection support."
+                        .to_string(),
+                );
+            }
+            (None, _) => None,
+        };
+
         Ok(Args {
             protoc: protoc.unwrap(),
             out_dir: out_dir.unwrap(),
@@ -664,6 +1047,9 @@ impl Args {
             proto_paths,
             direct_dep_crate_names,
             is_tonic,
+            is_serde,
+            codegen,
+            reflect,
             label: label.unwrap(),
             extra_args,
             compile_well_known_types,
@@ -671,6 +1057,41 @@ impl Args {
     }
 }
 
+/// Get the prost output a `.serde.rs` file generated by `protoc-gen-prost-serde` belongs to.
+///
+/// Returns `None` if the path is not a serde output.
+fn serde_output_target(path: &Path) -> Option<PathBuf> {
+    let file_name = path.file_name()?.to_str()?;
+    let package = file_name.strip_suffix(".serde.rs")?;
+    Some(path.with_file_name(format!("{}.rs", package)))
+}
+
+/// Append the contents of all `.serde.rs` files to the prost outputs of the same package.
+///
+/// This keeps the serde implementations next to the types they are for, so
+/// they are placed in the same module by `generate_lib_rs`. Serde outputs for
+/// packages without a prost output are removed.
+fn merge_serde_files(out_dir: &Path) {
+    for serde_file in find_generated_rust_files(out_dir) {
+        let rs_file = match serde_output_target(&serde_file) {
+            Some(rs_file) => rs_file,
+            None => continue,
+        };
+
+        // `protoc-gen-prost-serde` generates implementations for every package in
+        // the request, including imported ones. Those belong to the crates of the
+        // dependencies, which prost did not generate an output for here.
+        if rs_file.exists() {
+            let rs_content = fs::read_to_string(&rs_file).expect("Failed to read file.");
+            let serde_content = fs::read_to_string(&serde_file).expect("Failed to read file.");
+            fs::write(&rs_file, format!("{}\n{}", rs_content, serde_content))
+                .expect("Failed to write file.");
+        }
+        fs::remove_file(&serde_file)
+            .unwrap_or_else(|err| panic!("Failed to remove file: {err:?}: {serde_file:?}"));
+    }
+}
+
 /// Get the output directory with the label suffixed.
 fn get_output_dir(out_dir: &Path, label: &str) -> PathBuf {
     let label_as_path = label
@@ -769,6 +1190,9 @@ fn main() {
         proto_paths,
         direct_dep_crate_names,
         is_tonic,
+        is_serde,
+        codegen,
+        reflect,
         extra_args,
         compile_well_known_types,
     } = Args::parse().expect("Failed to parse args");
@@ -816,6 +1240,12 @@ fn main() {
     if is_tonic {
         args.push(format!("--tonic_out={}", out_dir.display()));
     }
+    if is_serde {
+        // The serde implementations are merged into the prost outputs below
+        // rather than included from the generated files.
+        args.push(format!("--prost-serde_out={}", out_dir.display()));
+        args.push("--prost-serde_opt=no_include".to_owned());
+    }
 
     if compile_well_known_types {
         args.push("--prost_opt=compile_well_known_types".to_owned());
@@ -824,6 +1254,21 @@ fn main() {
         }
     }
 
+    args.extend(
+        codegen
+            .prost_opts()
+            .into_iter()
+            .map(|opt| format!("--prost_opt={}", opt)),
+    );
+    if is_tonic {
+        args.extend(
+            codegen
+                .tonic_opts()
+                .into_iter()
+                .map(|opt| format!("--tonic_opt={}", opt)),
+        );
+    }
+
     args.extend(extra_args);
     args.extend(
         proto_paths
@@ -864,6 +1309,10 @@ fn main() {
         );
     }
 
+    if is_serde {
+        merge_serde_files(&out_dir);
+    }
+
     // Not all proto files will consistently produce `.rs` or `.tonic.rs` files. This is
     // caused by the proto file being transpiled not having an RPC service or other protos
     // defined (a natural and expected situation). To guarantee consistent outputs, all
@@ -939,6 +1388,42 @@ fn main() {
     let extern_paths = get_extern_paths(&descriptor_set, &crate_name, compile_well_known_types)
         .expect("Failed to compute proto package info");
 
+    let reflect_content = match reflect {
+        Some(reflect) => {
+            let mut descriptor_sets: Vec<FileDescriptorSet> = reflect
+                .descriptor_sets
+                .iter()
+                .map(parse_descriptor_set_file)
+                .collect();
+            descriptor_sets.push(descriptor_set.clone());
+
+            let message_paths = if reflect.messages {
+                get_reflect_message_paths(&descriptor_set, compile_well_known_types)
+            } else {
+                BTreeMap::new()
+            };
+
+            fs::write(
+                &reflect.descriptor_set_out,
+                merge_descriptor_sets(descriptor_sets).encode_to_vec(),
+            )
+            .expect("Failed to write file.");
+
+            let descriptor_set_file_name = reflect
+                .descriptor_set_out
+                .file_name()
+                .expect("Failed to get descriptor set file name")
+                .to_string_lossy();
+
+            generate_reflect_rs(
+                &reflect.crate_name,
+                &descriptor_set_file_name,
+                &message_paths,
+            )
+        }
+        None => String::new(),
+    };
+
     // Write outputs
     fs::write(
         &out_librs,
@@ -946,6 +1431,7 @@ fn main() {
             &rust_files,
             is_tonic,
             direct_dep_crate_names,
+            reflect_content,
             additional_content,
         ),
     )
@@ -1045,6 +1531,25 @@ mod test {
                 Some(&RustModulePath::from("bar::baz::Foo"))
             );
         }
+
+        {
+            let mut extern_paths = BTreeMap::new();
+            oneof_type_to_extern_paths(
+                &mut extern_paths,
+                &ProtoPath::from("bar.Baz"),
+                &RustModulePath::from("bar::baz"),
+                &OneofDescriptorProto {
+                    name: Some("foo_bar".to_string()),
+                    ..OneofDescriptorProto::default()
+                },
+            );
+
+            assert_eq!(extern_paths.len(), 1);
+            assert_eq!(
+                extern_paths.get(&ProtoPath::from("bar.Baz.foo_bar")),
+                Some(&RustModulePath::from("bar::baz::FooBar"))
+            );
+        }
     }
 
     #[test]
@@ -1121,7 +1626,7 @@ mod test {
                     ..DescriptorProto::default()
                 },
                 DescriptorProto {
-                    name: Some("Nested".to_string()),
+                    name: Some("NestedType".to_string()),
                     nested_type: vec![DescriptorProto {
                         name: Some("Baz".to_string()),
                         enum_type: vec![EnumDescriptorProto {
@@ -1154,16 +1659,16 @@ mod test {
                 Some(&RustModulePath::from("bar::Foo"))
             );
             assert_eq!(
-                extern_paths.get(&ProtoPath::from("bar.foo.Bar")),
+                extern_paths.get(&ProtoPath::from("bar.Foo.Bar")),
                 Some(&RustModulePath::from("bar::foo::Bar"))
             );
             assert_eq!(
-                extern_paths.get(&ProtoPath::from("bar.foo.Nested")),
-                Some(&RustModulePath::from("bar::foo::Nested"))
+                extern_paths.get(&ProtoPath::from("bar.Foo.NestedType")),
+                Some(&RustModulePath::from("bar::foo::NestedType"))
             );
             assert_eq!(
-                extern_paths.get(&ProtoPath::from("bar.foo.nested.Baz")),
-                Some(&RustModulePath::from("bar::foo::nested::Baz"))
+                extern_paths.get(&ProtoPath::from("bar.Foo.NestedType.Baz")),
+                Some(&RustModulePath::from("bar::foo::nested_type::Baz"))
             );
         }
 
@@ -1181,16 +1686,16 @@ mod test {
                 Some(&RustModulePath::from("bar::bob::Foo"))
             );
             assert_eq!(
-                extern_paths.get(&ProtoPath::from("bar.bob.foo.Bar")),
+                extern_paths.get(&ProtoPath::from("bar.bob.Foo.Bar")),
                 Some(&RustModulePath::from("bar::bob::foo::Bar"))
             );
             assert_eq!(
-                extern_paths.get(&ProtoPath::from("bar.bob.foo.Nested")),
-                Some(&RustModulePath::from("bar::bob::foo::Nested"))
+                extern_paths.get(&ProtoPath::from("bar.bob.Foo.NestedType")),
+                Some(&RustModulePath::from("bar::bob::foo::NestedType"))
             );
             assert_eq!(
-                extern_paths.get(&ProtoPath::from("bar.bob.foo.nested.Baz")),
-                Some(&RustModulePath::from("bar::bob::foo::nested::Baz"))
+                extern_paths.get(&ProtoPath::from("bar.bob.Foo.NestedType.Baz")),
+                Some(&RustModulePath::from("bar::bob::foo::nested_type::Baz"))
             );
         }
     }
@@ -1414,4 +1919,184 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn codegen_options_test() {
+        let mut codegen = CodegenOptions::default();
+        for arg in [
+            "--type_attribute=.=#[derive(serde::Serialize, serde::Deserialize)]",
+            "--type_attribute=.foo.Bar=#[serde(rename_all = \"camelCase\")]",
+            "--field_attribute=.foo.Bar.baz=#[doc = \"a\\\\b\"]",
+            "--bytes=.foo.Bar.data",
+            "--btree_map=.",
+            "--boxed=.foo.Bar.child",
+            "--no_tonic_client",
+        ] {
+            assert!(codegen.handle_arg(arg), "{}", arg);
+        }
+
+        assert!(!codegen.handle_arg("--prost_opt=bytes=."));
+        assert!(!codegen.handle_arg("--is_tonic"));
+
+        assert_eq!(
+            codegen.prost_opts(),
+            vec![
+                "type_attribute=.=#[derive(serde::Serialize\\, serde::Deserialize)]",
+                "type_attribute=.foo.Bar=#[serde(rename_all = \"camelCase\")]",
+                "field_attribute=.foo.Bar.baz=#[doc = \"a\\\\\\\\b\"]",
+                "bytes=.foo.Bar.data",
+                "btree_map=.",
+                "boxed=.foo.Bar.child",
+            ]
+        );
+        assert_eq!(codegen.tonic_opts(), vec!["no_client"]);
+    }
+
+    #[test]
+    fn codegen_options_default_test() {
+        let codegen = CodegenOptions::default();
+        assert!(codegen.prost_opts().is_empty());
+        assert!(codegen.tonic_opts().is_empty());
+    }
+
+    #[test]
+    fn serde_output_target_test() {
+        assert_eq!(
+            serde_output_target(Path::new("out/foo.bar.serde.rs")),
+            Some(PathBuf::from("out/foo.bar.rs"))
+        );
+        assert_eq!(
+            serde_output_target(Path::new("out/_.serde.rs")),
+            Some(PathBuf::from("out/_.rs"))
+        );
+        assert_eq!(serde_output_target(Path::new("out/foo.bar.rs")), None);
+        assert_eq!(serde_output_target(Path::new("out/foo.tonic.rs")), None);
+    }
+
+    #[test]
+    fn merge_descriptor_sets_test() {
+        let file = |name: &str, dependency: &[&str]| FileDescriptorProto {
+            name: Some(name.to_string()),
+            dependency: dependency.iter().map(|d| d.to_string()).collect(),
+            ..FileDescriptorProto::default()
+        };
+
+        let merged = merge_descriptor_sets(vec![
+            FileDescriptorSet {
+                file: vec![file("a.proto", &["c.proto", "b.proto"])],
+            },
+            FileDescriptorSet {
+                file: vec![file("b.proto", &["c.proto"]), file("c.proto", &[])],
+            },
+            FileDescriptorSet {
+                file: vec![file("c.proto", &[])],
+            },
+        ]);
+
+        assert_eq!(
+            merged
+                .file
+                .iter()
+                .map(|file| file.name())
+                .collect::<Vec<_>>(),
+            vec!["c.proto", "b.proto", "a.proto"]
+        );
+    }
+
+    #[test]
+    fn get_reflect_message_paths_test() {
+        let descriptor_set = FileDescriptorSet {
+            file: vec![
+                FileDescriptorProto {
+                    name: Some("foo.proto".to_string()),
+                    package: Some("foo.bar_baz".to_string()),
+                    message_type: vec![DescriptorProto {
+                        name: Some("OuterMessage".to_string()),
+                        nested_type: vec![
+                            DescriptorProto {
+                                name: Some("Inner".to_string()),
+                                ..DescriptorProto::default()
+                            },
+                            DescriptorProto {
+                                name: Some("ValuesEntry".to_string()),
+                                options: Some(prost_types::MessageOptions {
+                                    map_entry: Some(true),
+                                    ..prost_types::MessageOptions::default()
+                                }),
+                                ..DescriptorProto::default()
+                            },
+                        ],
+                        enum_type: vec![EnumDescriptorProto {
+                            name: Some("Kind".to_string()),
+                            ..EnumDescriptorProto::default()
+                        }],
+                        oneof_decl: vec![OneofDescriptorProto {
+                            name: Some("value".to_string()),
+                            ..OneofDescriptorProto::default()
+                        }],
+                        ..DescriptorProto::default()
+                    }],
+                    ..FileDescriptorProto::default()
+                },
+                FileDescriptorProto {
+                    name: Some("root.proto".to_string()),
+                    message_type: vec![DescriptorProto {
+                        name: Some("Root".to_string()),
+                        ..DescriptorProto::default()
+                    }],
+                    ..FileDescriptorProto::default()
+                },
+                FileDescriptorProto {
+                    name: Some("google/protobuf/duration.proto".to_string()),
+                    package: Some("google.protobuf".to_string()),
+                    message_type: vec![DescriptorProto {
+                        name: Some("Duration".to_string()),
+                        ..DescriptorProto::default()
+                    }],
+                    ..FileDescriptorProto::default()
+                },
+            ],
+        };
+
+        assert_eq!(
+            get_reflect_message_paths(&descriptor_set, false),
+            BTreeMap::from([
+                (
+                    ProtoPath::from("Root"),
+                    RustModulePath("crate::Root".to_string())
+                ),
+                (
+                    ProtoPath::from("foo.bar_baz.OuterMessage"),
+                    RustModulePath("crate::foo::bar_baz::OuterMessage".to_string())
+                ),
+                (
+                    ProtoPath::from("foo.bar_baz.OuterMessage.Inner"),
+                    RustModulePath("crate::foo::bar_baz::outer_message::Inner".to_string())
+                ),
+            ])
+        );
+
+        assert!(get_reflect_message_paths(&descriptor_set, true)
+            .contains_key(&ProtoPath::from("google.protobuf.Duration")));
+    }
+
+    #[test]
+    fn generate_reflect_rs_test() {
+        let message_paths = BTreeMap::from([(
+            ProtoPath::from("foo.Bar"),
+            RustModulePath("crate::foo::Bar".to_string()),
+        )]);
+
+        let content =
+            generate_reflect_rs("prost_reflect", "foo.descriptor_set.bin", &message_paths);
+
+        assert!(content.contains(
+            r#"pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!("foo.descriptor_set.bin");"#
+        ));
+        assert!(content.contains(
+            "pub static DESCRIPTOR_POOL: ::std::sync::LazyLock<::prost_reflect::DescriptorPool>"
+        ));
+        assert!(content.contains("impl ::prost_reflect::ReflectMessage for crate::foo::Bar {"));
+        assert!(content.contains(r#"crate::DESCRIPTOR_POOL.get_message_by_name("foo.Bar")"#));
+    }
 }
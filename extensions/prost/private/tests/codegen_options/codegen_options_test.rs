@@ -0,0 +1,37 @@
+//! Tests structured code generation options.
+
+use std::collections::{BTreeMap, HashSet};
+
+use codegen_options_proto::codegen_options::registry_server::{Registry, RegistryServer};
+use codegen_options_proto::codegen_options::{Child, Parent};
+use prost::bytes::Bytes;
+
+struct Service;
+
+#[tonic::async_trait]
+impl Registry for Service {
+    async fn register(
+        &self,
+        request: tonic::Request<Parent>,
+    ) -> Result<tonic::Response<Child>, tonic::Status> {
+        let child = request.into_inner().child.unwrap_or_default();
+        Ok(tonic::Response::new(*child))
+    }
+}
+
+#[test]
+fn test_codegen_options() {
+    let parent = Parent {
+        payload: Bytes::from_static(b"payload"),
+        counts: BTreeMap::from([("a".to_string(), 1)]),
+        child: Some(Box::new(Child {
+            name: "child".to_string(),
+        })),
+    };
+
+    // `type_attributes` derive `Eq` and `Hash`, which `bytes` and `btree_map` make possible.
+    let parents = HashSet::from([parent.clone()]);
+    assert!(parents.contains(&parent));
+
+    let _server = RegistryServer::new(Service);
+}
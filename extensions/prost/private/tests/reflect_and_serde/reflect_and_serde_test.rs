@@ -0,0 +1,60 @@
+//! Tests the reflection and serde support of `rust_prost_toolchain`.
+
+use prost_reflect::{DescriptorPool, ReflectMessage};
+use reflect_and_serde_proto::reflect_and_serde::{outer, Outer};
+
+fn new_outer() -> Outer {
+    Outer {
+        inner: Some(outer::Inner {
+            name: "inner".to_string(),
+        }),
+        kind: outer::Kind::Nested.into(),
+        counts: [("a".to_string(), 1)].into_iter().collect(),
+        created: Some(pbjson_types::Timestamp {
+            seconds: 1,
+            nanos: 0,
+        }),
+        value: Some(outer::Value::Number(42)),
+    }
+}
+
+#[test]
+fn test_file_descriptor_set() {
+    let pool = DescriptorPool::decode(reflect_and_serde_proto::FILE_DESCRIPTOR_SET).unwrap();
+
+    assert!(pool
+        .get_message_by_name("reflect_and_serde.Outer.Inner")
+        .is_some());
+    assert!(pool
+        .get_message_by_name("google.protobuf.Timestamp")
+        .is_some());
+}
+
+#[test]
+fn test_reflect_message() {
+    assert_eq!(
+        Outer::default().descriptor().full_name(),
+        "reflect_and_serde.Outer"
+    );
+    assert_eq!(
+        outer::Inner::default().descriptor().full_name(),
+        "reflect_and_serde.Outer.Inner"
+    );
+
+    let message = new_outer().transcode_to_dynamic();
+    assert_eq!(
+        message.get_field_by_name("number").unwrap().as_i64(),
+        Some(42)
+    );
+}
+
+#[test]
+fn test_serde() {
+    let json = serde_json::to_value(new_outer()).unwrap();
+
+    assert_eq!(json["inner"]["name"], "inner");
+    assert_eq!(json["kind"], "KIND_NESTED");
+    assert_eq!(json["counts"]["a"], 1);
+    assert_eq!(json["number"], "42");
+    assert_eq!(serde_json::from_value::<Outer>(json).unwrap(), new_outer());
+}
@@ -1,7 +1,7 @@
 """Bazel pyo3 rules"""
 
 load("@bazel_skylib//lib:paths.bzl", "paths")
-load("@rules_python//python:defs.bzl", "PyInfo")
+load("@rules_python//python:defs.bzl", "PyInfo", "py_test")
 load(
     "@rules_rust//rust:defs.bzl",
     "rust_analyzer_aspect",
@@ -98,21 +98,28 @@ def _py_pyo3_library_impl(ctx):
     files.append(ext)
 
     stub = None
+    stub_tree = None
     if _stubs_enabled(ctx.attr.stubs, toolchain):
         stub = ctx.actions.declare_file("{}.pyi".format(ctx.label.name))
 
+        # Stubs for the full module tree are written as a PEP 561 stub-only
+        # package so they do not shadow the extension module on import.
+        # https://peps.python.org/pep-0561/#stub-only-packages
+        stub_tree = ctx.actions.declare_directory("{}-stubs".format(ctx.label.name))
+
         args = ctx.actions.args()
         args.add(ctx.label.name, format = "--module_name=%s")
         args.add(ext, format = "--module_path=%s")
         args.add(stub, format = "--output=%s")
+        args.add(stub_tree.path, format = "--output_dir=%s")
         ctx.actions.run(
             mnemonic = "PyO3StubGen",
-            outputs = [stub],
+            outputs = [stub, stub_tree],
             inputs = [ext],
             executable = ctx.executable._stubgen,
             arguments = [args],
         )
-        files.append(stub)
+        files.extend([stub, stub_tree])
 
     providers = [
         DefaultInfo(
@@ -139,6 +146,7 @@ def _py_pyo3_library_impl(ctx):
 
         if stub:
             output_groups["pyo3_type_stubs"] = depset([stub])
+            output_groups["pyo3_type_stub_tree"] = depset([stub_tree])
 
         providers.append(OutputGroupInfo(**output_groups))
 
@@ -216,6 +224,7 @@ def pyo3_extension(
         rustc_env_files = [],
         rustc_flags = [],
         stubs = None,
+        stubtest = False,
         version = None,
         compilation_mode = "opt",
         **kwargs):
@@ -255,6 +264,11 @@ def pyo3_extension(
         rustc_flags (list, optional): List of compiler flags passed to `rustc`.
             For more details see [rust_shared_library][rsl].
         stubs (bool, optional): Whether or not to generate stubs (`.pyi` file) for the module.
+            The stubs of the module and all of its submodules are also available as a
+            `{name}-stubs` package in the `pyo3_type_stub_tree` output group.
+        stubtest (bool, optional): Whether or not to define a `{name}_stubtest` test which compares
+            the generated stubs against the public names of the compiled module. Requires stubs to be
+            generated, so `stubs` must not be `False` and if it is unset, `experimental_stubgen` must be enabled.
         version (str, optional): A version to inject in the cargo environment variable.
             For more details see [rust_shared_library][rsl].
         compilation_mode (str, optional): The [compilation_mode](https://bazel.build/reference/command-line-reference#flag--compilation_mode)
@@ -322,3 +336,30 @@ def pyo3_extension(
         visibility = visibility,
         **kwargs
     )
+
+    if stubtest:
+        # When `stubs` is unset, whether stubs are generated is up to the toolchain.
+        if stubs_int == 0:
+            fail("`stubtest` requires `stubs` to be enabled for `{}`".format(name))
+
+        native.filegroup(
+            name = name + "_stub_tree",
+            srcs = [name],
+            output_group = "pyo3_type_stub_tree",
+            tags = depset(tags + ["manual"]).to_list(),
+            visibility = ["//visibility:private"],
+        )
+
+        py_test(
+            name = name + "_stubtest",
+            srcs = [Label("//private:stubtest.py")],
+            main = Label("//private:stubtest.py"),
+            args = [
+                "--module_name={}".format(name),
+                "--module=$(rlocationpath :{})".format(name),
+                "--stubs=$(rlocationpath :{}_stub_tree)".format(name),
+            ],
+            data = [name, name + "_stub_tree"],
+            deps = [Label("@rules_python//python/runfiles")],
+            tags = tags,
+        )
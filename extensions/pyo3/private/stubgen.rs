@@ -1,8 +1,13 @@
 //! A tool for writing stubs from a [`pyo3::PyModule`].
+//!
+//! The stubs of the root module are written to `--output`. When `--output_dir`
+//! is provided, the stubs of the root module and all submodules are written as
+//! a package tree, along with a [PEP 561](https://peps.python.org/pep-0561/)
+//! `py.typed` marker.
 
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use pyo3_introspection::{introspect_cdylib, module_stub_files};
 
@@ -16,6 +21,9 @@ struct Args {
 
     /// The output path for the stubs file
     output: PathBuf,
+
+    /// The output directory for the stubs of the root module and all submodules.
+    output_dir: Option<PathBuf>,
 }
 
 impl Args {
@@ -23,6 +31,7 @@ impl Args {
         let mut module_name = None;
         let mut module_path = None;
         let mut output = None;
+        let mut output_dir = None;
 
         for arg in env::args().skip(1) {
             if let Some((key, value)) = arg.split_once('=') {
@@ -30,6 +39,7 @@ impl Args {
                     "--module_name" => module_name = Some(value.to_string()),
                     "--module_path" => module_path = Some(PathBuf::from(value)),
                     "--output" => output = Some(PathBuf::from(value)),
+                    "--output_dir" => output_dir = Some(PathBuf::from(value)),
                     _ => panic!("Unknown argument: {}", key),
                 }
             } else {
@@ -41,10 +51,24 @@ impl Args {
             module_name: module_name.expect("Missing --module_name argument"),
             module_path: module_path.expect("Missing --module_path argument"),
             output: output.expect("Missing --output argument"),
+            output_dir,
         }
     }
 }
 
+/// The name of the marker file for packages which ship type information.
+const PY_TYPED: &str = "py.typed";
+
+/// Write a file, creating any missing parent directories.
+fn write_file(path: &Path, content: &str) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .unwrap_or_else(|e| panic!("Failed to create directory {}: {}", parent.display(), e));
+    }
+    fs::write(path, content)
+        .unwrap_or_else(|e| panic!("Failed to write {}: {}", path.display(), e));
+}
+
 fn main() {
     let args = Args::parse();
 
@@ -53,20 +77,32 @@ fn main() {
         .expect("Failed to parse stubs from module.");
 
     // Generate stubs
-    let mut actual_stubs = module_stub_files(&module);
+    let stubs = module_stub_files(&module);
 
     // Extract stubs for root module.
-    let content = &actual_stubs
-        .remove(&PathBuf::from("__init__.pyi"))
+    let content = stubs
+        .get(Path::new("__init__.pyi"))
         .expect("Failed to locate stubs for root module.");
+    write_file(&args.output, content);
 
-    if !actual_stubs.is_empty() {
-        eprintln!("WARNING: Dropped stubs: {:#?}", actual_stubs);
-    }
-
-    // Save results
-    if let Some(parent) = args.output.parent() {
-        fs::create_dir_all(parent).unwrap();
+    match &args.output_dir {
+        Some(output_dir) => {
+            for (path, content) in &stubs {
+                write_file(&output_dir.join(path), content);
+            }
+            write_file(&output_dir.join(PY_TYPED), "");
+        }
+        None => {
+            let submodules = stubs
+                .keys()
+                .filter(|path| path.as_path() != Path::new("__init__.pyi"))
+                .collect::<Vec<_>>();
+            if !submodules.is_empty() {
+                eprintln!(
+                    "WARNING: Stubs for submodules are only written with `--output_dir`: {:#?}",
+                    submodules
+                );
+            }
+        }
     }
-    fs::write(&args.output, content).unwrap();
 }
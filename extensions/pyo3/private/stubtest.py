@@ -0,0 +1,209 @@
+"""A test which compares PyO3 generated stubs against the compiled extension module.
+
+This is a lightweight take on `mypy.stubtest` which only requires the Python
+standard library. The public names of every module and class defined in the
+stubs are compared against the runtime objects in both directions so stubs
+which drift from the compiled module are caught.
+"""
+
+import argparse
+import ast
+import importlib.machinery
+import importlib.util
+import inspect
+import sys
+from pathlib import Path
+from types import ModuleType
+from typing import Dict, List, Optional, Set
+
+from python.runfiles import Runfiles
+
+
+def _rlocation(runfiles: Runfiles, rlocationpath: str) -> Path:
+    """Look up a runfile and ensure the file exists
+
+    Args:
+        runfiles: The runfiles object
+        rlocationpath: The runfile key
+
+    Returns:
+        The requested runfile.
+    """
+    runfile = runfiles.Rlocation(rlocationpath)
+    if not runfile:
+        raise FileNotFoundError(f"Failed to find runfile: {rlocationpath}")
+    path = Path(runfile)
+    if not path.exists():
+        raise FileNotFoundError(f"Runfile does not exist: ({rlocationpath}) {path}")
+    return path
+
+
+def _is_public(name: str) -> bool:
+    """Determine whether or not a name is part of the public interface."""
+    return not name.startswith("_")
+
+
+class StubModule:
+    """The public interface of a module as described by a `.pyi` file."""
+
+    def __init__(self, path: Path) -> None:
+        self.path = path
+        self.names: Set[str] = set()
+        self.classes: Dict[str, Set[str]] = {}
+
+        tree = ast.parse(path.read_text(encoding="utf-8"), filename=str(path))
+        for node in tree.body:
+            if isinstance(node, ast.ClassDef):
+                self.names.add(node.name)
+                self.classes[node.name] = _body_names(node.body)
+            else:
+                self.names.update(_body_names([node]))
+
+        self.names = {name for name in self.names if _is_public(name)}
+
+
+def _body_names(body: List[ast.stmt]) -> Set[str]:
+    """Collect the names defined by a list of statements.
+
+    Imports are only considered definitions when using the explicit
+    re-export form (`import x as x` or `from y import x as x`).
+    """
+    names = set()
+    for node in body:
+        if isinstance(node, (ast.FunctionDef, ast.AsyncFunctionDef, ast.ClassDef)):
+            names.add(node.name)
+        elif isinstance(node, ast.AnnAssign) and isinstance(node.target, ast.Name):
+            names.add(node.target.id)
+        elif isinstance(node, ast.Assign):
+            for target in node.targets:
+                if isinstance(target, ast.Name):
+                    names.add(target.id)
+        elif isinstance(node, (ast.Import, ast.ImportFrom)):
+            for alias in node.names:
+                if alias.asname and alias.asname == alias.name.split(".")[-1]:
+                    names.add(alias.asname)
+        elif isinstance(node, ast.If):
+            names.update(_body_names(node.body))
+            names.update(_body_names(node.orelse))
+    return names
+
+
+def _find_stub(stubs_dir: Path, parts: List[str]) -> Optional[Path]:
+    """Locate the stub file for a module within a stub package."""
+    package = stubs_dir.joinpath(*parts, "__init__.pyi")
+    if package.exists():
+        return package
+    if parts:
+        module = stubs_dir.joinpath(*parts[:-1], parts[-1] + ".pyi")
+        if module.exists():
+            return module
+    return None
+
+
+def _load_extension(module_name: str, path: Path) -> ModuleType:
+    """Load a compiled extension module from a path."""
+    loader = importlib.machinery.ExtensionFileLoader(module_name, str(path))
+    spec = importlib.util.spec_from_file_location(module_name, path, loader=loader)
+    if spec is None:
+        raise ImportError(f"Failed to create a module spec for {path}")
+    module = importlib.util.module_from_spec(spec)
+    loader.exec_module(module)
+    return module
+
+
+def _check_module(
+    module: ModuleType,
+    stubs_dir: Path,
+    parts: List[str],
+    errors: List[str],
+) -> None:
+    """Compare a runtime module and its submodules against their stubs."""
+    qualname = ".".join([module.__name__.split(".")[0]] + parts)
+    stub_path = _find_stub(stubs_dir, parts)
+    if stub_path is None:
+        errors.append(f"{qualname}: no stubs were generated for this module")
+        return
+
+    stub = StubModule(stub_path)
+    runtime_names = {name for name in dir(module) if _is_public(name)}
+
+    for name in sorted(runtime_names):
+        value = getattr(module, name)
+        if isinstance(value, ModuleType):
+            _check_module(value, stubs_dir, parts + [name], errors)
+            continue
+        if name not in stub.names:
+            errors.append(f"{qualname}.{name}: is not present in stubs ({stub_path})")
+            continue
+        if inspect.isclass(value) and name in stub.classes:
+            _check_class(f"{qualname}.{name}", value, stub.classes[name], errors)
+
+    for name in sorted(stub.names - runtime_names):
+        errors.append(f"{qualname}.{name}: is not present at runtime ({stub_path})")
+
+
+def _check_class(
+    qualname: str,
+    cls: type,
+    stub_members: Set[str],
+    errors: List[str],
+) -> None:
+    """Compare the public members of a runtime class against its stub."""
+    runtime_members = {name for name in vars(cls) if _is_public(name)}
+    stub_members = {name for name in stub_members if _is_public(name)}
+
+    for name in sorted(runtime_members - stub_members):
+        errors.append(f"{qualname}.{name}: is not present in stubs")
+    for name in sorted(stub_members - runtime_members):
+        errors.append(f"{qualname}.{name}: is not present at runtime")
+
+
+def parse_args() -> argparse.Namespace:
+    """Parse command line arguments."""
+    parser = argparse.ArgumentParser(description=__doc__)
+    parser.add_argument(
+        "--module_name",
+        required=True,
+        help="The name of the PyO3 module.",
+    )
+    parser.add_argument(
+        "--module",
+        required=True,
+        help="The rlocationpath of the compiled extension module.",
+    )
+    parser.add_argument(
+        "--stubs",
+        required=True,
+        help="The rlocationpath of the generated stub package.",
+    )
+    return parser.parse_args()
+
+
+def main() -> None:
+    """The main entrypoint."""
+    args = parse_args()
+
+    runfiles = Runfiles.Create()
+    if not runfiles:
+        raise EnvironmentError("Failed to locate runfiles.")
+
+    module = _load_extension(args.module_name, _rlocation(runfiles, args.module))
+    stubs_dir = _rlocation(runfiles, args.stubs)
+
+    errors: List[str] = []
+    _check_module(module, stubs_dir, [], errors)
+
+    if errors:
+        print(
+            "Stubs for `{}` do not match the compiled module:\n  {}".format(
+                args.module_name, "\n  ".join(errors)
+            ),
+            file=sys.stderr,
+        )
+        sys.exit(1)
+
+    print(f"Stubs for `{args.module_name}` match the compiled module.")
+
+
+if __name__ == "__main__":
+    main()
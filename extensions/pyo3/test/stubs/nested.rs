@@ -0,0 +1,26 @@
+//! A PyO3 module with a nested submodule for testing stub package generation.
+
+use pyo3::prelude::*;
+
+#[pymodule]
+mod nested {
+    use super::*;
+
+    /// Returns the greeting of the root module.
+    #[pyfunction]
+    fn greet() -> String {
+        "hello".to_string()
+    }
+
+    /// A submodule defined within the root module.
+    #[pymodule]
+    mod math {
+        use super::*;
+
+        /// Doubles a number.
+        #[pyfunction]
+        fn double(value: usize) -> usize {
+            value * 2
+        }
+    }
+}
@@ -197,7 +197,7 @@ An example of this rule in use can be seen at [@rules_rust//examples/wasm](../ex
 
 def _rust_wasm_bindgen_toolchain_impl(ctx):
     all_test_files = depset()
-    if ctx.attr.wasm_bindgen_test or ctx.attr.webdriver or ctx.attr.browser_type:
+    if ctx.attr.webdriver or ctx.attr.browser_type:
         if not ctx.attr.wasm_bindgen_test:
             fail("Not all webdriver attributes provided. Missing `wasm_bindgen_test` on `{}`".format(ctx.label))
         if not ctx.attr.webdriver:
@@ -222,6 +222,21 @@ def _rust_wasm_bindgen_toolchain_impl(ctx):
 
         all_test_files = depset(transitive = all_depsets)
 
+    js_runtime_depsets = []
+    for js_runtime in [ctx.attr.node, ctx.attr.deno]:
+        if not js_runtime:
+            continue
+        if not ctx.attr.wasm_bindgen_test or not ctx.attr.wasm_bindgen_test_runner:
+            fail("`node` and `deno` require `wasm_bindgen_test` and `wasm_bindgen_test_runner` on `{}`".format(ctx.label))
+        js_runtime_depsets.extend([
+            js_runtime[DefaultInfo].files,
+            js_runtime[DefaultInfo].default_runfiles.files,
+            ctx.attr.wasm_bindgen_test_runner[DefaultInfo].files,
+            ctx.attr.wasm_bindgen_test_runner[DefaultInfo].default_runfiles.files,
+        ])
+    if js_runtime_depsets:
+        all_test_files = depset(transitive = [all_test_files] + js_runtime_depsets)
+
     return platform_common.ToolchainInfo(
         wasm_bindgen_cli = ctx.executable.wasm_bindgen_cli,
         wasm_bindgen_test = ctx.attr.wasm_bindgen_test,
@@ -231,6 +246,8 @@ def _rust_wasm_bindgen_toolchain_impl(ctx):
         webdriver_json = ctx.file.webdriver_json,
         browser_type = ctx.attr.browser_type,
         browser = ctx.executable.browser,
+        node = ctx.executable.node,
+        deno = ctx.executable.deno,
         all_test_files = all_test_files,
 
         # Deprecated
@@ -285,6 +302,18 @@ For additional information, see the [Bazel toolchains documentation][toolchains]
                 "safari",
             ],
         ),
+        "deno": attr.label(
+            doc = "The [Deno](https://deno.com) binary used by `rust_wasm_bindgen_test` targets with `runtime = \"deno\"`.",
+            cfg = "exec",
+            executable = True,
+            allow_files = True,
+        ),
+        "node": attr.label(
+            doc = "The [Node.js](https://nodejs.org) binary used by `rust_wasm_bindgen_test` targets with `runtime = \"node\"`.",
+            cfg = "exec",
+            executable = True,
+            allow_files = True,
+        ),
         "wasm_bindgen_cli": attr.label(
             doc = "The label of a `wasm-bindgen-cli` executable.",
             executable = True,
@@ -61,8 +61,12 @@ def _rlocationpath(file, workspace_name):
 
 def _rust_wasm_bindgen_test_impl(ctx):
     wb_toolchain = ctx.toolchains[Label("//:toolchain_type")]
-    if not wb_toolchain.webdriver:
-        fail("The currently registered wasm_bindgen_toolchain does not have a webdriver assigned. Tests are unavailable without one.")
+    if ctx.attr.runtime == "browser" and not wb_toolchain.webdriver:
+        fail("The currently registered wasm_bindgen_toolchain does not have a webdriver assigned. Browser tests are unavailable without one.")
+    if ctx.attr.runtime == "node" and not wb_toolchain.node:
+        fail("The currently registered wasm_bindgen_toolchain does not have `node` assigned. Node.js tests are unavailable without it.")
+    if ctx.attr.runtime == "deno" and not wb_toolchain.deno:
+        fail("The currently registered wasm_bindgen_toolchain does not have `deno` assigned. Deno tests are unavailable without it.")
 
     toolchain = find_toolchain(ctx)
 
@@ -164,17 +168,24 @@ def _rust_wasm_bindgen_test_impl(ctx):
         is_executable = True,
     )
 
-    if wb_toolchain.browser:
-        env["BROWSER"] = _rlocationpath(wb_toolchain.browser, ctx.workspace_name)
-
-    env["BROWSER_TYPE"] = wb_toolchain.browser_type
-    env["WEBDRIVER"] = _rlocationpath(wb_toolchain.webdriver, ctx.workspace_name)
-    env["WEBDRIVER_ARGS"] = " ".join(wb_toolchain.webdriver_args)
-    env["WEBDRIVER_JSON"] = _rlocationpath(wb_toolchain.webdriver_json, ctx.workspace_name)
+    env["WASM_BINDGEN_TEST_RUNTIME"] = ctx.attr.runtime
     env["WASM_BINDGEN_TEST_RUNNER"] = _rlocationpath(wb_toolchain.wasm_bindgen_test_runner, ctx.workspace_name)
 
-    # Force the use of a browser for now as there is no node integration.
-    env["WASM_BINDGEN_USE_BROWSER"] = "1"
+    if ctx.attr.runtime == "browser":
+        if wb_toolchain.browser:
+            env["BROWSER"] = _rlocationpath(wb_toolchain.browser, ctx.workspace_name)
+
+        env["BROWSER_TYPE"] = wb_toolchain.browser_type
+        env["WEBDRIVER"] = _rlocationpath(wb_toolchain.webdriver, ctx.workspace_name)
+        env["WEBDRIVER_ARGS"] = " ".join(wb_toolchain.webdriver_args)
+        env["WEBDRIVER_JSON"] = _rlocationpath(wb_toolchain.webdriver_json, ctx.workspace_name)
+
+        # Tests not configured with `run_in_browser` would otherwise run in Node.js.
+        env["WASM_BINDGEN_USE_BROWSER"] = "1"
+    elif ctx.attr.runtime == "node":
+        env["NODE"] = _rlocationpath(wb_toolchain.node, ctx.workspace_name)
+    elif ctx.attr.runtime == "deno":
+        env["DENO"] = _rlocationpath(wb_toolchain.deno, ctx.workspace_name)
 
     providers = []
 
@@ -308,6 +319,17 @@ rust_wasm_bindgen_test = rule(
             file of arguments to rustc: `@$(location //package:target)`.
         """,
         ),
+        "runtime": attr.string(
+            doc = """\
+            The environment to run tests in.
+
+            `browser` runs tests in the toolchain's browser through its webdriver. `node` and `deno`
+            run tests headlessly in the toolchain's Node.js or Deno binary, which requires no browser.
+            Regardless of the runtime, `--test_filter`, sharding and JUnit XML reports are supported.
+        """,
+            default = "browser",
+            values = ["browser", "node", "deno"],
+        ),
         "target_arch": attr.string(
             doc = "The target architecture to use for the wasm-bindgen command line option.",
             default = "wasm32",
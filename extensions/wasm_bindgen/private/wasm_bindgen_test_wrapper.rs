@@ -1,10 +1,20 @@
 //! A Bazel wrapper for the wasm-bindgen-test-runner binary.
+//!
+//! Tests are run in a browser through a webdriver, or headlessly in Node.js or
+//! Deno, depending on `WASM_BINDGEN_TEST_RUNTIME`. The wrapper integrates the
+//! runner with the Bazel test protocol:
+//!
+//! - `TESTBRIDGE_TEST_ONLY` (`--test_filter`) is passed to the runner as a test filter.
+//! - A JUnit XML report is written to `XML_OUTPUT_FILE`.
+//! - Tests are split between shards when `TEST_TOTAL_SHARDS` and `TEST_SHARD_INDEX` are set.
 
 use std::collections::BTreeMap;
 use std::env;
+use std::fmt::Write as _;
 use std::fs;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::{exit, Command};
+use std::process::{exit, Command, Stdio};
 
 use runfiles::{rlocation, Runfiles};
 
@@ -86,20 +96,12 @@ fn write_webdriver_for_browser(
     });
 }
 
-fn main() {
-    let runfiles = Runfiles::create().expect("Failed to locate runfiles");
-
-    let test_runner = rlocation!(
-        runfiles,
-        env::var("WASM_BINDGEN_TEST_RUNNER").expect("Failed to find TEST_WASM_BINARY env var")
-    )
-    .expect("Failed to locate test binary");
-    let test_bin = rlocation!(
-        runfiles,
-        env::var("TEST_WASM_BINARY").expect("Failed to find TEST_WASM_BINARY env var")
-    )
-    .expect("Failed to locate test binary");
-
+/// Configure the environment for running tests in a browser through a webdriver.
+fn configure_browser(
+    runfiles: &Runfiles,
+    env: &mut BTreeMap<String, String>,
+    undeclared_test_outputs: &Path,
+) {
     let browser_type = env::var("BROWSER_TYPE").expect("Failed to find `BROWSER_TYPE` env var");
     let browser = env::var_os("BROWSER").map(|_| {
         rlocation!(runfiles, env::var("BROWSER").unwrap()).expect("Failed to locate browser")
@@ -117,22 +119,9 @@ fn main() {
     )
     .expect("Failed to locate webdriver");
 
-    // Update any existing environment variables.
-    let mut env = env::vars().collect::<BTreeMap<_, _>>();
-    env.insert("TMP".to_string(), env["TEST_TMPDIR"].clone());
-    env.insert("TEMP".to_string(), env["TEST_TMPDIR"].clone());
-    env.insert("TMPDIR".to_string(), env["TEST_TMPDIR"].clone());
-    env.insert("HOME".to_string(), env["TEST_TMPDIR"].clone());
-    env.insert("USERPROFILE".to_string(), env["TEST_TMPDIR"].clone());
-
     let webdriver_args =
         env::var("WEBDRIVER_ARGS").expect("Failed to find WEBDRIVER_ARGS env var.");
 
-    let undeclared_test_outputs = PathBuf::from(
-        env::var("TEST_UNDECLARED_OUTPUTS_DIR")
-            .expect("TEST_UNDECLARED_OUTPUTS_DIR should always be defined for tests."),
-    );
-
     let updated_webdriver_json = undeclared_test_outputs.join("webdriver.json");
     env.insert(
         "WASM_BINDGEN_TEST_WEBDRIVER_JSON".to_string(),
@@ -224,19 +213,388 @@ fn main() {
             panic!("Unexpected browser type: {}", browser_type)
         }
     }
+}
 
-    // Run the test
-    let mut command = Command::new(test_runner);
-    command.envs(env).arg(test_bin).args(env::args().skip(1));
-    let result = command
-        .status()
-        .unwrap_or_else(|_| panic!("Failed to spawn command: {:#?}", command));
+/// Configure the environment for running tests in a JavaScript runtime such as Node.js or Deno.
+///
+/// `wasm-bindgen-test-runner` looks up the runtime by `name` on `PATH`, so a
+/// directory containing only a link to the runtime is prepended to `PATH`.
+fn configure_js_runtime(
+    runfiles: &Runfiles,
+    env: &mut BTreeMap<String, String>,
+    env_var: &str,
+    name: &str,
+    test_tmpdir: &Path,
+) {
+    let runtime = rlocation!(
+        runfiles,
+        env::var(env_var).unwrap_or_else(|_| panic!("Failed to find {} env var.", env_var))
+    )
+    .unwrap_or_else(|| panic!("Failed to locate {}", name));
+
+    let bin_dir = test_tmpdir.join("wasm_bindgen_test_bin");
+    fs::create_dir_all(&bin_dir)
+        .unwrap_or_else(|e| panic!("Failed to create directory: {}\n{:?}", bin_dir.display(), e));
+    let link = bin_dir.join(format!("{}{}", name, env::consts::EXE_SUFFIX));
+    if !link.exists() {
+        symlink(&runtime, &link);
+    }
+
+    let path = env::join_paths(
+        std::iter::once(bin_dir).chain(env.get("PATH").into_iter().flat_map(env::split_paths)),
+    )
+    .expect("Failed to join PATH");
+    env.insert("PATH".to_string(), path.to_string_lossy().to_string());
+}
+
+/// Link `original` to `link`, falling back to a copy where symlinks are unavailable.
+fn symlink(original: &Path, link: &Path) {
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(original, link);
+    #[cfg(windows)]
+    let result = std::os::windows::fs::symlink_file(original, link);
+    #[cfg(not(any(unix, windows)))]
+    let result: std::io::Result<()> = Err(std::io::ErrorKind::Unsupported.into());
+
+    if result.is_err() {
+        fs::copy(original, link).unwrap_or_else(|e| {
+            panic!(
+                "Failed to copy {} -> {}\n{:?}",
+                original.display(),
+                link.display(),
+                e
+            )
+        });
+    }
+}
+
+/// The outcome of a single test as reported by `wasm-bindgen-test`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TestOutcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// Parse the `test <name> ... <result>` lines printed by `wasm-bindgen-test`.
+fn parse_test_results(output: &str) -> Vec<(String, TestOutcome)> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("test "))
+        .filter_map(|line| line.split_once(" ... "))
+        .map(|(name, result)| {
+            let outcome = if result.starts_with("ok") {
+                TestOutcome::Passed
+            } else if result.starts_with("ignored") {
+                TestOutcome::Ignored
+            } else {
+                TestOutcome::Failed
+            };
+            (name.to_string(), outcome)
+        })
+        .collect()
+}
+
+/// Parse the output of `--list --format terse` into test names.
+fn parse_test_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_suffix(": test"))
+        .map(str::to_string)
+        .collect()
+}
 
-    if !result.success() {
-        exit(
-            result
-                .code()
-                .expect("Completed processes will always have exit codes."),
+/// Select the tests which run in the given shard.
+fn shard_tests(tests: &[String], total_shards: usize, shard_index: usize) -> Vec<String> {
+    tests
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| idx % total_shards == shard_index)
+        .map(|(_, test)| test.clone())
+        .collect()
+}
+
+/// Escape a string for use in XML text or attributes.
+fn escape_xml(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| matches!(c, '\t' | '\n' | '\r') || *c >= ' ')
+        .fold(String::new(), |mut escaped, c| {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                _ => escaped.push(c),
+            }
+            escaped
+        })
+}
+
+/// Render a JUnit XML report for the given test results.
+///
+/// The full output of the runner is attached to the suite since
+/// `wasm-bindgen-test` does not attribute output to individual tests.
+fn junit_xml(suite: &str, results: &[(String, TestOutcome)], output: &str) -> String {
+    let count = |outcome: TestOutcome| results.iter().filter(|(_, o)| *o == outcome).count();
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(xml, "<testsuites>").unwrap();
+    writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\">",
+        escape_xml(suite),
+        results.len(),
+        count(TestOutcome::Failed),
+        count(TestOutcome::Ignored),
+    )
+    .unwrap();
+    for (name, outcome) in results {
+        let (classname, test_name) = name.rsplit_once("::").unwrap_or(("", name));
+        write!(
+            xml,
+            "    <testcase classname=\"{}\" name=\"{}\"",
+            escape_xml(classname),
+            escape_xml(test_name)
         )
+        .unwrap();
+        match outcome {
+            TestOutcome::Passed => xml.push_str("/>\n"),
+            TestOutcome::Failed => {
+                xml.push_str(">\n      <failure message=\"FAIL\"/>\n    </testcase>\n")
+            }
+            TestOutcome::Ignored => xml.push_str(">\n      <skipped/>\n    </testcase>\n"),
+        }
+    }
+    writeln!(xml, "    <system-out>{}</system-out>", escape_xml(output)).unwrap();
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Run the test runner, streaming its stdout while also capturing it.
+fn run_and_capture(mut command: Command) -> (i32, String) {
+    command.stdout(Stdio::piped());
+    let mut child = command
+        .spawn()
+        .unwrap_or_else(|_| panic!("Failed to spawn command: {:#?}", command));
+
+    let mut output = String::new();
+    let stdout = child.stdout.take().expect("Failed to capture stdout");
+    for line in BufReader::new(stdout).lines() {
+        let line = line.expect("Failed to read test output");
+        println!("{}", line);
+        output.push_str(&line);
+        output.push('\n');
+    }
+
+    let status = child
+        .wait()
+        .unwrap_or_else(|_| panic!("Failed to wait on command: {:#?}", command));
+
+    (
+        status
+            .code()
+            .expect("Completed processes will always have exit codes."),
+        output,
+    )
+}
+
+fn main() {
+    let runfiles = Runfiles::create().expect("Failed to locate runfiles");
+
+    let test_runner = rlocation!(
+        runfiles,
+        env::var("WASM_BINDGEN_TEST_RUNNER").expect("Failed to find TEST_WASM_BINARY env var")
+    )
+    .expect("Failed to locate test binary");
+    let test_bin = rlocation!(
+        runfiles,
+        env::var("TEST_WASM_BINARY").expect("Failed to find TEST_WASM_BINARY env var")
+    )
+    .expect("Failed to locate test binary");
+
+    // Update any existing environment variables.
+    let mut env = env::vars().collect::<BTreeMap<_, _>>();
+    env.insert("TMP".to_string(), env["TEST_TMPDIR"].clone());
+    env.insert("TEMP".to_string(), env["TEST_TMPDIR"].clone());
+    env.insert("TMPDIR".to_string(), env["TEST_TMPDIR"].clone());
+    env.insert("HOME".to_string(), env["TEST_TMPDIR"].clone());
+    env.insert("USERPROFILE".to_string(), env["TEST_TMPDIR"].clone());
+
+    let undeclared_test_outputs = PathBuf::from(
+        env::var("TEST_UNDECLARED_OUTPUTS_DIR")
+            .expect("TEST_UNDECLARED_OUTPUTS_DIR should always be defined for tests."),
+    );
+    let test_tmpdir = PathBuf::from(&env["TEST_TMPDIR"]);
+
+    let runtime = env::var("WASM_BINDGEN_TEST_RUNTIME").unwrap_or_else(|_| "browser".to_string());
+    match runtime.as_str() {
+        "browser" => configure_browser(&runfiles, &mut env, &undeclared_test_outputs),
+        "node" => configure_js_runtime(&runfiles, &mut env, "NODE", "node", &test_tmpdir),
+        "deno" => {
+            configure_js_runtime(&runfiles, &mut env, "DENO", "deno", &test_tmpdir);
+            env.insert("WASM_BINDGEN_USE_DENO".to_string(), "1".to_string());
+        }
+        _ => panic!("Unexpected test runtime: {}", runtime),
+    }
+
+    let new_command = || {
+        let mut command = Command::new(&test_runner);
+        command.envs(&env).arg(&test_bin);
+        command
+    };
+
+    let filter = env::var("TESTBRIDGE_TEST_ONLY")
+        .ok()
+        .filter(|filter| !filter.is_empty());
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    let total_shards = env::var("TEST_TOTAL_SHARDS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|total| *total > 1);
+    if let Some(total_shards) = total_shards {
+        // Signal to Bazel that sharding is supported.
+        if let Ok(status_file) = env::var("TEST_SHARD_STATUS_FILE") {
+            fs::write(&status_file, "")
+                .unwrap_or_else(|e| panic!("Failed to write {}\n{:?}", status_file, e));
+        }
+        let shard_index: usize = env::var("TEST_SHARD_INDEX")
+            .expect("TEST_SHARD_INDEX should be set when TEST_TOTAL_SHARDS is")
+            .parse()
+            .expect("Failed to parse TEST_SHARD_INDEX");
+
+        let mut command = new_command();
+        command.args(["--list", "--format", "terse"]);
+        let output = command
+            .output()
+            .unwrap_or_else(|_| panic!("Failed to spawn command: {:#?}", command));
+        if !output.status.success() {
+            eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+            panic!("Failed to list tests: {:#?}", command);
+        }
+
+        let tests = parse_test_list(&String::from_utf8_lossy(&output.stdout));
+        let filtered: Vec<String> = tests
+            .iter()
+            .filter(|test| filter.as_ref().is_none_or(|filter| test.contains(filter)))
+            .cloned()
+            .collect();
+        let selected = shard_tests(&filtered, total_shards, shard_index);
+
+        // The runner accepts a single filter, so tests outside of the shard are skipped by name.
+        args.push("--exact".to_string());
+        for test in tests.into_iter().filter(|test| !selected.contains(test)) {
+            args.push("--skip".to_string());
+            args.push(test);
+        }
+    } else if let Some(filter) = &filter {
+        args.push(filter.clone());
+    }
+
+    // Run the test
+    let mut command = new_command();
+    command.args(&args);
+    let (code, output) = run_and_capture(command);
+
+    if let Ok(xml_output_file) = env::var("XML_OUTPUT_FILE") {
+        let suite = env::var("TEST_TARGET").unwrap_or_else(|_| "wasm_bindgen_test".to_string());
+        let results = parse_test_results(&output);
+        fs::write(&xml_output_file, junit_xml(&suite, &results, &output))
+            .unwrap_or_else(|e| panic!("Failed to write {}\n{:?}", xml_output_file, e));
+    }
+
+    if code != 0 {
+        exit(code)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OUTPUT: &str = "\
+running 3 tests
+test hello_world::tests::test_double_four ... ok
+test hello_world::tests::test_double_two ... FAIL
+test hello_world::tests::test_ignored ... ignored, not yet supported
+
+failures:
+
+---- hello_world::tests::test_double_two output ----
+    error output: assertion `left == right` failed
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+
+    #[test]
+    fn parse_test_results_test() {
+        assert_eq!(
+            parse_test_results(OUTPUT),
+            vec![
+                (
+                    "hello_world::tests::test_double_four".to_string(),
+                    TestOutcome::Passed
+                ),
+                (
+                    "hello_world::tests::test_double_two".to_string(),
+                    TestOutcome::Failed
+                ),
+                (
+                    "hello_world::tests::test_ignored".to_string(),
+                    TestOutcome::Ignored
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_test_list_test() {
+        let output = "tests::a: test\ntests::b: test\n\n2 tests, 0 benchmarks\n";
+        assert_eq!(
+            parse_test_list(output),
+            vec!["tests::a".to_string(), "tests::b".to_string()]
+        );
+    }
+
+    #[test]
+    fn shard_tests_test() {
+        let tests: Vec<String> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+
+        assert_eq!(shard_tests(&tests, 2, 0), vec!["a", "c", "e"]);
+        assert_eq!(shard_tests(&tests, 2, 1), vec!["b", "d"]);
+
+        let mut all: Vec<String> = (0..3).flat_map(|i| shard_tests(&tests, 3, i)).collect();
+        all.sort();
+        assert_eq!(all, tests);
+    }
+
+    #[test]
+    fn junit_xml_test() {
+        let xml = junit_xml("//pkg:test", &parse_test_results(OUTPUT), OUTPUT);
+
+        assert!(xml.contains(
+            r#"<testsuite name="//pkg:test" tests="3" failures="1" errors="0" skipped="1">"#
+        ));
+        assert!(
+            xml.contains(r#"<testcase classname="hello_world::tests" name="test_double_four"/>"#)
+        );
+        assert!(xml.contains(
+            "<testcase classname=\"hello_world::tests\" name=\"test_double_two\">\n      <failure message=\"FAIL\"/>"
+        ));
+        assert!(xml.contains("<skipped/>"));
+        assert!(xml.contains("assertion `left == right` failed"));
+    }
+
+    #[test]
+    fn escape_xml_test() {
+        assert_eq!(
+            escape_xml("<a href=\"x\">&\u{1b}[0m</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;[0m&lt;/a&gt;"
+        );
     }
 }
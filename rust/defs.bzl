@@ -18,6 +18,10 @@ load(
     "//rust:toolchain.bzl",
     _rust_stdlib_filegroup = "rust_stdlib_filegroup",
 )
+load(
+    "//rust/private:build_info.bzl",
+    _rust_build_info = "rust_build_info",
+)
 load(
     "//rust/private:clippy.bzl",
     _capture_clippy_output = "capture_clippy_output",
@@ -72,6 +76,10 @@ load(
     _rustfmt_aspect = "rustfmt_aspect",
     _rustfmt_test = "rustfmt_test",
 )
+load(
+    "//rust/private:stamped_file.bzl",
+    _rust_stamped_file = "rust_stamped_file",
+)
 load(
     "//rust/private:unpretty.bzl",
     _rust_unpretty = "rust_unpretty",
@@ -93,6 +101,9 @@ rust_proc_macro = _rust_proc_macro
 rust_binary = _rust_binary
 # See @rules_rust//rust/private:rust.bzl for a complete description.
 
+rust_build_info = _rust_build_info
+# See @rules_rust//rust/private:build_info.bzl for a complete description.
+
 rust_library_group = _rust_library_group
 # See @rules_rust//rust/private:rust.bzl for a complete description.
 
@@ -136,6 +147,9 @@ rust_unpretty_aspect = _rust_unpretty_aspect
 rust_unpretty = _rust_unpretty
 # See @rules_rust//rust/private:unpretty.bzl for a complete description.
 
+rust_stamped_file = _rust_stamped_file
+# See @rules_rust//rust/private:stamped_file.bzl for a complete description.
+
 error_format = _error_format
 # See @rules_rust//rust/private:rustc.bzl for a complete description.
 
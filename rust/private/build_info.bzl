@@ -0,0 +1,191 @@
+"""A macro for exposing workspace status values to Rust crates as typed build information."""
+
+load("@rules_cc//cc:defs.bzl", "cc_library")
+load("//rust/private:rust.bzl", "rust_library")
+load("//rust/private:stamp.bzl", "is_stamping_enabled")
+load("//rust/private:utils.bzl", "encode_label_as_crate_name")
+
+def _rust_build_info_sources_impl(ctx):
+    stamp = is_stamping_enabled(ctx.attr)
+
+    # The symbol through which the crate reaches its volatile values must be
+    # unique in a binary which depends on several build info crates.
+    symbol = "rules_rust_build_info_" + encode_label_as_crate_name(
+        ctx.label.workspace_name + "/" + ctx.label.package,
+        ctx.label.name,
+    )
+
+    # The Rust source only depends on the stable status, so the crate and its
+    # dependents are not rebuilt when only volatile values change.
+    args = ctx.actions.args()
+    args.add("--rust-output", ctx.outputs.rust_out)
+    args.add("--symbol", symbol)
+    args.add("--git-commit-key", ctx.attr.git_commit_key)
+    args.add("--git-dirty-key", ctx.attr.git_dirty_key)
+    inputs = []
+    if stamp:
+        args.add("--stable-status", ctx.info_file)
+        inputs.append(ctx.info_file)
+
+    ctx.actions.run(
+        executable = ctx.executable._generator,
+        inputs = inputs,
+        outputs = [ctx.outputs.rust_out],
+        arguments = [args],
+        mnemonic = "RustBuildInfo",
+        progress_message = "Generating build info %{output}",
+    )
+
+    volatile_args = ctx.actions.args()
+    volatile_args.add("--c-output", ctx.outputs.c_out)
+    volatile_args.add("--symbol", symbol)
+    volatile_inputs = []
+    if stamp:
+        volatile_args.add("--volatile-status", ctx.version_file)
+        volatile_inputs.append(ctx.version_file)
+
+    ctx.actions.run(
+        executable = ctx.executable._generator,
+        inputs = volatile_inputs,
+        outputs = [ctx.outputs.c_out],
+        arguments = [volatile_args],
+        mnemonic = "RustBuildInfoVolatile",
+        progress_message = "Generating volatile build info %{output}",
+    )
+
+    return [DefaultInfo(files = depset([ctx.outputs.rust_out, ctx.outputs.c_out]))]
+
+_rust_build_info_sources = rule(
+    doc = "Generates the Rust and C sources of a `rust_build_info` crate.",
+    implementation = _rust_build_info_sources_impl,
+    attrs = {
+        "c_out": attr.output(
+            doc = "The C source defining the volatile workspace status values.",
+            mandatory = True,
+        ),
+        "git_commit_key": attr.string(
+            doc = "The workspace status key of the git commit.",
+            mandatory = True,
+        ),
+        "git_dirty_key": attr.string(
+            doc = "The workspace status key of the git dirty flag.",
+            mandatory = True,
+        ),
+        "rust_out": attr.output(
+            doc = "The Rust source of the build info crate.",
+            mandatory = True,
+        ),
+        "stamp": attr.int(
+            doc = "See `rust_build_info`.",
+            default = -1,
+            values = [1, 0, -1],
+        ),
+        "_generator": attr.label(
+            doc = "The tool which generates the sources from workspace status files.",
+            default = Label("//util/workspace_status:build_info_generator"),
+            executable = True,
+            cfg = "exec",
+        ),
+        "_stamp_flag": attr.label(
+            doc = "A setting used to determine whether or not the `--stamp` flag is enabled",
+            default = Label("//rust/private:stamp"),
+        ),
+    },
+)
+
+def rust_build_info(
+        name,
+        stamp = -1,
+        git_commit_key = "STABLE_GIT_COMMIT",
+        git_dirty_key = "STABLE_GIT_DIRTY",
+        **kwargs):
+    """Generates a crate exposing [workspace status](https://bazel.build/docs/user-manual#workspace-status) values as typed build information.
+
+    The generated `rust_library` provides:
+
+    - `GIT_COMMIT: Option<&str>` and `GIT_DIRTY: Option<bool>`, from the `git_commit_key` and
+      `git_dirty_key` workspace status values. The dirty flag accepts `1`, `true`, `dirty` and
+      `modified`, or `0`, `false`, `clean` and the empty string.
+    - `BUILD_USER`, `BUILD_HOST` and `BUILD_EMBED_LABEL`, as `Option<&str>`.
+    - A `&str` constant for every custom `STABLE_*` key, e.g. `STABLE_RELEASE_CHANNEL`, and all
+      of them as `STABLE_STATUS: &[(&str, &str)]`. Keys whose constant would be named like one of
+      the items above (e.g. `STABLE_STATUS`) are only available through `STABLE_STATUS`.
+    - `build_timestamp() -> Option<SystemTime>`, `volatile_value(key)` and `volatile_status()`
+      for the values of the volatile status file.
+    - A `BuildInfo` struct of all of the above, returned by `build_info()`, whose `Display`
+      implementation formats a version such as `1.2.3 (abc123-dirty)` or `unknown`.
+
+    Values are `None` (and `STABLE_STATUS` is empty) if the target is not stamped.
+
+    The generated sources use the 2021 edition, so `edition` cannot be set.
+
+    The crate itself only depends on the stable status file. Volatile values are compiled into
+    a `cc_library` which is only linked into final binaries, so changes of e.g.
+    `BUILD_TIMESTAMP` neither rebuild the crate nor invalidate the compilation of its
+    dependents.
+
+    Example:
+
+    ```python
+    load("@rules_rust//rust:defs.bzl", "rust_binary", "rust_build_info")
+
+    rust_build_info(
+        name = "build_info",
+    )
+
+    rust_binary(
+        name = "app",
+        srcs = ["main.rs"],
+        deps = [":build_info"],
+    )
+    ```
+
+    With `main.rs` printing `build_info::build_info()` for `--version`, and a
+    `--workspace_status_command` which writes e.g. `STABLE_GIT_COMMIT` and `STABLE_GIT_DIRTY`.
+
+    Args:
+        name (str): The name of the generated `rust_library`.
+        stamp (int): Whether to stamp the crate with workspace status values. `1` always stamps,
+            `0` never stamps, and `-1` stamps according to the
+            [--[no]stamp](https://docs.bazel.build/versions/main/user-manual.html#flag--stamp) flag.
+        git_commit_key (str): The workspace status key of the git commit.
+        git_dirty_key (str): The workspace status key of the flag indicating uncommitted changes.
+        **kwargs (dict): Additional keyword arguments for the underlying `rust_library`. The
+            `tags` and `testonly` arguments are also passed to the generated source targets.
+    """
+    if "edition" in kwargs:
+        fail("`rust_build_info` generates 2021 edition sources, so `edition` cannot be set: {}".format(name))
+
+    common_kwargs = {
+        key: kwargs[key]
+        for key in ("tags", "testonly")
+        if key in kwargs
+    }
+
+    _rust_build_info_sources(
+        name = name + "_sources",
+        rust_out = name + ".rs",
+        c_out = name + "_volatile.c",
+        stamp = stamp,
+        git_commit_key = git_commit_key,
+        git_dirty_key = git_dirty_key,
+        visibility = ["//visibility:private"],
+        **common_kwargs
+    )
+
+    cc_library(
+        name = name + "_volatile",
+        srcs = [":" + name + "_volatile.c"],
+        linkstatic = True,
+        visibility = ["//visibility:private"],
+        **common_kwargs
+    )
+
+    rust_library(
+        name = name,
+        srcs = [":" + name + ".rs"],
+        edition = "2021",
+        deps = [":" + name + "_volatile"],
+        **kwargs
+    )
+
@@ -844,7 +844,8 @@ def construct_arguments(
         force_depend_on_objects = False,
         skip_expanding_rustc_env = False,
         require_explicit_unstable_features = False,
-        error_format = None):
+        error_format = None,
+        pipelining_key = None):
     """Builds an Args object containing common rustc flags
 
     Args:
@@ -878,6 +879,8 @@ def construct_arguments(
         skip_expanding_rustc_env (bool): Whether to skip expanding CrateInfo.rustc_env_attr
         require_explicit_unstable_features (bool): Whether to require all unstable features to be explicitly opted in to using `-Zallow-features=...`.
         error_format (str, optional): Error format to pass to the `--error-format` command line argument. If set to None, uses the "_error_format" entry in `attr`.
+        pipelining_key (str, optional): If set, the action is run as a request to a process wrapper persistent worker which
+            shares a single rustc process between the metadata and full compilation actions with the same key.
 
     Returns:
         tuple: A tuple of the following items
@@ -900,6 +903,12 @@ def construct_arguments(
     # Wrapper args first
     process_wrapper_flags = ctx.actions.args()
 
+    # Persistent workers require all arguments to be passed in param files.
+    if pipelining_key:
+        process_wrapper_flags.set_param_file_format("multiline")
+        process_wrapper_flags.use_param_file("@%s", use_always = True)
+        process_wrapper_flags.add("--pipelining-key", pipelining_key)
+
     for build_env_file in build_env_files:
         process_wrapper_flags.add("--env-file", build_env_file)
 
@@ -908,6 +917,17 @@ def construct_arguments(
     if require_explicit_unstable_features:
         process_wrapper_flags.add("--require-explicit-unstable-features", "true")
 
+    # Optionally collect reproduction bundles of internal compiler errors and retry them.
+    if toolchain._ice_bundle_dir:
+        process_wrapper_flags.add("--ice-bundle-dir", toolchain._ice_bundle_dir)
+    if toolchain._retry_on_ice:
+        process_wrapper_flags.add("--retry-on-ice", "true")
+
+    # Restore the outputs of identical compilations from a local cache.
+    if toolchain._rustc_cache_dir:
+        process_wrapper_flags.add("--cache-dir", toolchain._rustc_cache_dir)
+        process_wrapper_flags.add("--cache-max-size-mb", str(toolchain._rustc_cache_max_size_mb))
+
     # Certain rust build processes expect to find files from the environment
     # variable `$CARGO_MANIFEST_DIR`. Examples of this include pest, tera,
     # asakuma.
@@ -937,6 +957,9 @@ def construct_arguments(
 
     # Arguments for launching rustc from the process wrapper
     rustc_path = ctx.actions.args()
+    if pipelining_key:
+        rustc_path.set_param_file_format("multiline")
+        rustc_path.use_param_file("@%s", use_always = True)
     rustc_path.add("--")
     rustc_path.add(tool_path)
 
@@ -949,7 +972,7 @@ def construct_arguments(
     # Rustc arguments
     rustc_flags = ctx.actions.args()
     rustc_flags.set_param_file_format("multiline")
-    rustc_flags.use_param_file("@%s", use_always = False)
+    rustc_flags.use_param_file("@%s", use_always = bool(pipelining_key))
     rustc_flags.add(crate_info.root)
     rustc_flags.add(crate_info.name, format = "--crate-name=%s")
     rustc_flags.add(crate_info.type, format = "--crate-type=%s")
@@ -1302,6 +1325,23 @@ def rustc_compile_action(
     if experimental_use_cc_common_link:
         emit = ["obj"]
 
+    # With worker pipelining, the metadata and full compilation actions share a
+    # single rustc process run by a process wrapper persistent worker. The crate
+    # output uniquely identifies the compilation within the worker.
+    pipelining_key = None
+    execution_requirements = {}
+    if build_metadata and toolchain._worker_pipelining and ctx.executable._process_wrapper:
+        pipelining_key = crate_info.output.path
+        execution_requirements = {
+            "requires-worker-protocol": "json",
+            "supports-multiplex-workers": "1",
+            # Both actions must be sent to the same worker.
+            "worker-key-mnemonic": "Rustc",
+        }
+
+    # Recording warnings relies on the process wrapper parsing the json diagnostics of rustc.
+    collect_warnings = bool(toolchain._collect_warnings and ctx.executable._process_wrapper)
+
     # Determine whether to pass `--require-explicit-unstable-features true` to the process wrapper:
     require_explicit_unstable_features = False
     if hasattr(ctx.attr, "require_explicit_unstable_features"):
@@ -1332,9 +1372,10 @@ def rustc_compile_action(
         build_flags_files = build_flags_files,
         force_all_deps_direct = force_all_deps_direct,
         stamp = stamp,
-        use_json_output = bool(build_metadata) or bool(rustc_output) or bool(rustc_rmeta_output),
+        use_json_output = bool(build_metadata) or bool(rustc_output) or bool(rustc_rmeta_output) or collect_warnings,
         skip_expanding_rustc_env = skip_expanding_rustc_env,
         require_explicit_unstable_features = require_explicit_unstable_features,
+        pipelining_key = pipelining_key,
     )
 
     args_metadata = None
@@ -1362,6 +1403,7 @@ def rustc_compile_action(
             use_json_output = True,
             build_metadata = True,
             require_explicit_unstable_features = require_explicit_unstable_features,
+            pipelining_key = pipelining_key,
         )
 
     env = dict(ctx.configuration.default_shell_env)
@@ -1415,7 +1457,27 @@ def rustc_compile_action(
             dsym_folder = ctx.actions.declare_directory(crate_info.output.basename + ".dSYM", sibling = crate_info.output)
             action_outputs.append(dsym_folder)
 
+    resource_usage_files = []
+    rustc_profile = None
+    warnings = None
     if ctx.executable._process_wrapper:
+        if toolchain._collect_resource_usage:
+            resource_usage = _add_resource_usage_output(ctx, args, crate_info.output, "Rustc")
+            resource_usage_files.append(resource_usage)
+            action_outputs.append(resource_usage)
+        if collect_warnings:
+            warnings = _add_warnings_output(ctx, args, crate_info.output)
+            action_outputs.append(warnings)
+        rustc_profile, rustc_metadata_profile = _add_rustc_profile_output(ctx, args, toolchain, crate_info.output, args_metadata, build_metadata)
+        if rustc_profile:
+            action_outputs.append(rustc_profile)
+        if toolchain._check_reproducibility:
+            # Compile a second time from a different working directory and compare the outputs.
+            args.process_wrapper_flags.add("--check-reproducibility", "true")
+        if toolchain._rewrite_diagnostic_paths:
+            path_mapping = _add_diagnostic_path_mapping(ctx, crate_info, [x for x in [args, args_metadata] if x])
+            compile_inputs = depset([path_mapping], transitive = [compile_inputs])
+
         # Run as normal
         ctx.actions.run(
             executable = ctx.executable._process_wrapper,
@@ -1432,12 +1494,20 @@ def rustc_compile_action(
             ),
             toolchain = "@rules_rust//rust:toolchain_type",
             resource_set = get_rustc_resource_set(toolchain),
+            execution_requirements = execution_requirements,
         )
         if args_metadata:
+            metadata_outputs = [build_metadata] + [x for x in [rustc_rmeta_output] if x]
+            if toolchain._collect_resource_usage:
+                resource_usage = _add_resource_usage_output(ctx, args_metadata, build_metadata, "RustcMetadata")
+                resource_usage_files.append(resource_usage)
+                metadata_outputs.append(resource_usage)
+            if rustc_metadata_profile:
+                metadata_outputs.append(rustc_metadata_profile)
             ctx.actions.run(
                 executable = ctx.executable._process_wrapper,
                 inputs = compile_inputs,
-                outputs = [build_metadata] + [x for x in [rustc_rmeta_output] if x],
+                outputs = metadata_outputs,
                 env = env,
                 arguments = args_metadata.all,
                 mnemonic = "RustcMetadata",
@@ -1448,6 +1518,7 @@ def rustc_compile_action(
                     len(srcs),
                 ),
                 toolchain = "@rules_rust//rust:toolchain_type",
+                execution_requirements = execution_requirements,
             )
     elif hasattr(ctx.executable, "_bootstrap_process_wrapper"):
         # Run without process_wrapper
@@ -1660,6 +1731,12 @@ def rustc_compile_action(
             output_group_info["rustc_rmeta_output"] = depset([rustc_rmeta_output])
     if rustc_output:
         output_group_info["rustc_output"] = depset([rustc_output])
+    if resource_usage_files:
+        output_group_info["rustc_resource_usage"] = depset(resource_usage_files)
+    if rustc_profile:
+        output_group_info["rustc_profile"] = depset([rustc_profile])
+    if warnings:
+        output_group_info["rustc_warnings"] = depset([warnings])
 
     if output_group_info:
         providers.append(OutputGroupInfo(**output_group_info))
@@ -1671,6 +1748,133 @@ def rustc_compile_action(
 
     return providers
 
+def _add_resource_usage_output(ctx, args, output, mnemonic):
+    """Configures the process wrapper to record the resources consumed by rustc.
+
+    Args:
+        ctx (ctx): The rule's context object
+        args (struct): The arguments of the action, as returned by `construct_arguments`
+        output (File): The main output of the action
+        mnemonic (str): The mnemonic of the action
+
+    Returns:
+        File: The resource usage record of the action
+    """
+    resource_usage = ctx.actions.declare_file(output.basename + ".resource_usage.json", sibling = output)
+    args.process_wrapper_flags.add("--resource-usage-file", resource_usage)
+    args.process_wrapper_flags.add("--resource-usage-label", str(ctx.label))
+    args.process_wrapper_flags.add("--resource-usage-mnemonic", mnemonic)
+    return resource_usage
+
+def _add_warnings_output(ctx, args, output):
+    """Configures the process wrapper to record the warnings reported by rustc.
+
+    Args:
+        ctx (ctx): The rule's context object
+        args (struct): The arguments of the action, as returned by `construct_arguments`
+        output (File): The main output of the action
+
+    Returns:
+        File: The warnings of the action, one json record per line
+    """
+    warnings = ctx.actions.declare_file(output.basename + ".warnings.json", sibling = output)
+    args.process_wrapper_flags.add("--warnings-file", warnings)
+    args.process_wrapper_flags.add("--warnings-label", str(ctx.label))
+    return warnings
+
+def _add_diagnostic_path_mapping(ctx, crate_info, all_args):
+    """Configures the process wrapper to rewrite the file names in diagnostics to paths which can be opened from the workspace.
+
+    Sources symlinked into the output tree by `transform_sources` are mapped back to their original
+    location, and the remaining files in the output tree and in external repositories are mapped to
+    `${output_base}`, which the process wrapper infers from the execution root.
+
+    Args:
+        ctx (ctx): The rule's context object
+        crate_info (CrateInfo): The CrateInfo provider of the target being compiled
+        all_args (list): The arguments of the actions, as returned by `construct_arguments`
+
+    Returns:
+        File: The path mapping file, which must be an input of the actions
+    """
+    mapping = [
+        ("bazel-out/", "${{output_base}}/execroot/{}/bazel-out/".format(ctx.workspace_name)),
+        ("external/", "${output_base}/external/"),
+    ]
+
+    sources = {
+        src.path: src
+        for src in getattr(ctx.files, "srcs", []) + getattr(ctx.files, "compile_data", [])
+        if src.is_source
+    }
+    bin_dir = ctx.bin_dir.path + "/"
+    for src in crate_info.srcs.to_list() + crate_info.compile_data.to_list():
+        if src.is_source or not src.path.startswith(bin_dir):
+            continue
+        original = sources.get(src.path[len(bin_dir):])
+        if not original:
+            continue
+        if original.owner.workspace_root:
+            mapping.append((src.path, "${output_base}/" + original.path))
+        else:
+            mapping.append((src.path, original.path))
+
+    path_mapping = ctx.actions.declare_file(
+        crate_info.output.basename + ".diagnostic_path_mapping",
+        sibling = crate_info.output,
+    )
+    ctx.actions.write(
+        output = path_mapping,
+        content = "".join(["{}\t{}\n".format(from_path, to_path) for from_path, to_path in mapping]),
+    )
+    for args in all_args:
+        args.process_wrapper_flags.add("--diagnostic-path-mapping", path_mapping)
+    return path_mapping
+
+def _add_rustc_profile_output(ctx, args, toolchain, output, args_metadata = None, metadata_output = None):
+    """Configures rustc to capture profiling data according to the `rustc_profile` setting.
+
+    With worker pipelining, the rustc flags of both actions of a pipelined compilation are identical
+    so the worker can still share a single rustc process between them.
+
+    Args:
+        ctx (ctx): The rule's context object
+        args (struct): The arguments of the action, as returned by `construct_arguments`
+        toolchain (rust_toolchain): The current target's `rust_toolchain`
+        output (File): The main output of the action
+        args_metadata (struct, optional): The arguments of the metadata action, if pipelined
+        metadata_output (File, optional): The main output of the metadata action, if pipelined
+
+    Returns:
+        tuple: The profile outputs of the action and of the metadata action, which are None if
+            profiling is disabled or the metadata action produces no profile of its own.
+    """
+    if toolchain._rustc_profile == "self-profile":
+        profile = ctx.actions.declare_directory(output.basename + ".self_profile", sibling = output)
+        args.rustc_flags.add(profile.path, format = "-Zself-profile=%s")
+        metadata_profile = None
+        if args_metadata and toolchain._worker_pipelining:
+            # The metadata action passes the same directory without declaring it as an output.
+            # The shared rustc process then writes the profile of the full action, whichever
+            # request spawned it.
+            args_metadata.rustc_flags.add(profile.path, format = "-Zself-profile=%s")
+        elif args_metadata:
+            metadata_profile = ctx.actions.declare_directory(metadata_output.basename + ".self_profile", sibling = metadata_output)
+            args_metadata.rustc_flags.add(metadata_profile.path, format = "-Zself-profile=%s")
+        return profile, metadata_profile
+    if toolchain._rustc_profile == "time-passes":
+        profile = ctx.actions.declare_file(output.basename + ".time_passes.txt", sibling = output)
+        args.process_wrapper_flags.add("--rustc-time-passes-file", profile)
+        args.rustc_flags.add("-Ztime-passes")
+        metadata_profile = None
+        if args_metadata:
+            # Divert the output of the metadata action so it is not reported as diagnostics.
+            metadata_profile = ctx.actions.declare_file(metadata_output.basename + ".time_passes.txt", sibling = metadata_output)
+            args_metadata.process_wrapper_flags.add("--rustc-time-passes-file", metadata_profile)
+            args_metadata.rustc_flags.add("-Ztime-passes")
+        return profile, metadata_profile
+    return None, None
+
 def is_no_std(ctx, toolchain, crate_is_test):
     return not (is_exec_configuration(ctx) or crate_is_test or toolchain._no_std == "off")
 
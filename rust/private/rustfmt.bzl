@@ -255,7 +255,13 @@ def _rustfmt_test_impl(ctx):
 
 rustfmt_test = rule(
     implementation = _rustfmt_test_impl,
-    doc = "A test rule for performing `rustfmt --check` on a set of targets",
+    doc = """\
+A test rule for performing `rustfmt --check` on a set of targets.
+
+A unified diff is printed for every source file which is not formatted, and all diffs are
+written to `rustfmt.patch` in the test's undeclared outputs. The files reported by failing
+tests can be formatted with `bazel run @rules_rust//:rustfmt -- --fix-failing-tests`.
+""",
     attrs = {
         "targets": attr.label_list(
             doc = "Rust targets to run `rustfmt --check` on.",
@@ -0,0 +1,98 @@
+"""A rule for embedding workspace status values in files read by Rust crates."""
+
+load("//rust/private:stamp.bzl", "is_stamping_enabled")
+load("//rust/private:utils.bzl", "dedent")
+
+def _rust_stamped_file_impl(ctx):
+    template = ctx.file.template
+    output = ctx.outputs.out
+
+    args = ctx.actions.args()
+    args.add("--stamp-template", template)
+    args.add(output)
+    inputs = [template]
+    if is_stamping_enabled(ctx.attr):
+        args.add("--volatile-status-file", ctx.version_file)
+        args.add("--stable-status-file", ctx.info_file)
+        inputs.extend([ctx.version_file, ctx.info_file])
+
+    ctx.actions.run(
+        executable = ctx.executable._process_wrapper,
+        inputs = inputs,
+        outputs = [output],
+        arguments = [args],
+        mnemonic = "RustStampFile",
+        progress_message = "Stamping %{output}",
+    )
+
+    return [DefaultInfo(files = depset([output]))]
+
+rust_stamped_file = rule(
+    doc = dedent("""\
+        Stamps a template file with [workspace status](https://bazel.build/docs/user-manual#workspace-status) values.
+
+        Every `{KEY}` in `template` is replaced by the value of `KEY` in the stable or volatile
+        workspace status files, using the same substitution as stamped `rustc_env` values. Keys
+        which are not part of the workspace status, as well as all keys when stamping is disabled,
+        are left as they are.
+
+        The stamped file is produced by its own action, so it can be added to the `srcs` of a
+        crate as a module (e.g. a `version.rs`) or to its `compile_data` and read with
+        `include_str!` (e.g. a `build_info.json`), without stamping the whole crate. As with all
+        stamped actions, changes to volatile values alone do not rerun it, so they do not
+        invalidate the crate either.
+
+        Example:
+
+        ```python
+        load("@rules_rust//rust:defs.bzl", "rust_binary", "rust_stamped_file")
+
+        rust_stamped_file(
+            name = "version",
+            template = "version.rs.tpl",
+            out = "version.rs",
+        )
+
+        rust_binary(
+            name = "app",
+            srcs = ["main.rs", ":version"],
+        )
+        ```
+
+        With `version.rs.tpl` containing e.g. `pub const COMMIT: &str = "{STABLE_GIT_COMMIT}";`.
+    """),
+    implementation = _rust_stamped_file_impl,
+    attrs = {
+        "out": attr.output(
+            doc = "The stamped file.",
+            mandatory = True,
+        ),
+        "stamp": attr.int(
+            doc = dedent("""\
+                Whether to stamp the template with workspace status values. Possible values:
+
+                - `stamp = 1`: Always stamp the template, even in [--nostamp](https://docs.bazel.build/versions/main/user-manual.html#flag--stamp) builds.
+                - `stamp = 0`: Never stamp the template, which copies it unchanged.
+                - `stamp = -1`: Stamping is controlled by the [--[no]stamp](https://docs.bazel.build/versions/main/user-manual.html#flag--stamp) flag.
+            """),
+            default = -1,
+            values = [1, 0, -1],
+        ),
+        "template": attr.label(
+            doc = "The template containing `{KEY}` placeholders for workspace status values.",
+            allow_single_file = True,
+            mandatory = True,
+        ),
+        "_process_wrapper": attr.label(
+            doc = "A process wrapper which stamps the template.",
+            default = Label("//util/process_wrapper"),
+            executable = True,
+            allow_single_file = True,
+            cfg = "exec",
+        ),
+        "_stamp_flag": attr.label(
+            doc = "A setting used to determine whether or not the `--stamp` flag is enabled",
+            default = Label("//rust/private:stamp"),
+        ),
+    },
+)
@@ -122,6 +122,170 @@ def pipelined_compilation():
         build_setting_default = False,
     )
 
+def experimental_worker_pipelining():
+    """When set alongside `pipelined_compilation`, the `.rmeta` and `.rlib` of a crate are produced by a single rustc process.
+
+    The metadata and full compilation actions run as requests to a multiplex
+    [persistent worker](https://bazel.build/remote/persistent) of the process wrapper.
+    The first request to arrive starts rustc, the metadata action completes as soon as
+    the `.rmeta` file is written and the full action waits for the same process to write
+    the `.rlib`. This avoids compiling every pipelined crate twice.
+
+    The actions only share a rustc process when both are run by the worker strategy
+    (the default when available) without `--worker_sandboxing`. Otherwise each action
+    falls back to running its own rustc process.
+    """
+    bool_flag(
+        name = "experimental_worker_pipelining",
+        build_setting_default = False,
+    )
+
+def experimental_check_reproducibility():
+    """When set, each Rustc action checks that its outputs are reproducible.
+
+    After a successful compilation, the process wrapper runs rustc a second time from a
+    different working directory (a mirror of the execution root) and with a different
+    `HOME` and temporary directory, then compares the outputs byte for byte. On a mismatch,
+    the action fails with a report of the differing `.rlib` members, `.rmeta` offsets and
+    object file sections along with the likely causes, such as a missing
+    `--remap-path-prefix` or an absolute path which should use `${pwd}`.
+
+    This doubles the cost of every compilation and is only supported on unix hosts.
+    """
+    bool_flag(
+        name = "experimental_check_reproducibility",
+        build_setting_default = False,
+    )
+
+def experimental_collect_resource_usage():
+    """When set, each Rustc and RustcMetadata action records the resources consumed by rustc.
+
+    The process wrapper writes the wall time, user and system CPU time and peak resident set size
+    of rustc to a `<output>.resource_usage.json` file next to the crate output. These files are
+    available from the `rustc_resource_usage` output group and can be merged into a Chrome trace
+    and a summary table with `@rules_rust//tools/resource_usage_report`.
+    """
+    bool_flag(
+        name = "experimental_collect_resource_usage",
+        build_setting_default = False,
+    )
+
+def experimental_ice_bundle_dir():
+    """A directory on the host which Rustc and RustcMetadata actions write a reproduction bundle \
+    to when rustc encounters an internal compiler error (ICE).
+
+    Each crash is written to a new `<crate name>-<timestamp>-<pid>` directory within it, whose path
+    and a summary are printed to stderr. It contains the rustc command line with `${pwd}` and other
+    substitutions restored (`argv.txt`), the environment loaded from build script env files
+    (`env.txt`), the source files listed in the dep-info output of rustc (or only the crate root if
+    rustc did not get as far as writing it) and the library search paths (`inputs.txt`), copies of
+    the sources within the execution root (`sources/`), the stderr of rustc (`stderr.txt`) and any
+    `rustc-ice-*.txt` reports.
+
+    Bundles are not declared outputs, so they survive the failure of the action and do not change
+    the outputs of actions which succeed. The directory must be an absolute path which is writable from within
+    the sandbox, e.g. by also passing `--sandbox_writable_path=<dir>`, and bundles of remotely
+    executed actions stay on the executor. Actions sharing a rustc process through
+    `experimental_worker_pipelining` are not covered.
+    """
+    string_flag(
+        name = "experimental_ice_bundle_dir",
+        build_setting_default = "",
+    )
+
+def experimental_collect_warnings():
+    """When set, each Rustc action records the warnings reported by rustc.
+
+    The process wrapper writes the lint code, message and primary span of every warning to a
+    `<output>.warnings.json` file next to the crate output, one json record per line. These files
+    are available from the `rustc_warnings` output group and can be deduplicated across
+    configurations and checked against per-package budgets or a baseline with
+    `@rules_rust//tools/warnings_report`.
+    """
+    bool_flag(
+        name = "experimental_collect_warnings",
+        build_setting_default = False,
+    )
+
+def experimental_retry_on_ice():
+    """When set, rustc is run a second time with incremental compilation disabled when it \
+    encounters an internal compiler error (ICE).
+
+    Only a single retry is attempted. Both attempts are reported in the output of the action.
+    """
+    bool_flag(
+        name = "experimental_retry_on_ice",
+        build_setting_default = False,
+    )
+
+def experimental_rustc_cache_dir():
+    """A directory in which the process wrapper caches the outputs of rustc.
+
+    The outputs are keyed on the rustc version, the arguments and environment of rustc with the
+    execution root replaced by `${pwd}`, and the digests of the files named by the arguments and of
+    the sources listed in the dep-info output of rustc. On a hit, the `.rlib`, `.rmeta` and `.d`
+    outputs are restored and the diagnostics of the cached compilation are replayed without running
+    rustc. This allows identical crates, such as third-party dependencies, to be shared between
+    workspaces and output bases on a machine without access to a remote cache.
+
+    The cache is not a declared input or output of actions. The directory must be an absolute
+    path which is writable from within the sandbox, e.g. by also passing
+    `--sandbox_writable_path=<dir>`. Files read by procedural macros or build scripts which are not
+    reported in dep-info are not tracked, and actions sharing a rustc process through
+    `experimental_worker_pipelining` are not cached.
+    """
+    string_flag(
+        name = "experimental_rustc_cache_dir",
+        build_setting_default = "",
+    )
+
+def experimental_rustc_cache_max_size_mb():
+    """The size in megabytes of `experimental_rustc_cache_dir` beyond which the least recently used \
+    entries are evicted.
+    """
+    int_flag(
+        name = "experimental_rustc_cache_max_size_mb",
+        build_setting_default = 10240,
+    )
+
+def experimental_rewrite_diagnostic_paths():
+    """When set, the file names in the diagnostics of rustc are rewritten to paths which can be \
+    opened from the workspace.
+
+    Sources which are symlinked into the output tree because the crate also has generated sources
+    are reported by their workspace-relative path, while other files in the output tree and files
+    of external repositories are reported by their absolute path under
+    `$(bazel info output_base)`. The mapping is applied to both the json and the rendered
+    diagnostics, but not to the `--output-file` of the process wrapper.
+    """
+    bool_flag(
+        name = "experimental_rewrite_diagnostic_paths",
+        build_setting_default = False,
+    )
+
+# buildifier: disable=unnamed-macro
+def rustc_profile():
+    """A flag for capturing rustc profiling data for each Rustc action.
+
+    - `self-profile`: Passes `-Zself-profile` to rustc, writing `.mm_profdata` files to a
+      `<output>.self_profile` directory which can be analyzed with the
+      [measureme](https://github.com/rust-lang/measureme) tools.
+    - `time-passes`: Passes `-Ztime-passes` to rustc, writing its report to a
+      `<output>.time_passes.txt` file instead of the action output.
+
+    The profiles are available from the `rustc_profile` output group. Both modes require a
+    nightly toolchain. The metadata actions of pipelined compilation are not profiled.
+    """
+    string_flag(
+        name = "rustc_profile",
+        build_setting_default = "",
+        values = [
+            "",
+            "self-profile",
+            "time-passes",
+        ],
+    )
+
 def experimental_use_cc_common_link():
     """A flag to control whether to link rust_binary and rust_test targets using \
     cc_common.link instead of rustc.
@@ -762,6 +762,16 @@ def _rust_toolchain_impl(ctx):
         _rename_first_party_crates = rename_first_party_crates,
         _third_party_dir = third_party_dir,
         _pipelined_compilation = pipelined_compilation,
+        _worker_pipelining = ctx.attr._worker_pipelining[BuildSettingInfo].value,
+        _check_reproducibility = ctx.attr._experimental_check_reproducibility[BuildSettingInfo].value,
+        _collect_resource_usage = ctx.attr._experimental_collect_resource_usage[BuildSettingInfo].value,
+        _collect_warnings = ctx.attr._experimental_collect_warnings[BuildSettingInfo].value,
+        _ice_bundle_dir = ctx.attr._experimental_ice_bundle_dir[BuildSettingInfo].value,
+        _retry_on_ice = ctx.attr._experimental_retry_on_ice[BuildSettingInfo].value,
+        _rewrite_diagnostic_paths = ctx.attr._experimental_rewrite_diagnostic_paths[BuildSettingInfo].value,
+        _rustc_cache_dir = ctx.attr._experimental_rustc_cache_dir[BuildSettingInfo].value,
+        _rustc_cache_max_size_mb = ctx.attr._experimental_rustc_cache_max_size_mb[BuildSettingInfo].value,
+        _rustc_profile = ctx.attr._rustc_profile[BuildSettingInfo].value,
         _experimental_link_std_dylib = _experimental_link_std_dylib(ctx),
         _experimental_use_cc_common_link = _experimental_use_cc_common_link(ctx),
         _experimental_use_global_allocator = experimental_use_global_allocator,
@@ -977,6 +987,30 @@ rust_toolchain = rule(
         "_codegen_units": attr.label(
             default = Label("//rust/settings:codegen_units"),
         ),
+        "_experimental_check_reproducibility": attr.label(
+            default = Label("//rust/settings:experimental_check_reproducibility"),
+        ),
+        "_experimental_collect_resource_usage": attr.label(
+            default = Label("//rust/settings:experimental_collect_resource_usage"),
+        ),
+        "_experimental_collect_warnings": attr.label(
+            default = Label("//rust/settings:experimental_collect_warnings"),
+        ),
+        "_experimental_ice_bundle_dir": attr.label(
+            default = Label("//rust/settings:experimental_ice_bundle_dir"),
+        ),
+        "_experimental_retry_on_ice": attr.label(
+            default = Label("//rust/settings:experimental_retry_on_ice"),
+        ),
+        "_experimental_rewrite_diagnostic_paths": attr.label(
+            default = Label("//rust/settings:experimental_rewrite_diagnostic_paths"),
+        ),
+        "_experimental_rustc_cache_dir": attr.label(
+            default = Label("//rust/settings:experimental_rustc_cache_dir"),
+        ),
+        "_experimental_rustc_cache_max_size_mb": attr.label(
+            default = Label("//rust/settings:experimental_rustc_cache_max_size_mb"),
+        ),
         "_experimental_use_allocator_libraries_with_mangled_symbols_setting": attr.label(
             default = Label("//rust/settings:experimental_use_allocator_libraries_with_mangled_symbols"),
             providers = [BuildSettingInfo],
@@ -1011,6 +1045,9 @@ rust_toolchain = rule(
         "_rename_first_party_crates": attr.label(
             default = Label("//rust/settings:rename_first_party_crates"),
         ),
+        "_rustc_profile": attr.label(
+            default = Label("//rust/settings:rustc_profile"),
+        ),
         "_third_party_dir": attr.label(
             default = Label("//rust/settings:third_party_dir"),
         ),
@@ -1021,6 +1058,9 @@ rust_toolchain = rule(
                 "This flag is only relevant when used together with --@rules_rust//rust/settings:toolchain_generated_sysroot."
             ),
         ),
+        "_worker_pipelining": attr.label(
+            default = Label("//rust/settings:experimental_worker_pipelining"),
+        ),
     },
     toolchains = [
         "@bazel_tools//tools/cpp:toolchain_type",
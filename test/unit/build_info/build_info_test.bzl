@@ -0,0 +1,170 @@
+"""Starlark tests for `rust_build_info`"""
+
+load("@bazel_skylib//lib:unittest.bzl", "analysistest", "asserts")
+load("@bazel_skylib//rules:write_file.bzl", "write_file")
+load("@rules_cc//cc/common:cc_info.bzl", "CcInfo")
+load("//rust:defs.bzl", "rust_binary", "rust_build_info", "rust_test")
+load(
+    "//test/unit:common.bzl",
+    "assert_action_mnemonic",
+    "assert_argv_contains",
+    "assert_argv_contains_not",
+)
+
+def _has_input(action, basename_part):
+    return any([basename_part in f.basename for f in action.inputs.to_list()])
+
+def _stamped_sources_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    # The Rust source only depends on the stable status...
+    action = target.actions[0]
+    assert_action_mnemonic(env, action, "RustBuildInfo")
+    assert_argv_contains(env, action, "--stable-status")
+    assert_argv_contains_not(env, action, "--volatile-status")
+    asserts.true(env, _has_input(action, "stable-status"))
+    asserts.false(env, _has_input(action, "volatile-status"))
+
+    # ...and the C source only on the volatile one.
+    volatile_action = target.actions[1]
+    assert_action_mnemonic(env, volatile_action, "RustBuildInfoVolatile")
+    assert_argv_contains(env, volatile_action, "--volatile-status")
+    assert_argv_contains_not(env, volatile_action, "--stable-status")
+    asserts.true(env, _has_input(volatile_action, "volatile-status"))
+    asserts.false(env, _has_input(volatile_action, "stable-status"))
+
+    return analysistest.end(env)
+
+_stamped_sources_test = analysistest.make(_stamped_sources_test_impl)
+
+def _unstamped_sources_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    for action in target.actions:
+        assert_argv_contains_not(env, action, "--stable-status")
+        assert_argv_contains_not(env, action, "--volatile-status")
+
+    return analysistest.end(env)
+
+_unstamped_sources_test = analysistest.make(_unstamped_sources_test_impl)
+
+def _library_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    # The volatile values are not an input of the crate's compilation...
+    action = target.actions[0]
+    assert_action_mnemonic(env, action, "Rustc")
+    asserts.false(env, _has_input(action, "build_info_volatile"))
+
+    # ...but are linked into binaries which depend on it.
+    asserts.true(env, any([
+        "build_info_volatile" in linker_input.owner.name
+        for linker_input in target[CcInfo].linking_context.linker_inputs.to_list()
+    ]))
+
+    return analysistest.end(env)
+
+_library_test = analysistest.make(_library_test_impl)
+
+def _binary_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = target.actions[0]
+    assert_action_mnemonic(env, action, "Rustc")
+    asserts.true(env, _has_input(action, "build_info_volatile"))
+
+    return analysistest.end(env)
+
+_binary_test = analysistest.make(_binary_test_impl)
+
+def build_info_test_suite(name):
+    """Entry-point macro called from the BUILD file.
+
+    Args:
+        name (str): The name of the test suite.
+    """
+    rust_build_info(
+        name = "build_info",
+        stamp = 1,
+    )
+
+    rust_build_info(
+        name = "unstamped_build_info",
+        stamp = 0,
+    )
+
+    write_file(
+        name = "main_rs",
+        out = "main.rs",
+        content = [
+            "fn main() {",
+            "    println!(\"{}\", build_info::build_info());",
+            "}",
+            "",
+        ],
+    )
+
+    rust_binary(
+        name = "bin",
+        srcs = [":main.rs"],
+        edition = "2021",
+        deps = [":build_info"],
+    )
+
+    write_file(
+        name = "unstamped_test_rs",
+        out = "unstamped_test.rs",
+        content = [
+            "#[test]",
+            "fn unstamped() {",
+            "    let info = unstamped_build_info::build_info();",
+            "    assert_eq!(info.git_commit, None);",
+            "    assert_eq!(info.build_timestamp, None);",
+            "    assert_eq!(unstamped_build_info::volatile_status(), \"\");",
+            "    assert_eq!(info.to_string(), \"unknown\");",
+            "}",
+            "",
+        ],
+    )
+
+    rust_test(
+        name = "unstamped_test",
+        srcs = [":unstamped_test.rs"],
+        edition = "2021",
+        deps = [":unstamped_build_info"],
+    )
+
+    _stamped_sources_test(
+        name = "stamped_sources_test",
+        target_under_test = ":build_info_sources",
+    )
+
+    _unstamped_sources_test(
+        name = "unstamped_sources_test",
+        target_under_test = ":unstamped_build_info_sources",
+    )
+
+    _library_test(
+        name = "library_test",
+        target_under_test = ":build_info",
+    )
+
+    _binary_test(
+        name = "binary_test",
+        target_under_test = ":bin",
+    )
+
+    native.test_suite(
+        name = name,
+        tests = [
+            ":binary_test",
+            ":library_test",
+            ":stamped_sources_test",
+            ":unstamped_sources_test",
+            ":unstamped_test",
+        ],
+    )
@@ -0,0 +1,75 @@
+"""Starlark tests for `//rust/settings:experimental_check_reproducibility`"""
+
+load("@bazel_skylib//lib:unittest.bzl", "analysistest")
+load("@bazel_skylib//rules:write_file.bzl", "write_file")
+load("//rust:defs.bzl", "rust_library")
+load(
+    "//test/unit:common.bzl",
+    "assert_action_mnemonic",
+    "assert_argv_contains_not",
+    "assert_list_contains_adjacent_elements",
+)
+
+def _check_reproducibility_test_impl(ctx, enabled = True):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = target.actions[0]
+    assert_action_mnemonic(env, action, "Rustc")
+
+    if enabled:
+        assert_list_contains_adjacent_elements(env, action.argv, ["--check-reproducibility", "true"])
+    else:
+        assert_argv_contains_not(env, action, "--check-reproducibility")
+
+    return analysistest.end(env)
+
+_check_reproducibility_test = analysistest.make(
+    _check_reproducibility_test_impl,
+    config_settings = {str(Label("//rust/settings:experimental_check_reproducibility")): True},
+)
+
+def _no_check_reproducibility_test_impl(ctx):
+    return _check_reproducibility_test_impl(ctx, enabled = False)
+
+_no_check_reproducibility_test = analysistest.make(_no_check_reproducibility_test_impl)
+
+def check_reproducibility_test_suite(name):
+    """Entry-point macro called from the BUILD file.
+
+    Args:
+        name (str): The name of the test suite.
+    """
+    write_file(
+        name = "crate_lib",
+        out = "lib.rs",
+        content = [
+            "#[allow(dead_code)]",
+            "fn add() {}",
+            "",
+        ],
+    )
+
+    rust_library(
+        name = "lib",
+        srcs = [":lib.rs"],
+        edition = "2021",
+    )
+
+    _check_reproducibility_test(
+        name = "check_reproducibility_test",
+        target_under_test = ":lib",
+    )
+
+    _no_check_reproducibility_test(
+        name = "no_check_reproducibility_test",
+        target_under_test = ":lib",
+    )
+
+    native.test_suite(
+        name = name,
+        tests = [
+            ":check_reproducibility_test",
+            ":no_check_reproducibility_test",
+        ],
+    )
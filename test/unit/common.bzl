@@ -62,6 +62,12 @@ def assert_action_mnemonic(env, action, mnemonic):
             ),
         )
 
+def find_action(env, target, mnemonic):
+    """Returns the only action of `target` with the given mnemonic, failing the test otherwise."""
+    actions = [action for action in target.actions if action.mnemonic == mnemonic]
+    asserts.equals(env, 1, len(actions), "Expected exactly one {} action".format(mnemonic))
+    return actions[0]
+
 def _startswith(list, prefix):
     if len(list) < len(prefix):
         return False
@@ -0,0 +1,112 @@
+"""Starlark tests for `//rust/settings:experimental_rewrite_diagnostic_paths`"""
+
+load("@bazel_skylib//lib:unittest.bzl", "analysistest", "asserts")
+load("@bazel_skylib//rules:write_file.bzl", "write_file")
+load("//rust:defs.bzl", "rust_library")
+load("//test/unit:common.bzl", "assert_argv_contains_not")
+
+def _find_action(target, mnemonic):
+    for action in target.actions:
+        if action.mnemonic == mnemonic:
+            return action
+    return None
+
+def _diagnostic_path_mapping_test_impl(ctx, enabled = True):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = _find_action(target, "Rustc")
+    asserts.true(env, action != None, "Expected a Rustc action")
+    mapping = [
+        output
+        for write in target.actions
+        if write.mnemonic == "FileWrite"
+        for output in write.outputs.to_list()
+        if output.basename.endswith(".diagnostic_path_mapping")
+    ]
+
+    if not enabled:
+        assert_argv_contains_not(env, action, "--diagnostic-path-mapping")
+        asserts.equals(env, [], mapping)
+        return analysistest.end(env)
+
+    asserts.equals(env, 1, len(mapping))
+    mapping_file = mapping[0]
+    asserts.true(
+        env,
+        "--diagnostic-path-mapping" in action.argv,
+        "Expected --diagnostic-path-mapping in {}".format(action.argv),
+    )
+    asserts.true(env, mapping_file.path in action.argv)
+    asserts.true(env, mapping_file in action.inputs.to_list())
+
+    # `lib.rs` is symlinked next to the generated source and mapped back to the workspace.
+    symlinks = [src for src in action.inputs.to_list() if src.basename == "lib.rs" and not src.is_source]
+    asserts.equals(env, 1, len(symlinks))
+
+    content = _find_action(target, "FileWrite").content
+    for expected in [
+        "bazel-out/\t${{output_base}}/execroot/{}/bazel-out/\n".format(ctx.workspace_name),
+        "external/\t${output_base}/external/\n",
+        "{}\t{}/lib.rs\n".format(symlinks[0].path, ctx.label.package),
+    ]:
+        asserts.true(env, expected in content, "Expected '{}' in '{}'".format(expected, content))
+
+    # Generated sources are only covered by the `bazel-out/` prefix.
+    asserts.false(env, "generated.rs\t" in content)
+
+    return analysistest.end(env)
+
+_diagnostic_path_mapping_test = analysistest.make(
+    _diagnostic_path_mapping_test_impl,
+    config_settings = {
+        str(Label("//rust/settings:experimental_rewrite_diagnostic_paths")): True,
+    },
+)
+
+def _no_diagnostic_path_mapping_test_impl(ctx):
+    return _diagnostic_path_mapping_test_impl(ctx, enabled = False)
+
+_no_diagnostic_path_mapping_test = analysistest.make(_no_diagnostic_path_mapping_test_impl)
+
+def diagnostic_path_mapping_test_suite(name):
+    """Entry-point macro called from the BUILD file.
+
+    Args:
+        name (str): The name of the test suite.
+    """
+    write_file(
+        name = "generated_rs",
+        out = "generated.rs",
+        content = [
+            "pub fn add() {}",
+            "",
+        ],
+    )
+
+    rust_library(
+        name = "lib",
+        srcs = [
+            "lib.rs",
+            ":generated.rs",
+        ],
+        edition = "2021",
+    )
+
+    _diagnostic_path_mapping_test(
+        name = "diagnostic_path_mapping_test",
+        target_under_test = ":lib",
+    )
+
+    _no_diagnostic_path_mapping_test(
+        name = "no_diagnostic_path_mapping_test",
+        target_under_test = ":lib",
+    )
+
+    native.test_suite(
+        name = name,
+        tests = [
+            ":diagnostic_path_mapping_test",
+            ":no_diagnostic_path_mapping_test",
+        ],
+    )
@@ -9,6 +9,11 @@ ENABLE_PIPELINING = {
     str(Label("//rust/settings:pipelined_compilation")): True,
 }
 
+ENABLE_WORKER_PIPELINING = {
+    str(Label("//rust/settings:experimental_worker_pipelining")): True,
+    str(Label("//rust/settings:pipelined_compilation")): True,
+}
+
 # TODO: Fix pipeline compilation on windows
 # https://github.com/bazelbuild/rules_rust/issues/3383
 _NO_WINDOWS = select({
@@ -79,8 +84,32 @@ def _bin_test_impl(ctx):
 
     return analysistest.end(env)
 
+def _worker_pipelining_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    tut = analysistest.target_under_test(env)
+    rlib_action = [act for act in tut.actions if act.mnemonic == "Rustc"][0]
+    metadata_action = [act for act in tut.actions if act.mnemonic == "RustcMetadata"][0]
+
+    # Both actions must be sent to the same multiplex worker.
+    for action in [rlib_action, metadata_action]:
+        asserts.equals(env, "1", action.execution_info.get("supports-multiplex-workers"))
+        asserts.equals(env, "json", action.execution_info.get("requires-worker-protocol"))
+        asserts.equals(env, "Rustc", action.execution_info.get("worker-key-mnemonic"))
+
+    return analysistest.end(env)
+
+def _no_worker_pipelining_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    tut = analysistest.target_under_test(env)
+    for action in [act for act in tut.actions if act.mnemonic in ["Rustc", "RustcMetadata"]]:
+        asserts.false(env, "supports-multiplex-workers" in action.execution_info, "expected no worker execution requirements for " + action.mnemonic)
+
+    return analysistest.end(env)
+
 bin_test = analysistest.make(_bin_test_impl, config_settings = ENABLE_PIPELINING)
 second_lib_test = analysistest.make(_second_lib_test_impl, config_settings = ENABLE_PIPELINING)
+worker_pipelining_test = analysistest.make(_worker_pipelining_test_impl, config_settings = ENABLE_WORKER_PIPELINING)
+no_worker_pipelining_test = analysistest.make(_no_worker_pipelining_test_impl, config_settings = ENABLE_PIPELINING)
 
 def _pipelined_compilation_test():
     rust_proc_macro(
@@ -120,10 +149,22 @@ def _pipelined_compilation_test():
         target_under_test = ":bin",
         target_compatible_with = _NO_WINDOWS,
     )
+    worker_pipelining_test(
+        name = "worker_pipelining_test",
+        target_under_test = ":second",
+        target_compatible_with = _NO_WINDOWS,
+    )
+    no_worker_pipelining_test(
+        name = "no_worker_pipelining_test",
+        target_under_test = ":second",
+        target_compatible_with = _NO_WINDOWS,
+    )
 
     return [
         ":second_lib_test",
         ":bin_test",
+        ":worker_pipelining_test",
+        ":no_worker_pipelining_test",
     ]
 
 def _rmeta_is_propagated_through_custom_rule_test_impl(ctx):
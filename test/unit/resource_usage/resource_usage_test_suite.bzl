@@ -0,0 +1,259 @@
+"""Starlark tests for `//rust/settings:experimental_collect_resource_usage` and `//rust/settings:rustc_profile`"""
+
+load("@bazel_skylib//lib:unittest.bzl", "analysistest", "asserts")
+load("@bazel_skylib//rules:write_file.bzl", "write_file")
+load("//rust:defs.bzl", "rust_library")
+load(
+    "//test/unit:common.bzl",
+    "assert_argv_contains",
+    "assert_argv_contains_not",
+    "assert_argv_contains_prefix",
+    "assert_argv_contains_prefix_not",
+    "assert_list_contains_adjacent_elements",
+    "find_action",
+)
+
+def _output_basenames(action):
+    return [output.basename for output in action.outputs.to_list()]
+
+def _resource_usage_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    for mnemonic in ["Rustc", "RustcMetadata"]:
+        action = find_action(env, target, mnemonic)
+        assert_list_contains_adjacent_elements(env, action.argv, ["--resource-usage-mnemonic", mnemonic])
+        assert_list_contains_adjacent_elements(env, action.argv, ["--resource-usage-label", str(Label("//test/unit/resource_usage:lib"))])
+        records = [basename for basename in _output_basenames(action) if basename.endswith(".resource_usage.json")]
+        asserts.equals(env, 1, len(records), "Expected a resource usage record for " + mnemonic)
+
+    output_groups = target[OutputGroupInfo]
+    asserts.equals(env, 2, len(output_groups.rustc_resource_usage.to_list()))
+
+    return analysistest.end(env)
+
+_resource_usage_test = analysistest.make(
+    _resource_usage_test_impl,
+    config_settings = {
+        str(Label("//rust/settings:experimental_collect_resource_usage")): True,
+        str(Label("//rust/settings:pipelined_compilation")): True,
+    },
+)
+
+def _no_resource_usage_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = find_action(env, target, "Rustc")
+    assert_argv_contains_not(env, action, "--resource-usage-file")
+    assert_argv_contains_prefix_not(env, action, "-Zself-profile")
+    assert_argv_contains_not(env, action, "-Ztime-passes")
+    output_groups = target[OutputGroupInfo] if OutputGroupInfo in target else struct()
+    asserts.false(env, hasattr(output_groups, "rustc_resource_usage"))
+    asserts.false(env, hasattr(output_groups, "rustc_profile"))
+
+    return analysistest.end(env)
+
+_no_resource_usage_test = analysistest.make(_no_resource_usage_test_impl)
+
+def _self_profile_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = find_action(env, target, "Rustc")
+    assert_argv_contains_prefix(env, action, "-Zself-profile=")
+    profile = target[OutputGroupInfo].rustc_profile.to_list()
+    asserts.equals(env, 1, len(profile))
+    asserts.true(env, profile[0].is_directory)
+    assert_argv_contains(env, action, "-Zself-profile=" + profile[0].path)
+
+    return analysistest.end(env)
+
+_self_profile_test = analysistest.make(
+    _self_profile_test_impl,
+    config_settings = {str(Label("//rust/settings:rustc_profile")): "self-profile"},
+)
+
+def _time_passes_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = find_action(env, target, "Rustc")
+    assert_argv_contains(env, action, "-Ztime-passes")
+    profile = target[OutputGroupInfo].rustc_profile.to_list()
+    asserts.equals(env, 1, len(profile))
+    assert_list_contains_adjacent_elements(env, action.argv, ["--rustc-time-passes-file", profile[0].path])
+
+    return analysistest.end(env)
+
+_time_passes_test = analysistest.make(
+    _time_passes_test_impl,
+    config_settings = {str(Label("//rust/settings:rustc_profile")): "time-passes"},
+)
+
+def _pipelined_self_profile_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    profile = target[OutputGroupInfo].rustc_profile.to_list()
+    asserts.equals(env, 1, len(profile))
+    action = find_action(env, target, "Rustc")
+    assert_argv_contains(env, action, "-Zself-profile=" + profile[0].path)
+
+    # Without a worker, the metadata action must only write to outputs it declares.
+    metadata_action = find_action(env, target, "RustcMetadata")
+    metadata_profiles = [output for output in metadata_action.outputs.to_list() if output.basename.endswith(".self_profile")]
+    asserts.equals(env, 1, len(metadata_profiles))
+    asserts.true(env, metadata_profiles[0].is_directory)
+    asserts.false(env, metadata_profiles[0] == profile[0])
+    assert_argv_contains(env, metadata_action, "-Zself-profile=" + metadata_profiles[0].path)
+
+    return analysistest.end(env)
+
+_pipelined_self_profile_test = analysistest.make(
+    _pipelined_self_profile_test_impl,
+    config_settings = {
+        str(Label("//rust/settings:pipelined_compilation")): True,
+        str(Label("//rust/settings:rustc_profile")): "self-profile",
+    },
+)
+
+def _worker_pipelined_self_profile_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    profile = target[OutputGroupInfo].rustc_profile.to_list()
+    asserts.equals(env, 1, len(profile))
+
+    # Both actions must run the same rustc command line to share a rustc process.
+    for mnemonic in ["Rustc", "RustcMetadata"]:
+        action = find_action(env, target, mnemonic)
+        assert_argv_contains(env, action, "-Zself-profile=" + profile[0].path)
+    metadata_action = find_action(env, target, "RustcMetadata")
+    asserts.false(env, profile[0] in metadata_action.outputs.to_list())
+
+    return analysistest.end(env)
+
+_worker_pipelined_self_profile_test = analysistest.make(
+    _worker_pipelined_self_profile_test_impl,
+    config_settings = {
+        str(Label("//rust/settings:experimental_worker_pipelining")): True,
+        str(Label("//rust/settings:pipelined_compilation")): True,
+        str(Label("//rust/settings:rustc_profile")): "self-profile",
+    },
+)
+
+def _pipelined_time_passes_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    for mnemonic in ["Rustc", "RustcMetadata"]:
+        action = find_action(env, target, mnemonic)
+        assert_argv_contains(env, action, "-Ztime-passes")
+        profiles = [output for output in action.outputs.to_list() if output.basename.endswith(".time_passes.txt")]
+        asserts.equals(env, 1, len(profiles), "Expected a time passes file for " + mnemonic)
+        assert_list_contains_adjacent_elements(env, action.argv, ["--rustc-time-passes-file", profiles[0].path])
+
+    return analysistest.end(env)
+
+_pipelined_time_passes_test = analysistest.make(
+    _pipelined_time_passes_test_impl,
+    config_settings = {
+        str(Label("//rust/settings:pipelined_compilation")): True,
+        str(Label("//rust/settings:rustc_profile")): "time-passes",
+    },
+)
+
+def resource_usage_test_suite(name):
+    """Entry-point macro called from the BUILD file.
+
+    Args:
+        name (str): The name of the test suite.
+    """
+    write_file(
+        name = "crate_lib",
+        out = "lib.rs",
+        content = [
+            "#[allow(dead_code)]",
+            "fn add() {}",
+            "",
+        ],
+    )
+
+    rust_library(
+        name = "lib",
+        srcs = [":lib.rs"],
+        edition = "2021",
+    )
+
+    _resource_usage_test(
+        name = "resource_usage_test",
+        target_under_test = ":lib",
+        # TODO: Fix pipeline compilation on windows
+        # https://github.com/bazelbuild/rules_rust/issues/3383
+        target_compatible_with = select({
+            "@platforms//os:windows": ["@platforms//:incompatible"],
+            "//conditions:default": [],
+        }),
+    )
+
+    _no_resource_usage_test(
+        name = "no_resource_usage_test",
+        target_under_test = ":lib",
+    )
+
+    _self_profile_test(
+        name = "self_profile_test",
+        target_under_test = ":lib",
+    )
+
+    _time_passes_test(
+        name = "time_passes_test",
+        target_under_test = ":lib",
+    )
+
+    _pipelined_self_profile_test(
+        name = "pipelined_self_profile_test",
+        target_under_test = ":lib",
+        # TODO: Fix pipeline compilation on windows
+        # https://github.com/bazelbuild/rules_rust/issues/3383
+        target_compatible_with = select({
+            "@platforms//os:windows": ["@platforms//:incompatible"],
+            "//conditions:default": [],
+        }),
+    )
+
+    _worker_pipelined_self_profile_test(
+        name = "worker_pipelined_self_profile_test",
+        target_under_test = ":lib",
+        # TODO: Fix pipeline compilation on windows
+        # https://github.com/bazelbuild/rules_rust/issues/3383
+        target_compatible_with = select({
+            "@platforms//os:windows": ["@platforms//:incompatible"],
+            "//conditions:default": [],
+        }),
+    )
+
+    _pipelined_time_passes_test(
+        name = "pipelined_time_passes_test",
+        target_under_test = ":lib",
+        # TODO: Fix pipeline compilation on windows
+        # https://github.com/bazelbuild/rules_rust/issues/3383
+        target_compatible_with = select({
+            "@platforms//os:windows": ["@platforms//:incompatible"],
+            "//conditions:default": [],
+        }),
+    )
+
+    native.test_suite(
+        name = name,
+        tests = [
+            ":resource_usage_test",
+            ":no_resource_usage_test",
+            ":self_profile_test",
+            ":time_passes_test",
+            ":pipelined_self_profile_test",
+            ":worker_pipelined_self_profile_test",
+            ":pipelined_time_passes_test",
+        ],
+    )
@@ -0,0 +1,90 @@
+"""Starlark tests for `//rust/settings:experimental_rustc_cache_dir`"""
+
+load("@bazel_skylib//lib:unittest.bzl", "analysistest")
+load("@bazel_skylib//rules:write_file.bzl", "write_file")
+load("//rust:defs.bzl", "rust_library")
+load(
+    "//test/unit:common.bzl",
+    "assert_argv_contains_not",
+    "assert_list_contains_adjacent_elements",
+    "find_action",
+)
+
+def _rustc_cache_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    # Both actions of a pipelined compilation are cached.
+    for mnemonic in ["Rustc", "RustcMetadata"]:
+        action = find_action(env, target, mnemonic)
+        assert_list_contains_adjacent_elements(env, action.argv, ["--cache-dir", "/tmp/rustc_cache"])
+        assert_list_contains_adjacent_elements(env, action.argv, ["--cache-max-size-mb", "512"])
+
+    return analysistest.end(env)
+
+_rustc_cache_test = analysistest.make(
+    _rustc_cache_test_impl,
+    config_settings = {
+        str(Label("//rust/settings:experimental_rustc_cache_dir")): "/tmp/rustc_cache",
+        str(Label("//rust/settings:experimental_rustc_cache_max_size_mb")): 512,
+        str(Label("//rust/settings:pipelined_compilation")): True,
+    },
+)
+
+def _no_rustc_cache_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = find_action(env, target, "Rustc")
+    assert_argv_contains_not(env, action, "--cache-dir")
+    assert_argv_contains_not(env, action, "--cache-max-size-mb")
+
+    return analysistest.end(env)
+
+_no_rustc_cache_test = analysistest.make(_no_rustc_cache_test_impl)
+
+def rustc_cache_test_suite(name):
+    """Entry-point macro called from the BUILD file.
+
+    Args:
+        name (str): The name of the test suite.
+    """
+    write_file(
+        name = "crate_lib",
+        out = "lib.rs",
+        content = [
+            "#[allow(dead_code)]",
+            "fn add() {}",
+            "",
+        ],
+    )
+
+    rust_library(
+        name = "lib",
+        srcs = [":lib.rs"],
+        edition = "2021",
+    )
+
+    _rustc_cache_test(
+        name = "rustc_cache_test",
+        target_under_test = ":lib",
+        # TODO: Fix pipeline compilation on windows
+        # https://github.com/bazelbuild/rules_rust/issues/3383
+        target_compatible_with = select({
+            "@platforms//os:windows": ["@platforms//:incompatible"],
+            "//conditions:default": [],
+        }),
+    )
+
+    _no_rustc_cache_test(
+        name = "no_rustc_cache_test",
+        target_under_test = ":lib",
+    )
+
+    native.test_suite(
+        name = name,
+        tests = [
+            ":no_rustc_cache_test",
+            ":rustc_cache_test",
+        ],
+    )
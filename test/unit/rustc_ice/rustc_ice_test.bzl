@@ -0,0 +1,91 @@
+"""Starlark tests for `//rust/settings:experimental_ice_bundle_dir` and `//rust/settings:experimental_retry_on_ice`"""
+
+load("@bazel_skylib//lib:unittest.bzl", "analysistest", "asserts")
+load("@bazel_skylib//rules:write_file.bzl", "write_file")
+load("//rust:defs.bzl", "rust_library")
+load(
+    "//test/unit:common.bzl",
+    "assert_argv_contains_not",
+    "assert_list_contains_adjacent_elements",
+    "find_action",
+)
+
+_ICE_BUNDLE_DIR = "/tmp/rules_rust_ice_bundles"
+
+def _rustc_ice_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    output_groups = target[OutputGroupInfo] if OutputGroupInfo in target else struct()
+    asserts.false(env, hasattr(output_groups, "rustc_ice_bundles"))
+    for mnemonic in ["Rustc", "RustcMetadata"]:
+        action = find_action(env, target, mnemonic)
+        bundles = [output for output in action.outputs.to_list() if output.basename.endswith(".ice_bundle")]
+        asserts.equals(env, [], bundles, "Reproduction bundles should not be outputs of " + mnemonic)
+        assert_list_contains_adjacent_elements(env, action.argv, ["--ice-bundle-dir", _ICE_BUNDLE_DIR])
+        assert_list_contains_adjacent_elements(env, action.argv, ["--retry-on-ice", "true"])
+
+    return analysistest.end(env)
+
+_rustc_ice_test = analysistest.make(
+    _rustc_ice_test_impl,
+    config_settings = {
+        str(Label("//rust/settings:experimental_ice_bundle_dir")): _ICE_BUNDLE_DIR,
+        str(Label("//rust/settings:experimental_retry_on_ice")): True,
+        str(Label("//rust/settings:pipelined_compilation")): True,
+    },
+)
+
+def _no_rustc_ice_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = find_action(env, target, "Rustc")
+    assert_argv_contains_not(env, action, "--ice-bundle-dir")
+    assert_argv_contains_not(env, action, "--retry-on-ice")
+    output_groups = target[OutputGroupInfo] if OutputGroupInfo in target else struct()
+    asserts.false(env, hasattr(output_groups, "rustc_ice_bundles"))
+
+    return analysistest.end(env)
+
+_no_rustc_ice_test = analysistest.make(_no_rustc_ice_test_impl)
+
+def rustc_ice_test_suite(name):
+    """Entry-point macro called from the BUILD file.
+
+    Args:
+        name (str): The name of the test suite.
+    """
+    write_file(
+        name = "crate_lib",
+        out = "lib.rs",
+        content = [
+            "#[allow(dead_code)]",
+            "fn add() {}",
+            "",
+        ],
+    )
+
+    rust_library(
+        name = "lib",
+        srcs = [":lib.rs"],
+        edition = "2021",
+    )
+
+    _rustc_ice_test(
+        name = "rustc_ice_test",
+        target_under_test = ":lib",
+    )
+
+    _no_rustc_ice_test(
+        name = "no_rustc_ice_test",
+        target_under_test = ":lib",
+    )
+
+    native.test_suite(
+        name = name,
+        tests = [
+            ":no_rustc_ice_test",
+            ":rustc_ice_test",
+        ],
+    )
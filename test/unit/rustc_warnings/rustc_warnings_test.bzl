@@ -0,0 +1,92 @@
+"""Starlark tests for `//rust/settings:experimental_collect_warnings`"""
+
+load("@bazel_skylib//lib:unittest.bzl", "analysistest", "asserts")
+load("@bazel_skylib//rules:write_file.bzl", "write_file")
+load("//rust:defs.bzl", "rust_library")
+load(
+    "//test/unit:common.bzl",
+    "assert_action_mnemonic",
+    "assert_argv_contains_not",
+    "assert_list_contains_adjacent_elements",
+)
+
+def _rustc_warnings_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = target.actions[0]
+    assert_action_mnemonic(env, action, "Rustc")
+
+    warnings = target[OutputGroupInfo].rustc_warnings.to_list()
+    asserts.equals(env, 1, len(warnings))
+    asserts.true(env, warnings[0].basename.endswith(".warnings.json"))
+    asserts.true(env, warnings[0] in action.outputs.to_list())
+    assert_list_contains_adjacent_elements(env, action.argv, ["--warnings-file", warnings[0].path])
+    assert_list_contains_adjacent_elements(env, action.argv, ["--warnings-label", str(Label("//test/unit/rustc_warnings:lib"))])
+
+    # Warnings are extracted from the json diagnostics of rustc.
+    assert_list_contains_adjacent_elements(env, action.argv, ["--rustc-output-format", "rendered"])
+    asserts.true(env, "--error-format=json" in action.argv)
+
+    return analysistest.end(env)
+
+_rustc_warnings_test = analysistest.make(
+    _rustc_warnings_test_impl,
+    config_settings = {
+        str(Label("//rust/settings:experimental_collect_warnings")): True,
+    },
+)
+
+def _no_rustc_warnings_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = target.actions[0]
+    assert_action_mnemonic(env, action, "Rustc")
+    assert_argv_contains_not(env, action, "--warnings-file")
+    output_groups = target[OutputGroupInfo] if OutputGroupInfo in target else struct()
+    asserts.false(env, hasattr(output_groups, "rustc_warnings"))
+
+    return analysistest.end(env)
+
+_no_rustc_warnings_test = analysistest.make(_no_rustc_warnings_test_impl)
+
+def rustc_warnings_test_suite(name):
+    """Entry-point macro called from the BUILD file.
+
+    Args:
+        name (str): The name of the test suite.
+    """
+    write_file(
+        name = "crate_lib",
+        out = "lib.rs",
+        content = [
+            "#[allow(dead_code)]",
+            "fn add() {}",
+            "",
+        ],
+    )
+
+    rust_library(
+        name = "lib",
+        srcs = [":lib.rs"],
+        edition = "2021",
+    )
+
+    _rustc_warnings_test(
+        name = "rustc_warnings_test",
+        target_under_test = ":lib",
+    )
+
+    _no_rustc_warnings_test(
+        name = "no_rustc_warnings_test",
+        target_under_test = ":lib",
+    )
+
+    native.test_suite(
+        name = name,
+        tests = [
+            ":no_rustc_warnings_test",
+            ":rustc_warnings_test",
+        ],
+    )
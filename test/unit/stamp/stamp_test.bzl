@@ -1,7 +1,7 @@
 """Unittest to verify workspace status stamping is applied to environment files"""
 
 load("@bazel_skylib//lib:unittest.bzl", "analysistest")
-load("//rust:defs.bzl", "rust_binary", "rust_common", "rust_library", "rust_test")
+load("//rust:defs.bzl", "rust_binary", "rust_common", "rust_library", "rust_stamped_file", "rust_test")
 load(
     "//test/unit:common.bzl",
     "assert_action_mnemonic",
@@ -277,6 +277,86 @@ def _stamp_attribute_tests():
             ])
     return tests
 
+def _stamped_file_test_impl(ctx, attribute_value, build_flag_value):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    action = target.actions[0]
+    assert_action_mnemonic(env, action, "RustStampFile")
+    assert_argv_contains(env, action, "--stamp-template")
+    output = target[DefaultInfo].files.to_list()[0]
+    assert_argv_contains(env, action, output.path)
+
+    if attribute_value == 1 or (attribute_value == -1 and build_flag_value):
+        _assert_stamped(env, action)
+    else:
+        _assert_not_stamped(env, action)
+
+    return analysistest.end(env)
+
+def _stamped_file_always_stamp_test_impl(ctx):
+    return _stamped_file_test_impl(ctx, attribute_value = 1, build_flag_value = False)
+
+def _stamped_file_never_stamp_test_impl(ctx):
+    return _stamped_file_test_impl(ctx, attribute_value = 0, build_flag_value = True)
+
+def _stamped_file_build_flag_is_true_test_impl(ctx):
+    return _stamped_file_test_impl(ctx, attribute_value = -1, build_flag_value = True)
+
+def _stamped_file_build_flag_is_false_test_impl(ctx):
+    return _stamped_file_test_impl(ctx, attribute_value = -1, build_flag_value = False)
+
+stamped_file_always_stamp_test = analysistest.make(
+    _stamped_file_always_stamp_test_impl,
+    config_settings = {
+        "//command_line_option:stamp": False,
+    },
+)
+
+stamped_file_never_stamp_test = analysistest.make(
+    _stamped_file_never_stamp_test_impl,
+    config_settings = {
+        "//command_line_option:stamp": True,
+    },
+)
+
+stamped_file_build_flag_is_true_test = analysistest.make(
+    _stamped_file_build_flag_is_true_test_impl,
+    config_settings = {
+        "//command_line_option:stamp": True,
+    },
+)
+
+stamped_file_build_flag_is_false_test = analysistest.make(
+    _stamped_file_build_flag_is_false_test_impl,
+    config_settings = {
+        "//command_line_option:stamp": False,
+    },
+)
+
+def _stamped_file_tests():
+    tests = []
+    for name, stamp_value, stamped_file_test in [
+        ("always_stamp", 1, stamped_file_always_stamp_test),
+        ("never_stamp", 0, stamped_file_never_stamp_test),
+        ("consult_cmdline_value_is_true", -1, stamped_file_build_flag_is_true_test),
+        ("consult_cmdline_value_is_false", -1, stamped_file_build_flag_is_false_test),
+    ]:
+        rust_stamped_file(
+            name = "{}_stamped_file".format(name),
+            template = "version.rs.tpl",
+            out = "{}_version.rs".format(name),
+            stamp = stamp_value,
+        )
+
+        stamped_file_test(
+            name = "stamped_file_{}_test".format(name),
+            target_under_test = "{}_stamped_file".format(name),
+        )
+
+        tests.append("stamped_file_{}_test".format(name))
+    return tests
+
 def _process_wrapper_with_stamp_test_impl(ctx):
     env = analysistest.begin(ctx)
     target = analysistest.target_under_test(env)
@@ -309,7 +389,7 @@ def stamp_test_suite(name):
     Args:
         name (str): Name of the macro.
     """
-    tests = _build_flag_tests() + _stamp_attribute_tests() + _process_wrapper_tests()
+    tests = _build_flag_tests() + _stamp_attribute_tests() + _stamped_file_tests() + _process_wrapper_tests()
 
     native.test_suite(
         name = name,
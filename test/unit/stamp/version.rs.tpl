@@ -0,0 +1 @@
+pub const BUILD_USER: &str = "{BUILD_USER}";
@@ -0,0 +1,329 @@
+//! Merge the resource usage records written by the process wrapper into a
+//! Chrome trace and a table of the most expensive actions of a build.
+//!
+//! Records are produced with `--@rules_rust//rust/settings:experimental_collect_resource_usage`
+//! and collected from the `rustc_resource_usage` output group, e.g.:
+//!
+//! ```text
+//! bazel build //... \
+//!     --@rules_rust//rust/settings:experimental_collect_resource_usage \
+//!     --output_groups=+rustc_resource_usage
+//! bazel run @rules_rust//tools/resource_usage_report -- \
+//!     --trace=/tmp/trace.json $(bazel info bazel-bin)
+//! ```
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// The suffix of resource usage records written by the process wrapper.
+const RECORD_SUFFIX: &str = ".resource_usage.json";
+
+#[derive(Parser)]
+#[command(
+    name = "resource_usage_report",
+    about = "Summarize the resources consumed by the rustc actions of a build"
+)]
+struct Args {
+    /// Resource usage records, or directories to search for `*.resource_usage.json` files.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Write a Chrome trace (viewable in `chrome://tracing` or https://ui.perfetto.dev) to this path.
+    #[arg(long)]
+    trace: Option<PathBuf>,
+
+    /// The number of actions to list, ordered by wall time.
+    #[arg(long, default_value_t = 20)]
+    top: usize,
+}
+
+/// The resources consumed by a single action.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct Record {
+    label: Option<String>,
+    mnemonic: Option<String>,
+    start_time_us: u64,
+    wall_time_us: u64,
+    user_time_us: Option<u64>,
+    system_time_us: Option<u64>,
+    max_rss_kb: Option<u64>,
+    exit_code: i32,
+}
+
+impl Record {
+    fn name(&self) -> &str {
+        self.label.as_deref().unwrap_or("<unknown>")
+    }
+
+    fn mnemonic(&self) -> &str {
+        self.mnemonic.as_deref().unwrap_or("<unknown>")
+    }
+
+    fn end_time_us(&self) -> u64 {
+        self.start_time_us + self.wall_time_us
+    }
+}
+
+/// Recursively collect the resource usage records under `path`.
+fn collect_records(path: &Path, records: &mut Vec<Record>) -> Result<()> {
+    let metadata =
+        fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if metadata.is_dir() {
+        let mut entries = fs::read_dir(path)
+            .with_context(|| format!("Failed to read directory {}", path.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        for entry in entries {
+            let is_record = entry
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(RECORD_SUFFIX));
+            if is_record || entry.is_dir() {
+                collect_records(&entry, records)?;
+            }
+        }
+        return Ok(());
+    }
+
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let record = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse resource usage record {}", path.display()))?;
+    records.push(record);
+    Ok(())
+}
+
+/// Assign each record to a lane such that records within a lane do not
+/// overlap in time. Returns the lane of each record.
+fn assign_lanes(records: &[Record]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..records.len()).collect();
+    order.sort_by_key(|&i| (records[i].start_time_us, records[i].end_time_us()));
+
+    // The end time of the last record of each lane.
+    let mut lane_ends: Vec<u64> = Vec::new();
+    let mut lanes = vec![0; records.len()];
+    for i in order {
+        let record = &records[i];
+        let lane = match lane_ends
+            .iter()
+            .position(|&end| end <= record.start_time_us)
+        {
+            Some(lane) => lane,
+            None => {
+                lane_ends.push(0);
+                lane_ends.len() - 1
+            }
+        };
+        lane_ends[lane] = record.end_time_us();
+        lanes[i] = lane;
+    }
+    lanes
+}
+
+/// Render the records as a Chrome trace in the
+/// [Trace Event Format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU).
+fn chrome_trace(records: &[Record]) -> Value {
+    let build_start = records
+        .iter()
+        .map(|record| record.start_time_us)
+        .min()
+        .unwrap_or_default();
+    let lanes = assign_lanes(records);
+
+    let events: Vec<Value> = records
+        .iter()
+        .zip(lanes)
+        .map(|(record, lane)| {
+            json!({
+                "name": record.name(),
+                "cat": record.mnemonic(),
+                "ph": "X",
+                "ts": record.start_time_us - build_start,
+                "dur": record.wall_time_us,
+                "pid": 1,
+                "tid": lane,
+                "args": {
+                    "user_time_us": record.user_time_us,
+                    "system_time_us": record.system_time_us,
+                    "max_rss_kb": record.max_rss_kb,
+                    "exit_code": record.exit_code,
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ms",
+    })
+}
+
+fn format_seconds(us: Option<u64>) -> String {
+    match us {
+        Some(us) => format!("{:.2}", us as f64 / 1_000_000.0),
+        None => "-".to_owned(),
+    }
+}
+
+fn format_mib(kb: Option<u64>) -> String {
+    match kb {
+        Some(kb) => format!("{:.1}", kb as f64 / 1024.0),
+        None => "-".to_owned(),
+    }
+}
+
+/// Render a table of the `top` records with the longest wall time, followed by totals.
+fn format_table(records: &[Record], top: usize) -> String {
+    let mut sorted: Vec<&Record> = records.iter().collect();
+    sorted.sort_by(|a, b| {
+        b.wall_time_us
+            .cmp(&a.wall_time_us)
+            .then_with(|| a.name().cmp(b.name()))
+    });
+
+    let mut table = String::new();
+    writeln!(
+        table,
+        "{:>9} {:>9} {:>9} {:>10}  {:<14} Label",
+        "Wall (s)", "User (s)", "Sys (s)", "RSS (MiB)", "Mnemonic"
+    )
+    .unwrap();
+    for record in sorted.iter().take(top) {
+        writeln!(
+            table,
+            "{:>9} {:>9} {:>9} {:>10}  {:<14} {}",
+            format_seconds(Some(record.wall_time_us)),
+            format_seconds(record.user_time_us),
+            format_seconds(record.system_time_us),
+            format_mib(record.max_rss_kb),
+            record.mnemonic(),
+            record.name(),
+        )
+        .unwrap();
+    }
+
+    let total =
+        |field: fn(&Record) -> Option<u64>| -> u64 { records.iter().filter_map(field).sum() };
+    writeln!(
+        table,
+        "\n{} actions: {}s wall, {}s user, {}s sys",
+        records.len(),
+        format_seconds(Some(total(|r| Some(r.wall_time_us)))),
+        format_seconds(Some(total(|r| r.user_time_us))),
+        format_seconds(Some(total(|r| r.system_time_us))),
+    )
+    .unwrap();
+    table
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let mut records = Vec::new();
+    for input in &args.inputs {
+        collect_records(input, &mut records)?;
+    }
+    if records.is_empty() {
+        bail!(
+            "No resource usage records were found. Build with \
+            `--@rules_rust//rust/settings:experimental_collect_resource_usage` and \
+            `--output_groups=+rustc_resource_usage` to produce them."
+        );
+    }
+
+    if let Some(trace) = &args.trace {
+        let content = serde_json::to_string(&chrome_trace(&records))?;
+        fs::write(trace, content)
+            .with_context(|| format!("Failed to write trace to {}", trace.display()))?;
+    }
+
+    print!("{}", format_table(&records, args.top));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(label: &str, start_time_us: u64, wall_time_us: u64) -> Record {
+        Record {
+            label: Some(label.to_owned()),
+            mnemonic: Some("Rustc".to_owned()),
+            start_time_us,
+            wall_time_us,
+            user_time_us: Some(wall_time_us),
+            system_time_us: None,
+            max_rss_kb: Some(2048),
+            exit_code: 0,
+        }
+    }
+
+    #[test]
+    fn test_parse_record() {
+        let record: Record = serde_json::from_str(
+            r#"{"label":"//pkg:lib","mnemonic":"RustcMetadata","start_time_us":10,
+                "wall_time_us":5,"user_time_us":null,"system_time_us":null,
+                "max_rss_kb":null,"exit_code":0}"#,
+        )
+        .unwrap();
+        assert_eq!(record.name(), "//pkg:lib");
+        assert_eq!(record.mnemonic(), "RustcMetadata");
+        assert_eq!(record.end_time_us(), 15);
+        assert_eq!(record.user_time_us, None);
+    }
+
+    #[test]
+    fn test_assign_lanes() {
+        let records = vec![
+            record("//:a", 0, 10),
+            record("//:b", 5, 10),
+            record("//:c", 10, 5),
+            record("//:d", 12, 1),
+        ];
+        assert_eq!(assign_lanes(&records), vec![0, 1, 0, 2]);
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        let records = vec![record("//:a", 100, 10), record("//:b", 105, 10)];
+        let trace = chrome_trace(&records);
+        let events = trace["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["name"], "//:a");
+        assert_eq!(events[0]["ph"], "X");
+        assert_eq!(events[0]["ts"], 0);
+        assert_eq!(events[0]["tid"], 0);
+        assert_eq!(events[1]["ts"], 5);
+        assert_eq!(events[1]["dur"], 10);
+        assert_eq!(events[1]["tid"], 1);
+        assert_eq!(events[1]["args"]["max_rss_kb"], 2048);
+    }
+
+    #[test]
+    fn test_format_table() {
+        let records = vec![
+            record("//:fast", 0, 1_000_000),
+            record("//:slow", 0, 3_000_000),
+            record("//:medium", 0, 2_000_000),
+        ];
+        let table = format_table(&records, 2);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                " Wall (s)  User (s)   Sys (s)  RSS (MiB)  Mnemonic       Label",
+                "     3.00      3.00         -        2.0  Rustc          //:slow",
+                "     2.00      2.00         -        2.0  Rustc          //:medium",
+                "",
+                "3 actions: 6.00s wall, 6.00s user, 0.00s sys",
+            ]
+        );
+    }
+}
@@ -1,5 +1,12 @@
-use std::path::PathBuf;
-use std::process::Command;
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The name of the combined patch written to `TEST_UNDECLARED_OUTPUTS_DIR`.
+const PATCH_FILE_NAME: &str = "rustfmt.patch";
 
 fn main() {
     // Gather all and environment settings
@@ -9,12 +16,86 @@ fn main() {
     run_rustfmt(&options);
 }
 
+/// Format the content of a source file, returning the formatted content or an error message.
+fn format_source(options: &Config, edition: &str, source: &str) -> Result<String, String> {
+    let mut child = Command::new(&options.rustfmt_config.rustfmt)
+        .arg("--emit")
+        .arg("stdout")
+        .arg("--edition")
+        .arg(edition)
+        .arg("--config-path")
+        .arg(&options.rustfmt_config.config)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run rustfmt");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .expect("Failed to write to rustfmt");
+
+    let output = child.wait_with_output().expect("Failed to wait on rustfmt");
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+    }
+
+    String::from_utf8(output.stdout).map_err(|e| e.to_string())
+}
+
+/// Check the formatting of `sources` with a single rustfmt invocation, returning the indices of
+/// the sources which need formatting and the errors reported by rustfmt, if any.
+///
+/// Out-of-line modules which are not among `sources` are checked by rustfmt as well, but not
+/// reported, as they are expected to be checked through the manifests of their own targets.
+fn check_sources(
+    options: &Config,
+    edition: &str,
+    sources: &[PathBuf],
+) -> (BTreeSet<usize>, Option<String>) {
+    let output = Command::new(&options.rustfmt_config.rustfmt)
+        .arg("--check")
+        .arg("--files-with-diff")
+        .arg("--edition")
+        .arg(edition)
+        .arg("--config-path")
+        .arg(&options.rustfmt_config.config)
+        .args(sources)
+        .output()
+        .expect("Failed to run rustfmt");
+
+    // rustfmt reports the canonical paths of files which need formatting.
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let indices: HashMap<PathBuf, usize> = sources
+        .iter()
+        .enumerate()
+        .map(|(idx, source)| (canonical(source), idx))
+        .collect();
+    let misformatted = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| indices.get(&canonical(Path::new(line))).copied())
+        .collect();
+
+    let errors = String::from_utf8_lossy(&output.stderr).into_owned();
+    let errors = (!output.status.success() && !errors.trim().is_empty()).then_some(errors);
+
+    (misformatted, errors)
+}
+
 /// Run rustfmt on a set of Bazel targets
 fn run_rustfmt(options: &Config) {
     // In order to ensure the test parses all sources, we separately
     // track whether or not a failure has occurred when checking formatting.
     let mut is_failure: bool = false;
 
+    // All diffs, combined into a single patch which can be applied from the workspace root.
+    let mut patch = String::new();
+
     for manifest in options.manifests.iter() {
         // Ignore any targets which do not have source files. This can
         // occur in cases where all source files are generated.
@@ -22,22 +103,51 @@ fn run_rustfmt(options: &Config) {
             continue;
         }
 
-        // Run rustfmt
-        let status = Command::new(&options.rustfmt_config.rustfmt)
-            .arg("--check")
-            .arg("--edition")
-            .arg(&manifest.edition)
-            .arg("--config-path")
-            .arg(&options.rustfmt_config.config)
-            .args(&manifest.sources)
-            .status()
-            .expect("Failed to run rustfmt");
-
-        if !status.success() {
+        let (misformatted, errors) = check_sources(options, &manifest.edition, &manifest.sources);
+        if let Some(errors) = errors {
+            eprintln!("{}", errors);
+            is_failure = true;
+        }
+
+        // Only the sources which need formatting are formatted again to render their diffs.
+        for idx in misformatted {
+            let (source, path) = (&manifest.sources[idx], &manifest.workspace_paths[idx]);
+            let original = fs::read_to_string(source)
+                .unwrap_or_else(|_| panic!("Failed to read source file: {}", source.display()));
+
+            let formatted = match format_source(options, &manifest.edition, &original) {
+                Ok(formatted) => formatted,
+                Err(stderr) => {
+                    eprintln!("Failed to format {}:\n{}", path, stderr);
+                    is_failure = true;
+                    continue;
+                }
+            };
+
+            let diff = rustfmt_lib::unified_diff(path, &original, &formatted);
+            if diff.is_empty() {
+                continue;
+            }
+
+            println!("{}", rustfmt_lib::needs_formatting(&manifest.edition, path));
+            println!("{}", diff);
+            patch.push_str(&diff);
             is_failure = true;
         }
     }
 
+    if !patch.is_empty() {
+        if let Some(outputs_dir) = &options.outputs_dir {
+            let patch_path = outputs_dir.join(PATCH_FILE_NAME);
+            fs::write(&patch_path, &patch)
+                .unwrap_or_else(|_| panic!("Failed to write patch file: {}", patch_path.display()));
+            println!(
+                "A patch with all formatting changes was written to the undeclared test outputs as `{}`.",
+                PATCH_FILE_NAME
+            );
+        }
+    }
+
     if is_failure {
         std::process::exit(1);
     }
@@ -52,6 +162,9 @@ struct Config {
     /// A list of manifests containing information about sources
     /// to check using rustfmt.
     pub manifests: Vec<rustfmt_lib::RustfmtManifest>,
+
+    /// The directory for undeclared test outputs, if running as a test.
+    pub outputs_dir: Option<PathBuf>,
 }
 
 /// Parse settings from the environment into a config struct
@@ -68,5 +181,6 @@ fn parse_args() -> Config {
             .iter()
             .map(|manifest| rustfmt_lib::parse_rustfmt_manifest(manifest))
             .collect(),
+        outputs_dir: env::var_os("TEST_UNDECLARED_OUTPUTS_DIR").map(PathBuf::from),
     }
 }
@@ -0,0 +1,463 @@
+//! A minimal unified diff implementation for reporting formatting changes.
+
+use std::fmt::Write;
+use std::ops::{Index, IndexMut};
+
+/// The number of unchanged lines to show around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// A single line of a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    Context(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The furthest reaching x coordinates of the paths of a Myers diff, indexed by diagonal.
+struct V {
+    offset: isize,
+    v: Vec<usize>,
+}
+
+impl V {
+    fn new(max_d: usize) -> Self {
+        Self {
+            offset: max_d as isize,
+            v: vec![0; 2 * max_d + 1],
+        }
+    }
+}
+
+impl Index<isize> for V {
+    type Output = usize;
+
+    fn index(&self, k: isize) -> &usize {
+        &self.v[(k + self.offset) as usize]
+    }
+}
+
+impl IndexMut<isize> for V {
+    fn index_mut(&mut self, k: isize) -> &mut usize {
+        &mut self.v[(k + self.offset) as usize]
+    }
+}
+
+/// The number of leading lines `old` and `new` have in common.
+fn common_prefix_len(old: &[&str], new: &[&str]) -> usize {
+    old.iter().zip(new).take_while(|(a, b)| a == b).count()
+}
+
+/// The number of trailing lines `old` and `new` have in common.
+fn common_suffix_len(old: &[&str], new: &[&str]) -> usize {
+    old.iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Find the start of the middle snake of a shortest edit script turning `old` into `new`
+/// by searching forwards and backwards at the same time.
+///
+/// See "An O(ND) Difference Algorithm and Its Variations" by Eugene W. Myers.
+fn find_middle_snake(old: &[&str], new: &[&str], vf: &mut V, vb: &mut V) -> Option<(usize, usize)> {
+    let n = old.len();
+    let m = new.len();
+    let delta = n as isize - m as isize;
+    let odd = delta & 1 == 1;
+
+    vf[1] = 0;
+    vb[1] = 0;
+
+    let d_max = ((n + m).div_ceil(2) + 1) as isize;
+    for d in 0..d_max {
+        // Forward paths
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && vf[k - 1] < vf[k + 1]) {
+                vf[k + 1]
+            } else {
+                vf[k - 1] + 1
+            };
+            let y = (x as isize - k) as usize;
+
+            let (x0, y0) = (x, y);
+            if x < n && y < m {
+                x += common_prefix_len(&old[x..], &new[y..]);
+            }
+            vf[k] = x;
+
+            if odd && (k - delta).abs() < d && vf[k] + vb[-(k - delta)] >= n {
+                return Some((x0, y0));
+            }
+        }
+
+        // Backward paths, with coordinates counted from the ends of the sequences
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && vb[k - 1] < vb[k + 1]) {
+                vb[k + 1]
+            } else {
+                vb[k - 1] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+
+            if x < n && y < m {
+                let advance = common_suffix_len(&old[..n - x], &new[..m - y]);
+                x += advance;
+                y += advance;
+            }
+            vb[k] = x;
+
+            if !odd && (k - delta).abs() <= d && vb[k] + vf[-(k - delta)] >= n {
+                return Some((n - x, m - y));
+            }
+        }
+    }
+
+    None
+}
+
+/// Append a shortest edit script turning `old` into `new` to `lines`, splitting the
+/// problem at the middle snake to only use linear space.
+fn conquer<'a>(
+    old: &[&'a str],
+    new: &[&'a str],
+    vf: &mut V,
+    vb: &mut V,
+    lines: &mut Vec<Line<'a>>,
+) {
+    let prefix = common_prefix_len(old, new);
+    lines.extend(old[..prefix].iter().map(|line| Line::Context(line)));
+    let suffix = common_suffix_len(&old[prefix..], &new[prefix..]);
+    let old_changed = &old[prefix..old.len() - suffix];
+    let new_changed = &new[prefix..new.len() - suffix];
+
+    if old_changed.is_empty() {
+        lines.extend(new_changed.iter().map(|line| Line::Added(line)));
+    } else if new_changed.is_empty() {
+        lines.extend(old_changed.iter().map(|line| Line::Removed(line)));
+    } else if let Some((x, y)) = find_middle_snake(old_changed, new_changed, vf, vb) {
+        conquer(&old_changed[..x], &new_changed[..y], vf, vb, lines);
+        conquer(&old_changed[x..], &new_changed[y..], vf, vb, lines);
+    } else {
+        lines.extend(old_changed.iter().map(|line| Line::Removed(line)));
+        lines.extend(new_changed.iter().map(|line| Line::Added(line)));
+    }
+
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| Line::Context(line)),
+    );
+}
+
+/// Compute the line based edit script turning `original` into `formatted`.
+fn diff_lines<'a>(original: &[&'a str], formatted: &[&'a str]) -> Vec<Line<'a>> {
+    let max_d = (original.len() + formatted.len()).div_ceil(2) + 1;
+    let mut vf = V::new(max_d);
+    let mut vb = V::new(max_d);
+    let mut lines = Vec::with_capacity(original.len().max(formatted.len()));
+    conquer(original, formatted, &mut vf, &mut vb, &mut lines);
+
+    // List the removed lines of each run of changes before the added ones.
+    for run in lines.split_mut(|line| matches!(line, Line::Context(_))) {
+        run.sort_by_key(|line| matches!(line, Line::Added(_)));
+    }
+
+    lines
+}
+
+/// Write a single line of a hunk, noting when the line has no trailing newline.
+fn write_line(out: &mut String, prefix: char, line: &str) {
+    match line.strip_suffix('\n') {
+        Some(content) => writeln!(out, "{prefix}{content}").unwrap(),
+        None => {
+            writeln!(out, "{prefix}{line}").unwrap();
+            out.push_str("\\ No newline at end of file\n");
+        }
+    }
+}
+
+/// Render a unified diff between `original` and `formatted` for a file at `path`.
+///
+/// The headers use `a/` and `b/` prefixes so the output can be applied with
+/// `git apply` or `patch -p1` from the root of the workspace. An empty string
+/// is returned if the contents are identical.
+pub fn unified_diff(path: &str, original: &str, formatted: &str) -> String {
+    if original == formatted {
+        return String::new();
+    }
+
+    let original_lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let formatted_lines: Vec<&str> = formatted.split_inclusive('\n').collect();
+    let lines = diff_lines(&original_lines, &formatted_lines);
+
+    let mut out = String::new();
+    writeln!(out, "--- a/{path}").unwrap();
+    writeln!(out, "+++ b/{path}").unwrap();
+
+    // Indices of all changed lines, used to group changes into hunks.
+    let changes: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Context(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let mut idx = 0;
+    while idx < changes.len() {
+        // Extend the hunk while the context of the next change would overlap or touch it.
+        let start = changes[idx].saturating_sub(CONTEXT_LINES);
+        let mut last = changes[idx];
+        while idx + 1 < changes.len() && changes[idx + 1] - last <= 2 * CONTEXT_LINES + 1 {
+            idx += 1;
+            last = changes[idx];
+        }
+        let end = (last + CONTEXT_LINES + 1).min(lines.len());
+        idx += 1;
+
+        // The number of lines preceding the hunk in each file.
+        let old_start = lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_start = lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+        let hunk = &lines[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+
+        // Empty ranges refer to the line before the hunk.
+        let old_first = if old_len == 0 {
+            old_start
+        } else {
+            old_start + 1
+        };
+        let new_first = if new_len == 0 {
+            new_start
+        } else {
+            new_start + 1
+        };
+        writeln!(out, "@@ -{old_first},{old_len} +{new_first},{new_len} @@").unwrap();
+
+        for line in hunk {
+            match line {
+                Line::Context(content) => write_line(&mut out, ' ', content),
+                Line::Removed(content) => write_line(&mut out, '-', content),
+                Line::Added(content) => write_line(&mut out, '+', content),
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The lines `line 1` to `line 20`.
+    fn numbered_lines() -> String {
+        (1..=20).map(|i| format!("line {i}\n")).collect()
+    }
+
+    /// The length of the longest common subsequence of `a` and `b`.
+    fn lcs_len(a: &[&str], b: &[&str]) -> usize {
+        let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        lcs[0][0]
+    }
+
+    #[test]
+    fn test_diff_lines_is_shortest_edit_script() {
+        // A small linear congruential generator keeps the test deterministic.
+        let mut seed = 0x2545_f491_u64;
+        let mut next = |bound: u64| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) % bound
+        };
+        let alphabet = ["a\n", "b\n", "c\n", "d\n"];
+
+        for _ in 0..500 {
+            let old: Vec<&str> = (0..next(12)).map(|_| alphabet[next(4) as usize]).collect();
+            let new: Vec<&str> = (0..next(12)).map(|_| alphabet[next(4) as usize]).collect();
+            let lines = diff_lines(&old, &new);
+
+            let rebuilt_old: Vec<&str> = lines
+                .iter()
+                .filter_map(|line| match line {
+                    Line::Context(line) | Line::Removed(line) => Some(*line),
+                    Line::Added(_) => None,
+                })
+                .collect();
+            let rebuilt_new: Vec<&str> = lines
+                .iter()
+                .filter_map(|line| match line {
+                    Line::Context(line) | Line::Added(line) => Some(*line),
+                    Line::Removed(_) => None,
+                })
+                .collect();
+            assert_eq!(rebuilt_old, old);
+            assert_eq!(rebuilt_new, new);
+
+            let context = lines
+                .iter()
+                .filter(|line| matches!(line, Line::Context(_)))
+                .count();
+            assert_eq!(context, lcs_len(&old, &new), "{old:?} -> {new:?}");
+        }
+    }
+
+    #[test]
+    fn test_unified_diff_identical() {
+        assert_eq!(unified_diff("a.rs", "fn a() {}\n", "fn a() {}\n"), "");
+    }
+
+    #[test]
+    fn test_unified_diff_context() {
+        let original = numbered_lines();
+        let formatted = original.replace("line 10\n", "line ten\n");
+
+        assert_eq!(
+            unified_diff("src/lib.rs", &original, &formatted),
+            "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -7,7 +7,7 @@
+ line 7
+ line 8
+ line 9
+-line 10
++line ten
+ line 11
+ line 12
+ line 13
+"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_separate_hunks() {
+        let original = numbered_lines();
+        let formatted = original
+            .replace("line 2\n", "line two\n")
+            .replace("line 18\n", "line eighteen\n");
+
+        assert_eq!(
+            unified_diff("src/lib.rs", &original, &formatted),
+            "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,5 +1,5 @@
+ line 1
+-line 2
++line two
+ line 3
+ line 4
+ line 5
+@@ -15,6 +15,6 @@
+ line 15
+ line 16
+ line 17
+-line 18
++line eighteen
+ line 19
+ line 20
+"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_merged_hunks() {
+        let original = numbered_lines();
+        // The changes are separated by exactly twice the number of context lines.
+        let formatted = original
+            .replace("line 5\n", "line five\n")
+            .replace("line 12\n", "line twelve\n");
+
+        assert_eq!(
+            unified_diff("src/lib.rs", &original, &formatted),
+            "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -2,14 +2,14 @@
+ line 2
+ line 3
+ line 4
+-line 5
++line five
+ line 6
+ line 7
+ line 8
+ line 9
+ line 10
+ line 11
+-line 12
++line twelve
+ line 13
+ line 14
+ line 15
+"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_insertions_and_deletions() {
+        assert_eq!(
+            unified_diff("a.rs", "", "fn a() {}\n"),
+            "\
+--- a/a.rs
++++ b/a.rs
+@@ -0,0 +1,1 @@
++fn a() {}
+"
+        );
+        assert_eq!(
+            unified_diff(
+                "a.rs",
+                "use a;\n\nuse b;\nfn c() {}\n",
+                "use a;\nuse b;\nfn c() {}\n"
+            ),
+            "\
+--- a/a.rs
++++ b/a.rs
+@@ -1,4 +1,3 @@
+ use a;
+-
+ use b;
+ fn c() {}
+"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_no_newline_at_end_of_file() {
+        assert_eq!(
+            unified_diff("a.rs", "fn a() {}", "fn a() {}\n"),
+            "\
+--- a/a.rs
++++ b/a.rs
+@@ -1,1 +1,1 @@
+-fn a() {}
+\\ No newline at end of file
++fn a() {}
+"
+        );
+    }
+}
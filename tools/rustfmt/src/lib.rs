@@ -2,6 +2,10 @@ use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+mod diff;
+
+pub use diff::unified_diff;
+
 /// The expected extension of rustfmt manifest files generated by `rustfmt_aspect`.
 pub const RUSTFMT_MANIFEST_EXTENSION: &str = "rustfmt";
 
@@ -45,6 +49,9 @@ pub struct RustfmtManifest {
 
     /// A list of all (non-generated) source files for formatting.
     pub sources: Vec<PathBuf>,
+
+    /// The paths of `sources` relative to the root of their workspace.
+    pub workspace_paths: Vec<String>,
 }
 
 /// Parse rustfmt flags from a manifest generated by builds using `rustfmt_aspect`.
@@ -70,12 +77,40 @@ pub fn parse_rustfmt_manifest(manifest: &Path) -> RustfmtManifest {
     RustfmtManifest {
         edition,
         sources: lines
-            .into_iter()
+            .iter()
             .map(|src| runfiles::rlocation!(runfiles, src).unwrap())
             .collect(),
+        // Manifest entries are prefixed with the name of the owning workspace.
+        workspace_paths: lines
+            .iter()
+            .map(|src| {
+                src.split_once('/')
+                    .map(|(_, path)| path.to_owned())
+                    .unwrap_or_else(|| src.clone())
+            })
+            .collect(),
     }
 }
 
+/// The prefix of lines logged by `rustfmt_test` for each source file which needs formatting.
+pub const NEEDS_FORMATTING_PREFIX: &str = "rustfmt: needs formatting:";
+
+/// Render a line noting that a file (relative to the workspace root) needs formatting for a
+/// given edition. These lines are parsed by [parse_needs_formatting] to fix up failing tests.
+pub fn needs_formatting(edition: &str, path: &str) -> String {
+    format!("{} {} {}", NEEDS_FORMATTING_PREFIX, edition, path)
+}
+
+/// Parse a line rendered by [needs_formatting] into an edition and workspace relative path.
+pub fn parse_needs_formatting(line: &str) -> Option<(String, String)> {
+    let (edition, path) = line
+        .trim()
+        .strip_prefix(NEEDS_FORMATTING_PREFIX)?
+        .trim_start()
+        .split_once(' ')?;
+    Some((edition.to_owned(), path.to_owned()))
+}
+
 #[cfg(target_family = "windows")]
 const PATH_ENV_SEP: &str = ";";
 
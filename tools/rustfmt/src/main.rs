@@ -1,7 +1,12 @@
 //! A tool for querying Rust source files wired into Bazel and running Rustfmt on them.
+//!
+//! By default all targets in the workspace (or the targets passed as arguments) are formatted.
+//! For use in pre-commit hooks, `--files <path>...` or `--since <git-rev>` format only the
+//! given or changed files, using the editions of their owning targets.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::str;
@@ -11,11 +16,16 @@ fn main() {
     // Gather all command line and environment settings
     let options = parse_args();
 
-    // Gather a list of all formattable targets
-    let targets = query_rustfmt_targets(&options);
+    // Gather a list of all formattable sources
+    let sources = match &options.mode {
+        Mode::Query(packages) => query_rustfmt_targets(&options, &package_scope(packages)),
+        Mode::FailingTests(test_logs) => failing_test_sources(&options, test_logs),
+        Mode::Files(files) => changed_file_sources(&options, files),
+        Mode::Since(rev) => changed_file_sources(&options, &git_changed_files(&options, rev)),
+    };
 
-    // Run rustfmt on these targets
-    apply_rustfmt(&options, &targets);
+    // Run rustfmt on these sources
+    apply_rustfmt(&options, &sources);
 }
 
 /// The edition to use in cases where the default edition is unspecified by Bazel
@@ -72,6 +82,7 @@ fn bazel_command(bazel_bin: &Path, args: &[String], current_dir: &Path) -> Vec<S
 const EMPTY_EDITION: &str = "^$";
 
 /// Query for all `*.rs` files in a workspace that are dependencies of targets with the requested edition.
+/// The `scope` is a query expression for the targets to consider.
 fn edition_query(bazel_bin: &Path, edition: &str, scope: &str, current_dir: &Path) -> Vec<String> {
     let query_args = vec![
         "query".to_owned(),
@@ -83,7 +94,7 @@ fn edition_query(bazel_bin: &Path, edition: &str, scope: &str, current_dir: &Pat
         //             Except for targets tagged with `norustfmt`, `no-rustfmt`, or `no-format`.
         //             And except for targets with a populated `crate` attribute since `crate` defines edition for this target
         format!(
-            r#"let scope = set({scope}) in filter("^//.*\.rs$", kind("source file", deps(attr(edition, "{edition}", $scope) except attr(tags, "(^\[|, )(no-format|no-rustfmt|norustfmt)(, |\]$)", $scope) except attr(crate, ".*", $scope), 1)))"#,
+            r#"let scope = {scope} in filter("^//.*\.rs$", kind("source file", deps(attr(edition, "{edition}", $scope) except attr(tags, "(^\[|, )(no-format|no-rustfmt|norustfmt)(, |\]$)", $scope) except attr(crate, ".*", $scope), 1)))"#,
         ),
         "--keep_going".to_owned(),
         "--noimplicit_deps".to_owned(),
@@ -92,23 +103,32 @@ fn edition_query(bazel_bin: &Path, edition: &str, scope: &str, current_dir: &Pat
     bazel_command(bazel_bin, &query_args, current_dir)
 }
 
-/// Perform a `bazel` query to determine all source files which are to be
-/// formatted for particular Rust editions.
-fn query_rustfmt_targets(options: &Config) -> HashMap<String, Vec<String>> {
-    let scope = options
-        .packages
-        .clone()
-        .into_iter()
+/// Convert the label of a source file (eg `//my/pkg:src/lib.rs`) to a workspace relative path.
+fn label_to_path(label: &str) -> String {
+    label.replace(':', "/").trim_start_matches('/').to_owned()
+}
+
+/// Render a query scope for a list of targets/packages/scopes, defaulting to the whole workspace.
+fn package_scope(packages: &[String]) -> String {
+    let packages = packages
+        .iter()
+        .cloned()
         .reduce(|acc, item| acc + " " + &item)
         .unwrap_or_else(|| "//...:all".to_owned());
 
+    format!("set({packages})")
+}
+
+/// Perform a `bazel` query to determine all source files which are to be
+/// formatted for particular Rust editions.
+fn query_rustfmt_targets(options: &Config, scope: &str) -> HashMap<String, Vec<String>> {
     let editions = get_editions();
     let default_edition = get_default_edition();
 
     editions
         .into_iter()
         .map(|edition| {
-            let mut targets = edition_query(&options.bazel, &edition, &scope, &options.workspace);
+            let mut targets = edition_query(&options.bazel, &edition, scope, &options.workspace);
 
             // For all targets relying on the toolchain for it's edition,
             // query anything with an unset edition
@@ -116,33 +136,307 @@ fn query_rustfmt_targets(options: &Config) -> HashMap<String, Vec<String>> {
                 targets.extend(edition_query(
                     &options.bazel,
                     EMPTY_EDITION,
-                    &scope,
+                    scope,
                     &options.workspace,
                 ))
             }
 
-            (edition, targets)
+            (
+                edition,
+                targets.iter().map(|target| label_to_path(target)).collect(),
+            )
         })
         .collect()
 }
 
-/// Run rustfmt on a set of Bazel targets
-fn apply_rustfmt(options: &Config, editions_and_targets: &HashMap<String, Vec<String>>) {
-    // There is no work to do if the list of targets is empty
-    if editions_and_targets.is_empty() {
-        return;
+/// Determine the Bazel package owning a workspace relative path by searching
+/// its parent directories for a `BUILD` file.
+fn owning_package(workspace: &Path, path: &Path) -> Option<PathBuf> {
+    path.ancestors().skip(1).find_map(|dir| {
+        let package = workspace.join(dir);
+        if package.join("BUILD.bazel").exists() || package.join("BUILD").exists() {
+            Some(dir.to_path_buf())
+        } else {
+            None
+        }
+    })
+}
+
+/// Convert a workspace relative path to a source file to its label, eg `//my/pkg:src/lib.rs`.
+fn path_to_label(workspace: &Path, path: &Path) -> Option<(String, String)> {
+    let package = owning_package(workspace, path)?;
+    let name = path.strip_prefix(&package).ok()?;
+    let package = package.to_string_lossy().replace('\\', "/");
+    let name = name.to_string_lossy().replace('\\', "/");
+    Some((format!("//{package}:*"), format!("//{package}:{name}")))
+}
+
+/// A Rust target as reported by [rust_targets_query].
+#[derive(Debug, Default, PartialEq, Eq)]
+struct RustTarget {
+    /// The label of the target.
+    label: String,
+
+    /// The value of the `edition` attribute, which is empty if unset.
+    edition: String,
+
+    /// The labels of the direct inputs of the target.
+    inputs: Vec<String>,
+}
+
+/// Undo the escaping of an XML attribute value.
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Get the value of the attribute `name` of the XML element on `line`.
+fn xml_attribute(line: &str, name: &str) -> Option<String> {
+    let start = line.find(&format!(" {name}=\""))? + name.len() + 3;
+    let len = line[start..].find('"')?;
+    Some(xml_unescape(&line[start..start + len]))
+}
+
+/// Parse the Rust targets from the output of `bazel query --output=xml`, which
+/// writes every element on its own line.
+fn parse_rust_targets(lines: &[String]) -> Vec<RustTarget> {
+    let mut targets = Vec::new();
+    let mut current: Option<RustTarget> = None;
+    for line in lines.iter().map(|line| line.trim()) {
+        if line.starts_with("<rule ") {
+            current = xml_attribute(line, "name").map(|label| RustTarget {
+                label,
+                ..RustTarget::default()
+            });
+        } else if line == "</rule>" {
+            targets.extend(current.take());
+        } else if let Some(target) = current.as_mut() {
+            if line.starts_with("<rule-input ") {
+                target.inputs.extend(xml_attribute(line, "name"));
+            } else if line.starts_with("<string ")
+                && xml_attribute(line, "name").as_deref() == Some("edition")
+            {
+                target.edition = xml_attribute(line, "value").unwrap_or_default();
+            }
+        }
     }
+    targets
+}
+
+/// Query for the Rust targets within `scope` which are to be formatted, along with their
+/// editions and direct inputs, in a single `bazel query`.
+fn rust_targets_query(bazel_bin: &Path, scope: &str, current_dir: &Path) -> Vec<RustTarget> {
+    let query_args = vec![
+        "query".to_owned(),
+        // Query explanation:
+        // Get all targets with an `edition` attribute.
+        // Except for targets tagged with `norustfmt`, `no-rustfmt`, or `no-format`.
+        // And except for targets with a populated `crate` attribute since `crate` defines edition for this target
+        format!(
+            r#"let scope = {scope} in attr(edition, ".*", $scope) except attr(tags, "(^\[|, )(no-format|no-rustfmt|norustfmt)(, |\]$)", $scope) except attr(crate, ".*", $scope)"#,
+        ),
+        "--output=xml".to_owned(),
+        "--keep_going".to_owned(),
+        "--noimplicit_deps".to_owned(),
+    ];
 
-    for (edition, targets) in editions_and_targets.iter() {
-        if targets.is_empty() {
+    parse_rust_targets(&bazel_command(bazel_bin, &query_args, current_dir))
+}
+
+/// The source files changed in a workspace, as determined by [changed_file_labels].
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ChangedFiles {
+    /// Patterns for all targets of the packages owning the files, eg `//my/pkg:*`.
+    packages: BTreeSet<String>,
+
+    /// The labels of the files, eg `//my/pkg:src/lib.rs`.
+    labels: BTreeSet<String>,
+}
+
+/// Determine the labels of the existing Rust source files among `files`.
+fn changed_file_labels(workspace: &Path, files: &[PathBuf]) -> ChangedFiles {
+    let mut changed = ChangedFiles::default();
+    for file in files {
+        if file.extension().map(|ext| ext != "rs").unwrap_or(true)
+            || !workspace.join(file).is_file()
+        {
             continue;
         }
+        match path_to_label(workspace, file) {
+            Some((package, label)) => {
+                changed.packages.insert(package);
+                changed.labels.insert(label);
+            }
+            None => eprintln!("No Bazel package found for {}", file.display()),
+        }
+    }
+    changed
+}
+
+/// Group the changed files by the editions of the targets depending on them. Targets
+/// without an explicit edition use `default_edition`.
+fn changed_sources_by_edition(
+    targets: &[RustTarget],
+    labels: &BTreeSet<String>,
+    default_edition: &str,
+) -> HashMap<String, Vec<String>> {
+    let mut sources: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for target in targets {
+        let edition = if target.edition.is_empty() {
+            default_edition
+        } else {
+            &target.edition
+        };
+        // The owning targets may have other sources, which are not formatted.
+        for input in target.inputs.iter().filter(|input| labels.contains(*input)) {
+            sources
+                .entry(edition.to_owned())
+                .or_default()
+                .insert(label_to_path(input));
+        }
+    }
+
+    sources
+        .into_iter()
+        .map(|(edition, paths)| (edition, paths.into_iter().collect()))
+        .collect()
+}
+
+/// Determine the source files to format from a list of changed files.
+///
+/// Files are mapped to their owning targets (and therefore editions) by querying the
+/// reverse dependencies of each file within its own package, which avoids loading
+/// the rest of the workspace. Only the given files are formatted, not all sources of
+/// the owning targets.
+fn changed_file_sources(options: &Config, files: &[PathBuf]) -> HashMap<String, Vec<String>> {
+    let changed = changed_file_labels(&options.workspace, files);
+    if changed.labels.is_empty() {
+        return HashMap::new();
+    }
+
+    let join = |set: &BTreeSet<String>| set.iter().cloned().collect::<Vec<_>>().join(" ");
+    let scope = format!(
+        "rdeps(set({}), set({}), 1)",
+        join(&changed.packages),
+        join(&changed.labels)
+    );
+
+    let targets = rust_targets_query(&options.bazel, &scope, &options.workspace);
+    changed_sources_by_edition(&targets, &changed.labels, get_default_edition())
+}
+
+/// Run a git command in the workspace, returning the listed paths.
+fn git_paths(options: &Config, args: &[&str]) -> Vec<PathBuf> {
+    let output = Command::new("git")
+        .current_dir(&options.workspace)
+        .args(args)
+        .stderr(Stdio::inherit())
+        .output()
+        .expect("Failed to run git");
+
+    if !output.status.success() {
+        eprintln!("Failed to perform `git {}` command.", args.join(" "));
+        std::process::exit(output.status.code().unwrap_or(1));
+    }
+
+    str::from_utf8(&output.stdout)
+        .expect("Invalid stream from command")
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
 
-        // Get paths to all formattable sources
-        let sources: Vec<String> = targets
+/// Determine all files changed since a git revision, including uncommitted and untracked files.
+fn git_changed_files(options: &Config, rev: &str) -> Vec<PathBuf> {
+    let mut files = git_paths(
+        options,
+        &["diff", "--name-only", "--relative", "--diff-filter=d", rev],
+    );
+    files.extend(git_paths(
+        options,
+        &["ls-files", "--others", "--exclude-standard"],
+    ));
+    files
+}
+
+/// Recursively collect all `test.log` files within a directory.
+fn find_test_logs(dir: &Path, logs: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_test_logs(&path, logs);
+        } else if path
+            .file_name()
+            .map(|name| name == "test.log")
+            .unwrap_or(false)
+        {
+            logs.push(path);
+        }
+    }
+}
+
+/// Determine the source files reported as needing formatting by failing `rustfmt_test` targets.
+///
+/// Test logs may be passed explicitly as files or directories to search. If none are
+/// given, the `bazel-testlogs` directory of the workspace is searched.
+fn failing_test_sources(options: &Config, test_logs: &[PathBuf]) -> HashMap<String, Vec<String>> {
+    let roots = if test_logs.is_empty() {
+        vec![options.workspace.join("bazel-testlogs")]
+    } else {
+        test_logs
             .iter()
-            .map(|target| target.replace(':', "/").trim_start_matches('/').to_owned())
-            .collect();
+            .map(|path| options.workspace.join(path))
+            .collect()
+    };
+
+    let mut logs = Vec::new();
+    for root in roots {
+        if root.is_dir() {
+            find_test_logs(&root, &mut logs);
+        } else {
+            logs.push(root);
+        }
+    }
+
+    let mut sources: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for log in logs {
+        let content = fs::read_to_string(&log)
+            .unwrap_or_else(|_| panic!("Failed to read test log: {}", log.display()));
+        for (edition, path) in content
+            .lines()
+            .filter_map(rustfmt_lib::parse_needs_formatting)
+        {
+            sources.entry(edition).or_default().insert(path);
+        }
+    }
+
+    sources
+        .into_iter()
+        .map(|(edition, paths)| (edition, paths.into_iter().collect()))
+        .collect()
+}
+
+/// Run rustfmt on a set of workspace relative source files, grouped by edition
+fn apply_rustfmt(options: &Config, editions_and_sources: &HashMap<String, Vec<String>>) {
+    // There is no work to do if the list of sources is empty
+    if editions_and_sources.is_empty() {
+        return;
+    }
+
+    for (edition, sources) in editions_and_sources.iter() {
+        if sources.is_empty() {
+            continue;
+        }
 
         // Run rustfmt
         let status = Command::new(&options.rustfmt_config.rustfmt)
@@ -173,26 +467,272 @@ struct Config {
     /// Information about the current rustfmt binary to run.
     pub rustfmt_config: rustfmt_lib::RustfmtConfig,
 
+    /// How to determine the sources to format.
+    pub mode: Mode,
+}
+
+/// The ways in which sources to format can be selected.
+#[derive(Debug)]
+enum Mode {
     /// Optionally, users can pass a list of targets/packages/scopes
     /// (eg `//my:target` or `//my/pkg/...`) to control the targets
     /// to be formatted. If empty, all targets in the workspace will
     /// be formatted.
-    pub packages: Vec<String>,
+    Query(Vec<String>),
+
+    /// Format exactly the files reported by failing `rustfmt_test` targets, as found in
+    /// the given test logs (or `bazel-testlogs` if empty). Selected with `--fix-failing-tests`.
+    FailingTests(Vec<PathBuf>),
+
+    /// Format only the given source files, relative to the workspace root.
+    /// Selected with `--files`.
+    Files(Vec<PathBuf>),
+
+    /// Format only source files changed since a git revision. Selected with `--since <rev>`.
+    Since(String),
 }
 
 /// Parse command line arguments and environment variables to
 /// produce config data for running rustfmt.
 fn parse_args() -> Config {
-    Config{
-        workspace: PathBuf::from(
-            env::var("BUILD_WORKSPACE_DIRECTORY")
-            .expect("The environment variable BUILD_WORKSPACE_DIRECTORY is required for finding the workspace root")
-        ),
-        bazel: PathBuf::from(
-            env::var("BAZEL_REAL")
-            .unwrap_or_else(|_| "bazel".to_owned())
-        ),
+    let workspace = PathBuf::from(
+        env::var("BUILD_WORKSPACE_DIRECTORY")
+        .expect("The environment variable BUILD_WORKSPACE_DIRECTORY is required for finding the workspace root")
+    );
+
+    Config {
+        mode: parse_mode(env::args().skip(1).collect(), &workspace),
+        workspace,
+        bazel: PathBuf::from(env::var("BAZEL_REAL").unwrap_or_else(|_| "bazel".to_owned())),
         rustfmt_config: rustfmt_lib::parse_rustfmt_config(),
-        packages: env::args().skip(1).collect(),
+    }
+}
+
+/// Parse the mode of operation from command line arguments.
+fn parse_mode(args: Vec<String>, workspace: &Path) -> Mode {
+    match args.split_first() {
+        Some((flag, rest)) if flag == "--fix-failing-tests" => {
+            Mode::FailingTests(rest.iter().map(PathBuf::from).collect())
+        }
+        Some((flag, rest)) if flag == "--files" => {
+            // Paths are relative to where `bazel run` was invoked from.
+            let working_dir = env::var("BUILD_WORKING_DIRECTORY")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| workspace.to_path_buf());
+            Mode::Files(
+                rest.iter()
+                    .map(|file| {
+                        let path = working_dir.join(file);
+                        path.strip_prefix(workspace)
+                            .map(Path::to_path_buf)
+                            .unwrap_or(path)
+                    })
+                    .collect(),
+            )
+        }
+        Some((flag, rest)) if flag == "--since" => match rest {
+            [rev] => Mode::Since(rev.clone()),
+            _ => {
+                eprintln!("`--since` requires exactly one git revision.");
+                std::process::exit(1);
+            }
+        },
+        _ => Mode::Query(args),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A scratch workspace which is removed when dropped.
+    struct Workspace(PathBuf);
+
+    impl Workspace {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let root = env::temp_dir().join(format!(
+                "target_aware_rustfmt_{}_{}",
+                name,
+                std::process::id()
+            ));
+            for file in files {
+                let path = root.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, "").unwrap();
+            }
+            Self(root)
+        }
+    }
+
+    impl Drop for Workspace {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_owning_package() {
+        let workspace = Workspace::new(
+            "owning_package",
+            &[
+                "BUILD.bazel",
+                "pkg/BUILD",
+                "pkg/src/lib.rs",
+                "pkg/sub/BUILD.bazel",
+                "pkg/sub/main.rs",
+                "root.rs",
+            ],
+        );
+
+        assert_eq!(
+            owning_package(&workspace.0, Path::new("pkg/src/lib.rs")),
+            Some(PathBuf::from("pkg"))
+        );
+        assert_eq!(
+            owning_package(&workspace.0, Path::new("pkg/sub/main.rs")),
+            Some(PathBuf::from("pkg/sub"))
+        );
+        assert_eq!(
+            owning_package(&workspace.0, Path::new("root.rs")),
+            Some(PathBuf::from(""))
+        );
+
+        let orphan = Workspace::new("owning_package_orphan", &["src/lib.rs"]);
+        assert_eq!(owning_package(&orphan.0, Path::new("src/lib.rs")), None);
+    }
+
+    #[test]
+    fn test_path_to_label() {
+        let workspace = Workspace::new(
+            "path_to_label",
+            &[
+                "BUILD.bazel",
+                "pkg/BUILD.bazel",
+                "pkg/src/lib.rs",
+                "root.rs",
+            ],
+        );
+
+        assert_eq!(
+            path_to_label(&workspace.0, Path::new("pkg/src/lib.rs")),
+            Some(("//pkg:*".to_owned(), "//pkg:src/lib.rs".to_owned()))
+        );
+        assert_eq!(
+            path_to_label(&workspace.0, Path::new("root.rs")),
+            Some(("//:*".to_owned(), "//:root.rs".to_owned()))
+        );
+        assert_eq!(label_to_path("//pkg:src/lib.rs"), "pkg/src/lib.rs");
+        assert_eq!(label_to_path("//:root.rs"), "root.rs");
+    }
+
+    #[test]
+    fn test_changed_file_labels() {
+        let workspace = Workspace::new(
+            "changed_file_labels",
+            &[
+                "a/BUILD.bazel",
+                "a/lib.rs",
+                "a/main.rs",
+                "a/README.md",
+                "b/BUILD.bazel",
+                "b/src/lib.rs",
+                "orphan/lib.rs",
+            ],
+        );
+
+        let changed = changed_file_labels(
+            &workspace.0,
+            &[
+                PathBuf::from("a/lib.rs"),
+                PathBuf::from("a/main.rs"),
+                PathBuf::from("a/README.md"),
+                PathBuf::from("a/deleted.rs"),
+                PathBuf::from("b/src/lib.rs"),
+                PathBuf::from("orphan/lib.rs"),
+            ],
+        );
+        assert_eq!(
+            changed,
+            ChangedFiles {
+                packages: BTreeSet::from(["//a:*".to_owned(), "//b:*".to_owned()]),
+                labels: BTreeSet::from([
+                    "//a:lib.rs".to_owned(),
+                    "//a:main.rs".to_owned(),
+                    "//b:src/lib.rs".to_owned(),
+                ]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rust_targets() {
+        let output = r#"<?xml version="1.1" encoding="UTF-8" standalone="no"?>
+<query version="2">
+    <rule class="rust_library" location="/ws/a/BUILD.bazel:3:13" name="//a:lib">
+        <string name="name" value="lib"/>
+        <list name="srcs">
+            <label value="//a:lib.rs"/>
+        </list>
+        <list name="tags">
+            <string value="a&amp;b"/>
+        </list>
+        <string name="edition" value="2021"/>
+        <rule-input name="//a:lib.rs"/>
+        <rule-input name="//b:b&amp;c"/>
+    </rule>
+    <rule class="rust_binary" location="/ws/a/BUILD.bazel:9:12" name="//a:bin">
+        <string name="edition" value=""/>
+        <rule-input name="//a:main.rs"/>
+    </rule>
+</query>"#;
+
+        assert_eq!(
+            parse_rust_targets(&strings(&output.lines().collect::<Vec<_>>())),
+            vec![
+                RustTarget {
+                    label: "//a:lib".to_owned(),
+                    edition: "2021".to_owned(),
+                    inputs: strings(&["//a:lib.rs", "//b:b&c"]),
+                },
+                RustTarget {
+                    label: "//a:bin".to_owned(),
+                    edition: String::new(),
+                    inputs: strings(&["//a:main.rs"]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changed_sources_by_edition() {
+        let targets = vec![
+            RustTarget {
+                label: "//a:lib".to_owned(),
+                edition: "2021".to_owned(),
+                inputs: strings(&["//a:lib.rs", "//a:other.rs", "//a:shared.rs"]),
+            },
+            RustTarget {
+                label: "//a:bin".to_owned(),
+                edition: String::new(),
+                inputs: strings(&["//a:main.rs", "//a:shared.rs"]),
+            },
+        ];
+        let labels = BTreeSet::from([
+            "//a:lib.rs".to_owned(),
+            "//a:main.rs".to_owned(),
+            "//a:shared.rs".to_owned(),
+        ]);
+
+        assert_eq!(
+            changed_sources_by_edition(&targets, &labels, "2018"),
+            HashMap::from([
+                ("2021".to_owned(), strings(&["a/lib.rs", "a/shared.rs"])),
+                ("2018".to_owned(), strings(&["a/main.rs", "a/shared.rs"])),
+            ])
+        );
     }
 }
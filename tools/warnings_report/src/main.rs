@@ -0,0 +1,542 @@
+//! Deduplicate the warnings recorded by the process wrapper across a build
+//! and check them against per-package budgets and a baseline.
+//!
+//! Records are produced with `--@rules_rust//rust/settings:experimental_collect_warnings`
+//! and collected from the `rustc_warnings` output group, e.g.:
+//!
+//! ```text
+//! bazel build //... \
+//!     --@rules_rust//rust/settings:experimental_collect_warnings \
+//!     --output_groups=+rustc_warnings
+//! bazel run @rules_rust//tools/warnings_report -- \
+//!     --baseline=$PWD/warnings_baseline.json $(bazel info bazel-bin)
+//! ```
+//!
+//! A warning reported by several configurations of a crate, such as the exec
+//! and target configurations, is only counted once. The build fails when a
+//! package exceeds its budget or when warnings appear which are not in the
+//! baseline. Baselines match warnings by package, lint code, file and
+//! message, ignoring line numbers, so that unrelated edits do not invalidate
+//! them. Regenerating the baseline with `--write-baseline` after fixing
+//! warnings ratchets the number of allowed warnings down.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+
+/// The suffix of warnings files written by the process wrapper.
+const WARNINGS_SUFFIX: &str = ".warnings.json";
+
+#[derive(Parser)]
+#[command(
+    name = "warnings_report",
+    about = "Deduplicate the rustc warnings of a build and enforce budgets and baselines"
+)]
+struct Args {
+    /// Warnings files, or directories to search for `*.warnings.json` files.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// A baseline of known warnings, as written by `--write-baseline`. Warnings
+    /// which are not part of the baseline fail the check.
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Write the current warnings as a baseline to this path instead of checking them.
+    #[arg(long)]
+    write_baseline: Option<PathBuf>,
+
+    /// A json object mapping packages (e.g. `//foo/bar` or `@repo//baz`) to the
+    /// maximum number of distinct warnings allowed in them.
+    #[arg(long)]
+    budgets: Option<PathBuf>,
+
+    /// The maximum number of distinct warnings allowed in packages without a budget.
+    #[arg(long)]
+    default_budget: Option<usize>,
+}
+
+/// A warning recorded by the process wrapper.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Record {
+    label: Option<String>,
+    code: Option<String>,
+    message: String,
+    file: Option<String>,
+    line: Option<u64>,
+    column: Option<u64>,
+    rendered: Option<String>,
+}
+
+/// A warning reported by one or more targets of the build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Warning {
+    package: String,
+    labels: BTreeSet<String>,
+    code: Option<String>,
+    message: String,
+    file: Option<String>,
+    line: Option<u64>,
+    column: Option<u64>,
+    rendered: Option<String>,
+}
+
+impl Warning {
+    fn baseline_key(&self) -> BaselineKey {
+        BaselineKey {
+            package: self.package.clone(),
+            code: self.code.clone(),
+            file: self.file.clone(),
+            message: self.message.clone(),
+        }
+    }
+
+    fn location(&self) -> String {
+        match (&self.file, self.line, self.column) {
+            (Some(file), Some(line), Some(column)) => format!("{file}:{line}:{column}"),
+            (Some(file), _, _) => file.clone(),
+            _ => "<unknown>".to_owned(),
+        }
+    }
+}
+
+/// What identifies a warning in a baseline.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+struct BaselineKey {
+    package: String,
+    code: Option<String>,
+    file: Option<String>,
+    message: String,
+}
+
+/// The number of occurrences of a warning in a baseline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct BaselineEntry {
+    #[serde(flatten)]
+    key: BaselineKey,
+    count: usize,
+}
+
+/// The package of a label, with the main repository spelled `//`.
+fn package_of(label: &str) -> String {
+    let label = label
+        .strip_prefix("@@//")
+        .or_else(|| label.strip_prefix("@//"))
+        .map_or_else(|| label.to_owned(), |rest| format!("//{rest}"));
+    match label.rsplit_once(':') {
+        Some((package, _)) => package.to_owned(),
+        None => label,
+    }
+}
+
+/// Recursively collect the warning records under `path`. Returns the number
+/// of warnings files read.
+fn collect_records(path: &Path, records: &mut Vec<Record>) -> Result<usize> {
+    let metadata =
+        fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if metadata.is_dir() {
+        let mut entries = fs::read_dir(path)
+            .with_context(|| format!("Failed to read directory {}", path.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        let mut files = 0;
+        for entry in entries {
+            let is_warnings = entry
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(WARNINGS_SUFFIX));
+            if is_warnings || entry.is_dir() {
+                files += collect_records(&entry, records)?;
+            }
+        }
+        return Ok(files);
+    }
+
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(line).with_context(|| {
+            format!(
+                "Failed to parse warning at {}:{}",
+                path.display(),
+                index + 1
+            )
+        })?;
+        records.push(record);
+    }
+    Ok(1)
+}
+
+/// Merge records of the same warning, identified by its lint code, primary
+/// span and message. Warnings are attributed to the package of the first of
+/// the labels reporting them.
+fn deduplicate(records: Vec<Record>) -> Vec<Warning> {
+    type Key = (
+        Option<String>,
+        Option<String>,
+        Option<u64>,
+        Option<u64>,
+        String,
+    );
+    let mut warnings: BTreeMap<Key, Warning> = BTreeMap::new();
+    for record in records {
+        let key = (
+            record.code.clone(),
+            record.file.clone(),
+            record.line,
+            record.column,
+            record.message.clone(),
+        );
+        let warning = warnings.entry(key).or_insert_with(|| Warning {
+            package: String::new(),
+            labels: BTreeSet::new(),
+            code: record.code,
+            message: record.message,
+            file: record.file,
+            line: record.line,
+            column: record.column,
+            rendered: record.rendered,
+        });
+        warning
+            .labels
+            .insert(record.label.unwrap_or_else(|| "<unknown>".to_owned()));
+    }
+
+    let mut warnings: Vec<Warning> = warnings
+        .into_values()
+        .map(|mut warning| {
+            let first = warning.labels.iter().next().expect("at least one label");
+            warning.package = package_of(first);
+            warning
+        })
+        .collect();
+    warnings.sort_by(|a, b| {
+        (&a.package, &a.file, a.line, a.column).cmp(&(&b.package, &b.file, b.line, b.column))
+    });
+    warnings
+}
+
+/// Summarize `warnings` as a baseline.
+fn make_baseline(warnings: &[Warning]) -> Vec<BaselineEntry> {
+    let mut counts: BTreeMap<BaselineKey, usize> = BTreeMap::new();
+    for warning in warnings {
+        *counts.entry(warning.baseline_key()).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(key, count)| BaselineEntry { key, count })
+        .collect()
+}
+
+/// The warnings which exceed the occurrences recorded in `baseline`, and the
+/// number of baseline occurrences which no longer appear.
+fn new_warnings<'a>(
+    warnings: &'a [Warning],
+    baseline: &[BaselineEntry],
+) -> (Vec<&'a Warning>, usize) {
+    let mut allowed: BTreeMap<BaselineKey, usize> = baseline
+        .iter()
+        .map(|entry| (entry.key.clone(), entry.count))
+        .collect();
+    let mut new = Vec::new();
+    for warning in warnings {
+        match allowed.get_mut(&warning.baseline_key()) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => new.push(warning),
+        }
+    }
+    (new, allowed.values().sum())
+}
+
+/// The packages whose number of warnings exceeds their budget, along with
+/// the number of warnings and the budget.
+fn over_budget(
+    warnings: &[Warning],
+    budgets: &BTreeMap<String, usize>,
+    default_budget: Option<usize>,
+) -> Vec<(String, usize, usize)> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for warning in warnings {
+        *counts.entry(&warning.package).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .filter_map(|(package, count)| {
+            let budget = budgets.get(package).copied().or(default_budget)?;
+            (count > budget).then(|| (package.to_owned(), count, budget))
+        })
+        .collect()
+}
+
+/// Render a table of the number of warnings per package.
+fn format_summary(warnings: &[Warning], total_records: usize) -> String {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for warning in warnings {
+        *counts.entry(&warning.package).or_default() += 1;
+    }
+
+    let mut summary = String::new();
+    writeln!(summary, "{:>8}  Package", "Warnings").unwrap();
+    for (package, count) in &counts {
+        writeln!(summary, "{count:>8}  {package}").unwrap();
+    }
+    writeln!(
+        summary,
+        "\n{} distinct warnings in {} packages ({} reported)",
+        warnings.len(),
+        counts.len(),
+        total_records,
+    )
+    .unwrap();
+    summary
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+
+    let mut records = Vec::new();
+    let mut files = 0;
+    for input in &args.inputs {
+        files += collect_records(input, &mut records)?;
+    }
+    if files == 0 {
+        bail!(
+            "No warnings files were found. Build with \
+            `--@rules_rust//rust/settings:experimental_collect_warnings` and \
+            `--output_groups=+rustc_warnings` to produce them."
+        );
+    }
+    let total_records = records.len();
+    let warnings = deduplicate(records);
+
+    if let Some(path) = &args.write_baseline {
+        let content = serde_json::to_string_pretty(&make_baseline(&warnings))?;
+        fs::write(path, content + "\n")
+            .with_context(|| format!("Failed to write baseline to {}", path.display()))?;
+        println!(
+            "Wrote a baseline of {} warnings to {}",
+            warnings.len(),
+            path.display()
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    print!("{}", format_summary(&warnings, total_records));
+    let mut failed = false;
+
+    if let Some(path) = &args.baseline {
+        let baseline: Vec<BaselineEntry> = read_json(path)?;
+        let (new, fixed) = new_warnings(&warnings, &baseline);
+        if !new.is_empty() {
+            failed = true;
+            println!("\n{} warnings are not in the baseline:", new.len());
+            for warning in new {
+                let labels: Vec<&str> = warning.labels.iter().map(String::as_str).collect();
+                println!("\n{} ({})", warning.location(), labels.join(", "));
+                match &warning.rendered {
+                    Some(rendered) => print!("{rendered}"),
+                    None => println!("warning: {}", warning.message),
+                }
+            }
+        }
+        if fixed > 0 {
+            println!(
+                "\n{fixed} warnings of the baseline were fixed. Update it with --write-baseline \
+                 to prevent them from coming back."
+            );
+        }
+    }
+
+    if args.budgets.is_some() || args.default_budget.is_some() {
+        let budgets: BTreeMap<String, usize> = match &args.budgets {
+            Some(path) => read_json(path)?,
+            None => BTreeMap::new(),
+        };
+        let exceeded = over_budget(&warnings, &budgets, args.default_budget);
+        if !exceeded.is_empty() {
+            failed = true;
+            println!("\nPackages over their warning budget:");
+            for (package, count, budget) in exceeded {
+                println!("  {package}: {count} warnings, budget {budget}");
+            }
+        }
+    }
+
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(label: &str, code: &str, file: &str, line: u64, message: &str) -> Record {
+        Record {
+            label: Some(label.to_owned()),
+            code: Some(code.to_owned()),
+            message: message.to_owned(),
+            file: Some(file.to_owned()),
+            line: Some(line),
+            column: Some(1),
+            rendered: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_record() {
+        let record: Record = serde_json::from_str(
+            r#"{"label":"//pkg:lib","code":null,"message":"unused","file":"pkg/lib.rs",
+                "line":3,"column":5,"rendered":"warning: unused\n"}"#,
+        )
+        .unwrap();
+        assert_eq!(record.label.as_deref(), Some("//pkg:lib"));
+        assert_eq!(record.code, None);
+        assert_eq!(record.line, Some(3));
+    }
+
+    #[test]
+    fn test_package_of() {
+        assert_eq!(package_of("//foo/bar:lib"), "//foo/bar");
+        assert_eq!(package_of("@@//foo:lib"), "//foo");
+        assert_eq!(package_of("@//:lib"), "//");
+        assert_eq!(
+            package_of("@@rules_rust~~crate~crates__foo-1.0.0//:foo"),
+            "@@rules_rust~~crate~crates__foo-1.0.0//"
+        );
+    }
+
+    #[test]
+    fn test_deduplicate() {
+        let warnings = deduplicate(vec![
+            record(
+                "@crates__foo//:foo",
+                "dead_code",
+                "external/crates__foo/src/lib.rs",
+                4,
+                "unused",
+            ),
+            record(
+                "//app:bin",
+                "unused_imports",
+                "app/main.rs",
+                1,
+                "unused import",
+            ),
+            // The exec configuration reports the same warning again.
+            record(
+                "@crates__foo//:foo",
+                "dead_code",
+                "external/crates__foo/src/lib.rs",
+                4,
+                "unused",
+            ),
+            record(
+                "@crates__foo//:foo",
+                "dead_code",
+                "external/crates__foo/src/lib.rs",
+                9,
+                "unused",
+            ),
+        ]);
+        let summary: Vec<(&str, Option<u64>, usize)> = warnings
+            .iter()
+            .map(|w| (w.package.as_str(), w.line, w.labels.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("//app", Some(1), 1),
+                ("@crates__foo//", Some(4), 1),
+                ("@crates__foo//", Some(9), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_baseline() {
+        let baseline = make_baseline(&deduplicate(vec![
+            record("//a:lib", "dead_code", "a/lib.rs", 4, "unused"),
+            record("//a:lib", "dead_code", "a/lib.rs", 9, "unused"),
+            record("//b:lib", "unused_mut", "b/lib.rs", 2, "unused mut"),
+        ]));
+        assert_eq!(baseline.len(), 2);
+        assert_eq!(baseline[0].count, 2);
+
+        // Lines moved, one warning was fixed and a new one appeared.
+        let warnings = deduplicate(vec![
+            record("//a:lib", "dead_code", "a/lib.rs", 5, "unused"),
+            record("//a:lib", "dead_code", "a/lib.rs", 10, "unused"),
+            record("//a:lib", "dead_code", "a/lib.rs", 20, "unused"),
+        ]);
+        let (new, fixed) = new_warnings(&warnings, &baseline);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].line, Some(20));
+        assert_eq!(fixed, 1);
+
+        let json = serde_json::to_value(&baseline[1]).unwrap();
+        assert_eq!(json["package"], "//b");
+        assert_eq!(json["code"], "unused_mut");
+        assert_eq!(json["count"], 1);
+        let parsed: BaselineEntry = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, baseline[1]);
+    }
+
+    #[test]
+    fn test_over_budget() {
+        let warnings = deduplicate(vec![
+            record("//a:lib", "dead_code", "a/lib.rs", 1, "unused"),
+            record("//a:lib", "dead_code", "a/lib.rs", 2, "unused"),
+            record("//b:lib", "dead_code", "b/lib.rs", 1, "unused"),
+            record("//c:lib", "dead_code", "c/lib.rs", 1, "unused"),
+        ]);
+        let budgets = BTreeMap::from([("//a".to_owned(), 1), ("//b".to_owned(), 1)]);
+        assert_eq!(
+            over_budget(&warnings, &budgets, None),
+            vec![("//a".to_owned(), 2, 1)]
+        );
+        assert_eq!(
+            over_budget(&warnings, &budgets, Some(0)),
+            vec![("//a".to_owned(), 2, 1), ("//c".to_owned(), 1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_format_summary() {
+        let warnings = deduplicate(vec![
+            record("//a:lib", "dead_code", "a/lib.rs", 1, "unused"),
+            record("//a:lib", "dead_code", "a/lib.rs", 1, "unused"),
+            record("//b:lib", "dead_code", "b/lib.rs", 1, "unused"),
+        ]);
+        let summary = format_summary(&warnings, 3);
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Warnings  Package",
+                "       1  //a",
+                "       1  //b",
+                "",
+                "2 distinct warnings in 2 packages (3 reported)",
+            ]
+        );
+    }
+}
@@ -1,9 +1,10 @@
-//! Utilities for parsing [Args](https://bazel.build/rules/lib/builtins/Args.html) param files.
+//! Utilities for parsing and writing [Args](https://bazel.build/rules/lib/builtins/Args.html) param files.
 
+use std::io;
 use std::path::Path;
 
 /// The format for an [Args param file[(https://bazel.build/rules/lib/builtins/Args.html#set_param_file_format).
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ActionArgsFormat {
     /// Each item (argument name or value) is written verbatim to the param
     /// file with a newline character following it.
@@ -28,20 +29,68 @@ impl Default for ActionArgsFormat {
 /// Parsed [`ctx.action.args`](https://bazel.build/rules/lib/builtins/Args.html) params.
 type ActionArgv = Vec<String>;
 
+/// Split the first line off `text`, as [str::lines] does.
+fn split_line(text: &str) -> (&str, &str) {
+    match text.split_once('\n') {
+        Some((line, rest)) => (line.strip_suffix('\r').unwrap_or(line), rest),
+        None => (text, ""),
+    }
+}
+
+/// Parse a single argument quoted by Bazel's shell escaping from the start of
+/// `text`, i.e. single quoted strings in which each `'` is written as `'\''`.
+/// The argument may span multiple lines. Returns the argument and the text
+/// following its line, or `None` if `text` does not start with such an argument.
+fn parse_shell_quoted(text: &str) -> Option<(String, &str)> {
+    let mut arg = String::new();
+    let mut rest = text;
+    loop {
+        rest = rest.strip_prefix('\'')?;
+        let end = rest.find('\'')?;
+        arg.push_str(&rest[..end]);
+        rest = &rest[end + 1..];
+        match rest.strip_prefix("\\'") {
+            Some(after) => {
+                arg.push('\'');
+                rest = after;
+            }
+            None => break,
+        }
+    }
+    match split_line(rest) {
+        ("", after) => Some((arg, after)),
+        _ => None,
+    }
+}
+
+fn parse_shell(text: &str) -> ActionArgv {
+    let mut args = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some((arg, after)) = parse_shell_quoted(rest) {
+            args.push(arg);
+            rest = after;
+            continue;
+        }
+        // Lines which are not quoted as a whole are taken verbatim.
+        let (line, after) = split_line(rest);
+        let arg = if line.len() > 1 && line.starts_with('\'') && line.ends_with('\'') {
+            &line[1..line.len() - 1]
+        } else {
+            line
+        };
+        args.push(arg.to_owned());
+        rest = after;
+    }
+    args
+}
+
 /// Parse an [Args](https://bazel.build/rules/lib/builtins/Args.html) param file string into an argv list.
 pub fn parse_args_with_fmt(text: String, fmt: ActionArgsFormat) -> ActionArgv {
-    text.lines()
-        .map(|s| match fmt {
-            ActionArgsFormat::Shell => {
-                if s.starts_with('\'') && s.ends_with('\'') {
-                    s[1..s.len() - 1].to_owned()
-                } else {
-                    s.to_owned()
-                }
-            }
-            _ => s.to_owned(),
-        })
-        .collect()
+    match fmt {
+        ActionArgsFormat::Shell => parse_shell(&text),
+        _ => text.lines().map(str::to_owned).collect(),
+    }
 }
 
 /// Parse an [Args](https://bazel.build/rules/lib/builtins/Args.html) param file string into an argv list.
@@ -64,6 +113,143 @@ pub fn try_parse_args(path: &Path) -> Result<ActionArgv, std::io::Error> {
     Ok(parse_args(text))
 }
 
+/// Quote `arg` for the [ActionArgsFormat::Shell] format, as Bazel does.
+fn shell_escape(arg: &str) -> String {
+    if arg.is_empty() {
+        return "''".to_owned();
+    }
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "@%-_+:,./".contains(c);
+    if arg.chars().all(is_safe) {
+        return arg.to_owned();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+fn unrepresentable(arg: &str, fmt: ActionArgsFormat, reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "Argument {:?} cannot be written in the {:?} format: {}",
+            arg, fmt, reason
+        ),
+    )
+}
+
+/// Check that `arg` can be written on a single line of a param file.
+fn check_line(arg: &str, fmt: ActionArgsFormat) -> Result<(), io::Error> {
+    if arg.contains('\n') {
+        return Err(unrepresentable(arg, fmt, "it contains a newline"));
+    }
+    if arg.ends_with('\r') {
+        return Err(unrepresentable(arg, fmt, "it ends with a carriage return"));
+    }
+    Ok(())
+}
+
+/// Write an argv list as an [Args](https://bazel.build/rules/lib/builtins/Args.html) param file string.
+///
+/// Arguments which cannot be parsed back unchanged are rejected: Only the
+/// [ActionArgsFormat::Shell] format can represent newlines in arguments, and the
+/// [ActionArgsFormat::FlagPerLine] format only takes flags, with their values
+/// already joined as `--flag=value` as Bazel writes them. Separate values are
+/// rejected since they would be parsed back as part of the flag.
+pub fn write_args_with_fmt<S: AsRef<str>>(
+    args: &[S],
+    fmt: ActionArgsFormat,
+) -> Result<String, io::Error> {
+    let mut text = String::new();
+    match fmt {
+        ActionArgsFormat::Multiline => {
+            for arg in args {
+                let arg = arg.as_ref();
+                check_line(arg, fmt)?;
+                text.push_str(arg);
+                text.push('\n');
+            }
+        }
+        ActionArgsFormat::Shell => {
+            for arg in args {
+                text.push_str(&shell_escape(arg.as_ref()));
+                text.push('\n');
+            }
+        }
+        ActionArgsFormat::FlagPerLine => {
+            for arg in args {
+                let arg = arg.as_ref();
+                check_line(arg, fmt)?;
+                if !arg.starts_with("--") {
+                    return Err(unrepresentable(
+                        arg,
+                        fmt,
+                        "it is not a flag (values must be joined as `--flag=value`)",
+                    ));
+                }
+                text.push_str(arg);
+                text.push('\n');
+            }
+        }
+    }
+    Ok(text)
+}
+
+/// Write an argv list as an [Args](https://bazel.build/rules/lib/builtins/Args.html) param file string.
+pub fn write_args<S: AsRef<str>>(args: &[S]) -> Result<String, io::Error> {
+    write_args_with_fmt(args, ActionArgsFormat::default())
+}
+
+/// Write an argv list to an [Args](https://bazel.build/rules/lib/builtins/Args.html) param file.
+pub fn try_write_args_with_fmt<S: AsRef<str>>(
+    path: &Path,
+    args: &[S],
+    fmt: ActionArgsFormat,
+) -> Result<(), io::Error> {
+    std::fs::write(path, write_args_with_fmt(args, fmt)?)
+}
+
+/// Write an argv list to an [Args](https://bazel.build/rules/lib/builtins/Args.html) param file.
+pub fn try_write_args<S: AsRef<str>>(path: &Path, args: &[S]) -> Result<(), io::Error> {
+    try_write_args_with_fmt(path, args, ActionArgsFormat::default())
+}
+
+/// Replace every `@path` argument with the arguments of the param file at
+/// `path`, as rustc and most tools spawned by Bazel do. Param files are not
+/// expanded recursively.
+pub fn expand_param_files_with_fmt<I, S>(
+    args: I,
+    fmt: ActionArgsFormat,
+) -> Result<ActionArgv, io::Error>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut expanded = Vec::new();
+    for arg in args {
+        let arg = arg.into();
+        match arg.strip_prefix('@') {
+            Some(param_file) => {
+                let params = try_parse_args_with_fmt(Path::new(param_file), fmt).map_err(|e| {
+                    io::Error::new(
+                        e.kind(),
+                        format!("Failed to read param file {}: {}", param_file, e),
+                    )
+                })?;
+                expanded.extend(params);
+            }
+            None => expanded.push(arg),
+        }
+    }
+    Ok(expanded)
+}
+
+/// Replace every `@path` argument with the arguments of the param file at `path`.
+pub fn expand_param_files<I, S>(args: I) -> Result<ActionArgv, io::Error>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    expand_param_files_with_fmt(args, ActionArgsFormat::default())
+}
+
 #[cfg(test)]
 mod test {
     use std::path::PathBuf;
@@ -122,4 +308,155 @@ mod test {
             args
         )
     }
+
+    #[test]
+    fn test_write_args() {
+        let args = ["foo", "", "--qux=quux", "it's", "a\nb", "@%-_+:,./"];
+
+        assert_eq!(
+            write_args(&args).unwrap(),
+            "foo\n''\n'--qux=quux'\n'it'\\''s'\n'a\nb'\n@%-_+:,./\n"
+        );
+        assert_eq!(
+            write_args_with_fmt(&["foo", "", "'baz'"], ActionArgsFormat::Multiline).unwrap(),
+            "foo\n\n'baz'\n"
+        );
+        assert_eq!(
+            write_args_with_fmt(
+                &["--foo=bar", "--baz", "--qux=a=b"],
+                ActionArgsFormat::FlagPerLine
+            )
+            .unwrap(),
+            "--foo=bar\n--baz\n--qux=a=b\n"
+        );
+    }
+
+    #[test]
+    fn test_write_unrepresentable_args() {
+        for fmt in [ActionArgsFormat::Multiline, ActionArgsFormat::FlagPerLine] {
+            for arg in ["--a\nb", "--a\r"] {
+                let error = write_args_with_fmt(&[arg], fmt).unwrap_err();
+                assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            }
+        }
+        for args in [
+            &["value"][..],
+            &["--flag", "value"][..],
+            &["--flag", "-v"][..],
+        ] {
+            let error = write_args_with_fmt(args, ActionArgsFormat::FlagPerLine).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_shell_quoted_multiline_args() {
+        let text = "'a\nb'\nfoo\n'it'\\''s\n'\\'''\n".to_owned();
+        assert_eq!(
+            vec!["a\nb", "foo", "it's\n'"],
+            parse_args_with_fmt(text, ActionArgsFormat::Shell)
+        );
+    }
+
+    /// A minimal xorshift generator, so round trip tests are reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        /// An argument favouring characters which are special to one of the formats.
+        fn arg(&mut self) -> String {
+            const SPECIAL: &[char] = &[
+                '\'', '\\', '"', '\n', '\r', ' ', '\t', '=', '-', '@', '$', '\0', 'é', '🦀',
+            ];
+            (0..self.below(12))
+                .map(|_| match self.below(3) {
+                    0 => SPECIAL[self.below(SPECIAL.len())],
+                    1 => (b'a' + self.below(26) as u8) as char,
+                    _ => loop {
+                        if let Some(c) = char::from_u32(self.below(0x11_0000) as u32) {
+                            break c;
+                        }
+                    },
+                })
+                .collect()
+        }
+
+        fn argv(&mut self, arg: impl Fn(&mut Self) -> String) -> Vec<String> {
+            (0..self.below(8)).map(|_| arg(self)).collect()
+        }
+    }
+
+    #[test]
+    fn test_shell_round_trip() {
+        let mut rng = Rng(0x5eed);
+        for _ in 0..2000 {
+            let args = rng.argv(Rng::arg);
+            let text = write_args(&args).unwrap();
+            assert_eq!(parse_args(text.clone()), args, "param file: {:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_multiline_round_trip() {
+        let mut rng = Rng(0x5eed);
+        for _ in 0..2000 {
+            let args = rng.argv(|rng| rng.arg().replace(['\n', '\r'], ""));
+            let text = write_args_with_fmt(&args, ActionArgsFormat::Multiline).unwrap();
+            assert_eq!(
+                parse_args_with_fmt(text.clone(), ActionArgsFormat::Multiline),
+                args,
+                "param file: {:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_flag_per_line_round_trip() {
+        let mut rng = Rng(0x5eed);
+        for _ in 0..2000 {
+            let args = rng.argv(|rng| {
+                let name = rng.arg().replace(['\n', '\r', '='], "");
+                match rng.below(2) {
+                    0 => format!("--{name}"),
+                    _ => format!("--{name}={}", rng.arg().replace(['\n', '\r'], "")),
+                }
+            });
+            let text = write_args_with_fmt(&args, ActionArgsFormat::FlagPerLine).unwrap();
+            assert_eq!(
+                parse_args_with_fmt(text.clone(), ActionArgsFormat::FlagPerLine),
+                args,
+                "param file: {:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand_param_files() {
+        let test_tempdir = PathBuf::from(std::env::var("TEST_TMPDIR").unwrap());
+        let param_file = test_tempdir.join("test_expand_param_files.params");
+        try_write_args(&param_file, &["--foo", "it's", ""]).unwrap();
+        let arg = format!("@{}", param_file.display());
+
+        assert_eq!(
+            vec!["bin", "--foo", "it's", "", "bar"],
+            expand_param_files(["bin", &arg, "bar"]).unwrap()
+        );
+
+        let missing = format!("@{}", test_tempdir.join("missing.params").display());
+        let error = expand_param_files([missing.as_str()]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(error.to_string().contains("missing.params"));
+    }
 }
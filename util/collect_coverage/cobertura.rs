@@ -0,0 +1,339 @@
+//! Conversion of lcov coverage reports to [Cobertura](https://cobertura.github.io/cobertura/) XML.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// The coverage data of a single line.
+#[derive(Debug, Default)]
+struct LineCoverage {
+    /// The number of times the line was executed.
+    hits: u64,
+
+    /// The number of branches on the line, and how many of them were taken.
+    branches: Option<(u64, u64)>,
+}
+
+/// The coverage data of a file, keyed by line number.
+type FileCoverage = BTreeMap<u64, LineCoverage>;
+
+/// Parse an lcov report into coverage data per file.
+fn parse_lcov(lcov: &str) -> BTreeMap<String, FileCoverage> {
+    let mut files: BTreeMap<String, FileCoverage> = BTreeMap::new();
+    let mut current: Option<String> = None;
+
+    for line in lcov.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some(pair) => pair,
+            None => {
+                if line == "end_of_record" {
+                    current = None;
+                }
+                continue;
+            }
+        };
+
+        if key == "SF" {
+            current = Some(value.to_owned());
+            files.entry(value.to_owned()).or_default();
+            continue;
+        }
+
+        let lines = match current.as_ref().and_then(|file| files.get_mut(file)) {
+            Some(lines) => lines,
+            None => continue,
+        };
+
+        let fields: Vec<&str> = value.split(',').collect();
+        match (key, fields.as_slice()) {
+            ("DA", [number, hits, ..]) => {
+                if let (Ok(number), Ok(hits)) = (number.parse(), hits.parse::<u64>()) {
+                    let entry: &mut LineCoverage = lines.entry(number).or_default();
+                    entry.hits = entry.hits.max(hits);
+                }
+            }
+            ("BRDA", [number, _block, _branch, taken]) => {
+                if let Ok(number) = number.parse() {
+                    let taken = taken.parse::<u64>().map(|n| n > 0).unwrap_or(false);
+                    let entry = lines.entry(number).or_default();
+                    let (total, covered) = entry.branches.unwrap_or((0, 0));
+                    entry.branches = Some((total + 1, covered + u64::from(taken)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    files
+}
+
+/// Render a ratio as Cobertura expects it, avoiding division by zero.
+fn rate(covered: u64, valid: u64) -> String {
+    if valid == 0 {
+        "1".to_owned()
+    } else {
+        format!("{:.4}", covered as f64 / valid as f64)
+    }
+}
+
+/// Escape a string for use in an XML attribute.
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Totals of covered and valid lines and branches.
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    lines_covered: u64,
+    lines_valid: u64,
+    branches_covered: u64,
+    branches_valid: u64,
+}
+
+impl Totals {
+    fn of(lines: &FileCoverage) -> Self {
+        let mut totals = Self::default();
+        for line in lines.values() {
+            totals.lines_valid += 1;
+            totals.lines_covered += u64::from(line.hits > 0);
+            if let Some((total, covered)) = line.branches {
+                totals.branches_valid += total;
+                totals.branches_covered += covered;
+            }
+        }
+        totals
+    }
+
+    fn add(&mut self, other: Self) {
+        self.lines_covered += other.lines_covered;
+        self.lines_valid += other.lines_valid;
+        self.branches_covered += other.branches_covered;
+        self.branches_valid += other.branches_valid;
+    }
+
+    fn attributes(&self) -> String {
+        format!(
+            r#"line-rate="{}" branch-rate="{}" complexity="0""#,
+            rate(self.lines_covered, self.lines_valid),
+            rate(self.branches_covered, self.branches_valid),
+        )
+    }
+}
+
+/// Convert an lcov report to a Cobertura XML report.
+///
+/// Files are grouped into packages by their parent directory.
+pub fn from_lcov(lcov: &str, timestamp: u64) -> String {
+    let files = parse_lcov(lcov);
+
+    let mut packages: BTreeMap<String, Vec<(&String, &FileCoverage)>> = BTreeMap::new();
+    for (file, lines) in &files {
+        let package = Path::new(file)
+            .parent()
+            .map(|parent| parent.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        packages.entry(package).or_default().push((file, lines));
+    }
+
+    let mut totals = Totals::default();
+    let mut body = String::new();
+    for (package, files) in &packages {
+        let package_totals = files.iter().fold(Totals::default(), |mut acc, (_, lines)| {
+            acc.add(Totals::of(lines));
+            acc
+        });
+        totals.add(package_totals);
+
+        writeln!(
+            body,
+            r#"    <package name="{}" {}>"#,
+            escape(package),
+            package_totals.attributes()
+        )
+        .unwrap();
+        body.push_str("      <classes>\n");
+        for (file, lines) in files {
+            writeln!(
+                body,
+                r#"        <class name="{name}" filename="{name}" {}>"#,
+                Totals::of(lines).attributes(),
+                name = escape(file),
+            )
+            .unwrap();
+            body.push_str("          <methods/>\n          <lines>\n");
+            for (number, line) in lines.iter() {
+                match line.branches {
+                    Some((total, covered)) => writeln!(
+                        body,
+                        r#"            <line number="{}" hits="{}" branch="true" condition-coverage="{}% ({}/{})"/>"#,
+                        number,
+                        line.hits,
+                        (covered * 100).checked_div(total).unwrap_or(100),
+                        covered,
+                        total,
+                    ),
+                    None => writeln!(
+                        body,
+                        r#"            <line number="{}" hits="{}" branch="false"/>"#,
+                        number, line.hits,
+                    ),
+                }
+                .unwrap();
+            }
+            body.push_str("          </lines>\n        </class>\n");
+        }
+        body.push_str("      </classes>\n    </package>\n");
+    }
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" ?>\n");
+    xml.push_str(
+        "<!DOCTYPE coverage SYSTEM \"http://cobertura.sourceforge.net/xml/coverage-04.dtd\">\n",
+    );
+    writeln!(
+        xml,
+        r#"<coverage lines-covered="{}" lines-valid="{}" branches-covered="{}" branches-valid="{}" {} version="1.9" timestamp="{}">"#,
+        totals.lines_covered,
+        totals.lines_valid,
+        totals.branches_covered,
+        totals.branches_valid,
+        totals.attributes(),
+        timestamp,
+    )
+    .unwrap();
+    xml.push_str("  <sources>\n    <source>.</source>\n  </sources>\n");
+    xml.push_str("  <packages>\n");
+    xml.push_str(&body);
+    xml.push_str("  </packages>\n</coverage>\n");
+    xml
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_lcov() {
+        let lcov = "\
+SF:src/lib.rs
+DA:1,3
+DA:2,0
+BRDA:1,0,0,2
+BRDA:1,0,1,-
+end_of_record
+";
+        assert_eq!(
+            from_lcov(lcov, 1730574875),
+            r#"<?xml version="1.0" ?>
+<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">
+<coverage lines-covered="1" lines-valid="2" branches-covered="1" branches-valid="2" line-rate="0.5000" branch-rate="0.5000" complexity="0" version="1.9" timestamp="1730574875">
+  <sources>
+    <source>.</source>
+  </sources>
+  <packages>
+    <package name="src" line-rate="0.5000" branch-rate="0.5000" complexity="0">
+      <classes>
+        <class name="src/lib.rs" filename="src/lib.rs" line-rate="0.5000" branch-rate="0.5000" complexity="0">
+          <methods/>
+          <lines>
+            <line number="1" hits="3" branch="true" condition-coverage="50% (1/2)"/>
+            <line number="2" hits="0" branch="false"/>
+          </lines>
+        </class>
+      </classes>
+    </package>
+  </packages>
+</coverage>
+"#
+        );
+    }
+
+    #[test]
+    fn test_rates() {
+        let lcov = "\
+SF:src/lib.rs
+DA:1,1
+DA:2,1
+DA:3,0
+BRDA:1,0,0,1
+BRDA:1,0,1,0
+BRDA:2,0,0,-
+BRDA:2,0,1,0
+end_of_record
+SF:src/empty.rs
+end_of_record
+";
+        let xml = from_lcov(lcov, 0);
+        assert!(xml.contains(
+            r#"lines-covered="2" lines-valid="3" branches-covered="1" branches-valid="4" line-rate="0.6667" branch-rate="0.2500""#
+        ));
+        assert!(xml.contains(
+            r#"<line number="1" hits="1" branch="true" condition-coverage="50% (1/2)"/>"#
+        ));
+        assert!(xml.contains(
+            r#"<line number="2" hits="1" branch="true" condition-coverage="0% (0/2)"/>"#
+        ));
+        // Files without lines or branches are fully covered.
+        assert!(xml.contains(
+            r#"<class name="src/empty.rs" filename="src/empty.rs" line-rate="1" branch-rate="1" complexity="0">"#
+        ));
+    }
+
+    #[test]
+    fn test_multiple_records() {
+        let lcov = "\
+SF:src/a.rs
+DA:1,0
+DA:2,2
+end_of_record
+SF:tests/b.rs
+DA:1,1
+end_of_record
+SF:src/a.rs
+DA:1,5
+DA:2,1
+end_of_record
+SF:src/c.rs
+DA:1,0
+end_of_record
+";
+        let files = parse_lcov(lcov);
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            ["src/a.rs", "src/c.rs", "tests/b.rs"]
+        );
+        // Records of the same file are merged, keeping the highest hit count.
+        let hits = files["src/a.rs"]
+            .iter()
+            .map(|(number, line)| (*number, line.hits))
+            .collect::<Vec<_>>();
+        assert_eq!(hits, [(1, 5), (2, 2)]);
+
+        let xml = from_lcov(lcov, 0);
+        assert!(xml
+            .contains(r#"<package name="src" line-rate="0.6667" branch-rate="1" complexity="0">"#));
+        assert!(xml.contains(
+            r#"<package name="tests" line-rate="1.0000" branch-rate="1" complexity="0">"#
+        ));
+        assert!(xml.contains(r#"lines-covered="3" lines-valid="4""#));
+        assert_eq!(xml.matches("<class ").count(), 3);
+    }
+
+    #[test]
+    fn test_escaping() {
+        let lcov = "\
+SF:src/<a&b>/\"quoted\".rs
+DA:1,1
+end_of_record
+";
+        let xml = from_lcov(lcov, 0);
+        assert!(xml.contains(r#"<package name="src/&lt;a&amp;b&gt;" "#));
+        assert!(xml.contains(
+            r#"<class name="src/&lt;a&amp;b&gt;/&quot;quoted&quot;.rs" filename="src/&lt;a&amp;b&gt;/&quot;quoted&quot;.rs" "#
+        ));
+    }
+}
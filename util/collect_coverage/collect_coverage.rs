@@ -12,18 +12,38 @@
 //! - `COVERAGE_DIR``: Directory containing metadata files needed for coverage collection (e.g. gcda files, profraw).
 //! - `COVERAGE_OUTPUT_FILE`: The coverage action output path.
 //! - `ROOT`: Location from where the code coverage collection was invoked.
-//! - `RUNFILES_DIR`: Location of the test's runfiles.
+//! - `RUNFILES_DIR`: Location of the test's runfiles. Instrumented binaries found here, such
+//!   as binaries spawned by the test, are included in the report.
 //! - `VERBOSE_COVERAGE`: Print debug info from the coverage scripts
 //!
 //! The script looks in $COVERAGE_DIR for the Rust metadata coverage files
 //! (profraw) and uses lcov to get the coverage data. The coverage data
 //! is placed in $COVERAGE_DIR as a `coverage.dat` file.
+//!
+//! The report can be customized with the following environment variables, which
+//! can be set with `--test_env` or the `env` attribute of a test:
+//! - `RUST_COVERAGE_INCLUDE_FILENAME_REGEX`: `;` separated regexes. If set, only files
+//!   matching at least one of them are reported.
+//! - `RUST_COVERAGE_EXCLUDE_FILENAME_REGEX`: `;` separated regexes of files to omit.
+//! - `RUST_COVERAGE_KEEP_EXTERNAL`: Report third-party crates in `external/` which are
+//!   omitted by default.
+//! - `RUST_COVERAGE_FORMATS`: `,` separated additional reports to write to the undeclared
+//!   test outputs: `json` (an llvm-cov JSON summary) and `cobertura` (Cobertura XML).
+//! - `RUST_COVERAGE_BRANCH` / `RUST_COVERAGE_MCDC`: Include branch and MC/DC summaries if
+//!   supported by `llvm-cov`. Sources must be compiled with `-Zcoverage-options=branch`
+//!   or `-Zcoverage-options=mcdc` for this data to be present.
+
+mod cobertura;
+mod sections;
 
+use std::collections::BTreeSet;
 use std::env;
+use std::ffi::OsString;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 macro_rules! debug_log {
     ($($arg:tt)*) => {
@@ -85,6 +105,182 @@ fn find_test_binary(execroot: &Path, runfiles_dir: &Path) -> PathBuf {
     }
 }
 
+/// Names of the sections containing coverage mappings in instrumented ELF and
+/// Mach-O (`__llvm_covmap`) or PE/COFF (`.lcovmap`) binaries.
+const COVERAGE_MAPPING_SECTIONS: [&[u8]; 2] = [b"__llvm_covmap", b".lcovmap"];
+
+/// Determine whether a file is an executable or shared library with coverage instrumentation.
+fn is_instrumented_binary(path: &Path) -> bool {
+    let names = match fs::File::open(path).and_then(|mut file| sections::section_names(&mut file)) {
+        Ok(names) => names,
+        Err(_) => return false,
+    };
+
+    names
+        .iter()
+        .any(|name| COVERAGE_MAPPING_SECTIONS.contains(&name.as_slice()))
+}
+
+/// Recursively find all instrumented binaries within the runfiles of a test.
+///
+/// Tests may spawn other binaries (e.g. a server under test) whose profiles are merged
+/// into the same profdata, so their object files must be passed to `llvm-cov` as well.
+fn find_instrumented_binaries(
+    dir: &Path,
+    visited: &mut BTreeSet<PathBuf>,
+    binaries: &mut BTreeSet<PathBuf>,
+) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        // Runfiles are usually symlinks, so deduplicate by their real location.
+        let real_path = match fs::canonicalize(&path) {
+            Ok(real_path) => real_path,
+            Err(_) => continue,
+        };
+        if !visited.insert(real_path.clone()) {
+            continue;
+        }
+
+        if real_path.is_dir() {
+            find_instrumented_binaries(&path, visited, binaries);
+        } else if is_instrumented_binary(&real_path) {
+            binaries.insert(real_path);
+        }
+    }
+}
+
+/// Settings controlling the content and formats of coverage reports.
+#[derive(Debug)]
+struct ReportOptions {
+    /// Regexes of files to report. If empty, all files are reported.
+    include: Vec<String>,
+
+    /// Regexes of files to omit from the report.
+    exclude: Vec<String>,
+
+    /// Additional report formats to write.
+    formats: BTreeSet<String>,
+
+    /// Whether to include branch coverage summaries.
+    branch: bool,
+
+    /// Whether to include MC/DC coverage summaries.
+    mcdc: bool,
+}
+
+/// Whether a boolean variable is set to a truthy value.
+fn parse_flag(value: Option<String>) -> bool {
+    value.is_some_and(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
+/// Parse a list from a variable, ignoring empty entries.
+fn parse_list(value: Option<String>, separator: char) -> Vec<String> {
+    value
+        .map(|value| {
+            value
+                .split(separator)
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl ReportOptions {
+    fn from_env() -> Self {
+        let options = Self::from_vars(|name| env::var(name).ok());
+        debug_log!("Report options: {:#?}", options);
+        options
+    }
+
+    /// Read the options from the variables returned by `var`.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let mut exclude = vec!["/tmp/.+".to_owned()];
+        if !parse_flag(var("RUST_COVERAGE_KEEP_EXTERNAL")) {
+            exclude.push(".*external/.+".to_owned());
+        }
+        exclude.extend(parse_list(var("RUST_COVERAGE_EXCLUDE_FILENAME_REGEX"), ';'));
+
+        Self {
+            include: parse_list(var("RUST_COVERAGE_INCLUDE_FILENAME_REGEX"), ';'),
+            exclude,
+            formats: parse_list(var("RUST_COVERAGE_FORMATS"), ',')
+                .into_iter()
+                .collect(),
+            branch: parse_flag(var("RUST_COVERAGE_BRANCH")),
+            mcdc: parse_flag(var("RUST_COVERAGE_MCDC")),
+        }
+    }
+}
+
+/// Determine whether `llvm-cov export` supports a flag.
+fn llvm_cov_supports(llvm_cov: &Path, flag: &str) -> bool {
+    process::Command::new(llvm_cov)
+        .arg("export")
+        .arg("--help")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(flag))
+        .unwrap_or(false)
+}
+
+/// Run `llvm-cov export` with the given format, returning the report.
+fn llvm_cov_export(llvm_cov: &Path, format: &str, args: &[OsString]) -> String {
+    let mut llvm_cov_cmd = process::Command::new(llvm_cov);
+    llvm_cov_cmd
+        .arg("export")
+        .arg(format!("-format={}", format))
+        .args(args)
+        .stdout(process::Stdio::piped());
+
+    debug_log!("Spawning {:#?}", llvm_cov_cmd);
+    let child = llvm_cov_cmd
+        .spawn()
+        .expect("Failed to spawn llvm-cov process");
+
+    let output = child.wait_with_output().expect("llvm-cov process failed");
+
+    // Parse the child process's stdout to a string now that it's complete.
+    debug_log!("Parsing llvm-cov output");
+    String::from_utf8(output.stdout).expect("Failed to parse llvm-cov output")
+}
+
+/// Collect the source files listed in an lcov report.
+fn lcov_source_files(report: &str) -> BTreeSet<String> {
+    report
+        .lines()
+        .filter_map(|line| line.strip_prefix("SF:"))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Keep only the records of `files` in an lcov report.
+fn filter_lcov(report: &str, files: &BTreeSet<String>) -> String {
+    let mut filtered = String::new();
+    let mut record = String::new();
+    let mut keep = false;
+    for line in report.split_inclusive('\n') {
+        record.push_str(line);
+        let line = line.trim_end();
+        if let Some(file) = line.strip_prefix("SF:") {
+            keep = files.contains(file);
+        } else if line == "end_of_record" {
+            if keep {
+                filtered.push_str(&record);
+            }
+            record.clear();
+            keep = false;
+        }
+    }
+    filtered
+}
+
 fn main() {
     let coverage_dir = PathBuf::from(env::var("COVERAGE_DIR").unwrap());
     let execroot = PathBuf::from(env::var("ROOT").unwrap());
@@ -110,7 +306,7 @@ fn main() {
         &env::var("RUST_LLVM_PROFDATA").unwrap(),
     );
     let test_binary = find_test_binary(&execroot, &runfiles_dir);
-    let profraw_files: Vec<PathBuf> = fs::read_dir(coverage_dir)
+    let profraw_files: Vec<PathBuf> = fs::read_dir(&coverage_dir)
         .unwrap()
         .flatten()
         .filter_map(|entry| {
@@ -141,37 +337,111 @@ fn main() {
         process::exit(status.code().unwrap_or(1));
     }
 
-    let mut llvm_cov_cmd = process::Command::new(llvm_cov);
-    llvm_cov_cmd
-        .arg("export")
-        .arg("-format=lcov")
-        .arg("-instr-profile")
-        .arg(&profdata_file)
-        .arg("-ignore-filename-regex='.*external/.+'")
-        .arg("-ignore-filename-regex='/tmp/.+'")
-        .arg(format!("-path-equivalence=.,'{}'", execroot.display()))
-        .arg(test_binary)
-        .stdout(process::Stdio::piped());
+    let options = ReportOptions::from_env();
 
-    debug_log!("Spawning {:#?}", llvm_cov_cmd);
-    let child = llvm_cov_cmd
-        .spawn()
-        .expect("Failed to spawn llvm-cov process");
+    let mut args: Vec<OsString> = vec!["-instr-profile".into(), profdata_file.clone().into()];
+    args.extend(
+        options
+            .exclude
+            .iter()
+            .map(|regex| format!("-ignore-filename-regex={}", regex).into()),
+    );
+    args.push(format!("-path-equivalence=.,'{}'", execroot.display()).into());
+    for (enabled, flag) in [
+        (options.branch, "-show-branch-summary"),
+        (options.mcdc, "-show-mcdc-summary"),
+    ] {
+        if enabled {
+            if llvm_cov_supports(&llvm_cov, flag) {
+                args.push(flag.into());
+            } else {
+                debug_log!("{} is not supported by llvm-cov, skipping", flag);
+            }
+        }
+    }
+    // The test binary itself is passed first and all other binaries as additional objects.
+    let mut visited = BTreeSet::new();
+    if let Ok(real_test_binary) = fs::canonicalize(&test_binary) {
+        visited.insert(real_test_binary);
+    }
+    let mut binaries = BTreeSet::new();
+    find_instrumented_binaries(&runfiles_dir, &mut visited, &mut binaries);
+    debug_log!("Instrumented binaries in runfiles: {:#?}", binaries);
 
-    let output = child.wait_with_output().expect("llvm-cov process failed");
+    args.push(test_binary.into());
+    for binary in binaries {
+        args.push("-object".into());
+        args.push(binary.into());
+    }
 
-    // Parse the child process's stdout to a string now that it's complete.
-    debug_log!("Parsing llvm-cov output");
-    let report_str = std::str::from_utf8(&output.stdout).expect("Failed to parse llvm-cov output");
+    let mut lcov_report = llvm_cov_export(&llvm_cov, "lcov", &args);
 
-    debug_log!("Writing output to {}", coverage_output_file.display());
-    fs::write(
-        coverage_output_file,
-        report_str
+    // `llvm-cov` can only exclude files by regex, so the files matching the include regexes
+    // are those missing from a single additional export which also excludes them. The
+    // report is filtered to the included files, which are passed explicitly as sources to
+    // any further exports.
+    if !options.include.is_empty() {
+        let mut not_included_args = args.clone();
+        not_included_args.extend(
+            options
+                .include
+                .iter()
+                .map(|regex| format!("-ignore-filename-regex={}", regex).into()),
+        );
+        let not_included =
+            lcov_source_files(&llvm_cov_export(&llvm_cov, "lcov", &not_included_args));
+        let included: BTreeSet<String> = lcov_source_files(&lcov_report)
+            .difference(&not_included)
+            .cloned()
+            .collect();
+        debug_log!("Files matching include regexes: {:#?}", included);
+        lcov_report = filter_lcov(&lcov_report, &included);
+
+        if included.is_empty() {
+            // Passing no sources would report all files.
+            args.push("-ignore-filename-regex=.*".into());
+        } else {
+            args.extend(included.into_iter().map(OsString::from));
+        }
+    }
+
+    let sanitize = |report: String| {
+        report
             .replace("#/proc/self/cwd/", "")
-            .replace(&execroot.display().to_string(), ""),
-    )
-    .unwrap();
+            .replace(&execroot.display().to_string(), "")
+    };
+
+    let lcov_report = sanitize(lcov_report);
+
+    let reports_dir = env::var_os("TEST_UNDECLARED_OUTPUTS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| coverage_dir.clone());
+
+    if options.formats.contains("json") {
+        let mut json_args: Vec<OsString> = vec!["-summary-only".into()];
+        json_args.extend(args.iter().cloned());
+        let json_report = sanitize(llvm_cov_export(&llvm_cov, "text", &json_args));
+        let json_file = reports_dir.join("coverage.json");
+        debug_log!("Writing JSON summary to {}", json_file.display());
+        fs::write(json_file, json_report).unwrap();
+    }
+
+    if options.formats.contains("cobertura") {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or_default();
+        let cobertura_file = reports_dir.join("coverage.cobertura.xml");
+        debug_log!("Writing Cobertura report to {}", cobertura_file.display());
+        fs::write(
+            cobertura_file,
+            cobertura::from_lcov(&lcov_report, timestamp),
+        )
+        .unwrap();
+    }
+
+    debug_log!("Writing output to {}", coverage_output_file.display());
+    fs::write(coverage_output_file, lcov_report).unwrap();
 
     // Destroy the intermediate binary file so lcov_merger doesn't parse it twice.
     debug_log!("Cleaning up {}", profdata_file.display());
@@ -179,3 +449,76 @@ fn main() {
 
     debug_log!("Success!");
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    fn options(vars: &[(&str, &str)]) -> ReportOptions {
+        let vars: BTreeMap<&str, &str> = vars.iter().copied().collect();
+        ReportOptions::from_vars(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn test_default_report_options() {
+        let options = options(&[]);
+        assert!(options.include.is_empty());
+        assert_eq!(options.exclude, ["/tmp/.+", ".*external/.+"]);
+        assert!(options.formats.is_empty());
+        assert!(!options.branch);
+        assert!(!options.mcdc);
+    }
+
+    #[test]
+    fn test_report_options_from_vars() {
+        let options = options(&[
+            ("RUST_COVERAGE_INCLUDE_FILENAME_REGEX", "src/.+; ;lib/.+;"),
+            ("RUST_COVERAGE_EXCLUDE_FILENAME_REGEX", ".*_test.rs"),
+            ("RUST_COVERAGE_KEEP_EXTERNAL", "True"),
+            ("RUST_COVERAGE_FORMATS", "json, cobertura,json"),
+            ("RUST_COVERAGE_BRANCH", "1"),
+            ("RUST_COVERAGE_MCDC", "no"),
+        ]);
+        assert_eq!(options.include, ["src/.+", "lib/.+"]);
+        assert_eq!(options.exclude, ["/tmp/.+", ".*_test.rs"]);
+        assert_eq!(
+            options.formats,
+            BTreeSet::from(["cobertura".to_owned(), "json".to_owned()])
+        );
+        assert!(options.branch);
+        assert!(!options.mcdc);
+    }
+
+    #[test]
+    fn test_filter_lcov() {
+        let report = "\
+SF:src/lib.rs
+DA:1,3
+end_of_record
+SF:src/main.rs
+DA:1,0
+end_of_record
+SF:tests/test.rs
+DA:2,1
+end_of_record
+";
+        let all_files = lcov_source_files(report);
+        let not_included = lcov_source_files("SF:tests/test.rs\nDA:2,1\nend_of_record\n");
+        let included: BTreeSet<String> = all_files.difference(&not_included).cloned().collect();
+
+        assert_eq!(
+            filter_lcov(report, &included),
+            "\
+SF:src/lib.rs
+DA:1,3
+end_of_record
+SF:src/main.rs
+DA:1,0
+end_of_record
+"
+        );
+        assert_eq!(filter_lcov(report, &BTreeSet::new()), "");
+    }
+}
@@ -0,0 +1,398 @@
+//! Reading the section names of ELF, Mach-O and PE/COFF binaries.
+//!
+//! Only the headers, section tables and section name strings are read, so
+//! large binaries can be inspected without loading them into memory.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// The byte order of the fields of a binary.
+#[derive(Clone, Copy, Debug)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn bytes<const N: usize>(data: &[u8], offset: usize) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(
+            data.get(offset..offset + N)
+                .ok_or_else(|| invalid_data("field out of bounds"))?,
+        );
+        Ok(bytes)
+    }
+
+    fn u16(self, data: &[u8], offset: usize) -> io::Result<u16> {
+        let bytes = Self::bytes(data, offset)?;
+        Ok(match self {
+            Endian::Little => u16::from_le_bytes(bytes),
+            Endian::Big => u16::from_be_bytes(bytes),
+        })
+    }
+
+    fn u32(self, data: &[u8], offset: usize) -> io::Result<u32> {
+        let bytes = Self::bytes(data, offset)?;
+        Ok(match self {
+            Endian::Little => u32::from_le_bytes(bytes),
+            Endian::Big => u32::from_be_bytes(bytes),
+        })
+    }
+
+    fn u64(self, data: &[u8], offset: usize) -> io::Result<u64> {
+        let bytes = Self::bytes(data, offset)?;
+        Ok(match self {
+            Endian::Little => u64::from_le_bytes(bytes),
+            Endian::Big => u64::from_be_bytes(bytes),
+        })
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Read `len` bytes at `offset`, failing if the reader ends before that.
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::new();
+    reader.take(len).read_to_end(&mut data)?;
+    if (data.len() as u64) < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data)
+}
+
+/// The bytes of `data` up to the first NUL.
+fn c_str(data: &[u8]) -> &[u8] {
+    data.split(|b| *b == 0).next().unwrap_or_default()
+}
+
+/// The names of the sections of an ELF, Mach-O or PE/COFF binary.
+///
+/// Returns an empty list for other files, and an error for truncated or
+/// malformed binaries.
+pub(crate) fn section_names<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<Vec<u8>>> {
+    let magic = match read_at(reader, 0, 4) {
+        Ok(magic) => magic,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    match magic.as_slice() {
+        b"\x7fELF" => elf_section_names(reader),
+        [0xfe, 0xed, 0xfa, 0xce] => macho_section_names(reader, Endian::Big, false),
+        [0xfe, 0xed, 0xfa, 0xcf] => macho_section_names(reader, Endian::Big, true),
+        [0xce, 0xfa, 0xed, 0xfe] => macho_section_names(reader, Endian::Little, false),
+        [0xcf, 0xfa, 0xed, 0xfe] => macho_section_names(reader, Endian::Little, true),
+        [b'M', b'Z', ..] => pe_section_names(reader),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// The section index of an ELF binary indicating the real index is stored elsewhere.
+const SHN_XINDEX: u16 = 0xffff;
+
+fn elf_section_names<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<Vec<u8>>> {
+    let ident = read_at(reader, 0, 16)?;
+    let is_64 = match ident[4] {
+        1 => false,
+        2 => true,
+        _ => return Err(invalid_data("unknown ELF class")),
+    };
+    let endian = match ident[5] {
+        1 => Endian::Little,
+        2 => Endian::Big,
+        _ => return Err(invalid_data("unknown ELF byte order")),
+    };
+
+    let header = read_at(reader, 0, if is_64 { 64 } else { 52 })?;
+    let (shoff, shentsize, shnum, shstrndx) = if is_64 {
+        (
+            endian.u64(&header, 0x28)?,
+            endian.u16(&header, 0x3a)?,
+            endian.u16(&header, 0x3c)?,
+            endian.u16(&header, 0x3e)?,
+        )
+    } else {
+        (
+            u64::from(endian.u32(&header, 0x20)?),
+            endian.u16(&header, 0x2e)?,
+            endian.u16(&header, 0x30)?,
+            endian.u16(&header, 0x32)?,
+        )
+    };
+    if shoff == 0 {
+        return Ok(Vec::new());
+    }
+
+    // Binaries with many sections store the count and the index of the string
+    // table in the first section header.
+    let shentsize = u64::from(shentsize);
+    let first = ElfSection::parse(&read_at(reader, shoff, shentsize)?, endian, is_64)?;
+    let shnum = match shnum {
+        0 => first.size,
+        shnum => u64::from(shnum),
+    };
+    let shstrndx = match shstrndx {
+        SHN_XINDEX => u64::from(first.link),
+        shstrndx => u64::from(shstrndx),
+    };
+    if shentsize == 0 || shstrndx >= shnum {
+        return Err(invalid_data("ELF section name table out of bounds"));
+    }
+
+    let sections = read_at(reader, shoff, shnum * shentsize)?
+        .chunks_exact(shentsize as usize)
+        .map(|section| ElfSection::parse(section, endian, is_64))
+        .collect::<io::Result<Vec<_>>>()?;
+    let strtab = &sections[shstrndx as usize];
+    let strtab = read_at(reader, strtab.offset, strtab.size)?;
+
+    sections
+        .iter()
+        .map(|section| {
+            strtab
+                .get(section.name as usize..)
+                .map(|name| c_str(name).to_vec())
+                .ok_or_else(|| invalid_data("ELF section name out of bounds"))
+        })
+        .collect()
+}
+
+/// The fields of an ELF section header needed to read section names.
+struct ElfSection {
+    /// The offset of the name in the section name table.
+    name: u32,
+    offset: u64,
+    size: u64,
+    link: u32,
+}
+
+impl ElfSection {
+    fn parse(section: &[u8], endian: Endian, is_64: bool) -> io::Result<Self> {
+        Ok(if is_64 {
+            Self {
+                name: endian.u32(section, 0)?,
+                offset: endian.u64(section, 0x18)?,
+                size: endian.u64(section, 0x20)?,
+                link: endian.u32(section, 0x28)?,
+            }
+        } else {
+            Self {
+                name: endian.u32(section, 0)?,
+                offset: u64::from(endian.u32(section, 0x10)?),
+                size: u64::from(endian.u32(section, 0x14)?),
+                link: endian.u32(section, 0x18)?,
+            }
+        })
+    }
+}
+
+/// The load commands of Mach-O segments, which are followed by their sections.
+const LC_SEGMENT: u32 = 0x1;
+const LC_SEGMENT_64: u32 = 0x19;
+
+fn macho_section_names<R: Read + Seek>(
+    reader: &mut R,
+    endian: Endian,
+    is_64: bool,
+) -> io::Result<Vec<Vec<u8>>> {
+    let header_size = if is_64 { 32 } else { 28 };
+    let header = read_at(reader, 0, header_size)?;
+    let ncmds = endian.u32(&header, 16)?;
+    let sizeofcmds = endian.u32(&header, 20)?;
+    let commands = read_at(reader, header_size, u64::from(sizeofcmds))?;
+
+    let mut names = Vec::new();
+    let mut offset = 0;
+    for _ in 0..ncmds {
+        let cmd = endian.u32(&commands, offset)?;
+        let cmdsize = endian.u32(&commands, offset + 4)? as usize;
+        // The offset of `nsects` and the sizes of the segment and section structs.
+        let layout = match cmd {
+            LC_SEGMENT => Some((48, 56, 68)),
+            LC_SEGMENT_64 => Some((64, 72, 80)),
+            _ => None,
+        };
+        if let Some((nsects_offset, segment_size, section_size)) = layout {
+            let nsects = endian.u32(&commands, offset + nsects_offset)? as usize;
+            for index in 0..nsects {
+                let section = offset + segment_size + index * section_size;
+                let sectname = commands
+                    .get(section..section + 16)
+                    .ok_or_else(|| invalid_data("Mach-O section out of bounds"))?;
+                names.push(c_str(sectname).to_vec());
+            }
+        }
+        if cmdsize == 0 {
+            return Err(invalid_data("empty Mach-O load command"));
+        }
+        offset += cmdsize;
+    }
+    Ok(names)
+}
+
+fn pe_section_names<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<Vec<u8>>> {
+    let dos_header = read_at(reader, 0, 0x40)?;
+    let pe_offset = u64::from(Endian::Little.u32(&dos_header, 0x3c)?);
+    if read_at(reader, pe_offset, 4)? != b"PE\0\0" {
+        return Ok(Vec::new());
+    }
+
+    let coff_offset = pe_offset + 4;
+    let header = read_at(reader, coff_offset, 20)?;
+    let number_of_sections = Endian::Little.u16(&header, 2)?;
+    let symbol_table = Endian::Little.u32(&header, 8)?;
+    let number_of_symbols = Endian::Little.u32(&header, 12)?;
+    let optional_header_size = Endian::Little.u16(&header, 16)?;
+
+    let sections = read_at(
+        reader,
+        coff_offset + 20 + u64::from(optional_header_size),
+        u64::from(number_of_sections) * 40,
+    )?;
+    let mut names = Vec::new();
+    for section in sections.chunks_exact(40) {
+        let name = c_str(&section[..8]);
+        // Names longer than 8 bytes are stored as `/<offset>` into the string
+        // table, which follows the 18 byte symbol table entries.
+        let long_name_offset = name
+            .strip_prefix(b"/")
+            .and_then(|offset| std::str::from_utf8(offset).ok())
+            .and_then(|offset| offset.parse::<u64>().ok());
+        match long_name_offset {
+            Some(offset) if symbol_table != 0 => {
+                let string_table = u64::from(symbol_table) + u64::from(number_of_symbols) * 18;
+                reader.seek(SeekFrom::Start(string_table + offset))?;
+                let mut long_name = Vec::new();
+                reader.take(256).read_to_end(&mut long_name)?;
+                names.push(c_str(&long_name).to_vec());
+            }
+            _ => names.push(name.to_vec()),
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Cursor;
+
+    fn names(data: Vec<u8>) -> io::Result<Vec<String>> {
+        Ok(section_names(&mut Cursor::new(data))?
+            .into_iter()
+            .map(|name| String::from_utf8(name).unwrap())
+            .collect())
+    }
+
+    fn put(data: &mut Vec<u8>, offset: usize, bytes: &[u8]) {
+        if data.len() < offset + bytes.len() {
+            data.resize(offset + bytes.len(), 0);
+        }
+        data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// A little endian ELF64 binary with the given sections.
+    fn elf64(sections: &[&str]) -> Vec<u8> {
+        let mut strtab = vec![0];
+        let mut name_offsets = Vec::new();
+        for name in [".shstrtab"].iter().chain(sections) {
+            name_offsets.push(strtab.len() as u32);
+            strtab.extend_from_slice(name.as_bytes());
+            strtab.push(0);
+        }
+
+        let mut data = Vec::new();
+        put(&mut data, 0, b"\x7fELF\x02\x01\x01");
+        let strtab_offset = 64;
+        let shoff = strtab_offset + strtab.len();
+        put(&mut data, strtab_offset, &strtab);
+        put(&mut data, 0x28, &(shoff as u64).to_le_bytes());
+        put(&mut data, 0x3a, &64u16.to_le_bytes());
+        put(
+            &mut data,
+            0x3c,
+            &(name_offsets.len() as u16 + 1).to_le_bytes(),
+        );
+        put(&mut data, 0x3e, &1u16.to_le_bytes());
+        // The first section header is the null section.
+        put(&mut data, shoff, &[0; 64]);
+        for (index, name_offset) in name_offsets.iter().enumerate() {
+            let section = shoff + (index + 1) * 64;
+            put(&mut data, section, &name_offset.to_le_bytes());
+            put(
+                &mut data,
+                section + 0x18,
+                &(strtab_offset as u64).to_le_bytes(),
+            );
+            put(
+                &mut data,
+                section + 0x20,
+                &(strtab.len() as u64).to_le_bytes(),
+            );
+            put(&mut data, section + 0x38, &[0; 8]);
+        }
+        data
+    }
+
+    #[test]
+    fn test_elf_section_names() {
+        assert_eq!(
+            names(elf64(&[".text", "__llvm_covmap"])).unwrap(),
+            ["", ".shstrtab", ".text", "__llvm_covmap"]
+        );
+    }
+
+    #[test]
+    fn test_elf_truncated() {
+        let mut data = elf64(&[".text"]);
+        data.truncate(data.len() - 1);
+        assert!(names(data).is_err());
+    }
+
+    #[test]
+    fn test_macho_section_names() {
+        let mut data = Vec::new();
+        put(&mut data, 0, &[0xcf, 0xfa, 0xed, 0xfe]);
+        put(&mut data, 16, &2u32.to_le_bytes());
+        put(&mut data, 20, &(16 + 72 + 2 * 80u32).to_le_bytes());
+        // An unrelated load command.
+        put(&mut data, 32, &0x2u32.to_le_bytes());
+        put(&mut data, 36, &16u32.to_le_bytes());
+        // A segment with two sections.
+        let segment = 48;
+        put(&mut data, segment, &LC_SEGMENT_64.to_le_bytes());
+        put(&mut data, segment + 4, &(72 + 2 * 80u32).to_le_bytes());
+        put(&mut data, segment + 8, b"__LLVM_COV");
+        put(&mut data, segment + 64, &2u32.to_le_bytes());
+        put(&mut data, segment + 72, b"__llvm_covfun");
+        put(&mut data, segment + 72 + 80, b"__llvm_covmap");
+        put(&mut data, segment + 72 + 80 + 16, b"__LLVM_COV");
+        data.resize(32 + 16 + 72 + 2 * 80, 0);
+        assert_eq!(names(data).unwrap(), ["__llvm_covfun", "__llvm_covmap"]);
+    }
+
+    #[test]
+    fn test_pe_section_names() {
+        let mut data = Vec::new();
+        put(&mut data, 0, b"MZ");
+        let pe = 0x80;
+        put(&mut data, 0x3c, &(pe as u32).to_le_bytes());
+        put(&mut data, pe, b"PE\0\0");
+        put(&mut data, pe + 4 + 2, &2u16.to_le_bytes());
+        put(&mut data, pe + 4 + 16, &8u16.to_le_bytes());
+        let sections = pe + 4 + 20 + 8;
+        put(&mut data, sections, b".text");
+        put(&mut data, sections + 40, b".lcovmap");
+        data.resize(sections + 2 * 40, 0);
+        assert_eq!(names(data).unwrap(), [".text", ".lcovmap"]);
+    }
+
+    #[test]
+    fn test_other_files() {
+        assert!(names(b"#!/bin/sh\n".to_vec()).unwrap().is_empty());
+        assert!(names(b"MZ\0\0\0\0\0\0".to_vec()).is_err());
+        assert!(names(Vec::new()).unwrap().is_empty());
+    }
+}
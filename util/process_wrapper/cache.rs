@@ -0,0 +1,776 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A local, content addressed cache of rustc outputs.
+//!
+//! Results are looked up in two steps. The rustc version, the arguments and
+//! environment (with the execution root replaced by `${pwd}`) and the digests
+//! of the files named by the arguments form a base key. Each base key has
+//! manifests listing the source files rustc read according to its dep-info
+//! output, and the digests of those files complete the key of an entry:
+//!
+//! ```text
+//! <cache dir>/
+//!     manifests/<base key>/<digest of the source list>
+//!     entries/<entry key>/outputs/...
+//!     entries/<entry key>/stderr
+//!     entries/<entry key>/last_used
+//!     tmp/
+//! ```
+//!
+//! Entries are created and evicted by renaming directories, so that a
+//! concurrent lookup never observes a partial entry.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::digest::{file_digest, Sha256};
+use crate::options::Options;
+use crate::util::{crate_name, expand_param_files};
+
+/// Bumped whenever the layout of the cache or the computation of keys changes.
+const CACHE_VERSION: &str = "1";
+
+/// The size and modification time of a file.
+type Stamp = (u64, Option<SystemTime>);
+
+/// The local cache for a single rustc invocation.
+pub(crate) struct Cache {
+    root: PathBuf,
+    max_size: u64,
+    execroot: PathBuf,
+    base_key: String,
+    crate_name: Option<String>,
+    /// The `--out-dir` directories and the files they contained before rustc ran.
+    out_dirs: Vec<(PathBuf, HashMap<OsString, Stamp>)>,
+    /// The output files named explicitly by `--emit` and `-o`.
+    out_files: Vec<PathBuf>,
+}
+
+impl Cache {
+    /// Open the cache configured by `opts`. Returns `None` if caching is
+    /// disabled or the invocation cannot be cached.
+    pub(crate) fn open(opts: &Options) -> Result<Option<Self>, String> {
+        let execroot =
+            env::current_dir().map_err(|e| format!("failed to get current directory: {}", e))?;
+        Self::open_in(opts, execroot)
+    }
+
+    fn open_in(opts: &Options, execroot: PathBuf) -> Result<Option<Self>, String> {
+        let Some(root) = &opts.cache_dir else {
+            return Ok(None);
+        };
+        // Neither stdout nor profiles are part of an entry.
+        if opts.stdout_file.is_some() || opts.rustc_time_passes_file.is_some() {
+            return Ok(None);
+        }
+
+        let args = expand_param_files(&opts.child_arguments)?;
+        let (out_dirs, out_files) = output_locations(&args);
+        // Entries are shared between execution roots.
+        if out_dirs
+            .iter()
+            .chain(&out_files)
+            .any(|path| path.is_absolute())
+        {
+            return Ok(None);
+        }
+
+        let execroot_str = execroot.to_string_lossy().into_owned();
+        let normalize = |value: &str| value.replace(&execroot_str, "${pwd}");
+        let mut hasher = Sha256::new();
+        hasher.update_field(CACHE_VERSION.as_bytes());
+        hasher.update_field(rustc_version(opts)?.as_bytes());
+        hasher.update_field(&[opts.rustc_quit_on_rmeta as u8]);
+        hasher.update_field(normalize(&opts.executable).as_bytes());
+        hasher.update_field(&(args.len() as u64).to_le_bytes());
+        for arg in &args {
+            hasher.update_field(normalize(arg).as_bytes());
+        }
+        let mut environment: Vec<_> = opts.child_environment.iter().collect();
+        environment.sort();
+        hasher.update_field(&(environment.len() as u64).to_le_bytes());
+        for (key, value) in environment {
+            hasher.update_field(key.as_bytes());
+            hasher.update_field(normalize(value).as_bytes());
+        }
+        for path in referenced_files(&args, &out_files, &execroot) {
+            let digest = file_digest(&execroot.join(&path))
+                .map_err(|e| format!("failed to hash {}: {}", path.display(), e))?;
+            hasher.update_field(normalize(&path.to_string_lossy()).as_bytes());
+            hasher.update_field(digest.as_bytes());
+        }
+
+        let out_dirs = out_dirs
+            .into_iter()
+            .map(|dir| {
+                let contents = list_stamps(&execroot.join(&dir));
+                (dir, contents)
+            })
+            .collect();
+
+        Ok(Some(Self {
+            root: PathBuf::from(root),
+            max_size: opts.cache_max_size,
+            execroot,
+            base_key: hasher.finish(),
+            crate_name: crate_name(&args).map(str::to_owned),
+            out_dirs,
+            out_files,
+        }))
+    }
+
+    /// Restore the outputs of a previous invocation with the same inputs.
+    /// Returns the unprocessed stderr of that invocation on a hit.
+    pub(crate) fn lookup(&self) -> Option<Vec<u8>> {
+        let manifests = fs::read_dir(self.root.join("manifests").join(&self.base_key)).ok()?;
+        for manifest in manifests.filter_map(Result::ok) {
+            let Ok(content) = fs::read_to_string(manifest.path()) else {
+                continue;
+            };
+            let sources: Vec<String> = content.lines().map(str::to_owned).collect();
+            let Some(key) = self.entry_key(&sources) else {
+                continue;
+            };
+            let entry = self.root.join("entries").join(key);
+            if !entry.is_dir() {
+                continue;
+            }
+            match self.restore(&entry) {
+                Ok(raw_stderr) => return Some(raw_stderr),
+                Err(e) => debug_log!("failed to restore {}: {}", entry.display(), e),
+            }
+        }
+        None
+    }
+
+    /// Store the outputs of a successful invocation along with its
+    /// unprocessed stderr.
+    pub(crate) fn store(&self, raw_stderr: &[u8]) -> Result<(), String> {
+        let outputs = self.produced_outputs();
+        let mut sources = BTreeSet::new();
+        for output in &outputs {
+            if output.extension().is_some_and(|ext| ext == "d") {
+                let content = fs::read_to_string(self.execroot.join(output))
+                    .map_err(|e| format!("failed to read {}: {}", output.display(), e))?;
+                sources.extend(parse_dep_info(&content));
+            }
+        }
+        // Without dep-info, the sources of the crate are unknown.
+        if sources.is_empty() {
+            return Ok(());
+        }
+        let sources: Vec<String> = sources
+            .into_iter()
+            .filter(|source| !outputs.iter().any(|output| output == Path::new(source)))
+            .collect();
+        let Some(key) = self.entry_key(&sources) else {
+            return Ok(());
+        };
+
+        let error = |context: &Path, e: io::Error| format!("{}: {}", context.display(), e);
+        let tmp = self.tmp_path();
+        for output in &outputs {
+            copy_tree(
+                &self.execroot.join(output),
+                &tmp.join("outputs").join(output),
+            )
+            .map_err(|e| error(output, e))?;
+        }
+        fs::write(tmp.join("stderr"), raw_stderr).map_err(|e| error(&tmp, e))?;
+        fs::write(tmp.join("last_used"), "").map_err(|e| error(&tmp, e))?;
+        let entries = self.root.join("entries");
+        fs::create_dir_all(&entries).map_err(|e| error(&entries, e))?;
+        if fs::rename(&tmp, entries.join(&key)).is_err() {
+            // Another invocation stored the same entry concurrently.
+            let _ = fs::remove_dir_all(&tmp);
+        }
+
+        let mut manifest_content = sources.join("\n");
+        manifest_content.push('\n');
+        let mut manifest_hasher = Sha256::new();
+        manifest_hasher.update(manifest_content.as_bytes());
+        let manifests = self.root.join("manifests").join(&self.base_key);
+        fs::create_dir_all(&manifests).map_err(|e| error(&manifests, e))?;
+        let tmp = self.tmp_path();
+        fs::write(&tmp, manifest_content).map_err(|e| error(&tmp, e))?;
+        fs::rename(&tmp, manifests.join(manifest_hasher.finish())).map_err(|e| error(&tmp, e))?;
+
+        self.evict()
+            .map_err(|e| format!("failed to evict cache entries: {}", e))
+    }
+
+    /// The key of the entry for the current contents of `sources`, or `None`
+    /// if a source cannot be read.
+    fn entry_key(&self, sources: &[String]) -> Option<String> {
+        let mut hasher = Sha256::new();
+        hasher.update_field(self.base_key.as_bytes());
+        for source in sources {
+            hasher.update_field(source.as_bytes());
+            hasher.update_field(file_digest(&self.execroot.join(source)).ok()?.as_bytes());
+        }
+        Some(hasher.finish())
+    }
+
+    /// Copy the outputs of `entry` into the execution root.
+    fn restore(&self, entry: &Path) -> io::Result<Vec<u8>> {
+        let raw_stderr = fs::read(entry.join("stderr"))?;
+        let outputs = entry.join("outputs");
+        let mut files = Vec::new();
+        collect_files(&outputs, Path::new(""), &mut files)?;
+        for file in files {
+            let dest = self.execroot.join(&file);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // Existing outputs may be read-only.
+            let _ = fs::remove_file(&dest);
+            fs::copy(outputs.join(&file), &dest)?;
+        }
+        fs::write(entry.join("last_used"), "")?;
+        Ok(raw_stderr)
+    }
+
+    /// The outputs written by rustc, relative to the execution root.
+    fn produced_outputs(&self) -> Vec<PathBuf> {
+        let mut outputs = BTreeSet::new();
+        for (dir, before) in &self.out_dirs {
+            for (name, stamp) in list_stamps(&self.execroot.join(dir)) {
+                if self.is_crate_output(&name) && before.get(&name) != Some(&stamp) {
+                    outputs.insert(dir.join(name));
+                }
+            }
+        }
+        for file in &self.out_files {
+            if self.execroot.join(file).exists() {
+                outputs.insert(file.clone());
+            }
+        }
+        outputs.into_iter().collect()
+    }
+
+    /// Returns true if `name` in an output directory belongs to this crate,
+    /// as opposed to other crates sharing the directory or temporary files.
+    fn is_crate_output(&self, name: &OsString) -> bool {
+        let name = name.to_string_lossy();
+        if name.contains(".rcgu.") {
+            return false;
+        }
+        let Some(crate_name) = &self.crate_name else {
+            return true;
+        };
+        // Outputs are named `[lib]<crate name>[-<extra filename>][.<extension>]`.
+        let stem = name.split('.').next().unwrap_or_default();
+        let is_crate_stem = |stem: &str| {
+            stem.strip_prefix(crate_name.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+        };
+        is_crate_stem(stem) || stem.strip_prefix("lib").is_some_and(is_crate_stem)
+    }
+
+    /// A unique path in the temporary directory of the cache.
+    fn tmp_path(&self) -> PathBuf {
+        let tmp = self.root.join("tmp");
+        let _ = fs::create_dir_all(&tmp);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        tmp.join(format!("{}_{}", std::process::id(), nanos))
+    }
+
+    /// Evict the least recently used entries until the cache fits its size limit.
+    fn evict(&self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+        for entry in fs::read_dir(self.root.join("entries"))? {
+            let path = entry?.path();
+            let last_used = fs::metadata(path.join("last_used"))
+                .and_then(|m| m.modified())
+                .ok();
+            let size = tree_size(&path);
+            total += size;
+            entries.push((last_used, size, path));
+        }
+        if total <= self.max_size {
+            return Ok(());
+        }
+
+        entries.sort();
+        for (_, size, path) in entries {
+            if total <= self.max_size {
+                break;
+            }
+            let tmp = self.tmp_path();
+            if fs::rename(&path, &tmp).is_ok() {
+                let _ = fs::remove_dir_all(&tmp);
+                total -= size;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The verbose version of the compiler, which identifies its build.
+///
+/// Workers handle many requests with the same compiler, so the version is
+/// remembered for the resolved path and stamp of the executable.
+fn rustc_version(opts: &Options) -> Result<String, String> {
+    static VERSIONS: Mutex<BTreeMap<(PathBuf, Stamp), String>> = Mutex::new(BTreeMap::new());
+
+    let key = fs::canonicalize(&opts.executable).ok().and_then(|path| {
+        let metadata = fs::metadata(&path).ok()?;
+        Some((path, (metadata.len(), metadata.modified().ok())))
+    });
+    let versions = || VERSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(version) = key.as_ref().and_then(|key| versions().get(key).cloned()) {
+        return Ok(version);
+    }
+
+    let output = Command::new(&opts.executable)
+        .arg("-vV")
+        .env_clear()
+        .envs(&opts.child_environment)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("failed to run {} -vV: {}", opts.executable, e))?;
+    if !output.status.success() {
+        return Err(format!("{} -vV failed", opts.executable));
+    }
+    let version = String::from_utf8_lossy(&output.stdout).into_owned();
+    if let Some(key) = key {
+        versions().insert(key, version.clone());
+    }
+    Ok(version)
+}
+
+/// The output directories and files named by the rustc arguments `args`.
+fn output_locations(args: &[String]) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    let add_emit = |emit: &str, files: &mut Vec<PathBuf>| {
+        for kind in emit.split(',') {
+            match kind.split_once('=') {
+                Some((_, "-")) | None => {}
+                Some((_, path)) => files.push(PathBuf::from(path)),
+            }
+        }
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if let Some(dir) = arg.strip_prefix("--out-dir=") {
+            dirs.push(PathBuf::from(dir));
+        } else if let Some(emit) = arg.strip_prefix("--emit=") {
+            add_emit(emit, &mut files);
+        } else if arg == "--out-dir" || arg == "-o" || arg == "--emit" {
+            let Some(value) = iter.next() else {
+                continue;
+            };
+            match arg.as_str() {
+                "--out-dir" => dirs.push(PathBuf::from(value)),
+                "-o" => files.push(PathBuf::from(value)),
+                _ => add_emit(value, &mut files),
+            }
+        }
+    }
+    (dirs, files)
+}
+
+/// The existing files named by the rustc arguments `args`, such as `--extern`
+/// dependencies, linker inputs and native libraries, except for `outputs`.
+fn referenced_files(args: &[String], outputs: &[PathBuf], execroot: &Path) -> Vec<PathBuf> {
+    let mut files = BTreeSet::new();
+    let mut search_dirs = Vec::new();
+    let mut libs = Vec::new();
+    let add = |candidate: &str, files: &mut BTreeSet<PathBuf>| {
+        let path = PathBuf::from(candidate);
+        if !candidate.is_empty() && !outputs.contains(&path) && execroot.join(&path).is_file() {
+            files.insert(path);
+        }
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let (flag, value) = match arg.as_str() {
+            "-L" | "-l" => match iter.next() {
+                Some(value) => (arg.as_str(), value.as_str()),
+                None => continue,
+            },
+            _ if arg.starts_with("-L") || arg.starts_with("-l") => arg.split_at(2),
+            _ => ("", arg.as_str()),
+        };
+        // `[kind=]value`
+        let unqualified = value.rsplit('=').next().unwrap_or(value);
+        match flag {
+            "-L" => search_dirs.push(unqualified.to_owned()),
+            // `-l [kind[:modifiers]=]name[:rename]`
+            "-l" => libs.push(unqualified.split(':').next().unwrap_or_default().to_owned()),
+            _ => {
+                add(value, &mut files);
+                add(unqualified, &mut files);
+            }
+        }
+    }
+
+    for lib in &libs {
+        for dir in &search_dirs {
+            for name in [
+                format!("lib{}.a", lib),
+                format!("lib{}.so", lib),
+                format!("lib{}.dylib", lib),
+                format!("{}.lib", lib),
+            ]
+            .iter()
+            {
+                add(&format!("{}/{}", dir, name), &mut files);
+            }
+        }
+    }
+    files.into_iter().collect()
+}
+
+/// The prerequisites of the rules of a Makefile style dep-info file.
+fn parse_dep_info(content: &str) -> Vec<String> {
+    let mut sources = Vec::new();
+    for line in content.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let prerequisites = match line.find(": ") {
+            Some(index) => &line[index + 2..],
+            None if line.ends_with(':') => "",
+            None => continue,
+        };
+        let mut current = String::new();
+        let mut chars = prerequisites.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some(escaped) => current.push(escaped),
+                    None => current.push('\\'),
+                },
+                ' ' => {
+                    if !current.is_empty() {
+                        sources.push(std::mem::take(&mut current));
+                    }
+                }
+                _ => current.push(c),
+            }
+        }
+        if !current.is_empty() {
+            sources.push(current);
+        }
+    }
+    sources
+}
+
+/// The stamps of the entries of `dir`, which is empty if it does not exist.
+fn list_stamps(dir: &Path) -> HashMap<OsString, Stamp> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((
+                entry.file_name(),
+                (metadata.len(), metadata.modified().ok()),
+            ))
+        })
+        .collect()
+}
+
+/// Collect the files under `dir`, relative to `dir`.
+fn collect_files(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(dir, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Copy the file or directory `src` to `dest`.
+fn copy_tree(src: &Path, dest: &Path) -> io::Result<()> {
+    if src.is_dir() {
+        let mut files = Vec::new();
+        collect_files(src, Path::new(""), &mut files)?;
+        for file in files {
+            copy_tree(&src.join(&file), &dest.join(&file))?;
+        }
+        return Ok(());
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(src, dest).map(|_| ())
+}
+
+/// The total size of the files under `path`.
+fn tree_size(path: &Path) -> u64 {
+    let mut files = Vec::new();
+    if collect_files(path, Path::new(""), &mut files).is_err() {
+        return 0;
+    }
+    files
+        .iter()
+        .filter_map(|file| fs::metadata(path.join(file)).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::TempDir;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_output_locations() {
+        let (dirs, files) = output_locations(&strings(&[
+            "--out-dir=bazel-out/bin/pkg",
+            "--emit=dep-info,link=bazel-out/bin/pkg/foo,metadata=-",
+            "-o",
+            "bazel-out/bin/pkg/bar",
+        ]));
+        assert_eq!(dirs, vec![PathBuf::from("bazel-out/bin/pkg")]);
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("bazel-out/bin/pkg/foo"),
+                PathBuf::from("bazel-out/bin/pkg/bar"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_dep_info() {
+        assert_eq!(
+            parse_dep_info(
+                "out/libfoo.rmeta: src/lib.rs src/with\\ space.rs\n\
+                 \n\
+                 src/lib.rs:\n\
+                 src/with\\ space.rs:\n\
+                 \n\
+                 # env-dep:FOO=bar\n"
+            ),
+            strings(&["src/lib.rs", "src/with space.rs"])
+        );
+    }
+
+    #[test]
+    fn test_referenced_files() -> Result<(), String> {
+        let execroot = TempDir::create("process_wrapper_test").map_err(|e| e.to_string())?;
+        let execroot = execroot.path();
+        for file in ["libbar.rlib", "native/libz.a", "out/foo"].iter() {
+            let path = execroot.join(file);
+            fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+            fs::write(path, "").map_err(|e| e.to_string())?;
+        }
+
+        let files = referenced_files(
+            &strings(&[
+                "--extern=bar=libbar.rlib",
+                "-Lnative=native",
+                "-l",
+                "static=z",
+                "--emit=link=out/foo",
+                "--extern=missing=libmissing.rlib",
+            ]),
+            &[PathBuf::from("out/foo")],
+            execroot,
+        );
+        assert_eq!(
+            files,
+            vec![PathBuf::from("libbar.rlib"), PathBuf::from("native/libz.a")]
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_and_lookup() -> Result<(), String> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let to_string = |e: io::Error| e.to_string();
+        let scratch = TempDir::create("process_wrapper_test").map_err(to_string)?;
+        let execroot = scratch.path().join("execroot");
+        let cache_dir = scratch.path().join("cache");
+        fs::create_dir_all(execroot.join("src")).map_err(to_string)?;
+        fs::write(execroot.join("src/lib.rs"), "mod a;").map_err(to_string)?;
+        fs::write(execroot.join("src/a.rs"), "").map_err(to_string)?;
+        let rustc = scratch.path().join("rustc");
+        fs::write(&rustc, "#!/bin/sh\necho rustc 1.0.0\n").map_err(to_string)?;
+        fs::set_permissions(&rustc, fs::Permissions::from_mode(0o755)).map_err(to_string)?;
+
+        let opts = Options {
+            executable: rustc.to_string_lossy().into_owned(),
+            child_arguments: strings(&["src/lib.rs", "--crate-name=foo", "--out-dir=out"]),
+            child_environment: HashMap::new(),
+            touch_file: None,
+            copy_output: None,
+            stdout_file: None,
+            stderr_file: None,
+            output_file: None,
+            rustc_quit_on_rmeta: false,
+            rustc_output_format: None,
+            pipelining_key: None,
+            resource_usage_file: None,
+            resource_usage_label: None,
+            resource_usage_mnemonic: None,
+            rustc_time_passes_file: None,
+            check_reproducibility: false,
+            ice_bundle_dir: None,
+            retry_on_ice: false,
+            subst_mappings: Vec::new(),
+            env_file_vars: Vec::new(),
+            cache_dir: Some(cache_dir.to_string_lossy().into_owned()),
+            cache_max_size: 1024 * 1024,
+            diagnostic_path_mapping: None,
+            warnings_file: None,
+            warnings_label: None,
+            stamp_templates: Vec::new(),
+            workspace_status: Default::default(),
+        };
+        let open =
+            || Cache::open_in(&opts, execroot.clone())?.ok_or_else(|| "not cacheable".to_owned());
+
+        // Simulate a compilation.
+        let cache = open()?;
+        assert_eq!(cache.lookup(), None);
+        fs::create_dir_all(execroot.join("out")).map_err(to_string)?;
+        fs::write(execroot.join("out/libfoo.rlib"), "rlib").map_err(to_string)?;
+        fs::write(execroot.join("out/libother.rlib"), "other").map_err(to_string)?;
+        fs::write(
+            execroot.join("out/foo.d"),
+            "out/libfoo.rlib: src/lib.rs src/a.rs\n\nsrc/lib.rs:\nsrc/a.rs:\n",
+        )
+        .map_err(to_string)?;
+        cache.store(b"warning: unused\n")?;
+
+        // A hit restores the outputs of the crate.
+        fs::remove_dir_all(execroot.join("out")).map_err(to_string)?;
+        assert_eq!(open()?.lookup(), Some(b"warning: unused\n".to_vec()));
+        assert_eq!(
+            fs::read_to_string(execroot.join("out/libfoo.rlib")).map_err(to_string)?,
+            "rlib"
+        );
+        assert!(execroot.join("out/foo.d").is_file());
+        assert!(!execroot.join("out/libother.rlib").exists());
+
+        // Changing a source file which is only known from dep-info is a miss.
+        fs::write(execroot.join("src/a.rs"), "fn changed() {}").map_err(to_string)?;
+        assert_eq!(open()?.lookup(), None);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_evict() -> Result<(), String> {
+        let to_string = |e: io::Error| e.to_string();
+        let scratch = TempDir::create("process_wrapper_test").map_err(to_string)?;
+        let entries = scratch.path().join("entries");
+        for (name, age) in [("old", 20), ("new", 0), ("middle", 10)].iter() {
+            let entry = entries.join(name);
+            fs::create_dir_all(&entry).map_err(to_string)?;
+            fs::write(entry.join("stderr"), vec![0; 100]).map_err(to_string)?;
+            let last_used = fs::File::create(entry.join("last_used")).map_err(to_string)?;
+            last_used
+                .set_modified(SystemTime::now() - std::time::Duration::from_secs(*age))
+                .map_err(to_string)?;
+        }
+
+        let cache = Cache {
+            root: scratch.path().to_owned(),
+            max_size: 250,
+            execroot: scratch.path().to_owned(),
+            base_key: String::new(),
+            crate_name: None,
+            out_dirs: Vec::new(),
+            out_files: Vec::new(),
+        };
+        cache.evict().map_err(to_string)?;
+        assert!(!entries.join("old").exists());
+        assert!(entries.join("middle").exists());
+        assert!(entries.join("new").exists());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rustc_version_is_memoized() -> Result<(), String> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let to_string = |e: io::Error| e.to_string();
+        let scratch = TempDir::create("process_wrapper_test").map_err(to_string)?;
+        let calls = scratch.path().join("calls");
+        let rustc = scratch.path().join("rustc");
+        fs::write(
+            &rustc,
+            format!(
+                "#!/bin/sh\necho >> '{}'\necho rustc 1.0.0\n",
+                calls.display()
+            ),
+        )
+        .map_err(to_string)?;
+        fs::set_permissions(&rustc, fs::Permissions::from_mode(0o755)).map_err(to_string)?;
+
+        let opts = crate::options::options_from_args_with_pwd(
+            strings(&["process_wrapper", "--", &rustc.to_string_lossy()]),
+            "/",
+        )
+        .map_err(|e| e.to_string())?;
+        assert_eq!(rustc_version(&opts)?, "rustc 1.0.0\n");
+        assert_eq!(rustc_version(&opts)?, "rustc 1.0.0\n");
+        assert_eq!(fs::read_to_string(&calls).map_err(to_string)?, "\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_crate_output() {
+        let cache = Cache {
+            root: PathBuf::new(),
+            max_size: 0,
+            execroot: PathBuf::new(),
+            base_key: String::new(),
+            crate_name: Some("foo".to_owned()),
+            out_dirs: Vec::new(),
+            out_files: Vec::new(),
+        };
+        let is_crate_output = |name: &str| cache.is_crate_output(&OsString::from(name));
+        assert!(is_crate_output("libfoo-1234.rlib"));
+        assert!(is_crate_output("libfoo.rmeta"));
+        assert!(is_crate_output("foo-1234.d"));
+        assert!(is_crate_output("foo"));
+        assert!(is_crate_output("foo.exe"));
+        assert!(!is_crate_output("libfoo_bar-1234.rlib"));
+        assert!(!is_crate_output("foobar.d"));
+        assert!(!is_crate_output("libfoo-1234.foo.abc123-cgu.0.rcgu.o"));
+    }
+}
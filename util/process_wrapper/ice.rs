@@ -0,0 +1,651 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection and triage of rustc internal compiler errors (ICEs).
+//!
+//! When rustc panics, the process wrapper collects a bundle with everything
+//! needed to reproduce the crash outside of Bazel and can optionally retry
+//! the compilation once with incremental compilation disabled.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use action_args::ActionArgsFormat;
+use tinyjson::JsonValue;
+
+use crate::options::Options;
+use crate::util::{crate_name, expand_param_files, TempDir};
+
+/// The environment variable naming the directory rustc writes its
+/// `rustc-ice-*.txt` reports to.
+const RUSTC_ICE: &str = "RUSTC_ICE";
+
+/// The level of rustc JSON diagnostics reporting an ICE.
+const ICE_LEVEL: &str = "error: internal compiler error";
+
+/// Returns true if `line` of rustc's stderr reports an internal compiler error.
+pub(crate) fn is_ice_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('{') {
+        if let Ok(JsonValue::Object(map)) = trimmed.parse::<JsonValue>() {
+            return matches!(map.get("level"), Some(JsonValue::String(level)) if level == ICE_LEVEL);
+        }
+    }
+    trimmed.starts_with(ICE_LEVEL)
+        || trimmed.starts_with("thread 'rustc' panicked at")
+        || trimmed.starts_with("the compiler unexpectedly panicked")
+}
+
+/// Watches a rustc invocation for internal compiler errors.
+pub(crate) struct IceMonitor {
+    report_dir: TempDir,
+    detected: bool,
+    stderr: Vec<String>,
+}
+
+impl IceMonitor {
+    /// Create a monitor if `opts` requests any handling of ICEs.
+    pub(crate) fn new(opts: &Options) -> Result<Option<Self>, String> {
+        if opts.ice_bundle_dir.is_none() && !opts.retry_on_ice {
+            return Ok(None);
+        }
+        let report_dir = TempDir::create("process_wrapper_ice")
+            .map_err(|e| format!("failed to create ICE report directory: {}", e))?;
+        Ok(Some(Self {
+            report_dir,
+            detected: false,
+            stderr: Vec::new(),
+        }))
+    }
+
+    /// Point rustc at the report directory of the monitor unless the user
+    /// configured a location for ICE reports themselves.
+    pub(crate) fn configure(&self, opts: &Options, command: &mut Command) {
+        if !opts.child_environment.contains_key(RUSTC_ICE) {
+            command.env(RUSTC_ICE, self.report_dir.path());
+        }
+    }
+
+    /// Record a line, including its terminator, of the unprocessed stderr of rustc.
+    pub(crate) fn observe(&mut self, line: &str) {
+        self.detected |= is_ice_line(line);
+        self.stderr.push(line.to_owned());
+    }
+
+    /// Returns true if rustc reported an internal compiler error.
+    pub(crate) fn detected(&self) -> bool {
+        self.detected || !self.reports().is_empty()
+    }
+
+    /// The `rustc-ice-*.txt` reports written by rustc.
+    fn reports(&self) -> Vec<PathBuf> {
+        let mut reports: Vec<PathBuf> = fs::read_dir(self.report_dir.path())
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| {
+                        path.file_name()
+                            .and_then(|name| name.to_str())
+                            .is_some_and(|name| name.starts_with("rustc-ice-"))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        reports.sort();
+        reports
+    }
+}
+
+/// Undo the `--subst` mappings in `value`, replacing the longest values first
+/// so that nested paths map back to the most specific variable.
+fn reverse_subst(value: &str, subst_mappings: &[(String, String)]) -> String {
+    let mut mappings: Vec<&(String, String)> = subst_mappings
+        .iter()
+        .filter(|(_, v)| !v.is_empty())
+        .collect();
+    mappings.sort_by_key(|(_, v)| std::cmp::Reverse(v.len()));
+    let mut value = value.to_owned();
+    for (k, v) in mappings {
+        value = value.replace(v.as_str(), &format!("${{{}}}", k));
+    }
+    value
+}
+
+/// The path of the dep-info file rustc writes according to `args`, if any.
+///
+/// Without an explicit path, rustc writes `<crate name><extra filename>.d`
+/// to `--out-dir`.
+fn dep_info_path(args: &[String]) -> Option<PathBuf> {
+    let mut emit_dep_info = None;
+    let mut out_dir = None;
+    let mut extra_filename = String::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let arg = match arg.as_str() {
+            "--emit" | "--out-dir" | "-C" | "--codegen" => match iter.next() {
+                Some(value) => format!("{}={}", arg, value),
+                None => continue,
+            },
+            _ => arg.clone(),
+        };
+        if let Some(kinds) = arg.strip_prefix("--emit=") {
+            for kind in kinds.split(',') {
+                match kind.split_once('=') {
+                    Some(("dep-info", path)) => emit_dep_info = Some(Some(PathBuf::from(path))),
+                    None if kind == "dep-info" => emit_dep_info = Some(None),
+                    _ => {}
+                }
+            }
+        } else if let Some(dir) = arg.strip_prefix("--out-dir=") {
+            out_dir = Some(dir.to_owned());
+        } else if let Some(value) = arg
+            .strip_prefix("-C")
+            .or_else(|| arg.strip_prefix("--codegen="))
+            .map(|value| value.trim_start_matches('='))
+            .and_then(|value| value.strip_prefix("extra-filename="))
+        {
+            extra_filename = value.to_owned();
+        }
+    }
+    match emit_dep_info? {
+        Some(path) => Some(path),
+        None => {
+            Some(Path::new(&out_dir?).join(format!("{}{}.d", crate_name(args)?, extra_filename)))
+        }
+    }
+}
+
+/// The dependencies listed by the rules of a Makefile-style dep-info file, in
+/// order of first appearance.
+fn parse_dep_info(content: &str) -> Vec<String> {
+    let mut deps: Vec<String> = Vec::new();
+    for line in content.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        // The target of a rule ends at the first unescaped `: `.
+        let Some((_, rule_deps)) = line.split_once(": ").or_else(|| line.split_once(':')) else {
+            continue;
+        };
+        let mut dep = String::new();
+        let mut chars = rule_deps.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&' ') => dep.push(chars.next().unwrap_or(' ')),
+                ' ' => {
+                    if !dep.is_empty() && !deps.contains(&dep) {
+                        deps.push(dep.clone());
+                    }
+                    dep.clear();
+                }
+                _ => dep.push(c),
+            }
+        }
+        if !dep.is_empty() && !deps.contains(&dep) {
+            deps.push(dep);
+        }
+    }
+    deps
+}
+
+/// The source files read by rustc according to `args`: those listed by its
+/// dep-info file if rustc got far enough to write it, otherwise the crate root.
+fn source_files(args: &[String]) -> Vec<String> {
+    if let Some(content) = dep_info_path(args).and_then(|path| fs::read_to_string(path).ok()) {
+        let sources = parse_dep_info(&content);
+        if !sources.is_empty() {
+            return sources;
+        }
+    }
+    args.iter()
+        .filter(|arg| !arg.starts_with('-') && arg.ends_with(".rs"))
+        .cloned()
+        .collect()
+}
+
+/// The `--extern` dependencies and `-L` search paths of `args`.
+fn library_paths(args: &[String]) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let (flag, value) = match arg.as_str() {
+            "--extern" | "-L" => (arg.as_str(), iter.next().map(String::as_str)),
+            _ if arg.starts_with("--extern=") => ("--extern", arg.get("--extern=".len()..)),
+            _ if arg.starts_with("-L") => ("-L", arg.get("-L".len()..)),
+            _ => continue,
+        };
+        let Some(value) = value else {
+            continue;
+        };
+        let path = match flag {
+            // `--extern name=path`, where a bare `--extern name` has no path.
+            "--extern" => value.split_once('=').map(|(_, path)| path),
+            // `-L [kind=]path`
+            _ => Some(value.split_once('=').map_or(value, |(_, path)| path)),
+        };
+        if let Some(path) = path {
+            paths.push(path.to_owned());
+        }
+    }
+    paths
+}
+
+/// A reproduction bundle written by [write_bundle].
+pub(crate) struct IceBundle {
+    /// The directory of the bundle.
+    pub(crate) path: PathBuf,
+    /// The number of source files copied into the bundle.
+    pub(crate) sources: usize,
+    /// The number of `rustc-ice-*.txt` reports copied into the bundle.
+    pub(crate) reports: usize,
+}
+
+impl fmt::Display for IceBundle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} source files, {} ICE reports)",
+            self.path.display(),
+            self.sources,
+            self.reports
+        )
+    }
+}
+
+/// A directory under `bundle_dir` which is unique to this invocation of rustc.
+fn bundle_path(bundle_dir: &str, args: &[String]) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    Path::new(bundle_dir).join(format!(
+        "{}-{}-{}",
+        crate_name(args).unwrap_or("rustc"),
+        timestamp,
+        std::process::id()
+    ))
+}
+
+/// Write a reproduction bundle for an ICE of the child process described by
+/// `opts` to a new directory under `--ice-bundle-dir`.
+///
+/// The bundle is not an output of the action, so that it survives the action
+/// failing and does not change the outputs of actions which succeed.
+pub(crate) fn write_bundle(
+    opts: &Options,
+    monitor: &IceMonitor,
+) -> Result<Option<IceBundle>, String> {
+    let Some(bundle_dir) = &opts.ice_bundle_dir else {
+        return Ok(None);
+    };
+    let args = expand_param_files(&opts.child_arguments)?;
+    let bundle = bundle_path(bundle_dir, &args);
+    fs::create_dir_all(&bundle)
+        .map_err(|e| format!("failed to create {}: {}", bundle.display(), e))?;
+
+    let reverse = |value: &str| reverse_subst(value, &opts.subst_mappings);
+    let lines = |values: &mut dyn Iterator<Item = String>| {
+        values.fold(String::new(), |mut acc, value| {
+            acc.push_str(&value);
+            acc.push('\n');
+            acc
+        })
+    };
+    let write = |name: &str, content: &str| {
+        let path = bundle.join(name);
+        fs::write(&path, content).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+    };
+
+    write(
+        "argv.txt",
+        &lines(
+            &mut std::iter::once(&opts.executable)
+                .chain(&args)
+                .map(|arg| reverse(arg)),
+        ),
+    )?;
+    write(
+        "env.txt",
+        &lines(&mut opts.env_file_vars.iter().filter_map(|key| {
+            opts.child_environment
+                .get(key)
+                .map(|value| format!("{}={}", key, reverse(value)))
+        })),
+    )?;
+    let sources = source_files(&args);
+    write(
+        "inputs.txt",
+        &lines(
+            &mut sources
+                .iter()
+                .chain(&library_paths(&args))
+                .map(|path| reverse(path)),
+        ),
+    )?;
+    // Sources within the execroot are copied so the bundle is self-contained.
+    let mut copied_sources = 0;
+    for source in sources
+        .iter()
+        .map(Path::new)
+        .filter(|path| path.is_relative())
+    {
+        let dest = bundle.join("sources").join(source);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+        }
+        match fs::copy(source, &dest) {
+            Ok(_) => copied_sources += 1,
+            Err(e) => debug_log!("failed to copy {}: {}", source.display(), e),
+        }
+    }
+    write("stderr.txt", &monitor.stderr.concat())?;
+    let reports = monitor.reports();
+    for report in &reports {
+        let dest = bundle.join(report.file_name().unwrap_or_default());
+        fs::copy(report, &dest)
+            .map_err(|e| format!("failed to copy {}: {}", report.display(), e))?;
+    }
+
+    Ok(Some(IceBundle {
+        path: bundle,
+        sources: copied_sources,
+        reports: reports.len(),
+    }))
+}
+
+/// Returns true if `value` is the argument of a `-C incremental=...` flag.
+fn is_incremental_codegen_option(value: &str) -> bool {
+    value.starts_with("incremental=")
+}
+
+/// Remove the flags enabling incremental compilation from `args`, writing
+/// rewritten param files to `scratch`.
+pub(crate) fn without_incremental(args: &[String], scratch: &Path) -> Result<Vec<String>, String> {
+    fn strip(args: Vec<String>) -> Vec<String> {
+        let mut stripped = Vec::new();
+        let mut iter = args.into_iter().peekable();
+        while let Some(arg) = iter.next() {
+            if arg == "-C" || arg == "--codegen" {
+                if iter
+                    .peek()
+                    .is_some_and(|value| is_incremental_codegen_option(value))
+                {
+                    iter.next();
+                    continue;
+                }
+            } else if arg
+                .strip_prefix("-C")
+                .or_else(|| arg.strip_prefix("--codegen="))
+                .is_some_and(is_incremental_codegen_option)
+            {
+                continue;
+            }
+            stripped.push(arg);
+        }
+        stripped
+    }
+
+    let mut result = Vec::new();
+    for (index, arg) in args.iter().enumerate() {
+        let Some(param_file) = arg.strip_prefix('@') else {
+            result.push(arg.clone());
+            continue;
+        };
+        let path = scratch.join(format!("{}.params", index));
+        let params = action_args::try_parse_args_with_fmt(
+            Path::new(param_file),
+            ActionArgsFormat::Multiline,
+        )
+        .map_err(|e| format!("failed to read {}: {}", param_file, e))?;
+        action_args::try_write_args_with_fmt(&path, &strip(params), ActionArgsFormat::Multiline)
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        result.push(format!("@{}", path.display()));
+    }
+    Ok(strip(result))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::util::read_file_to_array;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_is_ice_line() {
+        assert!(is_ice_line(
+            r#"{"$message_type":"diagnostic","message":"oops","level":"error: internal compiler error","rendered":"error: internal compiler error: oops"}"#
+        ));
+        assert!(!is_ice_line(
+            r#"{"$message_type":"diagnostic","message":"oops","level":"error","rendered":"error: oops"}"#
+        ));
+        assert!(is_ice_line(
+            "error: internal compiler error: compiler/rustc_middle/src/ty/mod.rs:1: oops"
+        ));
+        assert!(is_ice_line(
+            "thread 'rustc' panicked at compiler/rustc_middle/src/ty/mod.rs:1:1:"
+        ));
+        assert!(!is_ice_line(
+            "error[E0425]: cannot find value `x` in this scope"
+        ));
+    }
+
+    #[test]
+    fn test_reverse_subst() {
+        let mappings = vec![
+            ("pwd".to_owned(), "/execroot".to_owned()),
+            ("out".to_owned(), "/execroot/bazel-out".to_owned()),
+            ("empty".to_owned(), "".to_owned()),
+        ];
+        assert_eq!(
+            reverse_subst("--remap-path-prefix=/execroot=.", &mappings),
+            "--remap-path-prefix=${pwd}=."
+        );
+        assert_eq!(
+            reverse_subst("-Ldependency=/execroot/bazel-out/bin", &mappings),
+            "-Ldependency=${out}/bin"
+        );
+    }
+
+    #[test]
+    fn test_dep_info_path() {
+        assert_eq!(
+            dep_info_path(&strings(&[
+                "src/lib.rs",
+                "--crate-name=foo",
+                "--emit=dep-info,link",
+                "--codegen=extra-filename=-123",
+                "--out-dir=bazel-out/bin",
+            ])),
+            Some(PathBuf::from("bazel-out/bin/foo-123.d"))
+        );
+        assert_eq!(
+            dep_info_path(&strings(&[
+                "--crate-name",
+                "foo",
+                "--emit",
+                "link=out/libfoo.rlib,dep-info=out/foo.d",
+            ])),
+            Some(PathBuf::from("out/foo.d"))
+        );
+        assert_eq!(
+            dep_info_path(&strings(&[
+                "--crate-name=foo",
+                "--emit=link",
+                "--out-dir=out"
+            ])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_dep_info() {
+        let content = "\
+bazel-out/bin/foo-123.d: src/lib.rs src/a\\ b.rs src/data.txt
+
+bazel-out/bin/libfoo-123.rlib: src/lib.rs src/a\\ b.rs src/data.txt
+
+src/lib.rs:
+src/a\\ b.rs:
+src/data.txt:
+
+# env-dep:CARGO_PKG_NAME=foo
+";
+        assert_eq!(
+            parse_dep_info(content),
+            strings(&["src/lib.rs", "src/a b.rs", "src/data.txt"])
+        );
+    }
+
+    #[test]
+    fn test_source_files() -> Result<(), String> {
+        let scratch = TempDir::create("process_wrapper_test").map_err(|e| e.to_string())?;
+        let out_dir = scratch.path().display().to_string();
+        let args = strings(&["src/lib.rs", "--crate-name=foo", "--emit=dep-info,link"])
+            .into_iter()
+            .chain([format!("--out-dir={}", out_dir)])
+            .collect::<Vec<_>>();
+
+        // Without a dep-info file, only the crate root is known.
+        assert_eq!(source_files(&args), strings(&["src/lib.rs"]));
+
+        fs::write(
+            scratch.path().join("foo.d"),
+            "out/foo.d: src/lib.rs src/mod.rs\n\nsrc/lib.rs:\nsrc/mod.rs:\n",
+        )
+        .map_err(|e| e.to_string())?;
+        assert_eq!(source_files(&args), strings(&["src/lib.rs", "src/mod.rs"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_library_paths() {
+        let args = strings(&[
+            "src/lib.rs",
+            "--crate-name=foo",
+            "--extern",
+            "bar=bazel-out/bin/libbar.rlib",
+            "--extern=proc_macro",
+            "-Ldependency=bazel-out/bin/deps",
+            "-L",
+            "native/lib",
+            "--out-dir=bazel-out/bin",
+        ]);
+        assert_eq!(
+            library_paths(&args),
+            strings(&[
+                "bazel-out/bin/libbar.rlib",
+                "bazel-out/bin/deps",
+                "native/lib"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_write_bundle() -> Result<(), String> {
+        let to_string = |e: std::io::Error| e.to_string();
+        let scratch = TempDir::create("process_wrapper_test").map_err(to_string)?;
+        let bundle_dir = scratch.path().join("bundles");
+        let opts = Options {
+            executable: "rustc".to_owned(),
+            child_arguments: strings(&["does/not/exist.rs", "--crate-name=foo"]),
+            child_environment: HashMap::new(),
+            touch_file: None,
+            copy_output: None,
+            stdout_file: None,
+            stderr_file: None,
+            output_file: None,
+            rustc_quit_on_rmeta: false,
+            rustc_output_format: None,
+            pipelining_key: None,
+            resource_usage_file: None,
+            resource_usage_label: None,
+            resource_usage_mnemonic: None,
+            rustc_time_passes_file: None,
+            check_reproducibility: false,
+            ice_bundle_dir: Some(bundle_dir.to_string_lossy().into_owned()),
+            retry_on_ice: false,
+            subst_mappings: Vec::new(),
+            env_file_vars: Vec::new(),
+            cache_dir: None,
+            cache_max_size: 0,
+            diagnostic_path_mapping: None,
+            warnings_file: None,
+            warnings_label: None,
+            stamp_templates: Vec::new(),
+            workspace_status: Default::default(),
+        };
+
+        let mut monitor = IceMonitor::new(&opts)?.ok_or("expected an ICE monitor")?;
+        monitor.observe("thread 'rustc' panicked at src/lib.rs:1:1:\n");
+        fs::write(
+            monitor.report_dir.path().join("rustc-ice-1.txt"),
+            "backtrace",
+        )
+        .map_err(to_string)?;
+        assert!(monitor.detected());
+
+        let bundle = write_bundle(&opts, &monitor)?.ok_or("expected a bundle")?;
+        assert_eq!(bundle.path.parent(), Some(bundle_dir.as_path()));
+        assert!(bundle
+            .path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("foo-")));
+        assert_eq!((bundle.sources, bundle.reports), (0, 1));
+        assert_eq!(
+            fs::read_to_string(bundle.path.join("argv.txt")).map_err(to_string)?,
+            "rustc\ndoes/not/exist.rs\n--crate-name=foo\n"
+        );
+        assert_eq!(
+            fs::read_to_string(bundle.path.join("rustc-ice-1.txt")).map_err(to_string)?,
+            "backtrace"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_without_incremental() -> Result<(), String> {
+        let scratch = TempDir::create("process_wrapper_test").map_err(|e| e.to_string())?;
+        let param_file = scratch.path().join("args.params");
+        fs::write(
+            &param_file,
+            "--codegen=incremental=/tmp/inc\n-Copt-level=3\n-C\nincremental=/tmp/inc\n",
+        )
+        .map_err(|e| e.to_string())?;
+
+        let args = without_incremental(
+            &[
+                "src/lib.rs".to_owned(),
+                "-Cincremental=/tmp/inc".to_owned(),
+                "-C".to_owned(),
+                "debuginfo=2".to_owned(),
+                format!("@{}", param_file.display()),
+            ],
+            scratch.path(),
+        )?;
+        assert_eq!(args[..3], strings(&["src/lib.rs", "-C", "debuginfo=2"]));
+        let rewritten = args[3].strip_prefix('@').unwrap();
+        assert_eq!(read_file_to_array(rewritten)?, strings(&["-Copt-level=3"]));
+        Ok(())
+    }
+}
@@ -12,23 +12,45 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        if std::env::var_os("RULES_RUST_PROCESS_WRAPPER_DEBUG").is_some() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+mod cache;
+mod digest;
 mod flags;
+mod ice;
 mod options;
 mod output;
+mod pipelining;
+mod reproducibility;
+mod resource_usage;
 mod rustc;
+mod stamp;
 mod util;
+mod worker;
 
 use std::collections::HashMap;
+use std::env;
 use std::fmt;
-use std::fs::{copy, OpenOptions};
+use std::fs::{copy, File, OpenOptions};
 use std::io;
 use std::process::{exit, Command, ExitStatus, Stdio};
+use std::time::{Instant, SystemTime};
 
 use tinyjson::JsonValue;
 
-use crate::options::options;
+use crate::cache::Cache;
+use crate::ice::IceMonitor;
+use crate::options::{options, Options};
 use crate::output::{process_output, LineOutput};
+use crate::resource_usage::{wait_with_usage, ResourceRecord, ResourceUsage};
 use crate::rustc::ErrorFormat;
+use crate::util::TempDir;
 
 #[cfg(windows)]
 fn status_code(status: ExitStatus, was_killed: bool) -> i32 {
@@ -55,7 +77,7 @@ fn status_code(status: ExitStatus, was_killed: bool) -> i32 {
 }
 
 #[derive(Debug)]
-struct ProcessWrapperError(String);
+pub(crate) struct ProcessWrapperError(String);
 
 impl fmt::Display for ProcessWrapperError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -65,14 +87,6 @@ impl fmt::Display for ProcessWrapperError {
 
 impl std::error::Error for ProcessWrapperError {}
 
-macro_rules! debug_log {
-    ($($arg:tt)*) => {
-        if std::env::var_os("RULES_RUST_PROCESS_WRAPPER_DEBUG").is_some() {
-            eprintln!($($arg)*);
-        }
-    };
-}
-
 fn json_warning(line: &str) -> JsonValue {
     JsonValue::Object(HashMap::from([
         (
@@ -91,12 +105,9 @@ fn json_warning(line: &str) -> JsonValue {
     ]))
 }
 
-fn process_line(
-    mut line: String,
-    quit_on_rmeta: bool,
-    format: ErrorFormat,
-    metadata_emitted: &mut bool,
-) -> Result<LineOutput, String> {
+/// Convert non-JSON noise interspersed with rustc's JSON output into JSON
+/// diagnostics. Returns `None` if the line should be skipped.
+fn normalize_line(line: String) -> Option<String> {
     // LLVM can emit lines that look like the following, and these will be interspersed
     // with the regular JSON output. Arguably, rustc should be fixed not to emit lines
     // like these (or to convert them to JSON), but for now we convert them to JSON
@@ -104,12 +115,20 @@ fn process_line(
     if line.contains("is not a recognized feature for this target (ignoring feature)")
         || line.starts_with(" WARN ")
     {
-        if let Ok(json_str) = json_warning(&line).stringify() {
-            line = json_str;
-        } else {
-            return Ok(LineOutput::Skip);
-        }
+        return json_warning(&line).stringify().ok();
     }
+    Some(line)
+}
+
+fn process_line(
+    line: String,
+    quit_on_rmeta: bool,
+    format: ErrorFormat,
+    metadata_emitted: &mut bool,
+) -> Result<LineOutput, String> {
+    let Some(line) = normalize_line(line) else {
+        return Ok(LineOutput::Skip);
+    };
     if quit_on_rmeta {
         rustc::stop_on_rmeta_completion(line, format, metadata_emitted)
     } else {
@@ -117,115 +136,373 @@ fn process_line(
     }
 }
 
-fn main() -> Result<(), ProcessWrapperError> {
-    let opts = options().map_err(|e| ProcessWrapperError(e.to_string()))?;
+/// Returns true if `line` is part of the `-Ztime-passes` output of rustc.
+pub(crate) fn is_time_passes_line(line: &str) -> bool {
+    line.starts_with("time:")
+}
+
+/// Write the resource usage record requested by `opts`, if any.
+pub(crate) fn write_resource_usage(
+    opts: &Options,
+    start: SystemTime,
+    started: Instant,
+    usage: Option<ResourceUsage>,
+    exit_code: i32,
+) -> Result<(), ProcessWrapperError> {
+    let Some(path) = &opts.resource_usage_file else {
+        return Ok(());
+    };
+    ResourceRecord {
+        label: opts.resource_usage_label.as_deref(),
+        mnemonic: opts.resource_usage_mnemonic.as_deref(),
+        start,
+        wall_time: started.elapsed(),
+        usage,
+        exit_code,
+    }
+    .write(path)
+    .map_err(ProcessWrapperError)
+}
+
+/// Write the warnings found in the json `output` of rustc to the warnings
+/// file requested by `opts`, if any.
+pub(crate) fn write_warnings<'a>(
+    opts: &Options,
+    output: impl IntoIterator<Item = &'a str>,
+) -> Result<(), ProcessWrapperError> {
+    let Some(path) = &opts.warnings_file else {
+        return Ok(());
+    };
+    let mut content = String::new();
+    if opts.rustc_output_format.is_some() {
+        let label = opts.warnings_label.as_deref();
+        for record in output
+            .into_iter()
+            .filter_map(|line| rustc::warning_record(line, label))
+        {
+            content.push_str(&record);
+            content.push('\n');
+        }
+    }
+    std::fs::write(path, content)
+        .map_err(|e| ProcessWrapperError(format!("Unable to write warnings file: {}", e)))
+}
+
+/// Open a file for writing, truncating any existing content.
+fn create_file(path: &str) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path)
+}
 
-    let mut command = Command::new(opts.executable);
+/// Build the command for the child process described by `opts`.
+fn child_command(opts: &Options, stdout: Stdio) -> Result<Command, ProcessWrapperError> {
+    let mut command = Command::new(&opts.executable);
     command
-        .args(opts.child_arguments)
+        .args(&opts.child_arguments)
         .env_clear()
-        .envs(opts.child_environment)
-        .stdout(if let Some(stdout_file) = opts.stdout_file {
-            OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(stdout_file)
+        .envs(&opts.child_environment)
+        .stdout(if let Some(stdout_file) = &opts.stdout_file {
+            create_file(stdout_file)
                 .map_err(|e| ProcessWrapperError(format!("unable to open stdout file: {}", e)))?
                 .into()
         } else {
-            Stdio::inherit()
+            stdout
         })
         .stderr(Stdio::piped());
-    debug_log!("{:#?}", command);
-    let mut child = command
-        .spawn()
-        .map_err(|e| ProcessWrapperError(format!("failed to spawn child process: {}", e)))?;
+    Ok(command)
+}
 
-    let mut stderr: Box<dyn io::Write> = if let Some(stderr_file) = opts.stderr_file {
+/// Perform the post processing steps requested by `opts` once the child
+/// process has successfully terminated.
+fn finalize(opts: &Options) -> Result<(), ProcessWrapperError> {
+    if let Some(tf) = &opts.touch_file {
+        create_file(tf)
+            .map_err(|e| ProcessWrapperError(format!("failed to create touch file: {}", e)))?;
+    }
+    if let Some((copy_source, copy_dest)) = &opts.copy_output {
+        copy(copy_source, copy_dest).map_err(|e| {
+            ProcessWrapperError(format!(
+                "failed to copy {} into {}: {}",
+                copy_source, copy_dest, e
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+/// Open the writer for processed child output, honoring `--stderr-file`.
+fn open_stderr<'a>(
+    opts: &Options,
+    default: &'a mut dyn io::Write,
+) -> Result<Box<dyn io::Write + 'a>, ProcessWrapperError> {
+    Ok(if let Some(stderr_file) = &opts.stderr_file {
         Box::new(
-            OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(stderr_file)
+            create_file(stderr_file)
                 .map_err(|e| ProcessWrapperError(format!("unable to open stderr file: {}", e)))?,
         )
     } else {
-        Box::new(io::stderr())
+        Box::new(default)
+    })
+}
+
+/// Run the child process described by `opts` to completion, writing its
+/// processed output to `stderr`. Returns the exit code of the process wrapper.
+///
+/// The outputs of the child process are restored from the local cache if
+/// `opts` enables it and the cache holds a result for the same inputs.
+pub(crate) fn run_child(
+    opts: &Options,
+    stderr: &mut dyn io::Write,
+    stdout: fn() -> Stdio,
+) -> Result<i32, ProcessWrapperError> {
+    stamp::stamp_templates(opts).map_err(ProcessWrapperError)?;
+    if opts.executable.is_empty() {
+        return Ok(0);
+    }
+
+    let cache = Cache::open(opts).unwrap_or_else(|e| {
+        debug_log!("local cache disabled: {}", e);
+        None
+    });
+    let Some(cache) = cache else {
+        return run_child_uncached(opts, stderr, stdout, None);
     };
+    if let Some(raw_stderr) = cache.lookup() {
+        return replay(opts, stderr, &raw_stderr);
+    }
 
-    let mut child_stderr = child.stderr.take().ok_or(ProcessWrapperError(
-        "unable to get child stderr".to_string(),
-    ))?;
+    let mut raw_stderr = Vec::new();
+    let code = run_child_uncached(opts, stderr, stdout, Some(&mut raw_stderr))?;
+    if code == 0 {
+        if let Err(e) = cache.store(&raw_stderr) {
+            debug_log!("failed to store result in local cache: {}", e);
+        }
+    }
+    Ok(code)
+}
+
+/// Run the child process described by `opts`, capturing its unprocessed
+/// output in `raw_stderr`.
+///
+/// If rustc encounters an internal compiler error, a reproduction bundle is
+/// written and the compilation is retried as requested by `opts`.
+fn run_child_uncached(
+    opts: &Options,
+    stderr: &mut dyn io::Write,
+    stdout: fn() -> Stdio,
+    mut raw_stderr: Option<&mut Vec<u8>>,
+) -> Result<i32, ProcessWrapperError> {
+    let mut ice = IceMonitor::new(opts).map_err(ProcessWrapperError)?;
+    let code = run_child_once(
+        opts,
+        stderr,
+        stdout(),
+        ice.as_mut(),
+        raw_stderr.as_deref_mut(),
+    )?;
+    let Some(ice) = ice.filter(|ice| code != 0 && ice.detected()) else {
+        return Ok(code);
+    };
+
+    if let Some(bundle) = ice::write_bundle(opts, &ice).map_err(ProcessWrapperError)? {
+        let _ = writeln!(
+            stderr,
+            "process wrapper: rustc encountered an internal compiler error, \
+             a reproduction bundle was written to {}",
+            bundle
+        );
+    }
+    if !opts.retry_on_ice {
+        return Ok(code);
+    }
+
+    let _ = writeln!(
+        stderr,
+        "process wrapper: retrying with incremental compilation disabled"
+    );
+    let scratch = TempDir::create("process_wrapper_ice_retry")
+        .map_err(|e| ProcessWrapperError(format!("failed to create retry directory: {}", e)))?;
+    let retry_opts = Options {
+        child_arguments: ice::without_incremental(&opts.child_arguments, scratch.path())
+            .map_err(ProcessWrapperError)?,
+        ..opts.clone()
+    };
+    if let Some(raw_stderr) = raw_stderr.as_deref_mut() {
+        raw_stderr.clear();
+    }
+    run_child_once(&retry_opts, stderr, stdout(), None, raw_stderr)
+}
+
+/// Process the unprocessed output of rustc read from `child_stderr` as
+/// requested by `opts`, passing each line to `observe` first. Returns true if
+/// rustc should be stopped because it emitted the requested metadata.
+fn forward_output(
+    opts: &Options,
+    child_stderr: &mut dyn io::Read,
+    stderr: &mut dyn io::Write,
+    observe: &mut dyn FnMut(&str),
+) -> Result<bool, ProcessWrapperError> {
+    let mut stderr = open_stderr(opts, stderr)?;
 
-    let mut output_file: Option<std::fs::File> = if let Some(output_file_name) = opts.output_file {
+    let mut output_file: Option<File> = if let Some(output_file_name) = &opts.output_file {
         Some(
-            OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(output_file_name)
+            create_file(output_file_name)
                 .map_err(|e| ProcessWrapperError(format!("Unable to open output_file: {}", e)))?,
         )
     } else {
         None
     };
 
-    let mut was_killed = false;
+    let mut time_passes_file: Option<File> =
+        if let Some(time_passes_file_name) = &opts.rustc_time_passes_file {
+            Some(create_file(time_passes_file_name).map_err(|e| {
+                ProcessWrapperError(format!("Unable to open time passes file: {}", e))
+            })?)
+        } else {
+            None
+        };
+    // Divert `-Ztime-passes` output, which is never json, before processing lines.
+    let time_passes = &mut time_passes_file;
+    let mut divert_time_passes = move |line: &str| -> Result<bool, String> {
+        match time_passes {
+            Some(file) if is_time_passes_line(line) => {
+                io::Write::write_all(file, line.as_bytes()).map_err(|e| e.to_string())?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    };
+
+    let path_mapping = opts.diagnostic_path_mapping.as_ref();
+    let mut diagnostics = Vec::new();
+    let mut metadata_emitted = false;
     let result = if let Some(format) = opts.rustc_output_format {
         let quit_on_rmeta = opts.rustc_quit_on_rmeta;
-        // Process json rustc output and kill the subprocess when we get a signal
+        // Process json rustc output and stop the subprocess when we get a signal
         // that we emitted a metadata file.
-        let mut me = false;
-        let metadata_emitted = &mut me;
-        let result = process_output(
-            &mut child_stderr,
+        let metadata_emitted = &mut metadata_emitted;
+        let mut diagnostics = opts.warnings_file.as_ref().map(|_| &mut diagnostics);
+        process_output(
+            child_stderr,
             stderr.as_mut(),
             output_file.as_mut(),
-            move |line| process_line(line, quit_on_rmeta, format, metadata_emitted),
-        );
-        if me {
-            // If recv returns Ok(), a signal was sent in this channel so we should terminate the child process.
-            // We can safely ignore the Result from kill() as we don't care if the process already terminated.
-            let _ = child.kill();
-            was_killed = true;
-        }
-        result
+            move |line| {
+                observe(&line);
+                if divert_time_passes(&line)? {
+                    return Ok(LineOutput::Skip);
+                }
+                if let Some(diagnostics) = diagnostics.as_mut() {
+                    diagnostics.push(line.clone());
+                }
+                let line = match path_mapping {
+                    Some(mapping) => mapping.rewrite_json(line),
+                    None => line,
+                };
+                process_line(line, quit_on_rmeta, format, metadata_emitted)
+            },
+        )
     } else {
         // Process output normally by forwarding stderr
         process_output(
-            &mut child_stderr,
+            child_stderr,
             stderr.as_mut(),
             output_file.as_mut(),
-            move |line| Ok(LineOutput::Message(line)),
+            move |line| {
+                observe(&line);
+                if divert_time_passes(&line)? {
+                    return Ok(LineOutput::Skip);
+                }
+                Ok(LineOutput::Message(match path_mapping {
+                    Some(mapping) => mapping.rewrite_rendered(&line),
+                    None => line,
+                }))
+            },
         )
     };
     result.map_err(|e| ProcessWrapperError(format!("failed to process stderr: {}", e)))?;
+    write_warnings(opts, diagnostics.iter().map(String::as_str))?;
+    Ok(metadata_emitted)
+}
+
+/// Replay the unprocessed output of a cached rustc invocation. Returns the
+/// exit code of the process wrapper.
+fn replay(
+    opts: &Options,
+    stderr: &mut dyn io::Write,
+    raw_stderr: &[u8],
+) -> Result<i32, ProcessWrapperError> {
+    let start = SystemTime::now();
+    let started = Instant::now();
+    forward_output(opts, &mut &raw_stderr[..], stderr, &mut |_| {})?;
+    write_resource_usage(opts, start, started, None, 0)?;
+    finalize(opts)?;
+    Ok(0)
+}
 
-    let status = child
-        .wait()
+/// Run the child process described by `opts` once, reporting its unprocessed
+/// output to `ice` and appending it to `raw_stderr`.
+fn run_child_once(
+    opts: &Options,
+    stderr: &mut dyn io::Write,
+    stdout: Stdio,
+    mut ice: Option<&mut IceMonitor>,
+    mut raw_stderr: Option<&mut Vec<u8>>,
+) -> Result<i32, ProcessWrapperError> {
+    let mut command = child_command(opts, stdout)?;
+    if let Some(ice) = &ice {
+        ice.configure(opts, &mut command);
+    }
+    debug_log!("{:#?}", command);
+    let start = SystemTime::now();
+    let started = Instant::now();
+    let mut child = command
+        .spawn()
+        .map_err(|e| ProcessWrapperError(format!("failed to spawn child process: {}", e)))?;
+
+    let mut child_stderr = child.stderr.take().ok_or(ProcessWrapperError(
+        "unable to get child stderr".to_string(),
+    ))?;
+
+    let metadata_emitted = forward_output(opts, &mut child_stderr, stderr, &mut |line| {
+        if let Some(ice) = ice.as_mut() {
+            ice.observe(line);
+        }
+        if let Some(raw_stderr) = raw_stderr.as_mut() {
+            raw_stderr.extend_from_slice(line.as_bytes());
+        }
+    })?;
+    let mut was_killed = false;
+    if metadata_emitted {
+        // We can safely ignore the Result from kill() as we don't care if the process already terminated.
+        let _ = child.kill();
+        was_killed = true;
+    }
+
+    let (status, usage) = wait_with_usage(&mut child)
         .map_err(|e| ProcessWrapperError(format!("failed to wait for child process: {}", e)))?;
     // If the child process is rustc and is killed after metadata generation, that's also a success.
     let code = status_code(status, was_killed);
-    let success = code == 0;
-    if success {
-        if let Some(tf) = opts.touch_file {
-            OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(tf)
-                .map_err(|e| ProcessWrapperError(format!("failed to create touch file: {}", e)))?;
-        }
-        if let Some((copy_source, copy_dest)) = opts.copy_output {
-            copy(&copy_source, &copy_dest).map_err(|e| {
-                ProcessWrapperError(format!(
-                    "failed to copy {} into {}: {}",
-                    copy_source, copy_dest, e
-                ))
-            })?;
-        }
+    write_resource_usage(opts, start, started, usage, code)?;
+    if code == 0 {
+        finalize(opts)?;
+    }
+
+    Ok(code)
+}
+
+fn main() -> Result<(), ProcessWrapperError> {
+    if env::args().any(|arg| arg == worker::PERSISTENT_WORKER_FLAG) {
+        return worker::run_worker();
+    }
+
+    let opts = options().map_err(|e| ProcessWrapperError(e.to_string()))?;
+
+    let mut code = run_child(&opts, &mut io::stderr(), Stdio::inherit)?;
+    if code == 0 && opts.check_reproducibility {
+        code = reproducibility::check(env::args().collect(), &mut io::stderr())?;
     }
 
     exit(code)
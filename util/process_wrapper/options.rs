@@ -1,10 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::fmt;
 use std::fs::File;
 use std::io::{self, Write};
+use std::path::Path;
 use std::process::exit;
 
+use workspace_status::WorkspaceStatus;
+
 use crate::flags::{FlagParseError, Flags, ParseOutcome};
 use crate::rustc;
 use crate::util::*;
@@ -24,7 +27,7 @@ impl fmt::Display for OptionError {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct Options {
     // Contains the path to the child executable
     pub(crate) executable: String,
@@ -49,9 +52,74 @@ pub(crate) struct Options {
     pub(crate) rustc_quit_on_rmeta: bool,
     // This controls the output format of rustc messages.
     pub(crate) rustc_output_format: Option<rustc::ErrorFormat>,
+    // If set, the metadata and full compilation of a crate run as persistent
+    // worker requests with this key share a single rustc process.
+    pub(crate) pipelining_key: Option<String>,
+    // If set, a JSON record of the wall time, CPU time and peak memory usage
+    // of the child process is written to this file.
+    pub(crate) resource_usage_file: Option<String>,
+    // The label of the target recorded in the resource usage file.
+    pub(crate) resource_usage_label: Option<String>,
+    // The mnemonic of the action recorded in the resource usage file.
+    pub(crate) resource_usage_mnemonic: Option<String>,
+    // If set, `-Ztime-passes` output of rustc is written to this file
+    // instead of being reported as diagnostics.
+    pub(crate) rustc_time_passes_file: Option<String>,
+    // If set, the child process is run a second time from a different working
+    // directory and environment to check that its outputs are reproducible.
+    pub(crate) check_reproducibility: bool,
+    // If set, a reproduction bundle is written to a new directory within this
+    // directory when rustc encounters an internal compiler error.
+    pub(crate) ice_bundle_dir: Option<String>,
+    // If set, rustc is run once more with incremental compilation disabled
+    // when it encounters an internal compiler error.
+    pub(crate) retry_on_ice: bool,
+    // The `--subst` mappings applied to the arguments and environment.
+    pub(crate) subst_mappings: Vec<(String, String)>,
+    // The names of the environment variables read from `--env-file`.
+    pub(crate) env_file_vars: Vec<String>,
+    // If set, the outputs of the child process are cached in this directory
+    // and restored instead of running the child process again.
+    pub(crate) cache_dir: Option<String>,
+    // The size in bytes beyond which the least recently used entries are
+    // evicted from the cache.
+    pub(crate) cache_max_size: u64,
+    // If set, the file names of diagnostic spans are rewritten with this mapping.
+    pub(crate) diagnostic_path_mapping: Option<rustc::PathMapping>,
+    // If set, the warnings reported by rustc are written to this file, one
+    // json record per line.
+    pub(crate) warnings_file: Option<String>,
+    // The label of the target recorded in the warnings file.
+    pub(crate) warnings_label: Option<String>,
+    // The `(template, output)` pairs of files to stamp before running the
+    // child process.
+    pub(crate) stamp_templates: Vec<(String, String)>,
+    // The values of the stable and volatile workspace status files.
+    pub(crate) workspace_status: WorkspaceStatus,
 }
 
+/// The default size limit of the cache enabled by `--cache-dir`.
+const DEFAULT_CACHE_MAX_SIZE_MB: u64 = 10 * 1024;
+
 pub(crate) fn options() -> Result<Options, OptionError> {
+    options_from_args(env::args().collect())
+}
+
+/// Parse options from an argument list whose first element is the program name.
+pub(crate) fn options_from_args(args: Vec<String>) -> Result<Options, OptionError> {
+    let current_dir = std::env::current_dir()
+        .map_err(|e| OptionError::Generic(format!("failed to get current directory: {e}")))?
+        .to_str()
+        .ok_or_else(|| OptionError::Generic("current directory not utf-8".to_owned()))?
+        .to_owned();
+    options_from_args_with_pwd(args, &current_dir)
+}
+
+/// Parse options from an argument list, substituting `${pwd}` with `current_dir`.
+pub(crate) fn options_from_args_with_pwd(
+    args: Vec<String>,
+    current_dir: &str,
+) -> Result<Options, OptionError> {
     // Process argument list until -- is encountered.
     // Everything after is sent to the child process.
     let mut subst_mapping_raw = None;
@@ -66,6 +134,20 @@ pub(crate) fn options() -> Result<Options, OptionError> {
     let mut output_file = None;
     let mut rustc_quit_on_rmeta_raw = None;
     let mut rustc_output_format_raw = None;
+    let mut pipelining_key = None;
+    let mut resource_usage_file = None;
+    let mut resource_usage_label = None;
+    let mut resource_usage_mnemonic = None;
+    let mut rustc_time_passes_file = None;
+    let mut check_reproducibility_raw = None;
+    let mut ice_bundle_dir = None;
+    let mut retry_on_ice_raw = None;
+    let mut cache_dir = None;
+    let mut cache_max_size_mb_raw = None;
+    let mut diagnostic_path_mapping_raw = None;
+    let mut warnings_file = None;
+    let mut warnings_label = None;
+    let mut stamp_templates_raw = None;
     let mut flags = Flags::new();
     let mut require_explicit_unstable_features = None;
     flags.define_repeated_flag("--subst", "", &mut subst_mapping_raw);
@@ -115,6 +197,86 @@ pub(crate) fn options() -> Result<Options, OptionError> {
         Default: `rendered`",
         &mut rustc_output_format_raw,
     );
+    flags.define_flag(
+        "--pipelining-key",
+        "When running as a persistent worker, requests for the metadata and the full \
+         compilation of a crate with the same key and inputs share one rustc process.",
+        &mut pipelining_key,
+    );
+    flags.define_flag(
+        "--resource-usage-file",
+        "Write the wall time, CPU time and peak memory usage of the child process to this file as JSON.",
+        &mut resource_usage_file,
+    );
+    flags.define_flag(
+        "--resource-usage-label",
+        "The label of the target to record in the --resource-usage-file.",
+        &mut resource_usage_label,
+    );
+    flags.define_flag(
+        "--resource-usage-mnemonic",
+        "The mnemonic of the action to record in the --resource-usage-file.",
+        &mut resource_usage_mnemonic,
+    );
+    flags.define_flag(
+        "--rustc-time-passes-file",
+        "Write `-Ztime-passes` output of rustc to this file instead of forwarding it.",
+        &mut rustc_time_passes_file,
+    );
+    flags.define_flag(
+        "--check-reproducibility",
+        "If set to 'true', the child process is run a second time from a different working \
+         directory and environment and the process wrapper fails if the outputs differ.",
+        &mut check_reproducibility_raw,
+    );
+    flags.define_flag(
+        "--ice-bundle-dir",
+        "If set, a reproduction bundle is written to a new directory within this directory \
+         when rustc encounters an internal compiler error.",
+        &mut ice_bundle_dir,
+    );
+    flags.define_flag(
+        "--retry-on-ice",
+        "If set to 'true', rustc is run once more with incremental compilation disabled when \
+         it encounters an internal compiler error.",
+        &mut retry_on_ice_raw,
+    );
+    flags.define_flag(
+        "--cache-dir",
+        "If set, the outputs of rustc are cached in this directory and restored instead of \
+         running rustc again for the same arguments, environment and inputs.",
+        &mut cache_dir,
+    );
+    flags.define_flag(
+        "--cache-max-size-mb",
+        "The size in megabytes beyond which the least recently used entries are evicted from \
+         the cache. Defaults to 10240.",
+        &mut cache_max_size_mb_raw,
+    );
+    flags.define_flag(
+        "--diagnostic-path-mapping",
+        "A file of tab separated path mappings, which are applied to the file names of the \
+         diagnostics of rustc.",
+        &mut diagnostic_path_mapping_raw,
+    );
+    flags.define_flag(
+        "--warnings-file",
+        "If set, the warnings reported by rustc are written to this file as json lines. \
+         Requires --rustc-output-format.",
+        &mut warnings_file,
+    );
+    flags.define_flag(
+        "--warnings-label",
+        "The label of the target to record in the --warnings-file.",
+        &mut warnings_label,
+    );
+    flags.define_repeated_flag(
+        "--stamp-template",
+        "A template file and the output to write it to, with every {KEY} replaced by the \
+         value of KEY in the --stable-status-file or --volatile-status-file. The child process \
+         may be omitted when only stamping templates.",
+        &mut stamp_templates_raw,
+    );
     flags.define_flag(
         "--require-explicit-unstable-features",
         "If set, an empty -Zallow-features= will be added to the rustc command line whenever no \
@@ -122,9 +284,9 @@ pub(crate) fn options() -> Result<Options, OptionError> {
         &mut require_explicit_unstable_features,
     );
 
-    let mut child_args = match flags
-        .parse(env::args().collect())
-        .map_err(OptionError::FlagError)?
+    let mut read_file = |s: &str| read_file_to_array(s).map_err(OptionError::Generic);
+    let args = expand_wrapper_args(args, &mut read_file)?;
+    let mut child_args = match flags.parse(args).map_err(OptionError::FlagError)?
     {
         ParseOutcome::Help(help) => {
             eprintln!("{help}");
@@ -132,11 +294,6 @@ pub(crate) fn options() -> Result<Options, OptionError> {
         }
         ParseOutcome::Parsed(p) => p,
     };
-    let current_dir = std::env::current_dir()
-        .map_err(|e| OptionError::Generic(format!("failed to get current directory: {e}")))?
-        .to_str()
-        .ok_or_else(|| OptionError::Generic("current directory not utf-8".to_owned()))?
-        .to_owned();
     let subst_mappings = subst_mapping_raw
         .unwrap_or_default()
         .into_iter()
@@ -144,12 +301,7 @@ pub(crate) fn options() -> Result<Options, OptionError> {
             let (key, val) = arg.split_once('=').ok_or_else(|| {
                 OptionError::Generic(format!("empty key for substitution '{arg}'"))
             })?;
-            let v = if val == "${pwd}" {
-                current_dir.as_str()
-            } else {
-                val
-            }
-            .to_owned();
+            let v = if val == "${pwd}" { current_dir } else { val }.to_owned();
             Ok((key.to_owned(), v))
         })
         .collect::<Result<Vec<(String, String)>, OptionError>>()?;
@@ -157,7 +309,23 @@ pub(crate) fn options() -> Result<Options, OptionError> {
         stable_status_file_raw.map_or_else(Vec::new, |s| read_stamp_status_to_array(s).unwrap());
     let volatile_stamp_mappings =
         volatile_status_file_raw.map_or_else(Vec::new, |s| read_stamp_status_to_array(s).unwrap());
+    let workspace_status = stable_stamp_mappings
+        .into_iter()
+        .chain(volatile_stamp_mappings)
+        .collect::<WorkspaceStatus>();
+    let stamp_templates_raw = stamp_templates_raw.unwrap_or_default();
+    if stamp_templates_raw.len() % 2 != 0 {
+        return Err(OptionError::Generic(
+            "\"--stamp-template\" needs a template and an output".to_owned(),
+        ));
+    }
+    let stamp_templates = stamp_templates_raw
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect::<Vec<_>>();
     let environment_file_block = env_from_files(env_file_raw.unwrap_or_default())?;
+    let mut env_file_vars: Vec<String> = environment_file_block.keys().cloned().collect();
+    env_file_vars.sort();
     let mut file_arguments = args_from_file(arg_file_raw.unwrap_or_default())?;
     // Process --copy-output
     let copy_output = copy_output_raw
@@ -180,6 +348,17 @@ pub(crate) fn options() -> Result<Options, OptionError> {
         .transpose()?;
 
     let rustc_quit_on_rmeta = rustc_quit_on_rmeta_raw.is_some_and(|s| s == "true");
+    let check_reproducibility = check_reproducibility_raw.is_some_and(|s| s == "true");
+    let retry_on_ice = retry_on_ice_raw.is_some_and(|s| s == "true");
+    let cache_max_size = cache_max_size_mb_raw
+        .map(|v| {
+            v.parse::<u64>()
+                .map_err(|_| OptionError::Generic(format!("invalid --cache-max-size-mb '{v}'")))
+        })
+        .transpose()?
+        .unwrap_or(DEFAULT_CACHE_MAX_SIZE_MB)
+        * 1024
+        * 1024;
     let rustc_output_format = rustc_output_format_raw
         .map(|v| match v.as_str() {
             "json" => Ok(rustc::ErrorFormat::Json),
@@ -189,15 +368,14 @@ pub(crate) fn options() -> Result<Options, OptionError> {
             ))),
         })
         .transpose()?;
+    let diagnostic_path_mapping = diagnostic_path_mapping_raw
+        .map(|path| rustc::PathMapping::load(&path, Path::new(current_dir)))
+        .transpose()
+        .map_err(OptionError::Generic)?;
 
     // Prepare the environment variables, unifying those read from files with the ones
     // of the current process.
-    let vars = environment_block(
-        environment_file_block,
-        &stable_stamp_mappings,
-        &volatile_stamp_mappings,
-        &subst_mappings,
-    );
+    let vars = environment_block(environment_file_block, &workspace_status, &subst_mappings);
 
     let require_explicit_unstable_features =
         require_explicit_unstable_features.is_some_and(|s| s == "true");
@@ -212,15 +390,20 @@ pub(crate) fn options() -> Result<Options, OptionError> {
         None,
     )?;
     // Split the executable path from the rest of the arguments.
-    let (exec_path, args) = child_args.split_first().ok_or_else(|| {
-        OptionError::Generic(
-            "at least one argument after -- is required (the child process path)".to_owned(),
-        )
-    })?;
+    let (exec_path, args) = match child_args.split_first() {
+        Some((exec_path, args)) => (exec_path.to_owned(), args.to_vec()),
+        // Stamping templates does not require a child process.
+        None if !stamp_templates.is_empty() => (String::new(), Vec::new()),
+        None => {
+            return Err(OptionError::Generic(
+                "at least one argument after -- is required (the child process path)".to_owned(),
+            ))
+        }
+    };
 
     Ok(Options {
-        executable: exec_path.to_owned(),
-        child_arguments: args.to_vec(),
+        executable: exec_path,
+        child_arguments: args,
         child_environment: vars,
         touch_file,
         copy_output,
@@ -229,9 +412,61 @@ pub(crate) fn options() -> Result<Options, OptionError> {
         output_file,
         rustc_quit_on_rmeta,
         rustc_output_format,
+        pipelining_key,
+        resource_usage_file,
+        resource_usage_label,
+        resource_usage_mnemonic,
+        rustc_time_passes_file,
+        check_reproducibility,
+        ice_bundle_dir,
+        retry_on_ice,
+        subst_mappings,
+        env_file_vars,
+        cache_dir,
+        cache_max_size,
+        diagnostic_path_mapping,
+        warnings_file,
+        warnings_label,
+        stamp_templates,
+        workspace_status,
     })
 }
 
+/// Expand param files containing arguments for the process wrapper itself.
+///
+/// Persistent workers require every argument of an action to be passed via
+/// param files, including the process wrapper flags and the `--` separator.
+/// Param files appearing after `--` belong to the child process and are left
+/// untouched.
+fn expand_wrapper_args(
+    args: Vec<String>,
+    read_file: &mut impl FnMut(&str) -> Result<Vec<String>, OptionError>,
+) -> Result<Vec<String>, OptionError> {
+    let mut pending: VecDeque<String> = args.into();
+    let mut expanded = Vec::new();
+    let mut in_child_args = false;
+
+    // The program name is never a param file.
+    if let Some(program) = pending.pop_front() {
+        expanded.push(program);
+    }
+
+    while let Some(arg) = pending.pop_front() {
+        if !in_child_args {
+            if let Some(param_file) = arg.strip_prefix('@') {
+                for line in read_file(param_file)?.into_iter().rev() {
+                    pending.push_front(line);
+                }
+                continue;
+            }
+            in_child_args = arg == "--";
+        }
+        expanded.push(arg);
+    }
+
+    Ok(expanded)
+}
+
 fn args_from_file(paths: Vec<String>) -> Result<Vec<String>, OptionError> {
     let mut args = vec![];
     for path in paths.iter() {
@@ -370,8 +605,7 @@ fn prepare_args(
 
 fn environment_block(
     environment_file_block: HashMap<String, String>,
-    stable_stamp_mappings: &[(String, String)],
-    volatile_stamp_mappings: &[(String, String)],
+    workspace_status: &WorkspaceStatus,
     subst_mappings: &[(String, String)],
 ) -> HashMap<String, String> {
     // Taking all environment variables from the current process
@@ -381,12 +615,8 @@ fn environment_block(
     // This is simpler than needing to track duplicates and explicitly override
     // them.
     environment_variables.extend(environment_file_block);
-    for (f, replace_with) in &[stable_stamp_mappings, volatile_stamp_mappings].concat() {
-        for value in environment_variables.values_mut() {
-            let from = format!("{{{f}}}");
-            let new = value.replace(from.as_str(), replace_with);
-            *value = new;
-        }
+    for value in environment_variables.values_mut() {
+        *value = workspace_status.stamp(value);
     }
     for (f, replace_with) in subst_mappings {
         for value in environment_variables.values_mut() {
@@ -430,6 +660,59 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_expand_wrapper_args() {
+        let read_files = HashMap::<String, Vec<String>>::from([
+            (
+                "wrapper_params".to_string(),
+                vec![
+                    "--subst".to_string(),
+                    "pwd=${pwd}".to_string(),
+                    "@nested_params".to_string(),
+                ],
+            ),
+            (
+                "nested_params".to_string(),
+                vec!["--pipelining-key".to_string(), "libfoo.rlib".to_string()],
+            ),
+            (
+                "rustc_path".to_string(),
+                vec!["--".to_string(), "rustc".to_string()],
+            ),
+        ]);
+        let mut read_file = |filename: &str| -> Result<Vec<String>, OptionError> {
+            read_files
+                .get(filename)
+                .cloned()
+                .ok_or_else(|| OptionError::Generic(format!("file not found: {}", filename)))
+        };
+
+        let args = expand_wrapper_args(
+            vec![
+                "process_wrapper".to_string(),
+                "@wrapper_params".to_string(),
+                "@rustc_path".to_string(),
+                "@rustc_params".to_string(),
+            ],
+            &mut read_file,
+        )
+        .unwrap();
+
+        assert_eq!(
+            args,
+            vec![
+                "process_wrapper".to_string(),
+                "--subst".to_string(),
+                "pwd=${pwd}".to_string(),
+                "--pipelining-key".to_string(),
+                "libfoo.rlib".to_string(),
+                "--".to_string(),
+                "rustc".to_string(),
+                "@rustc_params".to_string(),
+            ]
+        );
+    }
+
     #[test]
     fn test_enforce_allow_features_flag_user_requested_something_in_param_file() {
         let mut written_files = HashMap::<String, String>::new();
@@ -0,0 +1,607 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Single process pipelined compilation.
+//!
+//! With pipelining, a library crate is built by two actions: one producing the
+//! `.rmeta` file that dependent crates compile against and one producing the
+//! `.rlib`. Both actions run rustc with identical arguments. When the process
+//! wrapper runs as a persistent worker, the first of the two requests to arrive
+//! spawns rustc and the second one attaches to it. The metadata request
+//! completes as soon as rustc reports the `.rmeta` file as written while rustc
+//! keeps going to produce the `.rlib` for the full request.
+//!
+//! The full request never reaches the worker when its action is a cache hit or
+//! its output is not needed. The metadata request therefore owns the rustc
+//! process: unless a full request attaches within [FULL_REQUEST_TIMEOUT], rustc
+//! is killed and the compilation is forgotten.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::options::Options;
+use crate::output::LineOutput;
+use crate::resource_usage::{wait_with_usage, ResourceUsage};
+use crate::rustc::{self, ErrorFormat, PathMapping};
+use crate::{
+    child_command, create_file, finalize, is_time_passes_line, normalize_line, open_stderr,
+    status_code, write_resource_usage, write_warnings, ProcessWrapperError,
+};
+
+/// The part of a pipelined compilation a request is responsible for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stage {
+    /// The request produces the `.rmeta` file.
+    Metadata,
+    /// The request produces the `.rlib` file.
+    Full,
+}
+
+impl Stage {
+    /// Determine the stage of a request from its options.
+    pub(crate) fn of(opts: &Options) -> Self {
+        if opts.rustc_quit_on_rmeta {
+            Self::Metadata
+        } else {
+            Self::Full
+        }
+    }
+}
+
+/// Compute a fingerprint identifying a rustc invocation.
+///
+/// Both requests of a pipelined compilation only differ in the process wrapper
+/// flags, so the fingerprint covers the child command line, its environment
+/// and the digests of all action inputs.
+pub(crate) fn fingerprint(opts: &Options, inputs: &[(String, String)]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    opts.executable.hash(&mut hasher);
+    opts.child_arguments.hash(&mut hasher);
+    let mut environment: Vec<_> = opts.child_environment.iter().collect();
+    environment.sort();
+    environment.hash(&mut hasher);
+    inputs.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The progress of a rustc process shared by pipelined requests.
+#[derive(Debug, Default)]
+struct CompilationState {
+    /// Processed output to report to users.
+    output: Vec<u8>,
+    /// Unprocessed rustc output for `--output-file`.
+    raw_output: Vec<u8>,
+    /// `-Ztime-passes` output for `--rustc-time-passes-file`.
+    time_passes: Vec<u8>,
+    /// Artifacts rustc reported as written, keyed by their emit type.
+    artifacts: HashMap<String, String>,
+    /// The exit code once the process has terminated.
+    exit_code: Option<i32>,
+    /// The resources consumed by the process once it has terminated.
+    usage: Option<ResourceUsage>,
+    /// Whether a full request is waiting on or has consumed the process.
+    full_attached: bool,
+}
+
+impl CompilationState {
+    fn metadata_emitted(&self) -> bool {
+        self.artifacts.contains_key("metadata")
+    }
+}
+
+/// A rustc process shared by the metadata and full requests of a crate.
+#[derive(Debug, Default)]
+struct Compilation {
+    state: Mutex<CompilationState>,
+    changed: Condvar,
+    /// The rustc process until its output has been read to the end.
+    child: Mutex<Option<Child>>,
+}
+
+impl Compilation {
+    /// Spawn rustc and start processing its output in the background.
+    fn spawn(opts: &Options) -> Result<Arc<Self>, ProcessWrapperError> {
+        let format = opts.rustc_output_format.unwrap_or_default();
+        let path_mapping = opts.diagnostic_path_mapping.clone();
+
+        // Stdout of the worker is reserved for the worker protocol.
+        let mut command = child_command(opts, Stdio::from(io::stderr()))?;
+        debug_log!("{:#?}", command);
+        let mut child = command
+            .spawn()
+            .map_err(|e| ProcessWrapperError(format!("failed to spawn child process: {}", e)))?;
+        let child_stderr = child.stderr.take().ok_or(ProcessWrapperError(
+            "unable to get child stderr".to_string(),
+        ))?;
+
+        let compilation = Arc::new(Self {
+            child: Mutex::new(Some(child)),
+            ..Self::default()
+        });
+        let shared = Arc::clone(&compilation);
+        thread::spawn(move || {
+            let failure = shared.process_output(
+                io::BufReader::new(child_stderr),
+                format,
+                path_mapping.as_ref(),
+            );
+            let mut child = shared
+                .child
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take()
+                .expect("the child is only taken once its output is consumed");
+            let status = wait_with_usage(&mut child);
+
+            let mut state = shared.lock();
+            let code = match (status, failure) {
+                (Ok((status, usage)), None) => {
+                    state.usage = usage;
+                    status_code(status, false)
+                }
+                (Ok((status, usage)), Some(e)) => {
+                    state.usage = usage;
+                    let _ = writeln!(state.output, "failed to process stderr: {}", e);
+                    status_code(status, false).max(1)
+                }
+                (Err(e), _) => {
+                    let _ = writeln!(state.output, "failed to wait for child process: {}", e);
+                    1
+                }
+            };
+            state.exit_code = Some(code);
+            shared.changed.notify_all();
+        });
+
+        Ok(compilation)
+    }
+
+    /// Kill the rustc process unless it has already finished writing output.
+    fn kill(&self) {
+        if let Some(child) = self
+            .child
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+        {
+            let _ = child.kill();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CompilationState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record the output of rustc, waking up waiting requests whenever an
+    /// artifact is reported. Returns an error message if parsing the output
+    /// failed, in which case all remaining output is forwarded unprocessed.
+    fn process_output(
+        &self,
+        mut reader: impl BufRead,
+        format: ErrorFormat,
+        path_mapping: Option<&PathMapping>,
+    ) -> Option<String> {
+        let mut failure = None;
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => return Some(e.to_string()),
+            }
+
+            let mut state = self.lock();
+            state.raw_output.extend_from_slice(line.as_bytes());
+            if is_time_passes_line(&line) {
+                state.time_passes.extend_from_slice(line.as_bytes());
+                continue;
+            }
+            if failure.is_some() {
+                state.output.extend_from_slice(line.as_bytes());
+                continue;
+            }
+
+            let Some(mut normalized) = normalize_line(line.clone()) else {
+                continue;
+            };
+            if let Some(mapping) = path_mapping {
+                normalized = mapping.rewrite_json(normalized);
+            }
+            let mut artifacts = Vec::new();
+            match rustc::process_json_artifacts(normalized, format, &mut |emit, path| {
+                artifacts.push((emit, path))
+            }) {
+                Ok(LineOutput::Message(message)) => {
+                    state.output.extend_from_slice(message.as_bytes())
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    state.output.extend_from_slice(line.as_bytes());
+                    failure = Some(e);
+                }
+            }
+            if !artifacts.is_empty() {
+                state.artifacts.extend(artifacts);
+                self.changed.notify_all();
+            }
+        }
+        failure
+    }
+
+    /// Block until `ready` is satisfied by the state of the compilation.
+    fn wait_until(
+        &self,
+        ready: impl Fn(&CompilationState) -> bool,
+    ) -> MutexGuard<'_, CompilationState> {
+        let mut state = self.lock();
+        while !ready(&state) {
+            state = self
+                .changed
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        state
+    }
+}
+
+/// The most recent compilation for each pipelining key and its fingerprint.
+type Compilations = Mutex<HashMap<String, (u64, Arc<Compilation>)>>;
+
+/// How long the rustc process of a completed metadata request is kept running
+/// for a full request to attach to it.
+const FULL_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Remove the entry of `key` if it still refers to `compilation`.
+fn remove(compilations: &Compilations, key: &str, compilation: &Arc<Compilation>) {
+    let mut compilations = compilations.lock().unwrap_or_else(|e| e.into_inner());
+    if matches!(compilations.get(key), Some((_, c)) if Arc::ptr_eq(c, compilation)) {
+        compilations.remove(key);
+    }
+}
+
+/// Kill the rustc process of `compilation` and forget it unless a full request
+/// attaches within `timeout`.
+fn abandon_unless_attached(
+    compilations: &Compilations,
+    key: &str,
+    compilation: &Arc<Compilation>,
+    timeout: Duration,
+) {
+    let (state, _) = compilation
+        .changed
+        .wait_timeout_while(compilation.lock(), timeout, |s| !s.full_attached)
+        .unwrap_or_else(|e| e.into_inner());
+    if state.full_attached {
+        return;
+    }
+    drop(state);
+
+    // Full requests attach while holding the lock of the compilations, so none
+    // can attach once the compilation has been removed.
+    let mut entries = compilations.lock().unwrap_or_else(|e| e.into_inner());
+    if compilation.lock().full_attached {
+        return;
+    }
+    if matches!(entries.get(key), Some((_, c)) if Arc::ptr_eq(c, compilation)) {
+        entries.remove(key);
+    }
+    drop(entries);
+
+    debug_log!(
+        "no full request for pipelined compilation {}, killing rustc",
+        key
+    );
+    compilation.kill();
+}
+
+/// Tracks the rustc processes of pipelined compilations within a worker.
+#[derive(Debug)]
+pub(crate) struct Pipeline {
+    compilations: Arc<Compilations>,
+    /// How long a full request may take to attach to a compilation after its
+    /// metadata request completed.
+    full_request_timeout: Duration,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self {
+            compilations: Arc::default(),
+            full_request_timeout: FULL_REQUEST_TIMEOUT,
+        }
+    }
+}
+
+impl Pipeline {
+    /// Run the `stage` of the pipelined compilation identified by `key` and
+    /// `fingerprint`, writing the processed output to `stderr`.
+    ///
+    /// Returns `None` if the request cannot be served by a shared rustc
+    /// process, in which case it must be run on its own.
+    pub(crate) fn run(
+        &self,
+        key: &str,
+        fingerprint: u64,
+        opts: &Options,
+        stderr: &mut dyn Write,
+    ) -> Result<Option<i32>, ProcessWrapperError> {
+        let stage = Stage::of(opts);
+        let start = SystemTime::now();
+        let started = Instant::now();
+        let (compilation, spawned) = {
+            let mut compilations = self
+                .compilations
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            let (compilation, spawned) = match compilations.get(key) {
+                Some((existing, compilation)) if *existing == fingerprint => {
+                    (Arc::clone(compilation), false)
+                }
+                _ => {
+                    // Replacing the entry drops compilations of stale inputs.
+                    let compilation = Compilation::spawn(opts)?;
+                    compilations.insert(key.to_owned(), (fingerprint, Arc::clone(&compilation)));
+                    (compilation, true)
+                }
+            };
+            if stage == Stage::Full {
+                compilation.lock().full_attached = true;
+                compilation.changed.notify_all();
+            }
+            (compilation, spawned)
+        };
+
+        let state = match stage {
+            Stage::Metadata => {
+                compilation.wait_until(|s| s.metadata_emitted() || s.exit_code.is_some())
+            }
+            Stage::Full => compilation.wait_until(|s| s.exit_code.is_some()),
+        };
+        match stage {
+            // The full stage is the last request of a compilation, so its
+            // rustc process and output do not need to be kept around any longer.
+            Stage::Full => remove(&self.compilations, key, &compilation),
+            Stage::Metadata if !state.full_attached => {
+                let compilations = Arc::clone(&self.compilations);
+                let compilation = Arc::clone(&compilation);
+                let key = key.to_owned();
+                let timeout = self.full_request_timeout;
+                thread::spawn(move || {
+                    abandon_unless_attached(&compilations, &key, &compilation, timeout)
+                });
+            }
+            Stage::Metadata => {}
+        }
+
+        // Bazel removes the outputs of an action before running it. When
+        // attaching to a compilation which already wrote the outputs of this
+        // request, they may have been deleted in the meantime.
+        if !spawned {
+            let emit = match stage {
+                Stage::Metadata => "metadata",
+                Stage::Full => "link",
+            };
+            if let Some(artifact) = state.artifacts.get(emit) {
+                if !Path::new(artifact).exists() {
+                    debug_log!("pipelined artifact {} is missing, recompiling", artifact);
+                    return Ok(None);
+                }
+            }
+        }
+
+        let code = match stage {
+            Stage::Metadata if state.metadata_emitted() => 0,
+            _ => state.exit_code.unwrap_or(1),
+        };
+
+        let mut stderr = open_stderr(opts, stderr)?;
+        stderr
+            .write_all(&state.output)
+            .map_err(|e| ProcessWrapperError(format!("failed to write output: {}", e)))?;
+        if let Some(output_file) = &opts.output_file {
+            create_file(output_file)
+                .and_then(|mut file| file.write_all(&state.raw_output))
+                .map_err(|e| ProcessWrapperError(format!("Unable to write output_file: {}", e)))?;
+        }
+        if let Some(time_passes_file) = &opts.rustc_time_passes_file {
+            create_file(time_passes_file)
+                .and_then(|mut file| file.write_all(&state.time_passes))
+                .map_err(|e| {
+                    ProcessWrapperError(format!("Unable to write time passes file: {}", e))
+                })?;
+        }
+        write_warnings(opts, String::from_utf8_lossy(&state.raw_output).lines())?;
+        // The metadata request does not own the rustc process, so it only
+        // accounts for the time it spent waiting.
+        let usage = match stage {
+            Stage::Metadata => None,
+            Stage::Full => state.usage,
+        };
+        drop(state);
+
+        write_resource_usage(opts, start, started, usage, code)?;
+
+        if code == 0 {
+            finalize(opts)?;
+        }
+
+        Ok(Some(code))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_process_output_tracks_artifacts() {
+        let compilation = Compilation::default();
+        let output = concat!(
+            r#"{"$message_type":"diagnostic","rendered":"warning: unused\n"}"#,
+            "\n",
+            r#"{"$message_type":"artifact","artifact":"libfoo.rmeta","emit":"metadata"}"#,
+            "\n",
+            r#"{"$message_type":"artifact","artifact":"libfoo.rlib","emit":"link"}"#,
+            "\n",
+        );
+
+        let failure = compilation.process_output(output.as_bytes(), ErrorFormat::Rendered, None);
+        assert!(failure.is_none());
+
+        let state = compilation.lock();
+        assert_eq!(state.output, b"warning: unused\n");
+        assert_eq!(state.raw_output, output.as_bytes());
+        assert_eq!(
+            state.artifacts,
+            HashMap::from([
+                ("metadata".to_string(), "libfoo.rmeta".to_string()),
+                ("link".to_string(), "libfoo.rlib".to_string()),
+            ])
+        );
+        assert!(state.metadata_emitted());
+    }
+
+    #[test]
+    fn test_process_output_forwards_unparsable_output() {
+        let compilation = Compilation::default();
+        let output = concat!(
+            r#"{"$message_type":"diagnostic","rendered":"warning: unused\n"}"#,
+            "\n",
+            "not json\n",
+            r#"{"$message_type":"artifact","artifact":"libfoo.rmeta","emit":"metadata"}"#,
+            "\n",
+        );
+
+        let failure = compilation.process_output(output.as_bytes(), ErrorFormat::Rendered, None);
+        assert!(failure.is_some());
+
+        let state = compilation.lock();
+        assert_eq!(
+            String::from_utf8_lossy(&state.output),
+            concat!(
+                "warning: unused\n",
+                "not json\n",
+                r#"{"$message_type":"artifact","artifact":"libfoo.rmeta","emit":"metadata"}"#,
+                "\n",
+            )
+        );
+        assert!(!state.metadata_emitted());
+    }
+
+    #[test]
+    fn test_wait_until_metadata() {
+        let compilation = Arc::new(Compilation::default());
+        let shared = Arc::clone(&compilation);
+        let handle = thread::spawn(move || {
+            let state = shared.wait_until(|s| s.metadata_emitted() || s.exit_code.is_some());
+            state.metadata_emitted()
+        });
+
+        compilation.process_output(
+            &br#"{"$message_type":"artifact","artifact":"libfoo.rmeta","emit":"metadata"}
+"#[..],
+            ErrorFormat::Rendered,
+            None,
+        );
+
+        assert!(handle.join().unwrap());
+        assert!(compilation.lock().exit_code.is_none());
+    }
+
+    #[test]
+    fn test_remove_only_current_compilation() {
+        let pipeline = Pipeline::default();
+        let stale = Arc::new(Compilation::default());
+        let current = Arc::new(Compilation::default());
+        pipeline
+            .compilations
+            .lock()
+            .unwrap()
+            .insert("key".to_owned(), (1, Arc::clone(&current)));
+
+        remove(&pipeline.compilations, "key", &stale);
+        assert!(pipeline.compilations.lock().unwrap().contains_key("key"));
+
+        remove(&pipeline.compilations, "key", &current);
+        assert!(pipeline.compilations.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_metadata_only_compilation_is_abandoned() {
+        let artifact = std::env::temp_dir().join("process_wrapper_pipelining_test.rmeta");
+        std::fs::write(&artifact, "").unwrap();
+        let script = format!(
+            r#"echo '{{"$message_type":"artifact","artifact":"{}","emit":"metadata"}}' >&2; exec sleep 60"#,
+            artifact.display()
+        );
+        let opts = Options {
+            executable: "sh".to_owned(),
+            child_arguments: vec!["-c".to_owned(), script],
+            child_environment: HashMap::new(),
+            touch_file: None,
+            copy_output: None,
+            stdout_file: None,
+            stderr_file: None,
+            output_file: None,
+            rustc_quit_on_rmeta: true,
+            rustc_output_format: None,
+            pipelining_key: Some("libfoo.rlib".to_owned()),
+            resource_usage_file: None,
+            resource_usage_label: None,
+            resource_usage_mnemonic: None,
+            rustc_time_passes_file: None,
+            check_reproducibility: false,
+            ice_bundle_dir: None,
+            retry_on_ice: false,
+            subst_mappings: Vec::new(),
+            env_file_vars: Vec::new(),
+            cache_dir: None,
+            cache_max_size: 0,
+            diagnostic_path_mapping: None,
+            warnings_file: None,
+            warnings_label: None,
+            stamp_templates: Vec::new(),
+            workspace_status: Default::default(),
+        };
+        let pipeline = Pipeline {
+            full_request_timeout: Duration::from_millis(100),
+            ..Pipeline::default()
+        };
+
+        let code = pipeline
+            .run("libfoo.rlib", 1, &opts, &mut Vec::new())
+            .unwrap();
+        assert_eq!(code, Some(0));
+        let compilation = Arc::clone(&pipeline.compilations.lock().unwrap()["libfoo.rlib"].1);
+
+        // Without a full request, rustc is killed long before it would exit.
+        let (state, timeout) = compilation
+            .changed
+            .wait_timeout_while(compilation.lock(), Duration::from_secs(30), |s| {
+                s.exit_code.is_none()
+            })
+            .unwrap();
+        assert!(!timeout.timed_out());
+        assert_ne!(state.exit_code, Some(0));
+        assert!(pipeline.compilations.lock().unwrap().is_empty());
+    }
+}
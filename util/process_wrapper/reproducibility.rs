@@ -0,0 +1,844 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reproducibility checks for rustc outputs.
+//!
+//! After a successful compilation, rustc is run a second time from a mirror of
+//! the execution root at a different path, with a different `HOME` and
+//! temporary directory and with its outputs redirected to a scratch directory.
+//! The outputs of both runs are then compared byte for byte. Differences are
+//! reported per archive member and per ELF section along with the absolute
+//! paths which leaked into the outputs.
+
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use crate::options::{options_from_args_with_pwd, Options};
+use crate::util::{read_file_to_array, TempDir};
+use crate::{child_command, status_code, ProcessWrapperError};
+
+/// The maximum number of differences reported for a single output.
+const MAX_DIFFERENCES: usize = 10;
+
+/// Populate `mirror` with symlinks to every entry of `dir`.
+#[cfg(unix)]
+fn mirror_directory(dir: &Path, mirror: &Path) -> io::Result<()> {
+    fs::create_dir_all(mirror)?;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        std::os::unix::fs::symlink(entry.path(), mirror.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn mirror_directory(_dir: &Path, _mirror: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reproducibility checks are only supported on unix",
+    ))
+}
+
+/// Rewrites the output paths of rustc arguments to point into a scratch directory.
+struct Redirector {
+    /// The directory redirected outputs are written to.
+    out_root: PathBuf,
+    /// The directory rewritten param files are written to.
+    params_dir: PathBuf,
+    /// Pairs of original and redirected output paths.
+    outputs: Vec<(PathBuf, PathBuf)>,
+    /// Every argument seen, including the contents of param files.
+    seen: Vec<String>,
+}
+
+impl Redirector {
+    fn new(out_root: PathBuf, params_dir: PathBuf) -> Self {
+        Self {
+            out_root,
+            params_dir,
+            outputs: Vec::new(),
+            seen: Vec::new(),
+        }
+    }
+
+    /// Redirect a single output path. If `is_dir` is set, the redirected
+    /// directory is created, otherwise its parent is.
+    fn redirect(&mut self, path: &str, is_dir: bool) -> Result<String, String> {
+        let relative = Path::new(path)
+            .components()
+            .filter(|c| matches!(c, std::path::Component::Normal(_)))
+            .collect::<PathBuf>();
+        let redirected = self.out_root.join(relative);
+        let dir = if is_dir {
+            redirected.as_path()
+        } else {
+            redirected.parent().unwrap_or(&self.out_root)
+        };
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+        self.outputs.push((PathBuf::from(path), redirected.clone()));
+        redirected
+            .into_os_string()
+            .into_string()
+            .map_err(|p| format!("path is not utf-8: {:?}", p))
+    }
+
+    /// Redirect the paths of an `--emit` value such as `dep-info,link=out/foo`.
+    fn redirect_emit(&mut self, emit: &str) -> Result<String, String> {
+        emit.split(',')
+            .map(|kind| match kind.split_once('=') {
+                Some((kind, path)) => Ok(format!("{}={}", kind, self.redirect(path, false)?)),
+                None => Ok(kind.to_owned()),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|kinds| kinds.join(","))
+    }
+
+    /// Rewrite `args`, recursing into param files.
+    fn rewrite(
+        &mut self,
+        args: &[String],
+        read_file: &mut dyn FnMut(&str) -> Result<Vec<String>, String>,
+    ) -> Result<Vec<String>, String> {
+        let mut rewritten = Vec::with_capacity(args.len());
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            self.seen.push(arg.clone());
+            if let Some(param_file) = arg.strip_prefix('@') {
+                let content = read_file(param_file)?;
+                let content = self.rewrite(&content, read_file)?;
+                fs::create_dir_all(&self.params_dir).map_err(|e| e.to_string())?;
+                let path = self
+                    .params_dir
+                    .join(format!("{}.params", self.outputs.len() + self.seen.len()));
+                fs::write(&path, content.join("\n"))
+                    .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+                rewritten.push(format!("@{}", path.display()));
+            } else if let Some(dir) = arg.strip_prefix("--out-dir=") {
+                rewritten.push(format!("--out-dir={}", self.redirect(dir, true)?));
+            } else if let Some(emit) = arg.strip_prefix("--emit=") {
+                rewritten.push(format!("--emit={}", self.redirect_emit(emit)?));
+            } else if arg == "--out-dir" || arg == "-o" || arg == "--emit" {
+                rewritten.push(arg.clone());
+                if let Some(value) = iter.next() {
+                    self.seen.push(value.clone());
+                    rewritten.push(match arg.as_str() {
+                        "--out-dir" => self.redirect(value, true)?,
+                        "-o" => self.redirect(value, false)?,
+                        _ => self.redirect_emit(value)?,
+                    });
+                }
+            } else {
+                rewritten.push(arg.clone());
+            }
+        }
+        Ok(rewritten)
+    }
+}
+
+/// A member of an `ar` archive such as an `.rlib`.
+#[derive(Debug, PartialEq)]
+struct Member<'a> {
+    name: String,
+    data: &'a [u8],
+}
+
+/// Parse the members of a GNU or BSD `ar` archive.
+fn parse_archive(data: &[u8]) -> Option<Vec<Member<'_>>> {
+    let mut rest = data.strip_prefix(b"!<arch>\n")?;
+    let mut long_names: &[u8] = &[];
+    let mut members = Vec::new();
+    while rest.len() >= 60 {
+        let header = &rest[..60];
+        let raw_name = std::str::from_utf8(&header[..16]).ok()?.trim_end();
+        let size: usize = std::str::from_utf8(&header[48..58])
+            .ok()?
+            .trim()
+            .parse()
+            .ok()?;
+        let mut body = rest.get(60..60 + size)?;
+        rest = rest.get(60 + size + size % 2..).unwrap_or_default();
+
+        let name = if raw_name == "//" {
+            long_names = body;
+            continue;
+        } else if raw_name == "/" || raw_name == "/SYM64/" || raw_name.starts_with("__.SYMDEF") {
+            "<symbol table>".to_owned()
+        } else if let Some(len) = raw_name.strip_prefix("#1/") {
+            // BSD archives store long names at the start of the member data.
+            let len: usize = len.parse().ok()?;
+            let name = String::from_utf8_lossy(body.get(..len)?)
+                .trim_end_matches('\0')
+                .to_owned();
+            body = &body[len..];
+            name
+        } else if let Some(offset) = raw_name.strip_prefix('/') {
+            // GNU archives store long names in the `//` member.
+            let names = long_names.get(offset.parse::<usize>().ok()?..)?;
+            let end = names.windows(2).position(|w| w == b"/\n")?;
+            String::from_utf8_lossy(&names[..end]).into_owned()
+        } else {
+            raw_name.trim_end_matches('/').to_owned()
+        };
+        members.push(Member { name, data: body });
+    }
+    Some(members)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<usize> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?) as usize)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<usize> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<usize> {
+    usize::try_from(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+    .ok()
+}
+
+/// Parse the sections of a 64-bit little endian ELF object.
+fn parse_elf_sections(data: &[u8]) -> Option<Vec<(String, &[u8])>> {
+    const SHT_NOBITS: usize = 8;
+
+    if !data.starts_with(b"\x7fELF") || data.get(4) != Some(&2) || data.get(5) != Some(&1) {
+        return None;
+    }
+    let shoff = read_u64(data, 0x28)?;
+    let shentsize = read_u16(data, 0x3A)?;
+    let shnum = read_u16(data, 0x3C)?;
+    let shstrndx = read_u16(data, 0x3E)?;
+
+    let mut sections = Vec::with_capacity(shnum);
+    for index in 0..shnum {
+        let header = data.get(shoff + index * shentsize..)?;
+        let name = read_u32(header, 0)?;
+        let kind = read_u32(header, 4)?;
+        let offset = read_u64(header, 0x18)?;
+        let size = read_u64(header, 0x20)?;
+        let content = if kind == SHT_NOBITS {
+            &[][..]
+        } else {
+            data.get(offset..offset + size)?
+        };
+        sections.push((name, content));
+    }
+
+    let names = sections.get(shstrndx)?.1;
+    sections
+        .into_iter()
+        .map(|(name, content)| {
+            let name = names.get(name..)?;
+            let end = name.iter().position(|b| *b == 0)?;
+            Some((String::from_utf8_lossy(&name[..end]).into_owned(), content))
+        })
+        .collect()
+}
+
+/// Render the printable bytes around `offset`.
+fn context(data: &[u8], offset: usize) -> String {
+    let start = offset.saturating_sub(24);
+    let end = (offset + 40).min(data.len());
+    data.get(start..end)
+        .unwrap_or_default()
+        .iter()
+        .map(|b| {
+            if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+/// Pair items with the same name, distinguishing duplicates by their occurrence.
+fn pair_by_name<'a, T>(
+    a: &'a [(String, T)],
+    b: &'a [(String, T)],
+) -> Vec<(String, Option<&'a T>, Option<&'a T>)> {
+    let mut occurrences: HashMap<&str, Vec<&T>> = HashMap::new();
+    for (name, item) in b {
+        occurrences.entry(name).or_default().push(item);
+    }
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    let mut pairs = Vec::new();
+    for (name, item) in a {
+        let index = seen.entry(name).or_default();
+        let other = occurrences.get(name.as_str()).and_then(|o| o.get(*index));
+        *index += 1;
+        pairs.push((name.clone(), Some(item), other.copied()));
+    }
+    for (name, items) in occurrences {
+        let count = seen.get(name).copied().unwrap_or_default();
+        for item in items.into_iter().skip(count) {
+            pairs.push((name.to_owned(), None, Some(item)));
+        }
+    }
+    pairs
+}
+
+/// Describe the differences between two versions of an output, descending
+/// into archive members and ELF sections.
+fn describe_differences(name: &str, a: &[u8], b: &[u8], report: &mut Vec<String>) {
+    if a == b {
+        return;
+    }
+
+    if let (Some(a_members), Some(b_members)) = (parse_archive(a), parse_archive(b)) {
+        let a_members: Vec<_> = a_members.into_iter().map(|m| (m.name, m.data)).collect();
+        let b_members: Vec<_> = b_members.into_iter().map(|m| (m.name, m.data)).collect();
+        let before = report.len();
+        for (member, a_data, b_data) in pair_by_name(&a_members, &b_members) {
+            let member_name = format!("{}({})", name, member);
+            match (a_data, b_data) {
+                (Some(a_data), Some(b_data)) => {
+                    describe_differences(&member_name, a_data, b_data, report)
+                }
+                (Some(_), None) => report.push(format!("{}: only in the first build", member_name)),
+                _ => report.push(format!("{}: only in the second build", member_name)),
+            }
+        }
+        if report.len() == before {
+            report.push(format!("{}: archive headers differ", name));
+        }
+        return;
+    }
+
+    if let (Some(a_sections), Some(b_sections)) = (parse_elf_sections(a), parse_elf_sections(b)) {
+        let before = report.len();
+        for (section, a_data, b_data) in pair_by_name(&a_sections, &b_sections) {
+            match (a_data, b_data) {
+                (Some(a_data), Some(b_data)) if a_data == b_data => {}
+                (Some(a_data), Some(b_data)) => {
+                    let offset = first_difference(a_data, b_data);
+                    report.push(format!(
+                        "{}: section {} differs at offset {:#x}: \"{}\" vs \"{}\"",
+                        name,
+                        section,
+                        offset,
+                        context(a_data, offset),
+                        context(b_data, offset),
+                    ))
+                }
+                (Some(_), None) => report.push(format!(
+                    "{}: section {} only in the first build",
+                    name, section
+                )),
+                _ => report.push(format!(
+                    "{}: section {} only in the second build",
+                    name, section
+                )),
+            }
+        }
+        if report.len() == before {
+            report.push(format!("{}: ELF headers differ", name));
+        }
+        return;
+    }
+
+    let offset = first_difference(a, b);
+    report.push(format!(
+        "{}: differs at offset {:#x} (sizes {} and {}): \"{}\" vs \"{}\"",
+        name,
+        offset,
+        a.len(),
+        b.len(),
+        context(a, offset),
+        context(b, offset),
+    ));
+}
+
+fn first_difference(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .unwrap_or_else(|| a.len().min(b.len()))
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    !needle.is_empty()
+        && haystack
+            .windows(needle.len())
+            .any(|window| window == needle.as_bytes())
+}
+
+/// The values which differ between the two builds.
+struct Perturbation {
+    /// The execution root of the first build.
+    execroot: String,
+    /// The canonical path of the execution root of the first build.
+    canonical_execroot: Option<String>,
+    /// The execution root of the second build.
+    mirror: String,
+    /// Environment variables and their values in the second build.
+    environment: Vec<(&'static str, String)>,
+}
+
+/// Explain which settings are likely responsible for the differences.
+fn hints(
+    perturbation: &Perturbation,
+    differing: &[(Vec<u8>, Vec<u8>)],
+    args: &[String],
+    environment: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut hints = Vec::new();
+    let leaked = |first: &str, second: &str| {
+        differing
+            .iter()
+            .any(|(a, b)| contains(a, first) || contains(b, second))
+    };
+
+    if leaked(&perturbation.execroot, &perturbation.mirror) {
+        let remap = format!("--remap-path-prefix={}=", perturbation.mirror);
+        if args.iter().any(|arg| arg.starts_with(&remap)) {
+            hints.push(
+                "The absolute path of the execution root is embedded in the outputs even though \
+                 `--remap-path-prefix=${pwd}=...` is set. Paths passed to rustc through \
+                 environment variables or generated sources may not be relative to `${pwd}`."
+                    .to_owned(),
+            );
+        } else {
+            hints.push(
+                "The absolute path of the execution root is embedded in the outputs and \
+                 `--remap-path-prefix=${pwd}=...` is missing from the rustc flags."
+                    .to_owned(),
+            );
+        }
+    }
+
+    if let Some(canonical) = &perturbation.canonical_execroot {
+        if differing.iter().any(|(a, _)| contains(a, canonical)) {
+            hints.push(format!(
+                "The canonical path of the execution root ({}) is embedded in the outputs. \
+                 It is not covered by `--remap-path-prefix=${{pwd}}=...`; avoid canonicalizing \
+                 paths in build scripts and proc macros or add a `--remap-path-prefix` for it.",
+                canonical
+            ));
+        }
+    }
+
+    for (var, value) in &perturbation.environment {
+        if differing.iter().any(|(_, b)| contains(b, value)) {
+            hints.push(format!(
+                "The value of `${}` is embedded in the outputs.",
+                var
+            ));
+        }
+    }
+
+    for arg in args {
+        if arg.contains(&perturbation.execroot) {
+            hints.push(format!(
+                "The argument `{}` contains the absolute path of the execution root. \
+                 Use `${{pwd}}` with `--subst pwd=${{pwd}}` instead.",
+                arg
+            ));
+        }
+    }
+    let mut vars: Vec<_> = environment
+        .iter()
+        .filter(|(key, value)| *key != "PWD" && value.contains(&perturbation.execroot))
+        .map(|(key, _)| key)
+        .collect();
+    vars.sort();
+    for var in vars {
+        hints.push(format!(
+            "The environment variable `{}` contains the absolute path of the execution root. \
+             Use `${{pwd}}` with `--subst pwd=${{pwd}}` instead.",
+            var
+        ));
+    }
+
+    if hints.is_empty() {
+        hints.push(
+            "No absolute paths were found in the differing outputs. The differences may be \
+             caused by timestamps or nondeterministic iteration order (e.g. of a `HashMap`) \
+             in build scripts or proc macros."
+                .to_owned(),
+        );
+    }
+    hints
+}
+
+/// Collect the files under `dir`, relative to `dir`.
+fn collect_files(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(dir, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Pair the outputs of the first build with the redirected outputs of the second.
+fn output_pairs(outputs: &[(PathBuf, PathBuf)]) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut pairs = Vec::new();
+    for (original, redirected) in outputs {
+        if redirected.is_dir() {
+            let mut files = Vec::new();
+            collect_files(redirected, Path::new(""), &mut files)?;
+            files.sort();
+            for file in files {
+                // Dep-info files name the outputs, which are at a different path.
+                if file.extension().is_some_and(|ext| ext == "d") {
+                    continue;
+                }
+                pairs.push((original.join(&file), redirected.join(&file)));
+            }
+        } else {
+            pairs.push((original.clone(), redirected.clone()));
+        }
+    }
+    // Outputs the first build did not produce, such as an `.rlib` when rustc
+    // was stopped after writing metadata, cannot be compared.
+    pairs.retain(|(original, redirected)| original.is_file() && redirected.is_file());
+    Ok(pairs)
+}
+
+/// Run the child process described by the process wrapper arguments `args` a
+/// second time from a different working directory and environment and compare
+/// its outputs with those of the first run. Returns the exit code of the
+/// process wrapper.
+pub(crate) fn check(args: Vec<String>, stderr: &mut dyn Write) -> Result<i32, ProcessWrapperError> {
+    let scratch = TempDir::create("process_wrapper_reproducibility").map_err(|e| {
+        ProcessWrapperError(format!(
+            "failed to create reproducibility check directory: {}",
+            e
+        ))
+    })?;
+    let scratch = scratch.path();
+    let error = |context: &str, e: &dyn std::fmt::Display| {
+        ProcessWrapperError(format!("reproducibility check: {}: {}", context, e))
+    };
+
+    let current_dir =
+        env::current_dir().map_err(|e| error("failed to get current directory", &e))?;
+    let mirror = scratch
+        .join("execroot")
+        .join(current_dir.file_name().unwrap_or_default());
+    mirror_directory(&current_dir, &mirror)
+        .map_err(|e| error("failed to mirror the execution root", &e))?;
+
+    let perturbation = Perturbation {
+        execroot: current_dir.to_string_lossy().into_owned(),
+        canonical_execroot: fs::canonicalize(&current_dir)
+            .ok()
+            .filter(|canonical| *canonical != current_dir)
+            .map(|canonical| canonical.to_string_lossy().into_owned()),
+        mirror: mirror.to_string_lossy().into_owned(),
+        environment: ["HOME", "TMPDIR", "TMP", "TEMP"]
+            .iter()
+            .map(|&var| {
+                let value = scratch.join(var.to_lowercase());
+                (var, value.to_string_lossy().into_owned())
+            })
+            .collect(),
+    };
+
+    let mut opts: Options = options_from_args_with_pwd(args, &perturbation.mirror)
+        .map_err(|e| error("failed to parse arguments", &e))?;
+    for (var, value) in &perturbation.environment {
+        fs::create_dir_all(value).map_err(|e| error("failed to create directory", &e))?;
+        opts.child_environment
+            .insert(var.to_string(), value.clone());
+    }
+    opts.child_environment
+        .insert("PWD".to_owned(), perturbation.mirror.clone());
+
+    let mut redirector = Redirector::new(scratch.join("out"), scratch.join("params"));
+    opts.child_arguments = redirector
+        .rewrite(&opts.child_arguments, &mut |path| read_file_to_array(path))
+        .map_err(|e| error("failed to redirect outputs", &e))?;
+    if let Some(stdout_file) = opts.stdout_file.take() {
+        let redirected = scratch.join("stdout");
+        opts.stdout_file = Some(redirected.to_string_lossy().into_owned());
+        redirector
+            .outputs
+            .push((PathBuf::from(stdout_file), redirected));
+    }
+
+    let mut command = child_command(&opts, Stdio::null())?;
+    command.current_dir(&mirror);
+    debug_log!("{:#?}", command);
+    let mut child = command
+        .spawn()
+        .map_err(|e| error("failed to spawn child process", &e))?;
+    let mut output = Vec::new();
+    if let Some(mut child_stderr) = child.stderr.take() {
+        child_stderr
+            .read_to_end(&mut output)
+            .map_err(|e| error("failed to read stderr", &e))?;
+    }
+    let status = child
+        .wait()
+        .map_err(|e| error("failed to wait for child process", &e))?;
+    let write_error = |e: io::Error| error("failed to write report", &e);
+
+    if status_code(status, false) != 0 {
+        writeln!(
+            stderr,
+            "error: the compilation is not reproducible: it failed when run from {}:",
+            perturbation.mirror
+        )
+        .map_err(write_error)?;
+        stderr.write_all(&output).map_err(write_error)?;
+        return Ok(1);
+    }
+
+    let mut report = Vec::new();
+    let mut differing = Vec::new();
+    for (original, redirected) in
+        output_pairs(&redirector.outputs).map_err(|e| error("failed to list outputs", &e))?
+    {
+        let a = fs::read(&original).map_err(|e| error("failed to read output", &e))?;
+        let b = fs::read(&redirected).map_err(|e| error("failed to read output", &e))?;
+        if a == b {
+            continue;
+        }
+        let mut differences = Vec::new();
+        describe_differences(&original.to_string_lossy(), &a, &b, &mut differences);
+        let omitted = differences.len().saturating_sub(MAX_DIFFERENCES);
+        report.extend(differences.into_iter().take(MAX_DIFFERENCES));
+        if omitted > 0 {
+            report.push(format!(
+                "{}: {} more differences",
+                original.display(),
+                omitted
+            ));
+        }
+        differing.push((a, b));
+    }
+
+    if differing.is_empty() {
+        return Ok(0);
+    }
+
+    writeln!(
+        stderr,
+        "error: the outputs of the compilation are not reproducible. They differ when \
+         compiled from {} instead of {}:",
+        perturbation.mirror, perturbation.execroot
+    )
+    .map_err(write_error)?;
+    for line in report {
+        writeln!(stderr, "  {}", line).map_err(write_error)?;
+    }
+    writeln!(stderr, "\nLikely causes:").map_err(write_error)?;
+    for hint in hints(
+        &perturbation,
+        &differing,
+        &redirector.seen,
+        &opts.child_environment,
+    ) {
+        writeln!(stderr, "  - {}", hint).map_err(write_error)?;
+    }
+    Ok(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = b"!<arch>\n".to_vec();
+        for (name, content) in members {
+            data.extend(
+                format!(
+                    "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                    name,
+                    0,
+                    0,
+                    0,
+                    644,
+                    content.len()
+                )
+                .bytes(),
+            );
+            data.extend_from_slice(content);
+            if content.len() % 2 == 1 {
+                data.push(b'\n');
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_redirect_outputs() {
+        let scratch = TempDir::create("process_wrapper_test").unwrap();
+        let scratch = scratch.path();
+        let mut redirector = Redirector::new(scratch.join("out"), scratch.join("params"));
+        let args: Vec<String> = [
+            "src/lib.rs",
+            "--out-dir=bazel-out/bin/pkg",
+            "--emit=dep-info,link=bazel-out/bin/pkg/app",
+            "-o",
+            "bazel-out/bin/pkg/other",
+            "--codegen=opt-level=0",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let rewritten = redirector
+            .rewrite(&args, &mut |_| panic!("no param files"))
+            .unwrap();
+
+        let out = scratch.join("out").join("bazel-out/bin/pkg");
+        assert_eq!(
+            rewritten,
+            vec![
+                "src/lib.rs".to_owned(),
+                format!("--out-dir={}", out.display()),
+                format!("--emit=dep-info,link={}", out.join("app").display()),
+                "-o".to_owned(),
+                out.join("other").display().to_string(),
+                "--codegen=opt-level=0".to_owned(),
+            ]
+        );
+        assert_eq!(
+            redirector.outputs,
+            vec![
+                (PathBuf::from("bazel-out/bin/pkg"), out.clone()),
+                (PathBuf::from("bazel-out/bin/pkg/app"), out.join("app")),
+                (PathBuf::from("bazel-out/bin/pkg/other"), out.join("other")),
+            ]
+        );
+        assert!(out.is_dir());
+    }
+
+    #[test]
+    fn test_redirect_outputs_in_param_file() {
+        let scratch = TempDir::create("process_wrapper_test").unwrap();
+        let scratch = scratch.path();
+        let mut redirector = Redirector::new(scratch.join("out"), scratch.join("params"));
+
+        let rewritten = redirector
+            .rewrite(&["@rustc.params".to_owned()], &mut |path| {
+                assert_eq!(path, "rustc.params");
+                Ok(vec!["--out-dir".to_owned(), "bin".to_owned()])
+            })
+            .unwrap();
+
+        let param_file = rewritten[0].strip_prefix('@').unwrap();
+        assert_eq!(
+            fs::read_to_string(param_file).unwrap(),
+            format!("--out-dir\n{}", scratch.join("out").join("bin").display())
+        );
+        assert_eq!(redirector.seen, vec!["@rustc.params", "--out-dir", "bin"]);
+    }
+
+    #[test]
+    fn test_parse_archive() {
+        let long_name = "foo-1234.foo.abcdef-cgu.0.rcgu.o";
+        let names = format!("{}/\n", long_name);
+        let data = archive(&[
+            ("/", b"symbols"),
+            ("//", names.as_bytes()),
+            ("lib.rmeta/", b"rust"),
+            ("/0", b"object"),
+        ]);
+
+        assert_eq!(
+            parse_archive(&data).unwrap(),
+            vec![
+                Member {
+                    name: "<symbol table>".to_owned(),
+                    data: b"symbols",
+                },
+                Member {
+                    name: "lib.rmeta".to_owned(),
+                    data: b"rust",
+                },
+                Member {
+                    name: long_name.to_owned(),
+                    data: b"object",
+                },
+            ]
+        );
+        assert!(parse_archive(b"not an archive").is_none());
+    }
+
+    #[test]
+    fn test_describe_differences_in_archive_members() {
+        let a = archive(&[("lib.rmeta/", b"rust 2024-01-01"), ("a.o/", b"same")]);
+        let b = archive(&[("lib.rmeta/", b"rust 2024-01-02"), ("b.o/", b"new")]);
+
+        let mut report = Vec::new();
+        describe_differences("libfoo.rlib", &a, &b, &mut report);
+        assert_eq!(
+            report,
+            vec![
+                "libfoo.rlib(lib.rmeta): differs at offset 0xe (sizes 15 and 15): \
+                 \"rust 2024-01-01\" vs \"rust 2024-01-02\""
+                    .to_owned(),
+                "libfoo.rlib(a.o): only in the first build".to_owned(),
+                "libfoo.rlib(b.o): only in the second build".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hints() {
+        let perturbation = Perturbation {
+            execroot: "/execroot/_main".to_owned(),
+            canonical_execroot: None,
+            mirror: "/tmp/check/execroot/_main".to_owned(),
+            environment: vec![("HOME", "/tmp/check/home".to_owned())],
+        };
+        let differing = vec![(
+            b"src: /execroot/_main/src/lib.rs".to_vec(),
+            b"src: /tmp/check/execroot/_main/src/lib.rs home: /tmp/check/home".to_vec(),
+        )];
+        let args = vec!["-Lnative=/execroot/_main/lib".to_owned()];
+        let environment = HashMap::from([
+            ("OUT_DIR".to_owned(), "/execroot/_main/out".to_owned()),
+            ("PWD".to_owned(), "/execroot/_main".to_owned()),
+        ]);
+
+        let hints = hints(&perturbation, &differing, &args, &environment);
+        assert_eq!(hints.len(), 4);
+        assert!(hints[0].contains("`--remap-path-prefix=${pwd}=...` is missing"));
+        assert!(hints[1].contains("`$HOME`"));
+        assert!(hints[2].contains("`-Lnative=/execroot/_main/lib`"));
+        assert!(hints[3].contains("`OUT_DIR`"));
+    }
+
+    #[test]
+    fn test_hints_without_paths() {
+        let perturbation = Perturbation {
+            execroot: "/execroot/_main".to_owned(),
+            canonical_execroot: None,
+            mirror: "/tmp/check/execroot/_main".to_owned(),
+            environment: Vec::new(),
+        };
+        let differing = vec![(b"1".to_vec(), b"2".to_vec())];
+
+        let hints = hints(&perturbation, &differing, &[], &HashMap::new());
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].contains("nondeterministic iteration order"));
+    }
+}
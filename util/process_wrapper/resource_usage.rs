@@ -0,0 +1,260 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resource accounting for child processes.
+
+use std::collections::HashMap;
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tinyjson::JsonValue;
+
+/// CPU time and memory consumed by a child process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ResourceUsage {
+    pub(crate) user_time: Duration,
+    pub(crate) system_time: Duration,
+    /// Peak resident set size in kilobytes.
+    pub(crate) max_rss_kb: u64,
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::os::raw::{c_int, c_long};
+
+    #[cfg(target_os = "macos")]
+    type SusecondsT = i32;
+    #[cfg(not(target_os = "macos"))]
+    type SusecondsT = c_long;
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub(super) struct Timeval {
+        pub(super) tv_sec: c_long,
+        pub(super) tv_usec: SusecondsT,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub(super) struct Rusage {
+        pub(super) ru_utime: Timeval,
+        pub(super) ru_stime: Timeval,
+        pub(super) ru_maxrss: c_long,
+        // The remaining fields are unused but part of the struct layout:
+        // ixrss, idrss, isrss, minflt, majflt, nswap, inblock, oublock,
+        // msgsnd, msgrcv, nsignals, nvcsw, nivcsw
+        pub(super) ru_other: [c_long; 13],
+    }
+
+    extern "C" {
+        pub(super) fn wait4(
+            pid: c_int,
+            status: *mut c_int,
+            options: c_int,
+            rusage: *mut Rusage,
+        ) -> c_int;
+    }
+}
+
+/// Wait for a child process to terminate, collecting its resource usage.
+#[cfg(unix)]
+pub(crate) fn wait_with_usage(
+    child: &mut Child,
+) -> io::Result<(ExitStatus, Option<ResourceUsage>)> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let mut status = 0;
+    let mut rusage = sys::Rusage::default();
+    loop {
+        // SAFETY: `status` and `rusage` are valid for writes and the pid
+        // belongs to a child which has not yet been reaped.
+        let pid = unsafe { sys::wait4(child.id() as _, &mut status, 0, &mut rusage) };
+        if pid >= 0 {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+
+    let timeval = |tv: &sys::Timeval| {
+        Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+    };
+
+    // macOS reports the peak RSS in bytes while other platforms use kilobytes.
+    let max_rss_kb = if cfg!(target_os = "macos") {
+        rusage.ru_maxrss as u64 / 1024
+    } else {
+        rusage.ru_maxrss as u64
+    };
+
+    Ok((
+        ExitStatus::from_raw(status),
+        Some(ResourceUsage {
+            user_time: timeval(&rusage.ru_utime),
+            system_time: timeval(&rusage.ru_stime),
+            max_rss_kb,
+        }),
+    ))
+}
+
+/// Wait for a child process to terminate. Resource usage is not collected on
+/// this platform.
+#[cfg(not(unix))]
+pub(crate) fn wait_with_usage(
+    child: &mut Child,
+) -> io::Result<(ExitStatus, Option<ResourceUsage>)> {
+    Ok((child.wait()?, None))
+}
+
+/// A record of the resources consumed by an action.
+#[derive(Debug)]
+pub(crate) struct ResourceRecord<'a> {
+    pub(crate) label: Option<&'a str>,
+    pub(crate) mnemonic: Option<&'a str>,
+    pub(crate) start: SystemTime,
+    pub(crate) wall_time: Duration,
+    pub(crate) usage: Option<ResourceUsage>,
+    pub(crate) exit_code: i32,
+}
+
+impl ResourceRecord<'_> {
+    /// Render the record as a JSON object.
+    pub(crate) fn to_json(&self) -> JsonValue {
+        let string = |value: Option<&str>| match value {
+            Some(value) => JsonValue::String(value.to_owned()),
+            None => JsonValue::Null,
+        };
+        let number = |value: Option<u64>| match value {
+            Some(value) => JsonValue::Number(value as f64),
+            None => JsonValue::Null,
+        };
+        let start_time_us = self
+            .start
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+
+        JsonValue::Object(HashMap::from([
+            ("label".to_owned(), string(self.label)),
+            ("mnemonic".to_owned(), string(self.mnemonic)),
+            (
+                "start_time_us".to_owned(),
+                JsonValue::Number(start_time_us as f64),
+            ),
+            (
+                "wall_time_us".to_owned(),
+                JsonValue::Number(self.wall_time.as_micros() as f64),
+            ),
+            (
+                "user_time_us".to_owned(),
+                number(self.usage.map(|u| u.user_time.as_micros() as u64)),
+            ),
+            (
+                "system_time_us".to_owned(),
+                number(self.usage.map(|u| u.system_time.as_micros() as u64)),
+            ),
+            (
+                "max_rss_kb".to_owned(),
+                number(self.usage.map(|u| u.max_rss_kb)),
+            ),
+            (
+                "exit_code".to_owned(),
+                JsonValue::Number(self.exit_code as f64),
+            ),
+        ]))
+    }
+
+    /// Write the record to `path` as JSON.
+    pub(crate) fn write(&self, path: &str) -> Result<(), String> {
+        let json = self
+            .to_json()
+            .stringify()
+            .map_err(|e| format!("failed to serialize resource usage: {}", e))?;
+        std::fs::write(path, json)
+            .map_err(|e| format!("failed to write resource usage file {}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resource_record_to_json() {
+        let record = ResourceRecord {
+            label: Some("//pkg:lib"),
+            mnemonic: Some("Rustc"),
+            start: UNIX_EPOCH + Duration::from_secs(10),
+            wall_time: Duration::from_millis(1500),
+            usage: Some(ResourceUsage {
+                user_time: Duration::from_millis(1200),
+                system_time: Duration::from_millis(100),
+                max_rss_kb: 2048,
+            }),
+            exit_code: 0,
+        };
+
+        assert_eq!(
+            record.to_json(),
+            JsonValue::Object(HashMap::from([
+                (
+                    "label".to_owned(),
+                    JsonValue::String("//pkg:lib".to_owned())
+                ),
+                ("mnemonic".to_owned(), JsonValue::String("Rustc".to_owned())),
+                ("start_time_us".to_owned(), JsonValue::Number(10_000_000.0)),
+                ("wall_time_us".to_owned(), JsonValue::Number(1_500_000.0)),
+                ("user_time_us".to_owned(), JsonValue::Number(1_200_000.0)),
+                ("system_time_us".to_owned(), JsonValue::Number(100_000.0)),
+                ("max_rss_kb".to_owned(), JsonValue::Number(2048.0)),
+                ("exit_code".to_owned(), JsonValue::Number(0.0)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_resource_record_to_json_without_usage() {
+        let record = ResourceRecord {
+            label: None,
+            mnemonic: None,
+            start: UNIX_EPOCH,
+            wall_time: Duration::from_micros(5),
+            usage: None,
+            exit_code: 1,
+        };
+
+        let JsonValue::Object(json) = record.to_json() else {
+            panic!("Expected a JSON object");
+        };
+        assert_eq!(json["label"], JsonValue::Null);
+        assert_eq!(json["user_time_us"], JsonValue::Null);
+        assert_eq!(json["max_rss_kb"], JsonValue::Null);
+        assert_eq!(json["exit_code"], JsonValue::Number(1.0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_with_usage() {
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+        let (status, usage) = wait_with_usage(&mut child).unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert!(usage.is_some());
+    }
+}
@@ -12,7 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use tinyjson::JsonValue;
 
@@ -30,6 +34,13 @@ impl Default for ErrorFormat {
     }
 }
 
+fn get_field<'a>(value: &'a JsonValue, key: &str) -> Option<&'a JsonValue> {
+    match value {
+        JsonValue::Object(map) => map.get(key),
+        _ => None,
+    }
+}
+
 fn get_key(value: &JsonValue, key: &str) -> Option<String> {
     if let JsonValue::Object(map) = value {
         if let JsonValue::String(s) = map.get(key)? {
@@ -44,7 +55,10 @@ fn get_key(value: &JsonValue, key: &str) -> Option<String> {
 
 #[derive(Debug)]
 enum RustcMessage {
-    Emit(String),
+    Emit {
+        emit: String,
+        artifact: Option<String>,
+    },
     Message(String),
 }
 
@@ -52,7 +66,8 @@ impl TryFrom<JsonValue> for RustcMessage {
     type Error = ();
     fn try_from(val: JsonValue) -> Result<Self, Self::Error> {
         if let Some(emit) = get_key(&val, "emit") {
-            return Ok(Self::Emit(emit));
+            let artifact = get_key(&val, "artifact");
+            return Ok(Self::Emit { emit, artifact });
         }
         if let Some(rendered) = get_key(&val, "rendered") {
             return Ok(Self::Message(rendered));
@@ -78,6 +93,34 @@ pub(crate) fn process_json(line: String, error_format: ErrorFormat) -> LineResul
     })
 }
 
+/// process_json_artifacts parses the json output of rustc in the same way
+/// process_json does. In addition, every artifact notification is passed to
+/// `on_artifact` as an `(emit, path)` pair so callers can track which outputs
+/// rustc has finished writing.
+/// Returns an error if parsing json fails.
+pub(crate) fn process_json_artifacts(
+    line: String,
+    error_format: ErrorFormat,
+    on_artifact: &mut dyn FnMut(String, String),
+) -> LineResult {
+    let parsed: JsonValue = line
+        .parse()
+        .map_err(|_| "error parsing rustc output as json".to_owned())?;
+    Ok(match parsed.try_into() {
+        Ok(RustcMessage::Emit {
+            emit,
+            artifact: Some(artifact),
+        }) => {
+            on_artifact(emit, artifact);
+            LineOutput::Skip
+        }
+        Ok(RustcMessage::Message(rendered)) => {
+            output_based_on_error_format(line, rendered, error_format)
+        }
+        _ => LineOutput::Skip,
+    })
+}
+
 /// stop_on_rmeta_completion parses the json output of rustc in the same way
 /// process_rustc_json does. In addition, it will signal to stop when metadata
 /// is emitted so the compiler can be terminated.
@@ -94,7 +137,7 @@ pub(crate) fn stop_on_rmeta_completion(
         .parse()
         .map_err(|_| "error parsing rustc output as json".to_owned())?;
     Ok(match parsed.try_into() {
-        Ok(RustcMessage::Emit(emit)) if emit == "metadata" => {
+        Ok(RustcMessage::Emit { emit, .. }) if emit == "metadata" => {
             *kill = true;
             LineOutput::Terminate
         }
@@ -118,3 +161,363 @@ fn output_based_on_error_format(
         ErrorFormat::Rendered => LineOutput::Message(rendered),
     }
 }
+
+/// Extract a record for the `--warnings-file` from a json message of rustc.
+/// The record holds the lint code, message and primary span of the warning,
+/// along with `label`, the target being compiled. Returns `None` for anything
+/// but warnings with a primary span, e.g. the "N warnings emitted" summary.
+pub(crate) fn warning_record(line: &str, label: Option<&str>) -> Option<String> {
+    let parsed: JsonValue = line.parse().ok()?;
+    if get_key(&parsed, "level")? != "warning" {
+        return None;
+    }
+    let Some(JsonValue::Array(spans)) = get_field(&parsed, "spans") else {
+        return None;
+    };
+    let span = spans.iter().find(|span| {
+        matches!(
+            get_field(span, "is_primary"),
+            Some(JsonValue::Boolean(true))
+        )
+    })?;
+    let number = |key: &str| match get_field(span, key) {
+        Some(JsonValue::Number(n)) => JsonValue::Number(*n),
+        _ => JsonValue::Null,
+    };
+    let string = |value: Option<String>| value.map_or(JsonValue::Null, JsonValue::String);
+    let code = get_field(&parsed, "code").and_then(|code| get_key(code, "code"));
+
+    let record = HashMap::from([
+        ("label".to_owned(), string(label.map(str::to_owned))),
+        ("code".to_owned(), string(code)),
+        ("message".to_owned(), string(get_key(&parsed, "message"))),
+        ("file".to_owned(), string(get_key(span, "file_name"))),
+        ("line".to_owned(), number("line_start")),
+        ("column".to_owned(), number("column_start")),
+        ("rendered".to_owned(), string(get_key(&parsed, "rendered"))),
+    ]);
+    JsonValue::Object(record).stringify().ok()
+}
+
+/// The placeholder for `$(bazel info output_base)` in path mapping files.
+const OUTPUT_BASE: &str = "${output_base}";
+
+/// Rewrites the file names of diagnostic spans, so that errors in generated
+/// sources and external crates point to files editors can open instead of
+/// paths relative to the execution root.
+///
+/// Mapping files contain one `<from>\t<to>` pair per line. A `<from>` ending
+/// in `/` maps every path below it, any other `<from>` only maps exactly that
+/// path. `${output_base}` in `<to>` is replaced with the output base of the
+/// Bazel server, which is inferred from the execution root.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PathMapping {
+    exact: HashMap<String, String>,
+    // Sorted from the longest to the shortest prefix.
+    prefixes: Vec<(String, String)>,
+}
+
+impl PathMapping {
+    /// Load a mapping file for actions running in `execroot`.
+    pub(crate) fn load(path: &str, execroot: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read path mapping file '{path}': {e}"))?;
+        Self::parse(&content, output_base(execroot).as_deref())
+    }
+
+    fn parse(content: &str, output_base: Option<&Path>) -> Result<Self, String> {
+        let mut mapping = Self::default();
+        for line in content.lines().filter(|line| !line.is_empty()) {
+            let (from, to) = line
+                .split_once('\t')
+                .ok_or_else(|| format!("invalid path mapping '{line}'"))?;
+            let to = if to.contains(OUTPUT_BASE) {
+                // Without a known output base, leaving the path untouched is
+                // more helpful than pointing to a nonexistent location.
+                let Some(output_base) = output_base.and_then(Path::to_str) else {
+                    continue;
+                };
+                to.replace(OUTPUT_BASE, output_base)
+            } else {
+                to.to_owned()
+            };
+            if from.ends_with('/') {
+                mapping.prefixes.push((from.to_owned(), to));
+            } else {
+                mapping.exact.insert(from.to_owned(), to);
+            }
+        }
+        mapping
+            .prefixes
+            .sort_by_key(|(from, _)| Reverse(from.len()));
+        Ok(mapping)
+    }
+
+    /// The rewritten form of `path`, if any mapping applies to it.
+    pub(crate) fn map(&self, path: &str) -> Option<String> {
+        if let Some(to) = self.exact.get(path) {
+            return Some(to.clone());
+        }
+        self.prefixes.iter().find_map(|(from, to)| {
+            path.strip_prefix(from.as_str())
+                .map(|rest| format!("{to}{rest}"))
+        })
+    }
+
+    /// Rewrite the span file names and the rendered diagnostic of a json
+    /// message emitted by rustc. Lines which aren't json messages, or which
+    /// contain no mapped paths, are returned unchanged.
+    pub(crate) fn rewrite_json(&self, line: String) -> String {
+        let Ok(mut parsed) = line.parse::<JsonValue>() else {
+            return line;
+        };
+        if !self.rewrite_value(&mut parsed) {
+            return line;
+        }
+        match parsed.stringify() {
+            Ok(mut rewritten) => {
+                if line.ends_with('\n') {
+                    rewritten.push('\n');
+                }
+                rewritten
+            }
+            Err(_) => line,
+        }
+    }
+
+    fn rewrite_value(&self, value: &mut JsonValue) -> bool {
+        match value {
+            JsonValue::Object(map) => {
+                let mut changed = false;
+                for (key, value) in map.iter_mut() {
+                    match (key.as_str(), value) {
+                        ("file_name", JsonValue::String(path)) => {
+                            if let Some(mapped) = self.map(path) {
+                                *path = mapped;
+                                changed = true;
+                            }
+                        }
+                        ("rendered", JsonValue::String(text)) => {
+                            let rewritten = self.rewrite_rendered(text);
+                            if rewritten != *text {
+                                *text = rewritten;
+                                changed = true;
+                            }
+                        }
+                        (_, value) => changed |= self.rewrite_value(value),
+                    }
+                }
+                changed
+            }
+            JsonValue::Array(values) => values
+                .iter_mut()
+                .fold(false, |changed, value| self.rewrite_value(value) | changed),
+            _ => false,
+        }
+    }
+
+    /// Rewrite the locations (`--> file:line:col` and `::: file:line:col`)
+    /// of a diagnostic rendered by rustc, with or without colors.
+    pub(crate) fn rewrite_rendered(&self, text: &str) -> String {
+        text.split_inclusive('\n')
+            .map(|line| self.rewrite_rendered_line(line))
+            .collect()
+    }
+
+    fn rewrite_rendered_line(&self, line: &str) -> String {
+        let Some(marker) = ["--> ", "::: "]
+            .iter()
+            .find_map(|marker| line.find(marker).map(|index| index + marker.len()))
+        else {
+            return line.to_owned();
+        };
+        let start = skip_ansi_escapes(line, marker);
+        let end = line[start..]
+            .find(|c: char| c == '\x1b' || c.is_whitespace())
+            .map_or(line.len(), |end| start + end);
+        let (path, position) = split_position(&line[start..end]);
+        match self.map(path) {
+            Some(mapped) => format!("{}{}{}{}", &line[..start], mapped, position, &line[end..]),
+            None => line.to_owned(),
+        }
+    }
+}
+
+/// Infer `$(bazel info output_base)` from the execution root of an action,
+/// which is `<output_base>/execroot/<workspace>`, or a copy of it in
+/// `<output_base>/sandbox/<strategy>/<id>/execroot/<workspace>` or
+/// `<output_base>/bazel-workers/<worker>/<workspace>`.
+fn output_base(execroot: &Path) -> Option<PathBuf> {
+    let parent = execroot.parent()?;
+    let grandparent = parent.parent()?;
+    if parent.file_name()? != "execroot" {
+        return (grandparent.file_name()? == "bazel-workers")
+            .then(|| grandparent.parent())
+            .flatten()
+            .map(Path::to_path_buf);
+    }
+    let sandbox = grandparent.parent().and_then(Path::parent);
+    match sandbox {
+        Some(sandbox) if sandbox.file_name()? == "sandbox" => {
+            sandbox.parent().map(Path::to_path_buf)
+        }
+        _ => Some(grandparent.to_path_buf()),
+    }
+}
+
+/// Skip the ANSI escape sequences starting at `index` in `line`.
+fn skip_ansi_escapes(line: &str, mut index: usize) -> usize {
+    while line[index..].starts_with("\x1b[") {
+        match line[index..].find('m') {
+            Some(end) => index += end + 1,
+            None => break,
+        }
+    }
+    index
+}
+
+/// Split a `file:line:col` location into the file and the `:line:col` suffix.
+fn split_position(location: &str) -> (&str, &str) {
+    let mut split = location.len();
+    for _ in 0..2 {
+        match location[..split].rfind(':') {
+            Some(colon)
+                if colon + 1 < split
+                    && location[colon + 1..split]
+                        .bytes()
+                        .all(|b| b.is_ascii_digit()) =>
+            {
+                split = colon
+            }
+            _ => break,
+        }
+    }
+    location.split_at(split)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mapping() -> PathMapping {
+        PathMapping::parse(
+            "bazel-out/\t${output_base}/execroot/ws/bazel-out/\n\
+             external/\t${output_base}/external/\n\
+             bazel-out/k8-fastbuild/bin/pkg/lib.rs\tpkg/lib.rs\n",
+            Some(Path::new("/ob")),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_warning_record() {
+        let line = r#"{"$message_type":"diagnostic","message":"unused variable: `x`","code":{"code":"unused_variables","explanation":null},"level":"warning","spans":[{"file_name":"external/foo/src/lib.rs","line_start":3,"column_start":9,"is_primary":false},{"file_name":"src/lib.rs","line_start":2,"column_start":5,"is_primary":true}],"children":[],"rendered":"warning: unused variable: `x`\n"}"#;
+        let record: JsonValue = warning_record(line, Some("//pkg:lib"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let expected: JsonValue = r#"{"label":"//pkg:lib","code":"unused_variables","message":"unused variable: `x`","file":"src/lib.rs","line":2,"column":5,"rendered":"warning: unused variable: `x`\n"}"#.parse().unwrap();
+        assert_eq!(record, expected);
+
+        let no_code = line.replace(r#"{"code":"unused_variables","explanation":null}"#, "null");
+        let record: JsonValue = warning_record(&no_code, None).unwrap().parse().unwrap();
+        assert_eq!(get_field(&record, "code"), Some(&JsonValue::Null));
+        assert_eq!(get_field(&record, "label"), Some(&JsonValue::Null));
+
+        let summary = r#"{"$message_type":"diagnostic","message":"1 warning emitted","code":null,"level":"warning","spans":[],"children":[],"rendered":"warning: 1 warning emitted\n\n"}"#;
+        assert_eq!(warning_record(summary, None), None);
+        let error = line.replace(r#""level":"warning""#, r#""level":"error""#);
+        assert_eq!(warning_record(&error, None), None);
+        let artifact = r#"{"$message_type":"artifact","artifact":"libfoo.rlib","emit":"link"}"#;
+        assert_eq!(warning_record(artifact, None), None);
+    }
+
+    #[test]
+    fn test_map() {
+        let mapping = mapping();
+        assert_eq!(
+            mapping.map("bazel-out/k8-fastbuild/bin/pkg/lib.rs"),
+            Some("pkg/lib.rs".to_owned())
+        );
+        assert_eq!(
+            mapping.map("bazel-out/k8-fastbuild/bin/pkg/gen.rs"),
+            Some("/ob/execroot/ws/bazel-out/k8-fastbuild/bin/pkg/gen.rs".to_owned())
+        );
+        assert_eq!(
+            mapping.map("external/crate__foo/src/lib.rs"),
+            Some("/ob/external/crate__foo/src/lib.rs".to_owned())
+        );
+        assert_eq!(mapping.map("pkg/main.rs"), None);
+    }
+
+    #[test]
+    fn test_unknown_output_base() {
+        let mapping = PathMapping::parse(
+            "external/\t${output_base}/external/\nbazel-out/bin/a.rs\ta.rs\n",
+            None,
+        )
+        .unwrap();
+        assert_eq!(mapping.map("external/foo/lib.rs"), None);
+        assert_eq!(mapping.map("bazel-out/bin/a.rs"), Some("a.rs".to_owned()));
+    }
+
+    #[test]
+    fn test_output_base() {
+        assert_eq!(
+            output_base(Path::new("/ob/execroot/ws")),
+            Some(PathBuf::from("/ob"))
+        );
+        assert_eq!(
+            output_base(Path::new("/ob/sandbox/linux-sandbox/12/execroot/ws")),
+            Some(PathBuf::from("/ob"))
+        );
+        assert_eq!(
+            output_base(Path::new("/ob/bazel-workers/worker-3-Rustc/ws")),
+            Some(PathBuf::from("/ob"))
+        );
+        assert_eq!(output_base(Path::new("/tmp/remote/build")), None);
+    }
+
+    #[test]
+    fn test_rewrite_rendered() {
+        let mapping = mapping();
+        assert_eq!(
+            mapping.rewrite_rendered(
+                "error[E0308]: mismatched types\n --> bazel-out/k8-fastbuild/bin/pkg/lib.rs:2:18\n  |\n"
+            ),
+            "error[E0308]: mismatched types\n --> pkg/lib.rs:2:18\n  |\n"
+        );
+        assert_eq!(
+            mapping.rewrite_rendered(
+                "   \x1b[0m\x1b[1m\x1b[94m::: \x1b[0mexternal/foo/src/lib.rs:7:1\x1b[0m\n"
+            ),
+            "   \x1b[0m\x1b[1m\x1b[94m::: \x1b[0m/ob/external/foo/src/lib.rs:7:1\x1b[0m\n"
+        );
+        let unmapped = " --> pkg/main.rs:1:1\nnote: see external/foo\n";
+        assert_eq!(mapping.rewrite_rendered(unmapped), unmapped);
+    }
+
+    #[test]
+    fn test_rewrite_json() {
+        let mapping = mapping();
+        let line = r#"{"$message_type":"diagnostic","message":"unused","spans":[{"file_name":"bazel-out/k8-fastbuild/bin/pkg/lib.rs","line_start":2,"expansion":{"span":{"file_name":"external/foo/src/lib.rs"}}}],"children":[],"rendered":"warning: unused\n --> bazel-out/k8-fastbuild/bin/pkg/lib.rs:2:1\n"}"#;
+        let parsed: JsonValue = mapping.rewrite_json(line.to_owned()).parse().unwrap();
+        let span = &parsed["spans"][0];
+        assert_eq!(
+            span["file_name"],
+            JsonValue::String("pkg/lib.rs".to_owned())
+        );
+        assert_eq!(
+            span["expansion"]["span"]["file_name"],
+            JsonValue::String("/ob/external/foo/src/lib.rs".to_owned())
+        );
+        assert_eq!(
+            parsed["rendered"],
+            JsonValue::String("warning: unused\n --> pkg/lib.rs:2:1\n".to_owned())
+        );
+
+        let unmapped = r#"{"$message_type":"artifact","artifact":"bazel-out/k8-fastbuild/bin/pkg/libfoo.rmeta","emit":"metadata"}"#;
+        assert_eq!(mapping.rewrite_json(unmapped.to_owned()), unmapped);
+        assert_eq!(mapping.rewrite_json("not json".to_owned()), "not json");
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Workspace status stamping of environment variables and template files.
+
+use std::fs;
+
+use crate::options::Options;
+
+/// Write the stamped version of every `--stamp-template` to its output.
+pub(crate) fn stamp_templates(opts: &Options) -> Result<(), String> {
+    for (template, output) in &opts.stamp_templates {
+        let content = fs::read_to_string(template)
+            .map_err(|e| format!("failed to read stamp template {template}: {e}"))?;
+        fs::write(output, opts.workspace_status.stamp(&content))
+            .map_err(|e| format!("failed to write stamped file {output}: {e}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::TempDir;
+    use workspace_status::WorkspaceStatus;
+
+    #[test]
+    fn test_stamp_templates() {
+        let dir = TempDir::create("stamp_templates").unwrap();
+        let template = dir.path().join("version.rs.tpl");
+        let output = dir.path().join("version.rs");
+        fs::write(
+            &template,
+            "pub const USER: &str = \"{BUILD_USER}\";\n\
+             pub const HOST: &str = \"{BUILD_HOST}\";\n",
+        )
+        .unwrap();
+
+        let opts = crate::options::options_from_args_with_pwd(
+            vec![
+                "process_wrapper".to_owned(),
+                "--stamp-template".to_owned(),
+                template.to_string_lossy().into_owned(),
+                output.to_string_lossy().into_owned(),
+            ],
+            "/",
+        )
+        .unwrap();
+        assert_eq!(opts.executable, "");
+        let opts = Options {
+            // Values are not stamped again.
+            workspace_status: vec![("BUILD_USER", "{BUILD_HOST}"), ("BUILD_HOST", "host")]
+                .into_iter()
+                .collect::<WorkspaceStatus>(),
+            ..opts
+        };
+        stamp_templates(&opts).unwrap();
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "pub const USER: &str = \"{BUILD_HOST}\";\n\
+             pub const HOST: &str = \"host\";\n"
+        );
+    }
+}
@@ -12,8 +12,47 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use action_args::ActionArgsFormat;
+
+/// A temporary directory which is removed when dropped.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create a uniquely named temporary directory whose name starts with `prefix`.
+    pub(crate) fn create(prefix: &str) -> io::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!(
+            "{}_{}_{}",
+            prefix,
+            std::process::id(),
+            nanos
+        ));
+        fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if std::env::var_os("RULES_RUST_PROCESS_WRAPPER_DEBUG").is_some() {
+            eprintln!("keeping temporary directory {}", self.0.display());
+            return;
+        }
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
 
 pub(crate) fn read_file_to_array(path: &str) -> Result<Vec<String>, String> {
     let file = File::open(path).map_err(|e| e.to_string()).map_err(|err| {
@@ -28,6 +67,26 @@ pub(crate) fn read_file_to_array(path: &str) -> Result<Vec<String>, String> {
     read_to_array(file)
 }
 
+/// Inline the contents of rustc `@param` files into `args`.
+pub(crate) fn expand_param_files(args: &[String]) -> Result<Vec<String>, String> {
+    action_args::expand_param_files_with_fmt(args.iter().cloned(), ActionArgsFormat::Multiline)
+        .map_err(|e| e.to_string())
+}
+
+/// The crate name passed to rustc, if any.
+pub(crate) fn crate_name(args: &[String]) -> Option<&str> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--crate-name" {
+            return iter.next().map(String::as_str);
+        }
+        if let Some(name) = arg.strip_prefix("--crate-name=") {
+            return Some(name);
+        }
+    }
+    None
+}
+
 pub(crate) fn read_stamp_status_to_array(path: String) -> Result<Vec<(String, String)>, String> {
     let file = File::open(path).map_err(|e| e.to_string())?;
     stamp_status_to_array(file)
@@ -108,4 +167,15 @@ string",
         ];
         assert_eq!(expected, got);
     }
+
+    #[test]
+    fn test_crate_name() {
+        let args = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        assert_eq!(crate_name(&args(&["--crate-name=foo"])), Some("foo"));
+        assert_eq!(
+            crate_name(&args(&["lib.rs", "--crate-name", "bar"])),
+            Some("bar")
+        );
+        assert_eq!(crate_name(&args(&["lib.rs"])), None);
+    }
 }
@@ -0,0 +1,232 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A [multiplex persistent worker](https://bazel.build/remote/multiplex)
+//! using the JSON worker protocol.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use tinyjson::JsonValue;
+
+use crate::options::options_from_args;
+use crate::pipelining::{fingerprint, Pipeline};
+use crate::reproducibility;
+use crate::{run_child, ProcessWrapperError};
+
+/// The flag Bazel passes to the process wrapper when starting it as a worker.
+pub(crate) const PERSISTENT_WORKER_FLAG: &str = "--persistent_worker";
+
+/// A [WorkRequest](https://github.com/bazelbuild/bazel/blob/master/src/main/protobuf/worker_protocol.proto).
+#[derive(Debug, PartialEq)]
+struct WorkRequest {
+    arguments: Vec<String>,
+    /// Pairs of input paths and their digests.
+    inputs: Vec<(String, String)>,
+    request_id: i64,
+}
+
+impl WorkRequest {
+    fn parse(line: &str) -> Result<Self, String> {
+        let json: JsonValue = line
+            .parse()
+            .map_err(|e| format!("error parsing work request: {}", e))?;
+        let JsonValue::Object(request) = json else {
+            return Err(format!("work request is not an object: {}", line));
+        };
+
+        let string = |value: &JsonValue| match value {
+            JsonValue::String(s) => Some(s.clone()),
+            _ => None,
+        };
+
+        let arguments = match request.get("arguments") {
+            Some(JsonValue::Array(arguments)) => arguments.iter().filter_map(string).collect(),
+            _ => Vec::new(),
+        };
+
+        let inputs = match request.get("inputs") {
+            Some(JsonValue::Array(inputs)) => inputs
+                .iter()
+                .filter_map(|input| match input {
+                    JsonValue::Object(input) => Some((
+                        input.get("path").and_then(string).unwrap_or_default(),
+                        input.get("digest").and_then(string).unwrap_or_default(),
+                    )),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let request_id = match request.get("requestId") {
+            Some(JsonValue::Number(id)) => *id as i64,
+            _ => 0,
+        };
+
+        Ok(Self {
+            arguments,
+            inputs,
+            request_id,
+        })
+    }
+}
+
+/// Render a [WorkResponse](https://github.com/bazelbuild/bazel/blob/master/src/main/protobuf/worker_protocol.proto).
+fn work_response(request_id: i64, exit_code: i32, output: String) -> String {
+    JsonValue::Object(HashMap::from([
+        ("exitCode".to_string(), JsonValue::Number(exit_code as f64)),
+        ("output".to_string(), JsonValue::String(output)),
+        ("requestId".to_string(), JsonValue::Number(request_id as f64)),
+    ]))
+    .stringify()
+    .expect("work responses are always valid json")
+}
+
+/// Handle a single work request, returning the exit code and output.
+fn handle_request(pipeline: &Pipeline, request: &WorkRequest) -> (i32, Vec<u8>) {
+    let mut output = Vec::new();
+
+    // Work requests do not contain the program name.
+    let args: Vec<String> = std::iter::once("process_wrapper".to_string())
+        .chain(request.arguments.iter().cloned())
+        .collect();
+
+    let result = options_from_args(args.clone())
+        .map_err(|e| ProcessWrapperError(e.to_string()))
+        .and_then(|opts| {
+            // Reproducibility checks need the outputs of a standalone run.
+            if let (Some(key), false) = (&opts.pipelining_key, opts.check_reproducibility) {
+                let fingerprint = fingerprint(&opts, &request.inputs);
+                if let Some(code) = pipeline.run(key, fingerprint, &opts, &mut output)? {
+                    return Ok(code);
+                }
+            }
+
+            // Stdout of the worker is reserved for the worker protocol.
+            let code = run_child(&opts, &mut output, || Stdio::from(io::stderr()))?;
+            if code == 0 && opts.check_reproducibility {
+                return reproducibility::check(args, &mut output);
+            }
+            Ok(code)
+        });
+
+    match result {
+        Ok(code) => (code, output),
+        Err(e) => {
+            let _ = writeln!(output, "{}", e);
+            (1, output)
+        }
+    }
+}
+
+/// Serve work requests read from stdin until it is closed.
+pub(crate) fn run_worker() -> Result<(), ProcessWrapperError> {
+    let pipeline = Arc::new(Pipeline::default());
+    let stdout = Arc::new(Mutex::new(io::stdout()));
+
+    for line in io::stdin().lock().lines() {
+        let line =
+            line.map_err(|e| ProcessWrapperError(format!("failed to read work request: {}", e)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = WorkRequest::parse(&line).map_err(ProcessWrapperError)?;
+
+        let pipeline = Arc::clone(&pipeline);
+        let stdout = Arc::clone(&stdout);
+
+        // Requests are handled concurrently as the metadata request of a
+        // pipelined compilation completes while the full request is waiting
+        // on the same rustc process.
+        thread::spawn(move || {
+            let (code, output) = handle_request(&pipeline, &request);
+            let response = work_response(
+                request.request_id,
+                code,
+                String::from_utf8_lossy(&output).into_owned(),
+            );
+            let mut stdout = stdout.lock().unwrap_or_else(|e| e.into_inner());
+            let _ = writeln!(stdout, "{}", response);
+            let _ = stdout.flush();
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_work_request() {
+        let request = WorkRequest::parse(
+            r#"{
+                "arguments": ["--pipelining-key", "libfoo.rlib", "--", "rustc"],
+                "inputs": [
+                    {"path": "foo.rs", "digest": "abc"},
+                    {"path": "bar.rs", "digest": "def"}
+                ],
+                "requestId": 12
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            request,
+            WorkRequest {
+                arguments: vec![
+                    "--pipelining-key".to_string(),
+                    "libfoo.rlib".to_string(),
+                    "--".to_string(),
+                    "rustc".to_string(),
+                ],
+                inputs: vec![
+                    ("foo.rs".to_string(), "abc".to_string()),
+                    ("bar.rs".to_string(), "def".to_string()),
+                ],
+                request_id: 12,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_work_request_defaults() {
+        let request = WorkRequest::parse(r#"{"arguments": ["--", "rustc"]}"#).unwrap();
+        assert_eq!(request.request_id, 0);
+        assert!(request.inputs.is_empty());
+    }
+
+    #[test]
+    fn test_work_response() {
+        let response: JsonValue = work_response(3, 1, "error: oops\n".to_string())
+            .parse()
+            .unwrap();
+        assert_eq!(
+            response,
+            JsonValue::Object(HashMap::from([
+                ("exitCode".to_string(), JsonValue::Number(1.0)),
+                (
+                    "output".to_string(),
+                    JsonValue::String("error: oops\n".to_string())
+                ),
+                ("requestId".to_string(), JsonValue::Number(3.0)),
+            ]))
+        );
+    }
+}
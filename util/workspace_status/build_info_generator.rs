@@ -0,0 +1,470 @@
+//! Generates the sources of a `rust_build_info` crate from workspace status files.
+//!
+//! The Rust source only contains values of the stable status file, so it only
+//! changes, and only invalidates the crate and its dependents, when stable
+//! values change. Volatile values are compiled into a small C library instead,
+//! which the crate declares as `extern` and which is only linked into final
+//! binaries.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::process;
+
+use workspace_status::{WorkspaceStatus, WorkspaceStatusError};
+
+struct Options {
+    stable_status: Option<String>,
+    volatile_status: Option<String>,
+    symbol: String,
+    rust_output: Option<String>,
+    c_output: Option<String>,
+    git_commit_key: String,
+    git_dirty_key: String,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut opts = Options {
+        stable_status: None,
+        volatile_status: None,
+        symbol: "rules_rust_build_info".to_owned(),
+        rust_output: None,
+        c_output: None,
+        git_commit_key: "STABLE_GIT_COMMIT".to_owned(),
+        git_dirty_key: "STABLE_GIT_DIRTY".to_owned(),
+    };
+    let mut args = args.into_iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--stable-status" => opts.stable_status = Some(value),
+            "--volatile-status" => opts.volatile_status = Some(value),
+            "--symbol" => opts.symbol = value,
+            "--rust-output" => opts.rust_output = Some(value),
+            "--c-output" => opts.c_output = Some(value),
+            "--git-commit-key" => opts.git_commit_key = value,
+            "--git-dirty-key" => opts.git_dirty_key = value,
+            _ => return Err(format!("unknown argument: {}", flag)),
+        }
+    }
+    if opts.rust_output.is_none() && opts.c_output.is_none() {
+        return Err("at least one of --rust-output or --c-output is required".to_owned());
+    }
+    Ok(opts)
+}
+
+/// Interpret the value of the git dirty key, as written by common workspace
+/// status scripts.
+fn parse_dirty(key: &str, value: &str) -> Result<bool, WorkspaceStatusError> {
+    match value.trim() {
+        "1" | "true" | "dirty" | "modified" => Ok(true),
+        "" | "0" | "false" | "clean" => Ok(false),
+        _ => Err(WorkspaceStatusError::InvalidValue {
+            key: key.to_owned(),
+            value: value.to_owned(),
+            reason: "expected one of 1, true, dirty, modified, 0, false or clean".to_owned(),
+        }),
+    }
+}
+
+/// Convert a workspace status key to the name of a Rust constant.
+fn const_name(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn optional_str(value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("Some({:?})", value),
+        None => "None".to_owned(),
+    }
+}
+
+/// The names of the items of [RUST_TEMPLATE] which custom constants could collide with.
+const RESERVED_NAMES: &[&str] = &[
+    "BUILD_EMBED_LABEL",
+    "BUILD_HOST",
+    "BUILD_USER",
+    "GIT_COMMIT",
+    "GIT_DIRTY",
+    "STABLE_STATUS",
+];
+
+/// Render the Rust source of the build info crate from the stable status.
+fn rust_source(
+    status: &WorkspaceStatus,
+    symbol: &str,
+    git_commit_key: &str,
+    git_dirty_key: &str,
+) -> Result<String, WorkspaceStatusError> {
+    let git_dirty = status
+        .get(git_dirty_key)
+        .map(|value| parse_dirty(git_dirty_key, value))
+        .transpose()?;
+
+    let mut consts = String::new();
+    let mut stable = String::new();
+    let mut names: Vec<String> = RESERVED_NAMES.iter().map(|name| name.to_string()).collect();
+    for (key, value) in status.stable() {
+        let name = const_name(key);
+        // Keys whose constant would collide with a generated item, or which only
+        // differ in characters that are invalid in identifiers, are still
+        // available through `STABLE_STATUS`.
+        if !names.contains(&name) {
+            writeln!(consts, "/// The `{}` workspace status value.", key).unwrap();
+            writeln!(consts, "pub const {}: &str = {:?};", name, value).unwrap();
+            names.push(name);
+        }
+        writeln!(stable, "    ({:?}, {:?}),", key, value).unwrap();
+    }
+
+    let git_dirty = git_dirty.map_or("None".to_owned(), |dirty| format!("Some({})", dirty));
+    Ok(fill(
+        RUST_TEMPLATE,
+        &[
+            ("GIT_COMMIT_KEY", git_commit_key.to_owned()),
+            ("GIT_DIRTY_KEY", git_dirty_key.to_owned()),
+            ("GIT_COMMIT", optional_str(status.get(git_commit_key))),
+            ("GIT_DIRTY", git_dirty),
+            ("BUILD_USER", optional_str(status.build_user())),
+            ("BUILD_HOST", optional_str(status.build_host())),
+            (
+                "BUILD_EMBED_LABEL",
+                optional_str(status.build_embed_label()),
+            ),
+            ("STABLE_CONSTS", consts),
+            ("STABLE_STATUS", stable),
+            ("SYMBOL", symbol.to_owned()),
+        ],
+    ))
+}
+
+/// Replace the `{NAME}` placeholders of `template` in a single pass, so
+/// workspace status values are never substituted themselves.
+fn fill(template: &str, values: &[(&str, String)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = values.iter().find_map(|(name, value)| {
+            rest.strip_prefix('{')?
+                .strip_prefix(name)?
+                .strip_prefix('}')
+                .map(|after| (value, after))
+        });
+        match value {
+            Some((value, after)) => {
+                filled.push_str(value);
+                rest = after;
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// Escape `value` as the contents of a C string literal.
+fn c_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'"' => escaped.push_str("\\\""),
+            b'\\' => escaped.push_str("\\\\"),
+            b'\n' => escaped.push_str("\\n"),
+            // `?` is escaped to avoid trigraphs.
+            b' '..=b'~' if byte != b'?' => escaped.push(byte as char),
+            // Octal escapes are limited to three digits, unlike hex escapes
+            // which would consume following hex digits.
+            _ => write!(escaped, "\\{:03o}", byte).unwrap(),
+        }
+    }
+    escaped
+}
+
+/// Render the C source which defines the volatile status of the build.
+fn c_source(status: &WorkspaceStatus, symbol: &str) -> Result<String, WorkspaceStatusError> {
+    // Fail early on values the build info crate cannot interpret.
+    status.build_timestamp()?;
+
+    let mut volatile = String::new();
+    for (key, value) in status.iter() {
+        writeln!(volatile, "{} {}", key, value).unwrap();
+    }
+
+    Ok(format!(
+        "/* Generated by build_info_generator. Do not edit. */\n\
+         \n\
+         const char *{}_volatile_status(void) {{\n  return \"{}\";\n}}\n",
+        symbol,
+        c_string(&volatile)
+    ))
+}
+
+fn run(opts: &Options) -> Result<(), String> {
+    if let Some(output) = &opts.rust_output {
+        let status = WorkspaceStatus::from_files(opts.stable_status.as_deref())
+            .map_err(|e| e.to_string())?;
+        let source = rust_source(
+            &status,
+            &opts.symbol,
+            &opts.git_commit_key,
+            &opts.git_dirty_key,
+        )
+        .map_err(|e| e.to_string())?;
+        fs::write(output, source).map_err(|e| format!("Failed to write {}: {}", output, e))?;
+    }
+    if let Some(output) = &opts.c_output {
+        let status = WorkspaceStatus::from_files(opts.volatile_status.as_deref())
+            .map_err(|e| e.to_string())?;
+        let source = c_source(&status, &opts.symbol).map_err(|e| e.to_string())?;
+        fs::write(output, source).map_err(|e| format!("Failed to write {}: {}", output, e))?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(|opts| run(&opts));
+    if let Err(message) = result {
+        eprintln!("build_info_generator: {}", message);
+        process::exit(1);
+    }
+}
+
+const RUST_TEMPLATE: &str = r#"//! Build information from the workspace status of the build.
+//!
+//! Generated by `build_info_generator`. Do not edit.
+
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_char;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The git commit of the build, from the `{GIT_COMMIT_KEY}` workspace status value.
+pub const GIT_COMMIT: Option<&str> = {GIT_COMMIT};
+/// Whether the workspace had uncommitted changes, from the `{GIT_DIRTY_KEY}` workspace status value.
+pub const GIT_DIRTY: Option<bool> = {GIT_DIRTY};
+/// The user running the build, from the `BUILD_USER` workspace status value.
+pub const BUILD_USER: Option<&str> = {BUILD_USER};
+/// The host running the build, from the `BUILD_HOST` workspace status value.
+pub const BUILD_HOST: Option<&str> = {BUILD_HOST};
+/// The `--embed_label` of the build, from the `BUILD_EMBED_LABEL` workspace status value.
+pub const BUILD_EMBED_LABEL: Option<&str> = {BUILD_EMBED_LABEL};
+
+{STABLE_CONSTS}
+/// All `STABLE_*` workspace status values, ordered by key.
+pub const STABLE_STATUS: &[(&str, &str)] = &[
+{STABLE_STATUS}];
+
+extern "C" {
+    #[link_name = "{SYMBOL}_volatile_status"]
+    fn volatile_status_ptr() -> *const c_char;
+}
+
+/// The contents of the volatile workspace status file, one `KEY value` pair
+/// per line. Empty if the build was not stamped.
+///
+/// The volatile status is linked into the final binary instead of being
+/// compiled into this crate, so it does not invalidate compilation caches.
+pub fn volatile_status() -> &'static str {
+    // SAFETY: The symbol returns a pointer to a static, NUL terminated string.
+    unsafe { CStr::from_ptr(volatile_status_ptr()) }
+        .to_str()
+        .unwrap_or_default()
+}
+
+/// The value of `key` in the volatile workspace status, if present.
+pub fn volatile_value(key: &str) -> Option<&'static str> {
+    volatile_status()
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| value)
+}
+
+/// The time of the build, from the volatile `BUILD_TIMESTAMP` workspace status value.
+pub fn build_timestamp() -> Option<SystemTime> {
+    volatile_value("BUILD_TIMESTAMP")
+        .and_then(|seconds| seconds.parse().ok())
+        .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Information about the build of the current binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// See [GIT_COMMIT].
+    pub git_commit: Option<&'static str>,
+    /// See [GIT_DIRTY].
+    pub git_dirty: Option<bool>,
+    /// See [BUILD_USER].
+    pub build_user: Option<&'static str>,
+    /// See [BUILD_HOST].
+    pub build_host: Option<&'static str>,
+    /// See [BUILD_EMBED_LABEL].
+    pub build_embed_label: Option<&'static str>,
+    /// See [build_timestamp].
+    pub build_timestamp: Option<SystemTime>,
+    /// See [STABLE_STATUS].
+    pub stable_status: &'static [(&'static str, &'static str)],
+}
+
+/// The build information of the current binary.
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        git_commit: GIT_COMMIT,
+        git_dirty: GIT_DIRTY,
+        build_user: BUILD_USER,
+        build_host: BUILD_HOST,
+        build_embed_label: BUILD_EMBED_LABEL,
+        build_timestamp: build_timestamp(),
+        stable_status: STABLE_STATUS,
+    }
+}
+
+/// Formats a version string suitable for `--version` output, e.g.
+/// `1.2.3 (abc123-dirty)`, or `unknown` for builds which were not stamped.
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dirty = if self.git_dirty == Some(true) { "-dirty" } else { "" };
+        match (self.build_embed_label, self.git_commit) {
+            (Some(label), Some(commit)) => write!(f, "{} ({}{})", label, commit, dirty),
+            (Some(label), None) => write!(f, "{}", label),
+            (None, Some(commit)) => write!(f, "{}{}", commit, dirty),
+            (None, None) => write!(f, "unknown"),
+        }
+    }
+}
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn status(lines: &[&str]) -> WorkspaceStatus {
+        WorkspaceStatus::parse([lines.join("\n").as_str()]).unwrap()
+    }
+
+    #[test]
+    fn test_rust_source() {
+        let status = status(&[
+            "BUILD_EMBED_LABEL 1.2.3",
+            "BUILD_HOST host",
+            "BUILD_USER user",
+            "STABLE_GIT_COMMIT abc123",
+            "STABLE_GIT_DIRTY 1",
+            "STABLE_NOTE {BUILD_USER}",
+            "STABLE_RELEASE_CHANNEL \"beta\"",
+            "STABLE_team.name core",
+        ]);
+        let source = rust_source(
+            &status,
+            "my_symbol",
+            "STABLE_GIT_COMMIT",
+            "STABLE_GIT_DIRTY",
+        )
+        .unwrap();
+
+        assert!(source.contains("pub const GIT_COMMIT: Option<&str> = Some(\"abc123\");\n"));
+        assert!(source.contains("pub const GIT_DIRTY: Option<bool> = Some(true);\n"));
+        assert!(source.contains("pub const BUILD_USER: Option<&str> = Some(\"user\");\n"));
+        assert!(source.contains("pub const BUILD_EMBED_LABEL: Option<&str> = Some(\"1.2.3\");\n"));
+        assert!(source.contains("pub const STABLE_RELEASE_CHANNEL: &str = \"\\\"beta\\\"\";\n"));
+        assert!(source.contains("pub const STABLE_NOTE: &str = \"{BUILD_USER}\";\n"));
+        assert!(source.contains("pub const STABLE_TEAM_NAME: &str = \"core\";\n"));
+        assert!(source.contains("    (\"STABLE_team.name\", \"core\"),\n"));
+        assert!(source.contains("#[link_name = \"my_symbol_volatile_status\"]"));
+        assert!(!source.contains("{SYMBOL}"));
+    }
+
+    #[test]
+    fn test_rust_source_reserved_names() {
+        let status = status(&["STABLE_STATUS green", "STABLE_status blue"]);
+        let source = rust_source(
+            &status,
+            "my_symbol",
+            "STABLE_GIT_COMMIT",
+            "STABLE_GIT_DIRTY",
+        )
+        .unwrap();
+
+        assert_eq!(source.matches("pub const STABLE_STATUS").count(), 1);
+        assert!(source.contains("    (\"STABLE_STATUS\", \"green\"),\n"));
+        assert!(source.contains("    (\"STABLE_status\", \"blue\"),\n"));
+    }
+
+    #[test]
+    fn test_rust_source_unstamped() {
+        let source = rust_source(
+            &WorkspaceStatus::default(),
+            "my_symbol",
+            "STABLE_GIT_COMMIT",
+            "STABLE_GIT_DIRTY",
+        )
+        .unwrap();
+
+        assert!(source.contains("pub const GIT_COMMIT: Option<&str> = None;\n"));
+        assert!(source.contains("pub const GIT_DIRTY: Option<bool> = None;\n"));
+        assert!(source.contains("pub const STABLE_STATUS: &[(&str, &str)] = &[\n];\n"));
+    }
+
+    #[test]
+    fn test_rust_source_invalid_dirty() {
+        let status = status(&["STABLE_GIT_DIRTY maybe"]);
+        assert!(matches!(
+            rust_source(
+                &status,
+                "my_symbol",
+                "STABLE_GIT_COMMIT",
+                "STABLE_GIT_DIRTY"
+            ),
+            Err(WorkspaceStatusError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_c_source() {
+        let status = status(&["BUILD_TIMESTAMP 1730574875", "NOTE say \"hi\"?\\ é"]);
+        assert_eq!(
+            c_source(&status, "my_symbol").unwrap(),
+            "/* Generated by build_info_generator. Do not edit. */\n\
+             \n\
+             const char *my_symbol_volatile_status(void) {\n  \
+             return \"BUILD_TIMESTAMP 1730574875\\nNOTE say \\\"hi\\\"\\077\\\\ \\303\\251\\n\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_c_source_invalid_timestamp() {
+        let status = status(&["BUILD_TIMESTAMP yesterday"]);
+        assert!(c_source(&status, "my_symbol").is_err());
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let opts = parse_args(
+            ["--rust-output", "out.rs", "--symbol", "sym"]
+                .iter()
+                .map(|arg| arg.to_string()),
+        )
+        .unwrap();
+        assert_eq!(opts.rust_output.as_deref(), Some("out.rs"));
+        assert_eq!(opts.symbol, "sym");
+        assert_eq!(opts.git_commit_key, "STABLE_GIT_COMMIT");
+
+        assert!(parse_args(Vec::new()).is_err());
+        assert!(parse_args(["--rust-output".to_owned()]).is_err());
+    }
+}
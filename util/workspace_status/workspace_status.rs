@@ -1,12 +1,40 @@
 //! Utilities for parsing [workspace status stamps](https://bazel.build/docs/user-manual#workspace-status).
 
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 /// The error type of workspace status parsing.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum WorkspaceStatusError {
     /// The workspace status data is malformed and cannot be parsed.
     InvalidFormat(String),
+    /// A workspace status file could not be read.
+    Io(String),
+    /// The value of a key cannot be converted to the requested type.
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
 }
 
+impl fmt::Display for WorkspaceStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat(message) | Self::Io(message) => write!(f, "{}", message),
+            Self::InvalidValue { key, value, reason } => {
+                write!(f, "Invalid value for {}: \"{}\": {}", key, value, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WorkspaceStatusError {}
+
 /// Returns an iterator of workspace status stamp values parsed from the given text.
 pub fn parse_workspace_status_stamps(
     text: &'_ str,
@@ -22,6 +50,145 @@ pub fn parse_workspace_status_stamps(
     })
 }
 
+/// The values of the stable and volatile workspace status files of a build.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceStatus {
+    stamps: BTreeMap<String, String>,
+}
+
+impl WorkspaceStatus {
+    /// Parse the contents of workspace status files. Values of later files
+    /// take precedence over those of earlier ones.
+    pub fn parse<'a>(
+        texts: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, WorkspaceStatusError> {
+        let mut stamps = BTreeMap::new();
+        for text in texts {
+            for stamp in parse_workspace_status_stamps(text) {
+                let (key, value) = stamp?;
+                stamps.insert(key.to_owned(), value.to_owned());
+            }
+        }
+        Ok(Self { stamps })
+    }
+
+    /// Read and parse workspace status files, e.g. the `stable-status.txt`
+    /// and `volatile-status.txt` files of a build.
+    pub fn from_files<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+    ) -> Result<Self, WorkspaceStatusError> {
+        let texts = paths
+            .into_iter()
+            .map(|path| {
+                let path = path.as_ref();
+                fs::read_to_string(path).map_err(|e| {
+                    WorkspaceStatusError::Io(format!("Failed to read {}: {}", path.display(), e))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::parse(texts.iter().map(String::as_str))
+    }
+
+    /// The value of `key`, if present.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.stamps.get(key).map(String::as_str)
+    }
+
+    /// The value of `key` converted to `T`, if present.
+    pub fn get_parsed<T>(&self, key: &str) -> Result<Option<T>, WorkspaceStatusError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.get(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e: T::Err| WorkspaceStatusError::InvalidValue {
+                        key: key.to_owned(),
+                        value: value.to_owned(),
+                        reason: e.to_string(),
+                    })
+            })
+            .transpose()
+    }
+
+    /// All keys and values, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.stamps
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// The keys and values of the stable status, whose keys start with `STABLE_`.
+    pub fn stable(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.iter().filter(|(key, _)| key.starts_with("STABLE_"))
+    }
+
+    /// The time of the build, from the `BUILD_TIMESTAMP` key.
+    pub fn build_timestamp(&self) -> Result<Option<SystemTime>, WorkspaceStatusError> {
+        Ok(self
+            .get_parsed::<u64>("BUILD_TIMESTAMP")?
+            .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds)))
+    }
+
+    /// The name of the user running the build, from the `BUILD_USER` key.
+    pub fn build_user(&self) -> Option<&str> {
+        self.get("BUILD_USER")
+    }
+
+    /// The host running the build, from the `BUILD_HOST` key.
+    pub fn build_host(&self) -> Option<&str> {
+        self.get("BUILD_HOST")
+    }
+
+    /// The `--embed_label` of the build, from the `BUILD_EMBED_LABEL` key.
+    pub fn build_embed_label(&self) -> Option<&str> {
+        self.get("BUILD_EMBED_LABEL")
+            .filter(|label| !label.is_empty())
+    }
+
+    /// Replace every `{KEY}` in `template` with the value of `KEY`. This is
+    /// done in a single pass, so placeholders within values are not replaced.
+    /// Placeholders of unknown keys are left unchanged.
+    pub fn stamp(&self, template: &str) -> String {
+        let mut stamped = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let (before, placeholder) = rest.split_at(start);
+            stamped.push_str(before);
+            let value = placeholder
+                .find('}')
+                .and_then(|end| Some((self.get(&placeholder[1..end])?, end)));
+            match value {
+                Some((value, end)) => {
+                    stamped.push_str(value);
+                    rest = &placeholder[end + 1..];
+                }
+                None => {
+                    stamped.push('{');
+                    rest = &placeholder[1..];
+                }
+            }
+        }
+        stamped.push_str(rest);
+        stamped
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for WorkspaceStatus {
+    /// Collect keys and values. Later values of a key take precedence over
+    /// earlier ones.
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            stamps: iter
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -118,4 +285,91 @@ mod test {
             error
         );
     }
+
+    fn status() -> WorkspaceStatus {
+        let stable = [
+            "BUILD_EMBED_LABEL ",
+            "BUILD_HOST host",
+            "BUILD_USER user name",
+            "STABLE_GIT_COMMIT abc123",
+        ]
+        .join("\n");
+        let volatile = ["BUILD_TIMESTAMP 1730574875", "BUILD_USER other"].join("\n");
+        WorkspaceStatus::parse([stable.as_str(), volatile.as_str()]).unwrap()
+    }
+
+    #[test]
+    fn test_workspace_status() {
+        let status = status();
+        assert_eq!(status.get("STABLE_GIT_COMMIT"), Some("abc123"));
+        assert_eq!(status.get("MISSING"), None);
+        // Later files take precedence.
+        assert_eq!(status.build_user(), Some("other"));
+        assert_eq!(status.build_host(), Some("host"));
+        assert_eq!(status.build_embed_label(), None);
+        assert_eq!(
+            status.build_timestamp(),
+            Ok(Some(UNIX_EPOCH + Duration::from_secs(1730574875)))
+        );
+        assert_eq!(
+            status.stable().collect::<Vec<_>>(),
+            vec![("STABLE_GIT_COMMIT", "abc123")]
+        );
+    }
+
+    #[test]
+    fn test_get_parsed() {
+        let status = status();
+        assert_eq!(
+            status.get_parsed::<u64>("BUILD_TIMESTAMP"),
+            Ok(Some(1730574875))
+        );
+        assert_eq!(status.get_parsed::<u64>("MISSING"), Ok(None));
+        assert_eq!(
+            status.get_parsed::<u64>("BUILD_HOST"),
+            Err(WorkspaceStatusError::InvalidValue {
+                key: "BUILD_HOST".to_owned(),
+                value: "host".to_owned(),
+                reason: "invalid digit found in string".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_stamp() {
+        let status = status();
+        assert_eq!(
+            status.stamp(r#"{"commit": "{STABLE_GIT_COMMIT}", "host": "{BUILD_HOST}"}"#),
+            r#"{"commit": "abc123", "host": "host"}"#
+        );
+        assert_eq!(
+            status.stamp("{MISSING} {{BUILD_HOST}} {BUILD_HOST"),
+            "{MISSING} {host} {BUILD_HOST"
+        );
+    }
+
+    #[test]
+    fn test_stamp_single_pass() {
+        let status = WorkspaceStatus::from_iter([
+            ("STABLE_A", "{STABLE_B}"),
+            ("STABLE_B", "b"),
+            ("STABLE_C", "{STABLE_A}"),
+        ]);
+        assert_eq!(
+            status.stamp("{STABLE_A} {STABLE_B} {STABLE_C}"),
+            "{STABLE_B} b {STABLE_A}"
+        );
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let status = WorkspaceStatus::from_iter([("BUILD_USER", "user"), ("BUILD_USER", "other")]);
+        assert_eq!(status.build_user(), Some("other"));
+    }
+
+    #[test]
+    fn test_from_files() {
+        let error = WorkspaceStatus::from_files(["/nonexistent/stable-status.txt"]).unwrap_err();
+        assert!(matches!(error, WorkspaceStatusError::Io(_)));
+    }
 }